@@ -0,0 +1,249 @@
+use anyhow::Error;
+use indexmap::IndexMap;
+use std::{fs, io, path::PathBuf};
+
+use crate::config::config_dir;
+
+// how deep aliases can run other aliases, so one that runs itself stops with an error
+const MAX_DEPTH: usize = 16;
+
+fn aliases_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("aliases.toml"))
+}
+
+// commands of the user's own, which run other commands, like battle for "play drums horns;
+// volume music -v 30". They are kept in aliases.toml in the config folder, so they work in every
+// soundscape
+pub struct Aliases {
+    aliases: IndexMap<String, String>,
+    path: Option<PathBuf>,
+}
+
+impl Aliases {
+    pub fn new() -> Self {
+        Self {
+            aliases: IndexMap::new(),
+            path: aliases_path(),
+        }
+    }
+
+    // a missing file means there are no aliases yet
+    pub fn load(&mut self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        self.aliases = match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|err| {
+                Error::msg(format!(
+                    "error: could not read the aliases at {}. {err}",
+                    path.display()
+                ))
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "error: could not open the aliases at {}. {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(())
+    }
+
+    fn persist(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = toml::to_string(&self.aliases)?;
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(path, text))
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not save the aliases to {}. {err}",
+                    path.display()
+                ))
+            })
+    }
+
+    pub fn aliases(&self) -> &IndexMap<String, String> {
+        &self.aliases
+    }
+
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.aliases.get(name)
+    }
+
+    // defining an alias again replaces what it ran
+    pub fn define(&mut self, name: String, commands: String) -> Result<(), Error> {
+        self.aliases.insert(name, commands);
+        self.persist()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        if self.aliases.shift_remove(name).is_none() {
+            return Err(Error::msg(format!("error: there is no alias {name}")));
+        }
+        self.persist()
+    }
+
+    // the commands that a line runs, or None when it doesn't start with an alias
+    pub fn expand(&self, line: &str) -> Result<Option<Vec<String>>, Error> {
+        let Some(words) = shlex::split(line) else {
+            return Ok(None);
+        };
+        if !words
+            .first()
+            .is_some_and(|name| self.aliases.contains_key(name))
+        {
+            return Ok(None);
+        }
+        let mut commands = Vec::new();
+        self.expand_into(&words, 0, &mut commands)?;
+        Ok(Some(commands))
+    }
+
+    fn expand_into(
+        &self,
+        words: &[String],
+        depth: usize,
+        commands: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        let (name, args) = words.split_first().unwrap();
+        if depth == MAX_DEPTH {
+            return Err(Error::msg(format!(
+                "error: the alias {name} keeps running aliases. Does it run itself?"
+            )));
+        }
+        let body = substitute(name, &self.aliases[name], args)?;
+        for command in split_commands(&body) {
+            match shlex::split(&command) {
+                Some(words)
+                    if words
+                        .first()
+                        .is_some_and(|name| self.aliases.contains_key(name)) =>
+                {
+                    self.expand_into(&words, depth + 1, commands)?
+                }
+                _ => commands.push(command),
+            }
+        }
+        Ok(())
+    }
+}
+
+// the commands of an alias, which are separated by ; where it isn't in quotes
+fn split_commands(body: &str) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut command = String::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for char in body.chars() {
+        match (char, quote) {
+            _ if escaped => escaped = false,
+            ('\\', Some('"') | None) => escaped = true,
+            ('"' | '\'', None) => quote = Some(char),
+            (_, Some(open)) if char == open => quote = None,
+            (';', None) => {
+                commands.push(std::mem::take(&mut command));
+                continue;
+            }
+            _ => {}
+        }
+        command.push(char);
+    }
+    commands.push(command);
+    commands
+        .into_iter()
+        .map(|command| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+        .collect()
+}
+
+// puts what was given after the name of the alias in the place of $1, $2 and so on, and all of it
+// in the place of $@. $$ is a $ of its own
+fn substitute(name: &str, body: &str, args: &[String]) -> Result<String, Error> {
+    let quote = |arg: &String| {
+        shlex::try_quote(arg)
+            .map(|quoted| quoted.into_owned())
+            .map_err(|_| Error::msg("error: an argument can't have NUL characters in it."))
+    };
+    let mut substituted = String::new();
+    let mut used = 0;
+    let mut uses_all = false;
+    let mut chars = body.chars().peekable();
+    while let Some(char) = chars.next() {
+        match (char, chars.peek().copied()) {
+            ('$', Some('$')) => {
+                chars.next();
+                substituted.push('$');
+            }
+            ('$', Some('@')) => {
+                chars.next();
+                uses_all = true;
+                let quoted = args.iter().map(quote).collect::<Result<Vec<_>, _>>()?;
+                substituted.push_str(&quoted.join(" "));
+            }
+            ('$', Some(digit @ '1'..='9')) => {
+                chars.next();
+                let number = digit.to_digit(10).unwrap() as usize;
+                let arg = args.get(number - 1).ok_or_else(|| {
+                    Error::msg(format!(
+                        "error: {name} needs {number} argument{}, but got {}.",
+                        if number == 1 { "" } else { "s" },
+                        args.len()
+                    ))
+                })?;
+                used = used.max(number);
+                substituted.push_str(&quote(arg)?);
+            }
+            _ => substituted.push(char),
+        }
+    }
+    if !uses_all && args.len() > used {
+        return Err(Error::msg(format!(
+            "error: {name} takes {used} argument{}, but got {}.",
+            if used == 1 { "" } else { "s" },
+            args.len()
+        )));
+    }
+    Ok(substituted)
+}
+
+#[test]
+fn aliases_expand_with_their_arguments() {
+    let mut aliases = Aliases {
+        aliases: IndexMap::new(),
+        path: None,
+    };
+    let define = |aliases: &mut Aliases, name: &str, body: &str| {
+        aliases.define(name.to_string(), body.to_string()).unwrap()
+    };
+    define(
+        &mut aliases,
+        "battle",
+        "play drums horns; volume music -v 30",
+    );
+    define(&mut aliases, "loud", "volume $1 -v 150");
+    define(&mut aliases, "start", "battle; play $@");
+    define(&mut aliases, "forever", "forever");
+    assert_eq!(aliases.expand("play rain").unwrap(), None);
+    assert_eq!(
+        aliases.expand("battle").unwrap().unwrap(),
+        ["play drums horns", "volume music -v 30"]
+    );
+    assert_eq!(
+        aliases.expand("loud 'big rain'").unwrap().unwrap(),
+        ["volume 'big rain' -v 150"]
+    );
+    assert_eq!(
+        aliases.expand("start owl wind").unwrap().unwrap(),
+        ["play drums horns", "volume music -v 30", "play owl wind"]
+    );
+    assert!(aliases.expand("loud").is_err());
+    assert!(aliases.expand("battle rain").is_err());
+    assert!(aliases.expand("forever").is_err());
+    assert_eq!(
+        split_commands("cue add \"play a; play b\"; cue go;"),
+        ["cue add \"play a; play b\"", "cue go"]
+    );
+}
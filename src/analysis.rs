@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+// a sound is flagged when summing it to mono makes it this much quieter. Uncorrelated channels
+// lose 3 dB, so this only catches channels that actually work against each other
+const MONO_LOSS_WARNING_DB: f32 = 6.0;
+
+// how a stereo sound holds up when its channels are summed, like on a lot of venue speaker setups
+pub struct MonoCompatibility {
+    // 1 means both channels are the same, 0 that they have nothing in common and -1 that they cancel out
+    pub correlation: f32,
+    // how much quieter the mono sum is than the stereo sound
+    pub loss_db: f32,
+}
+
+impl MonoCompatibility {
+    // takes interleaved stereo samples. Returns None for silence
+    pub fn measure(samples: impl Iterator<Item = f32>) -> Option<Self> {
+        let (mut left_power, mut right_power, mut cross) = (0.0f64, 0.0f64, 0.0f64);
+        let mut samples = samples.map(f64::from);
+        while let (Some(left), Some(right)) = (samples.next(), samples.next()) {
+            left_power += left * left;
+            right_power += right * right;
+            cross += left * right;
+        }
+        if left_power == 0.0 && right_power == 0.0 {
+            return None;
+        }
+
+        let stereo_power = (left_power + right_power) / 2.0;
+        let mono_power = (left_power + right_power + 2.0 * cross) / 4.0;
+        let correlation = if left_power == 0.0 || right_power == 0.0 {
+            0.0
+        } else {
+            cross / (left_power * right_power).sqrt()
+        };
+        let loss_db = if mono_power <= 0.0 {
+            f64::INFINITY
+        } else {
+            -10.0 * (mono_power / stereo_power).log10()
+        };
+        Some(Self {
+            correlation: correlation as f32,
+            loss_db: loss_db as f32,
+        })
+    }
+
+    pub fn is_problematic(&self) -> bool {
+        self.loss_db > MONO_LOSS_WARNING_DB
+    }
+}
+
+// normalized sounds are brought to this average level, in dB below full scale. That leaves room
+// for the peaks, and for a few sounds playing on top of each other
+pub const NORMALIZE_TARGET_DB: f32 = -20.0;
+// very quiet recordings would mostly get louder noise out of more than this
+const MAX_NORMALIZE_BOOST_DB: f32 = 12.0;
+// loudness is measured in blocks of this length, like EBU R128 does
+const LOUDNESS_BLOCK: Duration = Duration::from_millis(400);
+// blocks quieter than this are silence, and don't make the sound count as quieter
+const SILENCE_GATE_DB: f32 = -70.0;
+
+// the average level of a sound in dB below full scale, leaving out the silent parts. Takes
+// interleaved samples. Returns None for silence
+pub fn measure_loudness(
+    samples: impl Iterator<Item = f32>,
+    channels: u16,
+    sample_rate: u32,
+) -> Option<f32> {
+    let block_len = (frames_in(LOUDNESS_BLOCK, sample_rate) * channels as usize).max(1);
+    let gate = 10f64.powf(SILENCE_GATE_DB as f64 / 10.0);
+    let (mut total, mut blocks) = (0.0f64, 0usize);
+    let (mut block_power, mut in_block) = (0.0f64, 0usize);
+    let mut add_block = |power: f64, len: usize| {
+        let mean = power / len as f64;
+        if mean > gate {
+            total += mean;
+            blocks += 1;
+        }
+    };
+    for sample in samples {
+        block_power += (sample as f64).powi(2);
+        in_block += 1;
+        if in_block == block_len {
+            add_block(block_power, in_block);
+            (block_power, in_block) = (0.0, 0);
+        }
+    }
+    if in_block > 0 {
+        add_block(block_power, in_block);
+    }
+    (blocks > 0).then(|| (10.0 * (total / blocks as f64).log10()) as f32)
+}
+
+// the gain that brings a sound of this loudness to the normalized level
+pub fn normalization_gain(loudness_db: f32) -> f32 {
+    let gain_db = (NORMALIZE_TARGET_DB - loudness_db).min(MAX_NORMALIZE_BOOST_DB);
+    10f32.powf(gain_db / 20.0)
+}
+
+// how many dB each sound in a group has to change to bring the group to the target level, given
+// the levels they are heard at now. Keeping the relative differences moves every sound by the same
+// amount, so that their average lands on the target. Otherwise every sound is put on the target
+pub fn balance(levels: &[f32], target: f32, keep_relative: bool) -> Vec<f32> {
+    if keep_relative {
+        let average = levels.iter().sum::<f32>() / levels.len().max(1) as f32;
+        vec![target - average; levels.len()]
+    } else {
+        levels.iter().map(|level| target - level).collect()
+    }
+}
+
+// how far loop points may be moved to find a better spot
+const LOOP_POINT_SEARCH: Duration = Duration::from_millis(50);
+// how much of the sound around the loop points has to line up
+const LOOP_POINT_WINDOW: Duration = Duration::from_millis(5);
+
+fn frames_in(duration: Duration, sample_rate: u32) -> usize {
+    (duration.as_secs_f64() * sample_rate as f64).round() as usize
+}
+
+// moves loop points to where the sound loops without a click. The start goes to the nearest
+// upward zero crossing, and the end to where the sound around it looks most like the sound around
+// the start, so the jump from the end back to the start is as smooth as possible. Takes mono
+// frames. Returns None when the part between the points is too short to loop
+pub fn find_loop_points(
+    frames: impl Iterator<Item = f32>,
+    sample_rate: u32,
+    start: Duration,
+    end: Duration,
+) -> Option<(Duration, Duration)> {
+    let search = frames_in(LOOP_POINT_SEARCH, sample_rate);
+    let window = frames_in(LOOP_POINT_WINDOW, sample_rate).max(1);
+    let reach = search + window;
+    let (start, end) = (frames_in(start, sample_rate), frames_in(end, sample_rate));
+    if end <= start + 2 * reach {
+        return None;
+    }
+
+    // only the frames around the loop points are kept, so long sounds don't fill up the memory
+    let start_from = start.saturating_sub(reach);
+    let end_from = end - reach;
+    let (mut around_start, mut around_end) = (Vec::new(), Vec::new());
+    for (index, frame) in frames.enumerate().take(end + reach) {
+        if index >= start_from && index < start + reach {
+            around_start.push(frame);
+        }
+        if index >= end_from {
+            around_end.push(frame);
+        }
+    }
+    let at_start = |index: usize| around_start.get(index - start_from).copied();
+    let at_end = |index: usize| around_end.get(index - end_from).copied();
+
+    let new_start = (start.saturating_sub(search).max(start_from + 1)..start + search)
+        .filter(|&index| match (at_start(index - 1), at_start(index)) {
+            (Some(before), Some(after)) => before < 0.0 && after >= 0.0,
+            _ => false,
+        })
+        .min_by_key(|&index| index.abs_diff(start))
+        .unwrap_or(start);
+
+    // everything before the start of the sound counts as silence
+    let around_new_start = |offset: isize| {
+        let index = new_start as isize + offset;
+        if index < start_from as isize {
+            return 0.0;
+        }
+        at_start(index as usize).unwrap_or(0.0)
+    };
+    let last_candidate = (end_from + around_end.len()).saturating_sub(window);
+    let new_end = (end - search..=(end + search).min(last_candidate))
+        .map(|candidate| {
+            let difference: f32 = (-(window as isize)..window as isize)
+                .map(|offset| {
+                    let frame = at_end((candidate as isize + offset) as usize).unwrap_or(0.0);
+                    (frame - around_new_start(offset)).powi(2)
+                })
+                .sum();
+            (candidate, difference)
+        })
+        .min_by(|(a, a_difference), (b, b_difference)| {
+            a_difference
+                .total_cmp(b_difference)
+                .then(a.abs_diff(end).cmp(&b.abs_diff(end)))
+        })
+        .map(|(candidate, _)| candidate)
+        .unwrap_or(end);
+
+    let to_duration = |frames: usize| Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+    Some((to_duration(new_start), to_duration(new_end)))
+}
+
+#[test]
+fn mono_compatibility_of_phase_issues() {
+    let stereo =
+        |pairs: &[(f32, f32)]| -> Vec<f32> { pairs.iter().flat_map(|&(l, r)| [l, r]).collect() };
+    let same = MonoCompatibility::measure(stereo(&[(0.5, 0.5), (-0.3, -0.3)]).into_iter()).unwrap();
+    assert!((same.correlation - 1.0).abs() < 1e-6 && same.loss_db.abs() < 1e-6);
+    assert!(!same.is_problematic());
+
+    let inverted =
+        MonoCompatibility::measure(stereo(&[(0.5, -0.5), (-0.3, 0.3)]).into_iter()).unwrap();
+    assert!((inverted.correlation + 1.0).abs() < 1e-6);
+    assert!(inverted.is_problematic());
+
+    let one_sided =
+        MonoCompatibility::measure(stereo(&[(0.5, 0.0), (-0.3, 0.0)]).into_iter()).unwrap();
+    assert!((one_sided.loss_db - 3.0103).abs() < 1e-3);
+    assert!(!one_sided.is_problematic());
+
+    assert!(MonoCompatibility::measure(stereo(&[(0.0, 0.0)]).into_iter()).is_none());
+}
+
+#[test]
+fn loop_points_line_up() {
+    // a 100 Hz sine starts at 0 and repeats every 441 frames, so loop points on whole periods are seamless
+    let sample_rate = 44_100;
+    let sine = |frame: usize| ((frame % 441) as f32 * std::f32::consts::TAU / 441.0).sin();
+    let frames = (0..sample_rate as usize * 2).map(sine);
+    let (start, end) = find_loop_points(
+        frames,
+        sample_rate,
+        Duration::from_millis(503),
+        Duration::from_millis(1497),
+    )
+    .unwrap();
+    let (start, end) = (
+        (start.as_secs_f64() * sample_rate as f64).round() as usize,
+        (end.as_secs_f64() * sample_rate as f64).round() as usize,
+    );
+    assert_eq!(start % 441, 0);
+    assert_eq!((end - start) % 441, 0);
+    assert!(start.abs_diff(22_182) < 441 && end.abs_diff(66_018) < 441);
+
+    assert!(find_loop_points(
+        std::iter::repeat_n(0.0, 1000),
+        sample_rate,
+        Duration::ZERO,
+        Duration::from_millis(10)
+    )
+    .is_none());
+}
+
+#[test]
+fn loudness_ignores_silence() {
+    // a full scale square wave has a mean square of 1, so 0 dB
+    let loud = [1.0f32, -1.0].repeat(44_100);
+    let loudness = measure_loudness(loud.iter().copied(), 1, 44_100).unwrap();
+    assert!(loudness.abs() < 1e-3);
+    // a second of silence after it doesn't make it any quieter
+    let with_silence = loud.iter().copied().chain(std::iter::repeat_n(0.0, 88_200));
+    let loudness = measure_loudness(with_silence, 1, 44_100).unwrap();
+    assert!(loudness.abs() < 1e-3);
+    assert!(measure_loudness(std::iter::repeat_n(0.0, 44_100), 1, 44_100).is_none());
+
+    assert!((normalization_gain(0.0) - 0.1).abs() < 1e-6);
+    assert!((normalization_gain(-60.0) - 10f32.powf(12.0 / 20.0)).abs() < 1e-4);
+}
+
+#[test]
+fn balancing_keeps_relative_differences() {
+    let changes = balance(&[-10.0, -20.0, -30.0], -25.0, true);
+    assert_eq!(changes, vec![-5.0; 3]);
+    let changes = balance(&[-10.0, -20.0, -30.0], -25.0, false);
+    assert_eq!(changes, vec![-15.0, -5.0, 5.0]);
+    assert!(balance(&[], -20.0, true).is_empty());
+}
@@ -0,0 +1,64 @@
+use anyhow::Error;
+use std::{
+    io::BufRead,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::hooks;
+use crate::{run_line, AppState, RespondResult, INTERACTIVE};
+
+// how often is checked whether the sounds are done, once the script has run
+const WAIT_INTERVAL: Duration = Duration::from_millis(200);
+
+// runs the commands in a script one per line, without the prompt, for shell scripts, cron and
+// macros. Stops at the first command that fails, and tells on which line. Returns whether the
+// script quit
+pub fn run(
+    state: &Mutex<AppState>,
+    script: impl BufRead,
+    source: &str,
+    saved: &AtomicBool,
+    background_mutated: &AtomicBool,
+) -> Result<bool, Error> {
+    // there is nobody to answer prompts
+    INTERACTIVE.set(false);
+    let mut has_been_saved = true;
+    for (number, line) in script.lines().enumerate() {
+        let line =
+            line.map_err(|err| Error::msg(format!("error: could not read {source}. {err}")))?;
+        let result = run_line(state, line.trim(), has_been_saved)
+            .map_err(|err| Error::msg(format!("{source}, line {}: {err}", number + 1)))?;
+        let RespondResult {
+            mutated,
+            saved: was_saved,
+            quit,
+        } = result;
+        has_been_saved = (has_been_saved || was_saved) && !mutated;
+        if background_mutated.swap(false, Ordering::SeqCst) {
+            has_been_saved = false;
+        }
+        saved.store(has_been_saved, Ordering::SeqCst);
+        if quit || hooks::take_quit_request() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// a script that starts sounds would be pointless if they stopped right away, so troubadour
+// keeps going until they have all ended, and the scheduled commands have run. Sounds that loop
+// keep it going until it is stopped, or a scheduled command quits
+pub fn wait_for_sounds(state: &Arc<Mutex<AppState>>) {
+    let is_playing = || {
+        let state = state.lock().unwrap();
+        state.players.values().any(|player| player.get_is_playing()) || !state.schedule.is_empty()
+    };
+    while is_playing() && !hooks::take_quit_request() {
+        thread::sleep(WAIT_INTERVAL);
+    }
+}
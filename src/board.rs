@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use crate::AppState;
+
+// every group gets the next colour, and the top level gets none
+const COLOURS: [&str; 6] = ["36", "33", "35", "32", "34", "31"];
+
+// the hotkeys and MIDI controls whose command names a sound or a group, by that name
+pub fn bound_keys(state: &AppState) -> HashMap<&str, Vec<String>> {
+    let hotkeys = state
+        .hotkeys
+        .bindings()
+        .iter()
+        .map(|(keys, command)| (keys.to_string(), command));
+    let controls = state
+        .midi
+        .bindings
+        .iter()
+        .map(|binding| (binding.to_string(), &binding.command));
+    let mut keys: HashMap<&str, Vec<String>> = HashMap::new();
+    for (label, command) in hotkeys.chain(controls) {
+        for word in shlex::split(command).unwrap_or_default() {
+            let name = state
+                .players
+                .get_key_value(&word)
+                .map(|(id, _)| id)
+                .or_else(|| state.groups.get_key_value(&word).map(|(name, _)| name));
+            if let Some(name) = name {
+                let labels = keys.entry(name.as_str()).or_default();
+                if !labels.contains(&label) {
+                    labels.push(label.clone());
+                }
+            }
+        }
+    }
+    keys
+}
+
+// the soundscape as a soundboard: a section per group, with what is playing in it and the keys
+// that trigger it
+pub fn render(state: &AppState, colour: bool) -> Vec<String> {
+    let keys = bound_keys(state);
+    let name_width = state
+        .players
+        .keys()
+        .map(|id| id.chars().count())
+        .max()
+        .unwrap_or_default();
+    let labels = |name: &str| match keys.get(name) {
+        Some(labels) => format!("  [{}]", labels.join(", ")),
+        None => String::new(),
+    };
+    let paint = |text: String, code: Option<&str>| match code {
+        Some(code) if colour => format!("\x1b[{code}m{text}\x1b[0m"),
+        _ => text,
+    };
+
+    let sections = [(None, &state.top_group)]
+        .into_iter()
+        .filter(|(_, members)| !members.is_empty())
+        .chain(
+            state
+                .groups
+                .iter()
+                .map(|(name, members)| (Some(name), members)),
+        );
+    let mut lines = Vec::new();
+    let mut next_colour = COLOURS.iter().cycle();
+    for (group, members) in sections {
+        let code = group.map(|_| *next_colour.next().unwrap());
+        let title = match group {
+            Some(name) => format!("{name}{}", labels(name)),
+            None => "top level".to_string(),
+        };
+        lines.push(paint(title, code));
+        for id in members {
+            let player = &state.players[id];
+            let status = if player.get_is_playing() {
+                "▶"
+            } else if player.get_is_paused() {
+                "‖"
+            } else {
+                "·"
+            };
+            lines.push(paint(
+                format!(
+                    "  {status} {id:name_width$} {:>3}%{}",
+                    player.get_heard_volume(),
+                    labels(id)
+                ),
+                code,
+            ));
+        }
+    }
+    if lines.is_empty() {
+        lines.push("There are no sounds yet.".to_string());
+    }
+    lines
+}
+
+#[test]
+fn groups_get_a_section_with_their_keys() {
+    use crate::midi::{Binding, ControlKind};
+    use crate::player::Player;
+    use std::time::Duration;
+
+    let dir = crate::testing::TempDir::new("board");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(30));
+    let mut state = crate::testing::app_state();
+    assert_eq!(render(&state, false), ["There are no sounds yet."]);
+    for (id, group) in [
+        ("rain", Some("forest")),
+        ("owl", Some("forest")),
+        ("bell", None),
+    ] {
+        let mut player = Player::new(tone.clone(), id.to_string(), &state.mixer).unwrap();
+        player.group = group.map(str::to_string);
+        state.players.insert(id.to_string(), player);
+        match group {
+            Some(group) => {
+                state
+                    .groups
+                    .entry(group.to_string())
+                    .or_default()
+                    .insert(id.to_string());
+            }
+            None => {
+                state.top_group.insert(id.to_string());
+            }
+        }
+    }
+    state.players.get_mut("rain").unwrap().play().unwrap();
+    state.midi.bindings.push(Binding {
+        kind: ControlKind::Note,
+        number: 36,
+        channel: None,
+        command: "play forest".to_string(),
+    });
+    assert_eq!(
+        render(&state, false),
+        [
+            "top level",
+            "  · bell 100%",
+            "forest  [note 36]",
+            "  ▶ rain 100%",
+            "  · owl  100%",
+        ]
+    );
+    // the sounds of a group are painted in its colour, the top level isn't
+    let painted = render(&state, true);
+    assert_eq!(painted[0], "top level");
+    assert_eq!(painted[2], "\x1b[36mforest  [note 36]\x1b[0m");
+    assert_eq!(painted[3], "\x1b[36m  ▶ rain 100%\x1b[0m");
+}
@@ -0,0 +1,216 @@
+use anyhow::Error;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::config;
+use crate::player::decode_error;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+// a hash that stays the same between versions of the compiler, unlike the one of std
+pub fn fnv(bytes: &[u8]) -> u64 {
+    fnv_extend(FNV_OFFSET, bytes)
+}
+
+fn fnv_extend(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// what a sound was like when it was decoded into the cache, and the name of its copy
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    modified: Option<SystemTime>,
+    name: String,
+}
+
+// decoded sounds are kept as plain wav files, which open and seek without any decoding work. They
+// are named after what is in the sound, so a copy is never used for a file that changed. Hashing
+// all of a sound takes a while, so the index remembers the size and the time of the last change
+// that each name was found for, and only a file that doesn't match them any more is hashed again
+struct Index {
+    path: PathBuf,
+    sounds: HashMap<PathBuf, Entry>,
+    changed: bool,
+}
+
+impl Index {
+    // a missing or broken index is an empty one. The copies it names are found again on the next
+    // build
+    fn open(dir: &Path) -> Self {
+        let path = dir.join("index.json");
+        let sounds = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            sounds,
+            changed: false,
+        }
+    }
+
+    fn persist(&self) -> io::Result<()> {
+        fs::write(&self.path, serde_json::to_string(&self.sounds)?)
+    }
+
+    // the name of the copy of a sound, or None when it was never decoded. With hash_new, a sound
+    // that was never decoded or that changed is hashed to find its name
+    fn name(&mut self, media: &Path, hash_new: bool) -> Option<String> {
+        let path = fs::canonicalize(media).ok()?;
+        let metadata = fs::metadata(&path).ok()?;
+        let (size, modified) = (metadata.len(), metadata.modified().ok());
+        let known = self.sounds.get(&path);
+        if let Some(entry) = known.filter(|entry| entry.size == size && entry.modified == modified)
+        {
+            return Some(entry.name.clone());
+        }
+        if known.is_none() && !hash_new {
+            return None;
+        }
+        let name = format!("{:016x}.wav", hash_file(&path).ok()?);
+        // a file that was only touched keeps its copy. One that changed only has a copy once it
+        // is built
+        if !hash_new && known.is_some_and(|entry| entry.name != name) {
+            return None;
+        }
+        let entry = Entry {
+            size,
+            modified,
+            name: name.clone(),
+        };
+        self.sounds.insert(path, entry);
+        self.changed = true;
+        Some(name)
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let mut file = BufReader::new(fs::File::open(path)?);
+    let mut buffer = vec![0; 1 << 16];
+    let mut hash = FNV_OFFSET;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hash);
+        }
+        hash = fnv_extend(hash, &buffer[..read]);
+    }
+}
+
+// the decoded copy of a sound, if the cache has one
+pub fn lookup(media: &Path) -> Option<PathBuf> {
+    let dir = config::cache_dir().filter(|dir| dir.is_dir())?;
+    let mut index = Index::open(&dir);
+    let cached = dir.join(index.name(media, false)?);
+    // a sound that was touched without changing is matched without hashing it from now on. When
+    // that can't be written down, it is just hashed again next time
+    if index.changed {
+        let _ = index.persist();
+    }
+    cached.is_file().then_some(cached)
+}
+
+// decodes a sound into the cache. Returns false when it was there already
+pub fn build(media: &Path) -> Result<bool, Error> {
+    let dir = config::cache_dir()
+        .ok_or_else(|| Error::msg("error: there is no folder to keep the cache in."))?;
+    let error = |err: hound::Error| {
+        Error::msg(format!(
+            "error: could not write to the cache in {}. {err}",
+            dir.display()
+        ))
+    };
+    fs::create_dir_all(&dir).map_err(|err| error(err.into()))?;
+    let mut index = Index::open(&dir);
+    let name = index
+        .name(media, true)
+        .ok_or_else(|| Error::msg(format!("error: could not read {}.", media.display())))?;
+    let cached = dir.join(name);
+    if index.changed {
+        index.persist().map_err(|err| error(err.into()))?;
+    }
+    if cached.is_file() {
+        return Ok(false);
+    }
+    let file = fs::File::open(media)
+        .map_err(|err| Error::msg(format!("error: could not read {}. {err}", media.display())))?;
+    let decoder = Decoder::new(BufReader::new(file)).map_err(|_| decode_error(media, "decode"))?;
+    let spec = WavSpec {
+        channels: decoder.channels(),
+        sample_rate: decoder.sample_rate(),
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    // written under another name first, so a build that is cut off doesn't leave half a sound
+    let partial = cached.with_extension("partial");
+    let mut writer = WavWriter::create(&partial, spec).map_err(error)?;
+    for sample in decoder.convert_samples::<i16>() {
+        writer.write_sample(sample).map_err(error)?;
+    }
+    writer.finalize().map_err(error)?;
+    fs::rename(&partial, &cached).map_err(|err| error(err.into()))?;
+    Ok(true)
+}
+
+// removes every decoded sound and the index of them, and returns how many bytes that freed
+pub fn clear() -> Result<u64, Error> {
+    let Some(dir) = config::cache_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(0);
+    };
+    let mut freed = 0;
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext == "wav" || ext == "partial" || ext == "json")
+        {
+            freed += entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(freed)
+}
+
+#[test]
+fn a_changed_file_gets_a_new_name() {
+    let dir = crate::testing::TempDir::new("cache");
+    let path = dir.join("rain.ogg");
+    fs::write(&path, b"one").unwrap();
+    let mut index = Index::open(&dir.join("cache"));
+    assert_eq!(index.name(&path, false), None);
+    let before = index.name(&path, true).unwrap();
+    assert!(index.changed);
+    // a file that matches the index isn't hashed
+    let rename = |index: &mut Index, name: &str| {
+        index.sounds.values_mut().next().unwrap().name = name.to_string();
+    };
+    rename(&mut index, "unhashed.wav");
+    assert_eq!(index.name(&path, false).unwrap(), "unhashed.wav");
+    rename(&mut index, &before);
+    // touching it without changing it keeps its copy
+    let touched = SystemTime::now() - std::time::Duration::from_secs(60);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(touched)
+        .unwrap();
+    assert_eq!(index.name(&path, false), Some(before.clone()));
+    fs::write(&path, b"two").unwrap();
+    assert_eq!(index.name(&path, false), None);
+    assert_ne!(index.name(&path, true), Some(before));
+    fs::remove_file(&path).unwrap();
+    assert_eq!(index.name(&path, true), None);
+    assert_eq!(fnv(b""), 0xcbf29ce484222325);
+}
@@ -0,0 +1,58 @@
+use anyhow::Error;
+use arboard::Clipboard;
+use std::path::PathBuf;
+
+use crate::http::percent_decode;
+
+pub fn read() -> Result<String, Error> {
+    Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| Error::msg(format!("error: could not read the clipboard. {err}")))
+}
+
+// file managers copy paths in all kinds of ways: quoted, as file:// URLs, or several on a line
+// each. Only the first one is used
+pub fn to_path(text: &str) -> Result<PathBuf, Error> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .ok_or(Error::msg("error: the clipboard is empty."))?;
+    let line = line
+        .strip_prefix(['"', '\''])
+        .and_then(|line| line.strip_suffix(['"', '\'']))
+        .unwrap_or(line);
+    if let Some(path) = line.strip_prefix("file://") {
+        // file://host/path is rare enough to not bother with the host
+        let path = path.strip_prefix("localhost").unwrap_or(path);
+        return percent_decode(path)
+            .map(PathBuf::from)
+            .ok_or(Error::msg(format!(
+                "error: {line} is not a valid file URL."
+            )));
+    }
+    if line.starts_with("http://") || line.starts_with("https://") {
+        return Err(Error::msg(
+            "error: the clipboard holds a web address. Download the sound first, and copy the path to the file.",
+        ));
+    }
+    Ok(PathBuf::from(line))
+}
+
+#[test]
+fn clipboard_text_becomes_a_path() {
+    assert_eq!(
+        to_path("  /sounds/rain.ogg\n").unwrap(),
+        PathBuf::from("/sounds/rain.ogg")
+    );
+    assert_eq!(
+        to_path("'/sounds/night rain.ogg'").unwrap(),
+        PathBuf::from("/sounds/night rain.ogg")
+    );
+    assert_eq!(
+        to_path("file:///sounds/night%20rain+wind.ogg\nfile:///sounds/other.ogg").unwrap(),
+        PathBuf::from("/sounds/night rain+wind.ogg")
+    );
+    assert!(to_path("https://example.com/rain.ogg").is_err());
+    assert!(to_path(" \n").is_err());
+}
@@ -0,0 +1,601 @@
+use anyhow::Error;
+use clap::{Arg, Command, CommandFactory};
+use indexmap::IndexMap;
+use rustyline::{
+    completion::{Completer, FilenameCompleter, Pair},
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::{ValidationContext, ValidationResult, Validator},
+    Context, Helper,
+};
+use std::{
+    borrow::Cow,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::fuzzy;
+use crate::{AppState, Commands};
+
+// the prompt doesn't wait longer than this for the soundscape, which is locked while a command
+// asks a question. Completing names is not worth hanging the prompt over
+const LOCK_ATTEMPTS: usize = 20;
+const LOCK_RETRY: Duration = Duration::from_millis(2);
+
+// how the prompt colours a line, as ANSI codes
+const COMMAND_COLOUR: &str = "1";
+const FLAG_COLOUR: &str = "36";
+const NAME_COLOUR: &str = "33";
+// underlined in red
+const UNKNOWN_COLOUR: &str = "4;31";
+
+// the names that can be completed
+#[derive(Default)]
+pub struct Names {
+    pub players: Vec<String>,
+    pub groups: Vec<String>,
+    pub scenes: Vec<String>,
+    // the players with each tag
+    pub tags: IndexMap<String, Vec<String>>,
+}
+
+impl Names {
+    pub fn of(state: &AppState) -> Self {
+        let mut tags: IndexMap<String, Vec<String>> = IndexMap::new();
+        for id in state.ordered_ids() {
+            for tag in &state.players[id].tags {
+                tags.entry(tag.clone()).or_default().push(id.clone());
+            }
+        }
+        Self {
+            players: state.ordered_ids().cloned().collect(),
+            groups: state.groups.keys().cloned().collect(),
+            scenes: state.scenes.keys().cloned().collect(),
+            tags,
+        }
+    }
+
+    // the players that a word stands for, when it is a pattern like forest* or a tag like @battle
+    pub fn selected_by(&self, word: &str) -> Option<Vec<&str>> {
+        if let Some(tag) = word.strip_prefix('@') {
+            let tagged = self.tags.get(tag).map(|ids| ids.iter().map(String::as_str));
+            return Some(tagged.into_iter().flatten().collect());
+        }
+        fuzzy::is_pattern(word).then(|| {
+            self.players
+                .iter()
+                .filter(|id| fuzzy::matches_pattern(word, id))
+                .map(String::as_str)
+                .collect()
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Completion {
+    // the word that starts at this byte can be replaced by any of these
+    Words(usize, Vec<String>),
+    // the word is a file path
+    Path,
+}
+
+// the words of the line, with where they start. The last one is the word that is being typed,
+// which is empty right after a space. Quotes are left in, but spaces inside them don't split
+fn split(line: &str) -> Vec<(usize, String)> {
+    let mut words = vec![(0, String::new())];
+    let mut quote = None;
+    for (index, char) in line.char_indices() {
+        match (quote, char) {
+            (None, ' ' | '\t') => {
+                if !words.last().unwrap().1.is_empty() {
+                    words.push((index + 1, String::new()));
+                } else {
+                    words.last_mut().unwrap().0 = index + 1;
+                }
+                continue;
+            }
+            (None, '"' | '\'') => quote = Some(char),
+            (Some(open), _) if open == char => quote = None,
+            _ => {}
+        }
+        words.last_mut().unwrap().1.push(char);
+    }
+    words
+}
+
+fn unquote(word: &str) -> String {
+    shlex::split(word)
+        .and_then(|words| words.into_iter().next())
+        .unwrap_or_else(|| word.trim_start_matches(['"', '\'']).to_string())
+}
+
+fn matching<'a>(options: impl IntoIterator<Item = &'a str>, typed: &str) -> Vec<String> {
+    let typed = unquote(typed);
+    options
+        .into_iter()
+        .filter(|option| option.starts_with(&typed))
+        .map(|option| {
+            shlex::try_quote(option)
+                .map(|quoted| quoted.into_owned())
+                .unwrap_or_else(|_| option.to_string())
+        })
+        .collect()
+}
+
+fn find_flag<'a>(command: &'a Command, word: &str) -> Option<&'a Arg> {
+    command.get_arguments().find(
+        |arg| match (word.strip_prefix("--"), word.strip_prefix('-')) {
+            (Some(long), _) => arg.get_long() == Some(long),
+            (None, Some(short)) => {
+                let mut chars = short.chars();
+                chars
+                    .next()
+                    .is_some_and(|short| arg.get_short() == Some(short))
+                    && chars.next().is_none()
+            }
+            _ => false,
+        },
+    )
+}
+
+// what goes in an argument of a command
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NameKind {
+    Player,
+    Group,
+    Scene,
+    Path,
+}
+
+fn kind_of(command: &Command, arg: &Arg) -> Option<NameKind> {
+    match (command.get_name(), arg.get_id().as_str()) {
+        (_, "path" | "out" | "folder") => Some(NameKind::Path),
+        (_, "groups" | "group" | "when" | "target") => Some(NameKind::Group),
+        (_, "ids" | "id" | "leader") => Some(NameKind::Player),
+        ("switch" | "volume", "name") => Some(NameKind::Scene),
+        _ => None,
+    }
+}
+
+// the command that the words are for, like scene switch, and the words after it
+fn find_command<'c, 'w>(root: &'c Command, words: &[&'w str]) -> (&'c Command, Vec<&'w str>) {
+    let mut command = root;
+    let mut rest = words.iter().copied().peekable();
+    while let Some(sub) = rest.peek().and_then(|word| command.find_subcommand(word)) {
+        command = sub;
+        rest.next();
+    }
+    (command, rest.collect())
+}
+
+fn takes_many(arg: &Arg) -> bool {
+    arg.get_num_args()
+        .is_some_and(|range| range.max_values() > 1)
+}
+
+// calls back with the index of every value in the words, and the argument it is for. Returns the
+// argument that a word after them would be for
+fn walk<'c>(
+    command: &'c Command,
+    words: &[&str],
+    mut value: impl FnMut(usize, Option<&'c Arg>),
+) -> Option<&'c Arg> {
+    // the option that the next word is a value of, or else the positional argument that is up.
+    // A positional argument that takes one value passes on to the next one
+    let mut current = None;
+    let mut positionals = command.get_positionals().peekable();
+    for (index, word) in words.iter().enumerate() {
+        if word.starts_with('-') {
+            current = find_flag(command, word).filter(|arg| arg.get_action().takes_values());
+            continue;
+        }
+        match current {
+            Some(arg) => {
+                value(index, Some(arg));
+                if !takes_many(arg) {
+                    current = None;
+                }
+            }
+            None => {
+                let positional = positionals.peek().copied();
+                value(index, positional);
+                if positional.is_some_and(|arg| !takes_many(arg)) {
+                    positionals.next();
+                }
+            }
+        }
+    }
+    current.or_else(|| positionals.peek().copied())
+}
+
+pub fn complete(root: &Command, line: &str, names: &Names) -> Completion {
+    let mut words = split(line);
+    let (start, typed) = words.pop().unwrap();
+    let words: Vec<&str> = words.iter().map(|(_, word)| word.as_str()).collect();
+    let (command, rest) = find_command(root, &words);
+
+    if command.has_subcommands() && rest.is_empty() {
+        let subcommands = command.get_subcommands().map(Command::get_name);
+        return Completion::Words(start, matching(subcommands, &typed));
+    }
+    if typed.starts_with('-') {
+        let mut flags = Vec::new();
+        for arg in command.get_arguments().filter(|arg| !arg.is_positional()) {
+            flags.extend(arg.get_long().map(|long| format!("--{long}")));
+            flags.extend(arg.get_short().map(|short| format!("-{short}")));
+        }
+        return Completion::Words(start, matching(flags.iter().map(String::as_str), &typed));
+    }
+
+    let arg = walk(command, &rest, |_, _| {});
+    let kind = arg.and_then(|arg| kind_of(command, arg));
+    if kind == Some(NameKind::Player) && typed.starts_with('@') {
+        let tags: Vec<String> = names.tags.keys().map(|tag| format!("@{tag}")).collect();
+        return Completion::Words(start, matching(tags.iter().map(String::as_str), &typed));
+    }
+    let options = match kind {
+        Some(NameKind::Path) => return Completion::Path,
+        Some(NameKind::Player) => &names.players,
+        Some(NameKind::Group) => &names.groups,
+        Some(NameKind::Scene) => &names.scenes,
+        None => return Completion::Words(start, Vec::new()),
+    };
+    Completion::Words(start, matching(options.iter().map(String::as_str), &typed))
+}
+
+// the names of players, groups, scenes and files that a command refers to, like the ones that
+// were written into a cue long before it runs
+pub fn referenced_names(root: &Command, words: &[String]) -> Vec<(NameKind, String)> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let (command, rest) = find_command(root, &words);
+    let mut names = Vec::new();
+    walk(command, &rest, |index, arg| {
+        if let Some(kind) = arg.and_then(|arg| kind_of(command, arg)) {
+            names.push((kind, rest[index].to_string()));
+        }
+    });
+    names
+}
+
+// names of players and groups that are typed up to a point, like tav for tavern, become the whole
+// name, if it is the only one that starts like that
+pub fn expand_prefixes(root: &Command, words: Vec<String>, names: &Names) -> Vec<String> {
+    let plain: Vec<&str> = words.iter().map(String::as_str).collect();
+    let (command, rest) = find_command(root, &plain);
+    let offset = plain.len() - rest.len();
+    let mut expanded = words.clone();
+    walk(command, &rest, |index, arg| {
+        let known = match arg.and_then(|arg| kind_of(command, arg)) {
+            Some(NameKind::Player) => &names.players,
+            Some(NameKind::Group) => &names.groups,
+            _ => return,
+        };
+        let word = rest[index];
+        if known.iter().any(|name| name == word) || word.to_lowercase() == "all" {
+            return;
+        }
+        if let Some(name) = fuzzy::unique_prefix(word, known) {
+            expanded[offset + index] = name.to_string();
+        }
+    });
+    expanded
+}
+
+// patterns like forest* and tags like @battle, where players go, become the players they stand
+// for. A name that is taken as it is, like a player that is really called forest*, stays
+pub fn expand_patterns(
+    root: &Command,
+    words: Vec<String>,
+    names: &Names,
+) -> Result<Vec<String>, Error> {
+    let plain: Vec<&str> = words.iter().map(String::as_str).collect();
+    let (command, rest) = find_command(root, &plain);
+    let offset = plain.len() - rest.len();
+    let mut expanded: Vec<Vec<String>> = words.iter().map(|word| vec![word.clone()]).collect();
+    let mut error = None;
+    walk(command, &rest, |index, arg| {
+        let Some(arg) = arg.filter(|arg| kind_of(command, arg) == Some(NameKind::Player)) else {
+            return;
+        };
+        let word = rest[index];
+        if names.players.iter().any(|name| name == word) {
+            return;
+        }
+        let Some(selected) = names.selected_by(word) else {
+            return;
+        };
+        if selected.is_empty() {
+            error.get_or_insert(match word.strip_prefix('@') {
+                Some(tag) => format!(
+                    "error: no player is tagged {tag}{}",
+                    fuzzy::did_you_mean(tag, names.tags.keys())
+                ),
+                None => format!("error: no player matches {word}"),
+            });
+        } else if selected.len() > 1 && !takes_many(arg) {
+            error.get_or_insert(format!(
+                "error: {word} stands for {} players, but only one goes here",
+                selected.len()
+            ));
+        } else {
+            expanded[offset + index] = selected.into_iter().map(str::to_string).collect();
+        }
+    });
+    match error {
+        Some(error) => Err(Error::msg(error)),
+        None => Ok(expanded.into_iter().flatten().collect()),
+    }
+}
+
+// colours the commands, options and names in a line at the prompt. Names of players, groups and
+// scenes that don't exist are underlined, so that a typo shows up before enter is hit
+pub fn highlight(root: &Command, line: &str, names: &Names) -> String {
+    if line.starts_with('#') {
+        return line.to_string();
+    }
+    let mut words = split(line);
+    if words.last().is_some_and(|(_, word)| word.is_empty()) {
+        words.pop();
+    }
+    let plain: Vec<&str> = words.iter().map(|(_, word)| word.as_str()).collect();
+    let (command, rest) = find_command(root, &plain);
+    let offset = plain.len() - rest.len();
+
+    let mut colours = vec![None; words.len()];
+    colours[..offset].fill(Some(COMMAND_COLOUR));
+    for (index, word) in rest.iter().enumerate() {
+        if word.starts_with('-') && find_flag(command, word).is_some() {
+            colours[offset + index] = Some(FLAG_COLOUR);
+        }
+    }
+    walk(command, &rest, |index, arg| {
+        let known = match arg.and_then(|arg| kind_of(command, arg)) {
+            Some(NameKind::Player) => &names.players,
+            Some(NameKind::Group) => &names.groups,
+            Some(NameKind::Scene) => &names.scenes,
+            Some(NameKind::Path) | None => return,
+        };
+        let name = unquote(rest[index]);
+        let is_player = arg.is_some_and(|arg| kind_of(command, arg) == Some(NameKind::Player));
+        let exists = known.contains(&name)
+            || (is_player
+                && (name.to_lowercase() == "all"
+                    || names
+                        .selected_by(&name)
+                        .is_some_and(|selected| !selected.is_empty())));
+        colours[offset + index] = Some(if exists { NAME_COLOUR } else { UNKNOWN_COLOUR });
+    });
+
+    let mut highlighted = String::new();
+    let mut end = 0;
+    for ((start, word), colour) in words.iter().zip(colours) {
+        highlighted.push_str(&line[end..*start]);
+        match colour {
+            Some(code) => highlighted.push_str(&format!("\x1b[{code}m{word}\x1b[0m")),
+            None => highlighted.push_str(word),
+        }
+        end = start + word.len();
+    }
+    highlighted.push_str(&line[end..]);
+    highlighted
+}
+
+// completes commands, their options and the names of players, groups and scenes at the prompt,
+// and file paths where a file goes
+pub struct CommandHelper {
+    state: Arc<Mutex<AppState>>,
+    commands: Command,
+    files: FilenameCompleter,
+}
+
+impl CommandHelper {
+    pub fn new(state: Arc<Mutex<AppState>>) -> Self {
+        Self {
+            state,
+            commands: Commands::command(),
+            files: FilenameCompleter::new(),
+        }
+    }
+
+    fn names(&self) -> Names {
+        for _ in 0..LOCK_ATTEMPTS {
+            if let Ok(state) = self.state.try_lock() {
+                return Names::of(&state);
+            }
+            thread::sleep(LOCK_RETRY);
+        }
+        Names::default()
+    }
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        match complete(&self.commands, &line[..pos], &self.names()) {
+            Completion::Path => self.files.complete(line, pos, ctx),
+            Completion::Words(start, words) => Ok((
+                start,
+                words
+                    .into_iter()
+                    .map(|word| Pair {
+                        display: word.clone(),
+                        replacement: word,
+                    })
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+}
+
+impl Highlighter for CommandHelper {
+    // runs on every key, so it doesn't wait for the soundscape when it is locked, like while a
+    // command asks a question
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        match self.state.try_lock() {
+            Ok(state) if state.config.colours() => {
+                Cow::Owned(highlight(&self.commands, line, &Names::of(&state)))
+            }
+            Ok(_) => Cow::Borrowed(line),
+            Err(_) => Cow::Borrowed(line),
+        }
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _forced: bool) -> bool {
+        true
+    }
+}
+
+impl Validator for CommandHelper {
+    // a line with a quote that isn't closed can't be run, so it is better fixed right away
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(match shlex::split(ctx.input()) {
+            Some(_) => ValidationResult::Valid(None),
+            None => ValidationResult::Invalid(Some("  (a quote isn't closed)".to_string())),
+        })
+    }
+}
+
+impl Helper for CommandHelper {}
+
+#[test]
+fn completes_commands_options_and_names() {
+    let names = Names {
+        players: vec![
+            "big rain".to_string(),
+            "bells".to_string(),
+            "owl".to_string(),
+        ],
+        groups: vec!["forest".to_string()],
+        scenes: vec!["night".to_string()],
+        tags: IndexMap::from([("birds".to_string(), vec!["owl".to_string()])]),
+    };
+    let root = Commands::command();
+    let complete = |line: &str| complete(&root, line, &names);
+    let words = |start, words: &[&str]| {
+        Completion::Words(start, words.iter().map(|word| word.to_string()).collect())
+    };
+
+    assert_eq!(complete("pla"), words(0, &["play", "playlist"]));
+    assert_eq!(complete("play b"), words(5, &["'big rain'", "bells"]));
+    assert_eq!(complete("play 'big"), words(5, &["'big rain'"]));
+    assert_eq!(complete("play owl -g f"), words(12, &["forest"]));
+    assert_eq!(complete("play -g forest o"), words(15, &["owl"]));
+    assert_eq!(complete("volume owl --v"), words(11, &["--volume"]));
+    assert_eq!(complete("scene sw"), words(6, &["switch"]));
+    assert_eq!(complete("scene switch n"), words(13, &["night"]));
+    assert_eq!(complete("add -n owl -p ./so"), Completion::Path);
+    assert_eq!(complete("volume owl -v 5"), words(14, &[]));
+    assert_eq!(complete("play @b"), words(5, &["@birds"]));
+
+    assert_eq!(
+        referenced_names(
+            &root,
+            &shlex::split("play owl 'big rain' -g forest -g 'deep woods'").unwrap()
+        ),
+        [
+            (NameKind::Player, "owl".to_string()),
+            (NameKind::Player, "big rain".to_string()),
+            (NameKind::Group, "forest".to_string()),
+            (NameKind::Group, "deep woods".to_string()),
+        ]
+    );
+    assert_eq!(
+        referenced_names(&root, &shlex::split("scene switch night -f 5s").unwrap()),
+        [(NameKind::Scene, "night".to_string())]
+    );
+}
+
+#[test]
+fn expands_unambiguous_prefixes() {
+    let names = Names {
+        players: vec![
+            "tavern".to_string(),
+            "tavern fire".to_string(),
+            "owl".to_string(),
+        ],
+        groups: vec!["forest".to_string()],
+        scenes: vec!["tavern night".to_string()],
+        ..Names::default()
+    };
+    let root = Commands::command();
+    let expand = |line: &str| expand_prefixes(&root, shlex::split(line).unwrap(), &names).join(" ");
+    assert_eq!(expand("play ow -g for"), "play owl -g forest");
+    // tav could be either tavern, and tavern itself is taken as it is
+    assert_eq!(expand("play tav tavern"), "play tav tavern");
+    // only names of players and groups are expanded
+    assert_eq!(expand("scene switch tav"), "scene switch tav");
+    assert_eq!(expand("volume ow -v 5"), "volume owl -v 5");
+}
+
+#[test]
+fn expands_patterns_and_tags() {
+    let names = Names {
+        players: vec![
+            "forest birds".to_string(),
+            "forest wind".to_string(),
+            "horn".to_string(),
+        ],
+        tags: IndexMap::from([("battle".to_string(), vec!["horn".to_string()])]),
+        ..Names::default()
+    };
+    let root = Commands::command();
+    let expand = |line: &str| {
+        expand_patterns(&root, shlex::split(line).unwrap(), &names)
+            .map(|words| words.join("|"))
+            .map_err(|err| err.to_string())
+    };
+    assert_eq!(
+        expand("play forest* @battle -g forest*"),
+        Ok("play|forest birds|forest wind|horn|-g|forest*".to_string())
+    );
+    assert_eq!(expand("play horn"), Ok("play|horn".to_string()));
+    assert_eq!(
+        expand("play @batle"),
+        Err("error: no player is tagged batle. Did you mean battle?".to_string())
+    );
+    // only the first argument of tag add is a player
+    assert_eq!(
+        expand("tag add forest?birds @battle"),
+        Ok("tag|add|forest birds|@battle".to_string())
+    );
+    assert_eq!(
+        expand("mark add forest* chorus 1:00"),
+        Err("error: forest* stands for 2 players, but only one goes here".to_string())
+    );
+}
+
+#[test]
+fn highlights_names_that_dont_exist() {
+    let names = Names {
+        players: vec!["big rain".to_string(), "owl".to_string()],
+        groups: vec!["forest".to_string()],
+        scenes: Vec::new(),
+        tags: IndexMap::from([("birds".to_string(), vec!["owl".to_string()])]),
+    };
+    let root = Commands::command();
+    let highlight = |line: &str| highlight(&root, line, &names);
+    assert_eq!(
+        highlight("play 'big rain'  owll -g forest"),
+        "\x1b[1mplay\x1b[0m \x1b[33m'big rain'\x1b[0m  \x1b[4;31mowll\x1b[0m \x1b[36m-g\x1b[0m \x1b[33mforest\x1b[0m"
+    );
+    assert_eq!(
+        highlight("volume all -v 5 "),
+        "\x1b[1mvolume\x1b[0m \x1b[33mall\x1b[0m \x1b[36m-v\x1b[0m 5 "
+    );
+    assert_eq!(highlight("# play owll"), "# play owll");
+    assert_eq!(
+        highlight("play @birds @fish"),
+        "\x1b[1mplay\x1b[0m \x1b[33m@birds\x1b[0m \x1b[4;31m@fish\x1b[0m"
+    );
+}
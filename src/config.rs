@@ -0,0 +1,566 @@
+use anyhow::Error;
+use chrono::NaiveTime;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::events::AppEvent;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    PlayerFinished,
+    SceneActivated,
+}
+
+// runs a command whenever the event happens to the player or scene with this name
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Hook {
+    pub on: HookEvent,
+    pub name: String,
+    pub run: String,
+}
+
+impl Hook {
+    pub fn matches(&self, event: &AppEvent) -> bool {
+        match event {
+            AppEvent::PlayerFinished(name) => {
+                self.on == HookEvent::PlayerFinished && &self.name == name
+            }
+            AppEvent::SceneActivated(name) => {
+                self.on == HookEvent::SceneActivated && &self.name == name
+            }
+            _ => false,
+        }
+    }
+}
+
+// durations are written the same way as on the command line, like "3s" or "1m 30s"
+mod duration_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{}ms", duration.as_millis()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        duration_str::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
+
+// times of day are written like "22:00"
+mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S: Serializer>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveTime, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&text, FORMAT).map_err(serde::de::Error::custom)
+    }
+}
+
+pub const DEFAULT_QUIET_VOLUME: u32 = 40;
+
+fn default_quiet_volume() -> u32 {
+    DEFAULT_QUIET_VOLUME
+}
+
+// caps the master volume between these times, for late sessions with neighbours. The hours can
+// run past midnight
+#[derive(Serialize, Deserialize)]
+pub struct QuietHours {
+    #[serde(with = "time_of_day")]
+    pub from: NaiveTime,
+    #[serde(with = "time_of_day")]
+    pub to: NaiveTime,
+    #[serde(default = "default_quiet_volume")]
+    pub volume: u32,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.from <= self.to {
+            self.from <= time && time < self.to
+        } else {
+            time >= self.from || time < self.to
+        }
+    }
+}
+
+// after the audio device changes, the first sound that starts ramps up from a low level, in case
+// the new device is a lot louder than the old one
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeStart {
+    pub enabled: bool,
+    #[serde(with = "duration_string")]
+    pub ramp: Duration,
+}
+
+impl Default for SafeStart {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ramp: Duration::from_secs(3),
+        }
+    }
+}
+
+// sounds that are dropped into this folder are added automatically
+#[derive(Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub path: PathBuf,
+    #[serde(default = "default_watch_group")]
+    pub group: String,
+}
+
+fn default_watch_group() -> String {
+    "inbox".to_string()
+}
+
+// what happens to players and groups in a save that have the same name as ones that are already
+// there, when a save is added to the current soundscape
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadConflicts {
+    #[default]
+    Ask,
+    Overwrite,
+    Skip,
+    // gives the loaded one a number, like rain-2
+    Rename,
+}
+
+// how long a sound that is still playing takes to fade out when it is removed, so it doesn't cut
+// off with a click
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct RemoveFade(#[serde(with = "duration_string")] pub Duration);
+
+impl Default for RemoveFade {
+    fn default() -> Self {
+        Self(Duration::from_millis(300))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // limits the loudness of the master output, so that stacked sounds can't blow out your ears
+    pub headphone_protect: bool,
+    pub safe_start: SafeStart,
+    pub watch_folder: Option<WatchFolder>,
+    pub quiet_hours: Option<QuietHours>,
+    pub hooks: Vec<Hook>,
+    pub load_conflicts: LoadConflicts,
+    pub remove_fade: RemoveFade,
+    // where the sounds of saves from other machines are, for every soundscape
+    pub remaps: IndexMap<String, String>,
+    // copies sounds that are added to a project into its media folder
+    pub copy_to_project: bool,
+    // takes the start of a name, like tav, for the only player or group whose name starts like that
+    pub match_prefixes: bool,
+    // the fades of sounds that neither set one themselves nor get one from their group
+    #[serde(with = "duration_string")]
+    pub default_fade_in: Duration,
+    #[serde(with = "duration_string")]
+    pub default_fade_out: Duration,
+    // the volume that added sounds start at, in percent
+    pub default_volume: u32,
+    // asks before things that can't be undone, like loading over unsaved changes. Off answers yes
+    // to those, but not to other questions
+    pub confirm: bool,
+    // how often unsaved changes are saved to autosave.json. Zero doesn't autosave
+    #[serde(with = "duration_string")]
+    pub autosave_interval: Duration,
+    // the save that was loaded or saved last, which is kept up to date by troubadour
+    pub last_save: Option<PathBuf>,
+    // colours the prompt and the board. Setting NO_COLOR turns it off too
+    pub colour: bool,
+    // asks at the start whether to open the last save again, like --resume does
+    pub ask_to_resume: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            headphone_protect: false,
+            safe_start: SafeStart::default(),
+            watch_folder: None,
+            quiet_hours: None,
+            hooks: Vec::new(),
+            load_conflicts: LoadConflicts::default(),
+            remove_fade: RemoveFade::default(),
+            remaps: IndexMap::new(),
+            copy_to_project: false,
+            match_prefixes: false,
+            default_fade_in: Duration::ZERO,
+            default_fade_out: Duration::ZERO,
+            default_volume: 100,
+            confirm: true,
+            autosave_interval: Duration::ZERO,
+            last_save: None,
+            colour: true,
+            ask_to_resume: false,
+        }
+    }
+}
+
+// the settings that are only looked at when troubadour starts
+const READ_AT_START: [&str; 4] = [
+    "headphone_protect",
+    "safe_start",
+    "watch_folder",
+    "autosave_interval",
+];
+
+// whether questions are asked, see Config::confirm. It is kept here, because questions are asked
+// where the config can't be reached
+static CONFIRM: AtomicBool = AtomicBool::new(true);
+
+pub fn confirms() -> bool {
+    CONFIRM.load(Ordering::Relaxed)
+}
+
+pub fn set_confirms(confirm: bool) {
+    CONFIRM.store(confirm, Ordering::Relaxed);
+}
+
+// in portable mode, everything is kept in this folder next to the executable instead, so a setup
+// can be carried around on a usb stick
+const PORTABLE_DIR: &str = "troubadour-data";
+
+static PORTABLE: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn use_portable_dir() -> Result<PathBuf, Error> {
+    let exe = env::current_exe().map_err(|err| {
+        Error::msg(format!(
+            "error: could not find out where troubadour is installed. {err}"
+        ))
+    })?;
+    let dir = exe.parent().unwrap_or(&exe).join(PORTABLE_DIR);
+    fs::create_dir_all(&dir).map_err(|err| {
+        Error::msg(format!(
+            "error: could not create the portable folder at {}. {err}",
+            dir.display()
+        ))
+    })?;
+    Ok(PORTABLE.get_or_init(|| dir).clone())
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    // tests don't touch the config of whoever runs them
+    if cfg!(test) {
+        return None;
+    }
+    if let Some(dir) = PORTABLE.get() {
+        return Some(dir.clone());
+    }
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("troubadour"))
+}
+
+// where decoded copies of sounds are kept, see cache. It can be thrown away at any time
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = PORTABLE.get() {
+        return Some(dir.join("cache"));
+    }
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    };
+    base.map(|base| base.join("troubadour"))
+}
+
+// the audio device that was used last time, so that the next session can tell when it changed
+fn last_device_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("last_device"))
+}
+
+pub fn history_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("history"))
+}
+
+pub fn last_device() -> Option<String> {
+    fs::read_to_string(last_device_path()?).ok()
+}
+
+pub fn remember_device(name: &str) {
+    if let Some(path) = last_device_path() {
+        // not being able to remember the device only means that the next start is a safe one
+        let _ = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(path, name));
+    }
+}
+
+// the sounds that were playing when troubadour was closed, and the save they belong to, so --resume
+// can play them again
+#[derive(Serialize, Deserialize)]
+struct LastSession {
+    save: PathBuf,
+    playing: Vec<String>,
+}
+
+fn last_session_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("last_session.toml"))
+}
+
+pub fn remember_playing(save: &Path, playing: Vec<String>) {
+    if let Some(path) = last_session_path() {
+        write_session(&path, save, playing);
+    }
+}
+
+fn write_session(path: &Path, save: &Path, playing: Vec<String>) {
+    let session = LastSession {
+        save: save.to_path_buf(),
+        playing,
+    };
+    if let Ok(text) = toml::to_string(&session) {
+        // not being able to remember them only means they aren't offered when resuming
+        let _ = fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(path, text));
+    }
+}
+
+// the sounds that were playing the last time the save was open
+pub fn playing_in(save: &Path) -> Vec<String> {
+    last_session_path()
+        .map(|path| read_session(&path, save))
+        .unwrap_or_default()
+}
+
+fn read_session(path: &Path, save: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| toml::from_str::<LastSession>(&text).ok())
+        .filter(|session| session.save == save)
+        .map(|session| session.playing)
+        .unwrap_or_default()
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    // a missing config file just means that the defaults are used
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let config: Self = toml::from_str(&read(&path)?).map_err(|err| {
+            Error::msg(format!(
+                "error: could not read the config file at {}. {err}",
+                path.display()
+            ))
+        })?;
+        set_confirms(config.confirm);
+        Ok(config)
+    }
+
+    pub fn colours(&self) -> bool {
+        self.colour && env::var_os("NO_COLOR").is_none()
+    }
+
+    // the value of a setting, written the way it is in the config file. Settings can be in a
+    // table, like safe_start.ramp
+    pub fn get(&self, key: &str) -> Option<String> {
+        let mut value = toml::Value::try_from(self).ok()?;
+        for part in key.split('.') {
+            value = value.get(part)?.clone();
+        }
+        Some(match value {
+            toml::Value::Table(table) => toml::to_string(&table).ok()?.trim_end().to_string(),
+            value => value.to_string(),
+        })
+    }
+
+    // changes a setting in the config file, and returns the config as it is now. The rest of the
+    // file is left the way it was written, comments and all
+    pub fn set(key: &str, value: &str) -> Result<Self, Error> {
+        let path = Self::path()
+            .ok_or_else(|| Error::msg("error: there is no folder to keep the config in."))?;
+        let mut document: DocumentMut = read(&path)?.parse().map_err(|err| {
+            Error::msg(format!(
+                "error: could not read the config file at {}. {err}",
+                path.display()
+            ))
+        })?;
+        // values are taken the way they are written in the file, but text doesn't need quotes
+        let value = value
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| value.into());
+        let (tables, last) = match key.rsplit_once('.') {
+            Some((tables, last)) => (tables.split('.').collect(), last),
+            None => (Vec::new(), key),
+        };
+        let mut table = document.as_table_mut();
+        for name in tables {
+            table = table
+                .entry(name)
+                .or_insert_with(|| Item::Table(Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| Error::msg(format!("error: {name} is not a table of settings.")))?;
+        }
+        let written = value.to_string();
+        table.insert(last, Item::Value(value));
+        let config: Self = toml::from_str(&document.to_string()).map_err(|err| {
+            Error::msg(format!("error: {} can't be {key}. {err}", written.trim()))
+        })?;
+        // what isn't a setting is taken without a word, so it is checked by reading it back
+        if config.get(key).is_none() {
+            return Err(Error::msg(format!("error: {key} is not a setting.")));
+        }
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, document.to_string()))
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not save the config file to {}. {err}",
+                    path.display()
+                ))
+            })?;
+        set_confirms(config.confirm);
+        Ok(config)
+    }
+
+    // keeps track of the save that was loaded or saved last
+    pub fn remember_save(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+        let value = toml_edit::Value::from(path.to_string_lossy().as_ref()).to_string();
+        // not being able to remember it only means that it can't be opened again without its path
+        if Self::set("last_save", &value).is_ok() {
+            self.last_save = Some(path);
+        }
+    }
+
+    pub fn read_at_start(key: &str) -> bool {
+        READ_AT_START.contains(&key.split('.').next().unwrap_or(key))
+    }
+}
+
+// a missing file reads as an empty one
+fn read(path: &Path) -> Result<String, Error> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(Error::msg(format!(
+            "error: could not open the config file at {}. {err}",
+            path.display()
+        ))),
+    }
+}
+
+#[test]
+fn parse_hooks() {
+    let config: Config = toml::from_str(
+        r#"
+        [[hooks]]
+        on = "player-finished"
+        name = "intro"
+        run = "play theme"
+
+        [[hooks]]
+        on = "scene-activated"
+        name = "combat"
+        run = "volume -g ambience -v 30"
+        "#,
+    )
+    .unwrap();
+    let finished = AppEvent::PlayerFinished("intro".to_string());
+    let activated = AppEvent::SceneActivated("combat".to_string());
+    assert!(config.hooks[0].matches(&finished));
+    assert!(!config.hooks[0].matches(&activated));
+    assert!(config.hooks[1].matches(&activated));
+    assert!(!config.hooks[1].matches(&AppEvent::SceneActivated("intro".to_string())));
+}
+
+#[test]
+fn parse_safe_start() {
+    assert!(!Config::default().safe_start.enabled);
+    assert_eq!(Config::default().load_conflicts, LoadConflicts::Ask);
+    assert_eq!(Config::default().remove_fade.0, Duration::from_millis(300));
+    assert!(!Config::default().match_prefixes);
+    assert!(Config::default().confirm);
+    assert_eq!(Config::default().default_volume, 100);
+    let config: Config = toml::from_str("default_fade_out = \"2s\"\nconfirm = false").unwrap();
+    assert_eq!(config.default_fade_out, Duration::from_secs(2));
+    assert!(!config.confirm);
+    assert_eq!(config.get("default_fade_out").unwrap(), "\"2000ms\"");
+    assert_eq!(config.get("safe_start.enabled").unwrap(), "false");
+    assert_eq!(config.get("last_save"), None);
+    let config: Config = toml::from_str("remove_fade = \"1s\"").unwrap();
+    assert_eq!(config.remove_fade.0, Duration::from_secs(1));
+    let config: Config = toml::from_str("load_conflicts = \"rename\"").unwrap();
+    assert_eq!(config.load_conflicts, LoadConflicts::Rename);
+    let config: Config = toml::from_str("[remaps]\n'D:\\Sounds' = \"/home/me/sounds\"").unwrap();
+    assert_eq!(config.remaps[r"D:\Sounds"], "/home/me/sounds");
+    let config: Config = toml::from_str(
+        r#"
+        [safe_start]
+        enabled = true
+        ramp = "5s"
+        "#,
+    )
+    .unwrap();
+    assert!(config.safe_start.enabled);
+    assert_eq!(config.safe_start.ramp, Duration::from_secs(5));
+}
+
+#[test]
+fn parse_quiet_hours() {
+    let config: Config = toml::from_str(
+        r#"
+        [quiet_hours]
+        from = "22:30"
+        to = "07:00"
+        "#,
+    )
+    .unwrap();
+    let quiet_hours = config.quiet_hours.unwrap();
+    assert_eq!(quiet_hours.volume, DEFAULT_QUIET_VOLUME);
+    let at = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+    assert!(quiet_hours.contains(at(23, 0)));
+    assert!(quiet_hours.contains(at(3, 0)));
+    assert!(!quiet_hours.contains(at(7, 0)));
+    assert!(!quiet_hours.contains(at(22, 0)));
+    assert!(toml::from_str::<Config>("[quiet_hours]\nfrom = \"late\"\nto = \"07:00\"").is_err());
+}
+
+#[test]
+fn last_sessions_are_restored() {
+    let dir = crate::testing::TempDir::new("last session");
+    let path = dir.join("troubadour/last_session.toml");
+    let (tavern, dungeon) = (dir.join("tavern.json"), dir.join("dungeon.json"));
+    assert!(read_session(&path, &tavern).is_empty());
+    let playing = vec!["rain".to_string(), "big fire".to_string()];
+    write_session(&path, &tavern, playing.clone());
+    assert_eq!(read_session(&path, &tavern), playing);
+    // only the save that was open last time is resumed
+    assert!(read_session(&path, &dungeon).is_empty());
+    write_session(&path, &dungeon, Vec::new());
+    assert!(read_session(&path, &tavern).is_empty());
+}
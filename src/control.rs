@@ -0,0 +1,5 @@
+// sources that are steered from the main thread, like fades, effects and the limiter, look up what
+// they should do behind a lock, and the play head publishes its position behind one. They only do
+// so once every this many samples, to keep the lock out of the hot path. 256 samples is under 3ms
+// of stereo at 44.1kHz, which is too short to hear a setting change in steps
+pub const SAMPLES_PER_UPDATE: usize = 256;
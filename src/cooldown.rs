@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// keeps a sound from being triggered again too soon, like a one-shot on a pad that is hit twice
+// in the heat of the moment
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Cooldown {
+    pub length: Duration,
+    // a trigger during the cooldown plays the sound once it is over, instead of being ignored
+    pub queue: bool,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum Trigger {
+    Play,
+    Queue,
+    Ignore,
+}
+
+impl Cooldown {
+    pub fn left(&self, since_last: Option<Duration>) -> Option<Duration> {
+        since_last
+            .filter(|&since_last| since_last < self.length)
+            .map(|since_last| self.length - since_last)
+    }
+
+    // what a trigger does, given how long ago the last one played the sound
+    pub fn trigger(&self, since_last: Option<Duration>) -> Trigger {
+        match self.left(since_last) {
+            None => Trigger::Play,
+            Some(_) if self.queue => Trigger::Queue,
+            Some(_) => Trigger::Ignore,
+        }
+    }
+}
+
+#[test]
+fn triggers_during_the_cooldown_are_held() {
+    let ignoring = Cooldown {
+        length: Duration::from_secs(2),
+        queue: false,
+    };
+    assert_eq!(ignoring.trigger(None), Trigger::Play);
+    assert_eq!(
+        ignoring.trigger(Some(Duration::from_millis(500))),
+        Trigger::Ignore
+    );
+    assert_eq!(
+        ignoring.left(Some(Duration::from_millis(500))),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(
+        ignoring.trigger(Some(Duration::from_secs(2))),
+        Trigger::Play
+    );
+
+    let queueing = Cooldown {
+        queue: true,
+        ..ignoring
+    };
+    assert_eq!(
+        queueing.trigger(Some(Duration::from_secs(1))),
+        Trigger::Queue
+    );
+    assert_eq!(
+        queueing.trigger(Some(Duration::from_secs(3))),
+        Trigger::Play
+    );
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+// a step of a show, like in theatre cue software: the commands that run together when the
+// operator says go
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Cue {
+    pub commands: Vec<String>,
+}
+
+// the cues in the order they are run. Cues are numbered from 1, like on a script
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CueList {
+    pub cues: Vec<Cue>,
+    // the cue that go runs next, from 0. Where the show is doesn't go in the save
+    #[serde(skip)]
+    next: usize,
+}
+
+impl CueList {
+    // returns the number of the new cue
+    pub fn add(&mut self, cue: Cue) -> usize {
+        self.cues.push(cue);
+        self.cues.len()
+    }
+
+    // the number of the cue that go runs next, None once the last one has run
+    pub fn standby(&self) -> Option<usize> {
+        (self.next < self.cues.len()).then_some(self.next + 1)
+    }
+
+    // the next cue and its number, which is then passed
+    pub fn go(&mut self) -> Option<(usize, Cue)> {
+        let cue = self.cues.get(self.next)?.clone();
+        self.next += 1;
+        Some((self.next, cue))
+    }
+
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.cues.clear();
+        self.next = 0;
+    }
+}
+
+#[test]
+fn go_runs_the_cues_in_order() {
+    let cue = |command: &str| Cue {
+        commands: vec![command.to_string()],
+    };
+    let mut list = CueList::default();
+    assert_eq!(list.add(cue("play storm")), 1);
+    assert_eq!(list.add(cue("stop music")), 2);
+    assert_eq!(list.standby(), Some(1));
+    assert_eq!(list.go(), Some((1, cue("play storm"))));
+    assert_eq!(list.go(), Some((2, cue("stop music"))));
+    assert_eq!(list.standby(), None);
+    assert_eq!(list.go(), None);
+    // cues added after the end are next up
+    list.add(cue("play birds"));
+    assert_eq!(list.standby(), Some(3));
+    list.reset();
+    assert_eq!(list.go(), Some((1, cue("play storm"))));
+}
@@ -0,0 +1,238 @@
+use anyhow::Error;
+use std::{num::NonZeroU64, sync::mpsc::Receiver};
+
+// the bot token is read from here, so that it never ends up in a save or in the config
+#[cfg(feature = "discord")]
+const TOKEN_VARIABLE: &str = "TROUBADOUR_DISCORD_TOKEN";
+
+// streams the master output into a voice channel, so the players of an online campaign hear the
+// same mix as the GM. The bot only logs in when it first joins a channel
+#[cfg(feature = "discord")]
+#[derive(Default)]
+pub struct Discord {
+    bot: Option<bot::Bot>,
+    // the guild and channel the bot is in
+    call: Option<(NonZeroU64, NonZeroU64)>,
+}
+
+#[cfg(feature = "discord")]
+impl Discord {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn channel(&self) -> Option<(NonZeroU64, NonZeroU64)> {
+        self.call
+    }
+
+    // moves the bot to the channel, if it is in another one already
+    pub fn join(
+        &mut self,
+        guild: NonZeroU64,
+        channel: NonZeroU64,
+        mix: Receiver<Vec<f32>>,
+    ) -> Result<(), Error> {
+        self.leave()?;
+        let bot = match &mut self.bot {
+            Some(bot) => bot,
+            None => {
+                let token = std::env::var(TOKEN_VARIABLE).map_err(|_| {
+                    Error::msg(format!(
+                        "error: there is no bot token. Put it in the {TOKEN_VARIABLE} environment variable."
+                    ))
+                })?;
+                self.bot.insert(bot::Bot::start(&token)?)
+            }
+        };
+        bot.join(guild, channel, mix)?;
+        self.call = Some((guild, channel));
+        Ok(())
+    }
+
+    // returns whether the bot was in a channel
+    pub fn leave(&mut self) -> Result<bool, Error> {
+        let (Some(bot), Some((guild, _))) = (&mut self.bot, self.call.take()) else {
+            return Ok(false);
+        };
+        bot.leave(guild)?;
+        Ok(true)
+    }
+}
+
+#[cfg(feature = "discord")]
+mod bot {
+    use anyhow::Error;
+    use serenity::{all::GatewayIntents, Client};
+    use songbird::{
+        input::{core::io::MediaSource, Input, RawAdapter},
+        tracks::TrackHandle,
+        SerenityInit, Songbird,
+    };
+    use std::{
+        io::{self, Read, Seek, SeekFrom},
+        num::NonZeroU64,
+        sync::{
+            mpsc::{Receiver, RecvTimeoutError},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+    use tokio::runtime::Runtime;
+
+    use crate::mixer::{CHANNELS, SAMPLE_RATE};
+    use crate::progress;
+
+    // while nothing plays, there is no audio device, and so nothing comes from the tap. Discord
+    // gets silence in the meantime, so the call doesn't stall
+    const SILENCE_AFTER: Duration = Duration::from_millis(20);
+    const SILENCE: usize = SAMPLE_RATE as usize / 50 * CHANNELS as usize;
+
+    // the master output, as the raw f32 samples that songbird encodes to Opus
+    struct Mix {
+        tap: Mutex<Receiver<Vec<f32>>>,
+        bytes: Vec<u8>,
+        position: usize,
+    }
+
+    impl Read for Mix {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.position == self.bytes.len() {
+                let chunk = match self.tap.lock().unwrap().recv_timeout(SILENCE_AFTER) {
+                    Ok(chunk) => chunk,
+                    Err(RecvTimeoutError::Timeout) => vec![0.0; SILENCE],
+                    // the mixer is gone
+                    Err(RecvTimeoutError::Disconnected) => return Ok(0),
+                };
+                self.bytes = chunk
+                    .iter()
+                    .flat_map(|sample| sample.to_le_bytes())
+                    .collect();
+                self.position = 0;
+            }
+            let count = buf.len().min(self.bytes.len() - self.position);
+            buf[..count].copy_from_slice(&self.bytes[self.position..][..count]);
+            self.position += count;
+            Ok(count)
+        }
+    }
+
+    impl Seek for Mix {
+        fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+    }
+
+    impl MediaSource for Mix {
+        fn is_seekable(&self) -> bool {
+            false
+        }
+
+        fn byte_len(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    // the client runs on its own runtime, next to the prompt
+    pub struct Bot {
+        runtime: Runtime,
+        voice: Arc<Songbird>,
+        track: Option<TrackHandle>,
+    }
+
+    impl Bot {
+        pub fn start(token: &str) -> Result<Self, Error> {
+            let runtime = Runtime::new().map_err(|err| {
+                Error::msg(format!("error: could not start the Discord bot. {err}"))
+            })?;
+            let voice = Songbird::serenity();
+            let builder = Client::builder(
+                token,
+                GatewayIntents::GUILDS | GatewayIntents::GUILD_VOICE_STATES,
+            )
+            .register_songbird_with(voice.clone());
+            let client = runtime.block_on(async { builder.await });
+            let mut client = client.map_err(|err| {
+                Error::msg(format!("error: could not start the Discord bot. {err}"))
+            })?;
+            runtime.spawn(async move {
+                if let Err(err) = client.start().await {
+                    progress::suspend();
+                    println!("\nerror: the Discord bot stopped. {err}");
+                }
+            });
+            Ok(Self {
+                runtime,
+                voice,
+                track: None,
+            })
+        }
+
+        pub fn join(
+            &mut self,
+            guild: NonZeroU64,
+            channel: NonZeroU64,
+            tap: Receiver<Vec<f32>>,
+        ) -> Result<(), Error> {
+            let mix = Mix {
+                tap: Mutex::new(tap),
+                bytes: Vec::new(),
+                position: 0,
+            };
+            let input: Input = RawAdapter::new(mix, SAMPLE_RATE, CHANNELS as u32).into();
+            let track = self.runtime.block_on(async {
+                let call = self.voice.join(guild, channel).await?;
+                let mut call = call.lock().await;
+                Ok::<_, songbird::error::JoinError>(call.play_only_input(input))
+            });
+            let track = track.map_err(|err| {
+                Error::msg(format!(
+                    "error: could not join the voice channel {channel}. {err}"
+                ))
+            })?;
+            self.track = Some(track);
+            Ok(())
+        }
+
+        pub fn leave(&mut self, guild: NonZeroU64) -> Result<(), Error> {
+            // stopping the track drops the tap, so the mixer stops copying the output
+            if let Some(track) = self.track.take() {
+                track.stop().unwrap_or_default();
+            }
+            self.runtime
+                .block_on(self.voice.remove(guild))
+                .map_err(|err| {
+                    Error::msg(format!("error: could not leave the voice channel. {err}"))
+                })
+        }
+    }
+}
+
+// without the discord feature, there is nothing to stream with
+#[cfg(not(feature = "discord"))]
+pub struct Discord;
+
+#[cfg(not(feature = "discord"))]
+impl Discord {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn channel(&self) -> Option<(NonZeroU64, NonZeroU64)> {
+        None
+    }
+
+    pub fn join(
+        &mut self,
+        _: NonZeroU64,
+        _: NonZeroU64,
+        _: Receiver<Vec<f32>>,
+    ) -> Result<(), Error> {
+        Err(Error::msg(
+            "error: this troubadour was built without Discord support. Build it with --features discord.",
+        ))
+    }
+
+    pub fn leave(&mut self) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
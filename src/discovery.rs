@@ -0,0 +1,98 @@
+use anyhow::Error;
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::{fs, net::IpAddr};
+
+use crate::VERSION;
+
+// one service type for all of them. The api in the TXT record tells them apart
+const SERVICE_TYPE: &str = "_troubadour._tcp.local.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Api {
+    // the server from serve, one command per line or JSON-RPC
+    Lines,
+    WebSocket,
+    Http,
+}
+
+impl Api {
+    fn as_str(self) -> &'static str {
+        match self {
+            Api::Lines => "lines",
+            Api::WebSocket => "websocket",
+            Api::Http => "http",
+        }
+    }
+}
+
+fn host_name() -> String {
+    fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "troubadour".to_string())
+}
+
+// endpoints that only listen on this machine can't be reached from the network, so there is no
+// use in telling the network about them. Returns the ones that can be reached, and the apis of
+// those that can't
+pub fn reachable(endpoints: &[(Api, IpAddr, u16)]) -> (Vec<(Api, u16)>, Vec<Api>) {
+    let (local, reachable): (Vec<_>, Vec<_>) =
+        endpoints.iter().partition(|(_, ip, _)| ip.is_loopback());
+    (
+        reachable
+            .iter()
+            .map(|(api, _, port)| (*api, *port))
+            .collect(),
+        local.iter().map(|(api, _, _)| *api).collect(),
+    )
+}
+
+// tells phones and other machines on the network where to find troubadour, over mDNS. The
+// endpoints are advertised for as long as the returned daemon lives
+pub fn advertise(endpoints: &[(Api, IpAddr, u16)]) -> Result<ServiceDaemon, Error> {
+    let daemon = ServiceDaemon::new()
+        .map_err(|err| Error::msg(format!("error: could not start advertising. {err}")))?;
+    let host = host_name();
+    let (reachable, local) = reachable(endpoints);
+    for api in local {
+        println!(
+            "warning: the {} endpoint only listens on this machine, so it is not advertised.",
+            api.as_str()
+        );
+    }
+    for (api, port) in reachable {
+        let name = format!("Troubadour on {host} ({})", api.as_str());
+        let properties = [("api", api.as_str()), ("version", VERSION)];
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &name,
+            &format!("{host}.local."),
+            (),
+            port,
+            &properties[..],
+        )
+        .map(ServiceInfo::enable_addr_auto)
+        .and_then(|service| daemon.register(service))
+        .map_err(|err| Error::msg(format!("error: could not advertise {name}. {err}")))?;
+        println!("Advertising {name} on the network.");
+    }
+    Ok(daemon)
+}
+
+#[test]
+fn only_reachable_endpoints_are_advertised() {
+    let endpoints = [
+        (Api::WebSocket, "127.0.0.1".parse().unwrap(), 7879),
+        (Api::Http, "0.0.0.0".parse().unwrap(), 7880),
+        (Api::Lines, "192.168.1.10".parse().unwrap(), 7878),
+    ];
+    assert_eq!(
+        reachable(&endpoints),
+        (
+            vec![(Api::Http, 7880), (Api::Lines, 7878)],
+            vec![Api::WebSocket]
+        )
+    );
+}
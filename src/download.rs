@@ -0,0 +1,356 @@
+use anyhow::Error;
+use std::{
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Read},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::cache::fnv;
+use crate::config;
+
+// how long a server gets to answer, in seconds
+const CONNECT_TIMEOUT: &str = "20";
+// a download that is slower than this many bytes a second for this many seconds has stalled, and
+// is given up on
+const STALLED_SPEED: &str = "1024";
+const STALLED_TIME: &str = "30";
+// a video that isn't downloaded and turned into an mp3 in this long is given up on
+const VIDEO_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+// how much of a sound has to be in before it is played, so the decoder finds its start
+const HEAD_START: u64 = 256 * 1024;
+
+// the downloads that curl is still writing, by the path they will have when they are done
+static DOWNLOADS: Mutex<Vec<(PathBuf, Arc<Download>)>> = Mutex::new(Vec::new());
+
+// a sound that is still coming in. It is written to a partial file, which is read while it grows,
+// and which gets the name of the sound when it is complete
+pub struct Download {
+    partial: PathBuf,
+    // what went wrong, once it is done
+    finished: Mutex<Option<Result<(), String>>>,
+    changed: Condvar,
+}
+
+impl Download {
+    pub fn is_finished(&self) -> bool {
+        self.finished.lock().unwrap().is_some()
+    }
+
+    // waits a moment for more of the sound, or for the download to be done
+    pub fn wait_for_more(&self) {
+        let finished = self.finished.lock().unwrap();
+        if finished.is_none() {
+            drop(
+                self.changed
+                    .wait_timeout(finished, Duration::from_millis(50))
+                    .unwrap(),
+            );
+        }
+    }
+
+    fn wait_until_finished(&self) -> Result<(), String> {
+        let finished = self
+            .changed
+            .wait_while(self.finished.lock().unwrap(), |finished| finished.is_none())
+            .unwrap();
+        finished.clone().unwrap()
+    }
+
+    fn finish(&self, result: Result<(), String>) {
+        *self.finished.lock().unwrap() = Some(result);
+        self.changed.notify_all();
+    }
+}
+
+// the download of the sound that will be at path, while it is still coming in
+pub fn in_progress(path: &Path) -> Option<Arc<Download>> {
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(downloading, _)| downloading == path)
+        .map(|(_, download)| download.clone())
+}
+
+// where the sound that will be at path can be read right now
+pub fn readable(path: &Path) -> PathBuf {
+    match in_progress(path) {
+        Some(download) => download.partial.clone(),
+        None => path.to_path_buf(),
+    }
+}
+
+// waits until the sound at path is complete, if it is still being downloaded
+pub fn wait(path: &Path) -> Result<(), Error> {
+    match in_progress(path) {
+        Some(download) => download.wait_until_finished().map_err(Error::msg),
+        None => Ok(()),
+    }
+}
+
+// whether a path that was given is a web address, like https://example.com/rain.ogg
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+fn downloads_dir() -> Option<PathBuf> {
+    config::cache_dir().map(|dir| dir.join("downloads"))
+}
+
+// the file that a sound from the web is kept in. It keeps the extension of the address, which is
+// how the format is told
+fn local_path(url: &str) -> Option<PathBuf> {
+    let file_name = url.split(['?', '#']).next()?.rsplit('/').next()?;
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("sound");
+    Some(downloads_dir()?.join(format!("{:016x}.{extension}", fnv(url.as_bytes()))))
+}
+
+// downloads a sound from the web, or finds it where it was downloaded before. curl does the
+// downloading, since it knows about https, redirects and proxies. It returns as soon as enough of
+// the sound is in to start playing it, and the rest comes in while it plays
+pub fn fetch(url: &str) -> Result<PathBuf, Error> {
+    let path = local_path(url)
+        .ok_or_else(|| Error::msg("error: there is no folder to keep downloaded sounds in."))?;
+    if path.is_file() || in_progress(&path).is_some() {
+        return Ok(path);
+    }
+    fs::create_dir_all(path.parent().unwrap()).map_err(|err| {
+        Error::msg(format!(
+            "error: could not save {url} to {}. {err}",
+            path.display()
+        ))
+    })?;
+    // written under another name first, so a download that is cut off doesn't leave half a sound.
+    // It keeps the extension, so its format can be told while it comes in
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let partial = path.with_extension(format!("partial.{extension}"));
+    let mut curl = Command::new("curl")
+        .args(["--fail", "--location", "--silent", "--show-error"])
+        .args(["--connect-timeout", CONNECT_TIMEOUT])
+        .args(["--speed-limit", STALLED_SPEED, "--speed-time", STALLED_TIME])
+        .arg("--output")
+        .arg(&partial)
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                Error::msg("error: sounds are downloaded with curl, which could not be found.")
+            }
+            _ => Error::msg(format!("error: could not run curl. {err}")),
+        })?;
+    let download = Arc::new(Download {
+        partial: partial.clone(),
+        finished: Mutex::new(None),
+        changed: Condvar::new(),
+    });
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .push((path.clone(), download.clone()));
+    let (url_, path_, download_) = (url.to_string(), path.clone(), download.clone());
+    thread::spawn(move || {
+        let mut stderr = String::new();
+        if let Some(mut pipe) = curl.stderr.take() {
+            pipe.read_to_string(&mut stderr).unwrap_or_default();
+        }
+        let result = match curl.wait() {
+            Ok(status) if status.success() => fs::rename(&partial, &path_)
+                .map_err(|err| format!("error: could not save {url_}. {err}")),
+            Ok(_) => Err(format!(
+                "error: could not download {url_}. {}",
+                stderr.trim().trim_start_matches("curl: ")
+            )),
+            Err(err) => Err(format!("error: could not download {url_}. {err}")),
+        };
+        if result.is_err() {
+            fs::remove_file(&partial).ok();
+        }
+        DOWNLOADS
+            .lock()
+            .unwrap()
+            .retain(|(downloading, _)| *downloading != path_);
+        download_.finish(result);
+    });
+    loop {
+        if download.is_finished() {
+            return download
+                .wait_until_finished()
+                .map(|_| path)
+                .map_err(Error::msg);
+        }
+        let size = fs::metadata(&download.partial).map_or(0, |meta| meta.len());
+        if size >= HEAD_START {
+            return Ok(path);
+        }
+        download.wait_for_more();
+    }
+}
+
+// downloads the sound of a video, like one on YouTube, into dir, or into the cache when there is
+// no dir. yt-dlp does the downloading, and turns it into an mp3, since the formats of video sites
+// can't be decoded. A video that was downloaded before isn't downloaded again. Progress is counted
+// in percent
+pub fn fetch_video(
+    url: &str,
+    dir: Option<&Path>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<PathBuf, Error> {
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => downloads_dir()
+            .ok_or_else(|| Error::msg("error: there is no folder to keep downloaded sounds in."))?,
+    };
+    fs::create_dir_all(&dir).map_err(|err| {
+        Error::msg(format!(
+            "error: could not create the folder {}. {err}",
+            dir.display()
+        ))
+    })?;
+    let mut yt_dlp = Command::new("yt-dlp")
+        .args(["--no-playlist", "--quiet", "--progress", "--newline"])
+        .args([
+            "--progress-template",
+            "download:progress %(progress._percent_str)s",
+        ])
+        .args(["--socket-timeout", CONNECT_TIMEOUT])
+        .args([
+            "--extract-audio",
+            "--audio-format",
+            "mp3",
+            "--embed-metadata",
+        ])
+        .args(["--print", "after_move:filepath", "--paths"])
+        .arg(&dir)
+        .args(["--output", "%(title)s [%(id)s].%(ext)s", "--"])
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => Error::msg(
+                "error: the sound of videos is downloaded with yt-dlp, which could not be found.",
+            ),
+            _ => Error::msg(format!("error: could not run yt-dlp. {err}")),
+        })?;
+    // both outputs are read at once, so neither fills up while the other is waited on
+    let (sender, lines) = mpsc::channel();
+    let outputs: [Box<dyn Read + Send>; 2] = [
+        Box::new(yt_dlp.stdout.take().unwrap()),
+        Box::new(yt_dlp.stderr.take().unwrap()),
+    ];
+    for (is_error, output) in outputs.into_iter().enumerate() {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                sender.send((is_error == 1, line)).unwrap_or_default();
+            }
+        });
+    }
+    drop(sender);
+    let started = Instant::now();
+    let (mut path, mut errors) = (None, Vec::new());
+    loop {
+        let (is_error, line) = match lines.recv_timeout(Duration::from_millis(200)) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) if started.elapsed() < VIDEO_TIMEOUT => continue,
+            Err(RecvTimeoutError::Timeout) => {
+                yt_dlp.kill().unwrap_or_default();
+                yt_dlp.wait().ok();
+                return Err(Error::msg(format!(
+                    "error: gave up on downloading {url}, after {} minutes.",
+                    VIDEO_TIMEOUT.as_secs() / 60
+                )));
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if let Some(percent) = line.strip_prefix("progress ") {
+            let percent = percent.trim().trim_end_matches('%').parse::<f32>();
+            progress(percent.unwrap_or_default() as usize, 100, url);
+        } else if is_error {
+            errors.push(line);
+        } else {
+            path = Some(PathBuf::from(line.trim()));
+        }
+    }
+    match (yt_dlp.wait(), path) {
+        (Ok(status), Some(path)) if status.success() => Ok(path),
+        _ => Err(Error::msg(format!(
+            "error: could not download {url}. {}",
+            errors.join(" ").trim().trim_start_matches("ERROR: ")
+        ))),
+    }
+}
+
+#[test]
+fn downloads_keep_their_extension() {
+    let path = |url: &str| {
+        local_path(url).map(|path| path.extension().unwrap().to_string_lossy().into_owned())
+    };
+    assert!(is_url(Path::new("https://example.com/rain.ogg")));
+    assert!(!is_url(Path::new("sounds/rain.ogg")));
+    assert_eq!(
+        path("https://example.com/rain.ogg?dl=1").as_deref(),
+        Some("ogg")
+    );
+    assert_eq!(
+        path("https://example.com/sounds/").as_deref(),
+        Some("sound")
+    );
+    assert_ne!(
+        local_path("https://example.com/a/rain.ogg"),
+        local_path("https://example.com/b/rain.ogg")
+    );
+}
+
+#[test]
+fn downloads_are_read_while_they_come_in() {
+    let dir = crate::testing::TempDir::new("growing");
+    let path = dir.join("rain.ogg");
+    let partial = dir.join("rain.partial.ogg");
+    fs::write(&partial, b"the start").unwrap();
+    let download = Arc::new(Download {
+        partial: partial.clone(),
+        finished: Mutex::new(None),
+        changed: Condvar::new(),
+    });
+    DOWNLOADS
+        .lock()
+        .unwrap()
+        .push((path.clone(), download.clone()));
+    assert_eq!(readable(&path), partial);
+    let mut handle = crate::handles::open(&path).unwrap();
+    let complete = path.clone();
+    let curl = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        let mut file = fs::OpenOptions::new().append(true).open(&partial).unwrap();
+        std::io::Write::write_all(&mut file, b" and the rest").unwrap();
+        fs::rename(&partial, &complete).unwrap();
+        DOWNLOADS
+            .lock()
+            .unwrap()
+            .retain(|(downloading, _)| *downloading != complete);
+        download.finish(Ok(()));
+    });
+    // reading doesn't stop at the end of what is in yet
+    let mut text = String::new();
+    handle.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "the start and the rest");
+    curl.join().unwrap();
+    assert!(wait(&path).is_ok());
+    assert_eq!(readable(&path), path);
+}
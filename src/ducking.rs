@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::limiter::from_db;
+
+// a sound counts as heard once it is louder than -50 dBFS
+pub const HEARD_LEVEL: f32 = 0.003;
+// how fast the target goes down, once the trigger is heard
+pub const ATTACK: Duration = Duration::from_millis(50);
+pub const DEFAULT_RELEASE: Duration = Duration::from_millis(300);
+// a voice has short gaps between words. The target stays down through those, instead of pumping
+const HOLD: Duration = Duration::from_millis(250);
+
+// lowers the target group while anything in the trigger group can be heard
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Duck {
+    pub when: String,
+    pub target: String,
+    // how far the target goes down, in dB
+    pub amount: f32,
+    // how long the target takes to come back up
+    pub release: Duration,
+    #[serde(skip)]
+    last_heard: Option<Instant>,
+}
+
+impl Duck {
+    pub fn new(when: String, target: String, amount: f32, release: Duration) -> Self {
+        Self {
+            when,
+            target,
+            amount,
+            release,
+            last_heard: None,
+        }
+    }
+
+    pub fn gain(&self) -> f32 {
+        from_db(-self.amount)
+    }
+
+    // returns whether the target should be down right now
+    pub fn poll(&mut self, heard: bool, now: Instant) -> bool {
+        if heard {
+            self.last_heard = Some(now);
+        }
+        self.last_heard
+            .is_some_and(|last_heard| now.duration_since(last_heard) < HOLD)
+    }
+}
+
+#[test]
+fn ducks_hold_through_short_gaps() {
+    let mut duck = Duck::new(
+        "voice".to_string(),
+        "music".to_string(),
+        12.0,
+        DEFAULT_RELEASE,
+    );
+    assert!((duck.gain() - 0.251).abs() < 0.001);
+
+    let start = Instant::now();
+    assert!(!duck.poll(false, start));
+    assert!(duck.poll(true, start));
+    assert!(duck.poll(false, start + Duration::from_millis(100)));
+    assert!(!duck.poll(false, start + HOLD));
+    assert!(duck.poll(true, start + HOLD));
+}
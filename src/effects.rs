@@ -0,0 +1,178 @@
+use rodio::Source;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::control::SAMPLES_PER_UPDATE;
+
+#[derive(Clone, Copy)]
+struct EffectSettings {
+    // the side signal is scaled by this. 0 folds the sound to mono, above 1 makes it wider
+    width: f32,
+    // -1 only leaves the left channel, 1 only the right one
+    pan: f32,
+}
+
+// settings that are shared between a player and the sources it appends to its sink, so they can
+// be changed while the sound is playing
+#[derive(Clone)]
+pub struct EffectControl(Arc<Mutex<EffectSettings>>);
+
+impl EffectControl {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(EffectSettings {
+            width: 1.0,
+            pan: 0.0,
+        })))
+    }
+
+    pub fn set_width(&self, width: f32) {
+        self.0.lock().unwrap().width = width;
+    }
+
+    pub fn set_pan(&self, pan: f32) {
+        self.0.lock().unwrap().pan = pan;
+    }
+
+    fn settings(&self) -> EffectSettings {
+        *self.0.lock().unwrap()
+    }
+}
+
+impl Default for EffectControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// works on whole stereo frames. Mono sounds are spread over both channels first, so they can be
+// panned too. Anything else passes through untouched
+pub struct Effects<S> {
+    input: S,
+    control: EffectControl,
+    settings: EffectSettings,
+    until_update: usize,
+    right: Option<f32>,
+}
+
+impl<S> Effects<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: EffectControl) -> Self {
+        Self {
+            settings: control.settings(),
+            input,
+            control,
+            until_update: SAMPLES_PER_UPDATE,
+            right: None,
+        }
+    }
+}
+
+impl<S> Iterator for Effects<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.right.take() {
+            return Some(right);
+        }
+        let left = self.input.next()?;
+        let right = match self.input.channels() {
+            1 => left,
+            2 => match self.input.next() {
+                Some(right) => right,
+                None => return Some(left),
+            },
+            _ => return Some(left),
+        };
+
+        if self.until_update == 0 {
+            self.settings = self.control.settings();
+            self.until_update = SAMPLES_PER_UPDATE;
+        }
+        self.until_update = self.until_update.saturating_sub(2);
+
+        let mid = (left + right) / 2.0;
+        let side = (left - right) / 2.0 * self.settings.width;
+        // panning turns the other channel down, so the middle stays as loud as it was
+        let pan = self.settings.pan;
+        self.right = Some((mid - side) * (1.0 + pan.min(0.0)));
+        Some((mid + side) * (1.0 - pan.max(0.0)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.input.size_hint();
+        match self.input.channels() {
+            1 => (lower * 2, upper.map(|upper| upper * 2)),
+            _ => (lower, upper),
+        }
+    }
+}
+
+impl<S> Source for Effects<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let len = self.input.current_frame_len()?;
+        match self.input.channels() {
+            1 => Some(len * 2 + self.right.is_some() as usize),
+            _ => Some(len + self.right.is_some() as usize),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        match self.input.channels() {
+            1 => 2,
+            channels => channels,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[test]
+fn width_scales_the_side_signal() {
+    let samples = vec![1.0, 0.0, 0.5, 0.5, -0.2, 0.6];
+    let process = |width: f32| -> Vec<f32> {
+        let input = rodio::buffer::SamplesBuffer::new(2, 44_100, samples.clone());
+        let control = EffectControl::new();
+        control.set_width(width);
+        Effects::new(input, control).collect()
+    };
+    let assert_close = |actual: Vec<f32>, expected: &[f32]| {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} != {expected:?}");
+        }
+    };
+    assert_close(process(1.0), &samples);
+    assert_close(process(0.0), &[0.5, 0.5, 0.5, 0.5, 0.2, 0.2]);
+    assert_close(process(2.0), &[1.5, -0.5, 0.5, 0.5, -0.6, 1.0]);
+}
+
+#[test]
+fn pan_turns_the_other_channel_down() {
+    let process = |channels: u16, samples: Vec<f32>, pan: f32| -> Vec<f32> {
+        let input = rodio::buffer::SamplesBuffer::new(channels, 44_100, samples);
+        let control = EffectControl::new();
+        control.set_pan(pan);
+        Effects::new(input, control).collect()
+    };
+    assert_eq!(process(2, vec![0.5, 0.25], -0.5), vec![0.5, 0.125]);
+    assert_eq!(process(2, vec![0.5, 0.25], 1.0), vec![0.0, 0.25]);
+    // mono sounds are spread over both channels
+    assert_eq!(process(1, vec![0.5, -0.5], 0.0), vec![0.5, 0.5, -0.5, -0.5]);
+    assert_eq!(process(1, vec![0.5], 0.5), vec![0.25, 0.5]);
+}
@@ -0,0 +1,184 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::mpsc,
+};
+
+use serde::Serialize;
+
+use crate::player::Player;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "data", rename_all = "kebab-case")]
+pub enum AppEvent {
+    Started(String),
+    Paused(String),
+    // also sent when a sound finishes by itself
+    Stopped(String),
+    LoopWrapped(String),
+    VolumeChanged { id: String, volume: u32 },
+    // a sound reached its end by itself, so not because it was stopped or faded out
+    PlayerFinished(String),
+    SceneActivated(String),
+    // a sound was dropped into the watch folder, and added as a player
+    AutoAdded(String),
+}
+
+impl Display for AppEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AppEvent::Started(id) => write!(f, "{id} started playing"),
+            AppEvent::Paused(id) => write!(f, "{id} paused"),
+            AppEvent::Stopped(id) => write!(f, "{id} stopped"),
+            AppEvent::LoopWrapped(id) => write!(f, "{id} looped"),
+            AppEvent::VolumeChanged { id, volume } => write!(f, "{id} changed volume to {volume}%"),
+            AppEvent::PlayerFinished(id) => write!(f, "{id} finished"),
+            AppEvent::SceneActivated(name) => write!(f, "switched to scene {name}"),
+            AppEvent::AutoAdded(id) => write!(f, "{id} was added from the watch folder"),
+        }
+    }
+}
+
+pub type SubscriptionId = usize;
+
+enum Subscriber {
+    Channel(mpsc::Sender<AppEvent>),
+    Callback(Box<dyn FnMut(&AppEvent) + Send>),
+}
+
+// lets front-ends get pushed what happens, instead of polling the players themselves
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<(SubscriptionId, Subscriber)>,
+    next_id: SubscriptionId,
+}
+
+impl EventBus {
+    fn add(&mut self, subscriber: Subscriber) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscribers.push((id, subscriber));
+        id
+    }
+
+    // the subscription ends when the receiver is dropped
+    pub fn subscribe(&mut self) -> mpsc::Receiver<AppEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.add(Subscriber::Channel(tx));
+        rx
+    }
+
+    // callbacks are called on whichever thread the event happens, while the state is locked
+    pub fn on(&mut self, callback: impl FnMut(&AppEvent) + Send + 'static) -> SubscriptionId {
+        self.add(Subscriber::Callback(Box::new(callback)))
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|(other, _)| *other != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn publish(&mut self, event: &AppEvent) {
+        self.subscribers
+            .retain_mut(|(_, subscriber)| match subscriber {
+                Subscriber::Channel(tx) => tx.send(event.clone()).is_ok(),
+                Subscriber::Callback(callback) => {
+                    callback(event);
+                    true
+                }
+            });
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct PlayerStatus {
+    playing: bool,
+    paused: bool,
+    volume: u32,
+    loops: u128,
+}
+
+impl PlayerStatus {
+    fn of(player: &Player) -> Self {
+        let elapsed = player.get_play_time().saturating_sub(player.get_delay());
+        let loops = match player.pass_at(elapsed) {
+            Some((passes_done, _, _)) if player.get_is_playing() || player.get_is_paused() => {
+                passes_done as u128
+            }
+            _ => 0,
+        };
+        Self {
+            playing: player.get_is_playing(),
+            paused: player.get_is_paused(),
+            volume: player.get_volume(),
+            loops,
+        }
+    }
+}
+
+// players don't know their own id, so changes are found by comparing them to how they were last time
+#[derive(Default)]
+pub struct Observer {
+    last: HashMap<String, PlayerStatus>,
+}
+
+impl Observer {
+    pub fn observe(&mut self, players: &HashMap<String, Player>) -> Vec<AppEvent> {
+        let mut events = Vec::new();
+        self.last.retain(|id, _| players.contains_key(id));
+        for (id, player) in players {
+            let now = PlayerStatus::of(player);
+            // new players start out stopped, so only differences from that are reported
+            let before = self.last.insert(id.clone(), now).unwrap_or(PlayerStatus {
+                playing: false,
+                paused: false,
+                volume: now.volume,
+                loops: 0,
+            });
+            if now == before {
+                continue;
+            }
+            let id = id.clone();
+            if now.playing && !before.playing {
+                events.push(AppEvent::Started(id.clone()));
+            } else if now.paused && !before.paused {
+                events.push(AppEvent::Paused(id.clone()));
+            } else if !now.playing && !now.paused && (before.playing || before.paused) {
+                events.push(AppEvent::Stopped(id.clone()));
+            }
+            if now.loops > before.loops && before.playing {
+                events.push(AppEvent::LoopWrapped(id.clone()));
+            }
+            if now.volume != before.volume {
+                events.push(AppEvent::VolumeChanged {
+                    id,
+                    volume: now.volume,
+                });
+            }
+        }
+        events
+    }
+}
+
+#[test]
+fn event_bus_delivers_to_subscribers() {
+    use std::sync::{Arc, Mutex};
+
+    let mut bus = EventBus::default();
+    let rx = bus.subscribe();
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let callback_seen = seen.clone();
+    let callback = bus.on(move |event| callback_seen.lock().unwrap().push(event.clone()));
+
+    let started = AppEvent::Started("rain".to_string());
+    bus.publish(&started);
+    assert_eq!(rx.try_recv(), Ok(started.clone()));
+    assert_eq!(*seen.lock().unwrap(), vec![started.clone()]);
+
+    assert!(bus.unsubscribe(callback));
+    drop(rx);
+    bus.publish(&started);
+    assert!(bus.subscribers.is_empty());
+    assert_eq!(seen.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,170 @@
+use rodio::{Sample, Source};
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::control::SAMPLES_PER_UPDATE;
+
+struct FadeState {
+    from: f32,
+    to: f32,
+    start: Instant,
+    duration: Duration,
+    stop_when_done: bool,
+}
+
+impl FadeState {
+    fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        } else {
+            (self.start.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        }
+    }
+
+    fn gain(&self) -> f32 {
+        (self.to - self.from).mul_add(self.progress(), self.from)
+    }
+
+    fn is_done(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
+
+// a gain ramp that is shared between a player and the sources it appends to its sink.
+// The gain is applied on top of the volume of the sink
+#[derive(Clone)]
+pub struct FadeControl(Arc<Mutex<FadeState>>);
+
+impl FadeControl {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(FadeState {
+            from: 1.0,
+            to: 1.0,
+            start: Instant::now(),
+            duration: Duration::ZERO,
+            stop_when_done: false,
+        })))
+    }
+
+    pub fn start(&self, from: f32, to: f32, duration: Duration, stop_when_done: bool) {
+        *self.0.lock().unwrap() = FadeState {
+            from,
+            to,
+            start: Instant::now(),
+            duration,
+            stop_when_done,
+        };
+    }
+
+    // starts a fade from wherever the current fade is right now
+    pub fn start_from_current(&self, to: f32, duration: Duration, stop_when_done: bool) {
+        self.start(self.gain(), to, duration, stop_when_done);
+    }
+
+    pub fn reset(&self) {
+        self.start(1.0, 1.0, Duration::ZERO, false);
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.0.lock().unwrap().gain()
+    }
+
+    pub fn is_fading(&self) -> bool {
+        !self.0.lock().unwrap().is_done()
+    }
+
+    // how long the fade has left, and whether it stops the sound at the end. None when it is done
+    pub fn remaining(&self) -> Option<(Duration, bool)> {
+        let state = self.0.lock().unwrap();
+        (!state.is_done()).then(|| {
+            (
+                state.duration.saturating_sub(state.start.elapsed()),
+                state.stop_when_done,
+            )
+        })
+    }
+
+    // whether the sound has been faded out, and should be considered stopped
+    pub fn has_stopped(&self) -> bool {
+        let state = self.0.lock().unwrap();
+        state.stop_when_done && state.is_done()
+    }
+
+    fn poll(&self) -> (f32, bool) {
+        let state = self.0.lock().unwrap();
+        (state.gain(), state.stop_when_done && state.is_done())
+    }
+}
+
+impl Default for FadeControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Faded<S> {
+    input: S,
+    control: FadeControl,
+    gain: f32,
+    until_update: usize,
+}
+
+impl<S> Faded<S> {
+    pub fn new(input: S, control: FadeControl) -> Self {
+        Self {
+            input,
+            control,
+            gain: 1.0,
+            until_update: 0,
+        }
+    }
+}
+
+impl<S> Iterator for Faded<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.until_update == 0 {
+            let (gain, stopped) = self.control.poll();
+            if stopped {
+                return None;
+            }
+            self.gain = gain;
+            self.until_update = SAMPLES_PER_UPDATE;
+        }
+        self.until_update -= 1;
+        self.input.next().map(|sample| sample.amplify(self.gain))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Faded<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
@@ -0,0 +1,129 @@
+// how many names a "did you mean" offers at most
+const MAX_SUGGESTIONS: usize = 3;
+
+// how many letters have to be added, removed, swapped out or swapped around to get from one name
+// to the other. Capitals don't count
+pub fn distance(from: &str, to: &str) -> usize {
+    let from: Vec<char> = from.to_lowercase().chars().collect();
+    let to: Vec<char> = to.to_lowercase().chars().collect();
+    let mut distances = vec![vec![0; to.len() + 1]; from.len() + 1];
+    distances[0] = (0..=to.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=from.len() {
+        for j in 1..=to.len() {
+            let swapped_out = usize::from(from[i - 1] != to[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + swapped_out);
+            if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[from.len()][to.len()]
+}
+
+// the names that look the most like a name that doesn't exist, the closest first. Names that start
+// with it count too, since that is how names are shortened
+pub fn closest<'a>(name: &str, names: impl IntoIterator<Item = &'a String>) -> Vec<&'a str> {
+    let allowed = (name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = names
+        .into_iter()
+        .map(|candidate| (distance(name, candidate), candidate.as_str()))
+        .filter(|(distance, candidate)| *distance <= allowed || candidate.starts_with(name))
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+// the name that is the only one to start with this
+pub fn unique_prefix<'a>(
+    prefix: &str,
+    names: impl IntoIterator<Item = &'a String>,
+) -> Option<&'a str> {
+    let mut matching = names
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix));
+    let only = matching.next()?;
+    matching.next().is_none().then_some(only.as_str())
+}
+
+// whether a word is a pattern, which can stand for many names
+pub fn is_pattern(word: &str) -> bool {
+    word.contains(['*', '?'])
+}
+
+// whether a name fits a pattern, where * stands for any letters and ? for one, like forest*
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // the names that the pattern up to here fits, by how far they got into the name
+    let mut fits = vec![false; name.len() + 1];
+    fits[0] = true;
+    for token in pattern {
+        let mut next = vec![false; name.len() + 1];
+        for (at, _) in fits.iter().enumerate().filter(|(_, fit)| **fit) {
+            match token {
+                '*' => next[at..].fill(true),
+                '?' if at < name.len() => next[at + 1] = true,
+                letter if name.get(at) == Some(&letter) => next[at + 1] = true,
+                _ => {}
+            }
+        }
+        fits = next;
+    }
+    fits[name.len()]
+}
+
+// the end of an error about a name that doesn't exist, like ". Did you mean tavern?"
+pub fn did_you_mean<'a>(name: &str, names: impl IntoIterator<Item = &'a String>) -> String {
+    match closest(name, names).as_slice() {
+        [] => String::new(),
+        [only] => format!(". Did you mean {only}?"),
+        [first @ .., last] => format!(". Did you mean {} or {last}?", first.join(", ")),
+    }
+}
+
+#[test]
+fn names_that_look_alike_are_suggested() {
+    assert_eq!(distance("tavern", "tavern"), 0);
+    assert_eq!(distance("tavren", "tavern"), 1);
+    assert_eq!(distance("tavern", "cavern"), 1);
+    assert_eq!(distance("Rain", "rain"), 0);
+    assert_eq!(distance("", "owl"), 3);
+    let names: Vec<String> = ["tavern", "tavern fire", "rain", "forest"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    assert_eq!(closest("tavren", &names), ["tavern"]);
+    assert_eq!(closest("tav", &names), ["tavern", "tavern fire"]);
+    assert_eq!(did_you_mean("rian", &names), ". Did you mean rain?");
+    assert_eq!(
+        did_you_mean("tav", &names),
+        ". Did you mean tavern or tavern fire?"
+    );
+    assert_eq!(did_you_mean("thunder", &names), "");
+    assert_eq!(unique_prefix("for", &names), Some("forest"));
+    assert_eq!(unique_prefix("tav", &names), None);
+    assert_eq!(unique_prefix("owl", &names), None);
+}
+
+#[test]
+fn patterns_match_any_letters() {
+    assert!(is_pattern("forest*"));
+    assert!(!is_pattern("forest"));
+    assert!(matches_pattern("forest*", "forest birds"));
+    assert!(matches_pattern("forest*", "forest"));
+    assert!(matches_pattern("*rain*", "big rain far"));
+    assert!(matches_pattern("owl?", "owl2"));
+    assert!(!matches_pattern("owl?", "owl"));
+    assert!(!matches_pattern("forest*", "deep forest"));
+    assert!(matches_pattern("a*b*c", "abbbc"));
+}
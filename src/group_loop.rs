@@ -0,0 +1,93 @@
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// the members of a looping group play through once and then restart together. Separate loops
+// slowly drift apart, this keeps them in phase
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GroupLoop {
+    // the group restarts when this member ends. Without a leader, it restarts when its longest member ends
+    pub leader: Option<String>,
+    // the members are locked to this loop length instead. They loop by themselves, padded with
+    // silence or cut off, so they line up at every round without waiting for each other
+    #[serde(default)]
+    pub length: Option<Duration>,
+    // the members that have reached their end in the current round
+    #[serde(skip)]
+    finished: IndexSet<String>,
+}
+
+impl GroupLoop {
+    pub fn new(leader: Option<String>, length: Option<Duration>) -> Self {
+        Self {
+            leader,
+            length,
+            finished: IndexSet::new(),
+        }
+    }
+
+    // a member left the group
+    pub fn forget(&mut self, id: &String) {
+        if self.leader.as_ref() == Some(id) {
+            self.leader = None;
+        }
+        self.finished.shift_remove(id);
+    }
+
+    // returns the members that have to restart, once the group has reached its loop point.
+    // Members that were stopped by hand are not part of the round, so they stay stopped
+    pub fn poll(
+        &mut self,
+        members: &IndexSet<String>,
+        finished: &[String],
+        is_running: impl Fn(&String) -> bool,
+    ) -> Option<Vec<String>> {
+        self.finished
+            .extend(finished.iter().filter(|id| members.contains(*id)).cloned());
+        if self.finished.is_empty() {
+            return None;
+        }
+        let restart: Vec<String> = match &self.leader {
+            Some(leader) if self.finished.contains(leader) => members
+                .iter()
+                .filter(|id| self.finished.contains(*id) || is_running(id))
+                .cloned()
+                .collect(),
+            None if !members.iter().any(&is_running) => members
+                .iter()
+                .filter(|id| self.finished.contains(*id))
+                .cloned()
+                .collect(),
+            _ => return None,
+        };
+        self.finished.clear();
+        Some(restart)
+    }
+}
+
+#[test]
+fn group_loop_restarts_together() {
+    let members: IndexSet<String> = ["rain", "wind", "thunder"].map(String::from).into();
+    let finished = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+    let mut longest = GroupLoop::new(None, None);
+    assert_eq!(
+        longest.poll(&members, &finished(&["wind"]), |id| id != "wind"),
+        None
+    );
+    // thunder was stopped by hand, so it doesn't come back
+    assert_eq!(
+        longest.poll(&members, &finished(&["rain"]), |_| false),
+        Some(finished(&["rain", "wind"]))
+    );
+
+    let mut led = GroupLoop::new(Some("wind".to_string()), None);
+    assert_eq!(
+        led.poll(&members, &finished(&["rain"]), |id| id != "rain"),
+        None
+    );
+    assert_eq!(
+        led.poll(&members, &finished(&["wind"]), |id| id == "thunder"),
+        Some(finished(&["rain", "wind", "thunder"]))
+    );
+}
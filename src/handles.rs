@@ -0,0 +1,124 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::cache;
+use crate::download::{self, Download};
+
+// how many sound files are open right now. Players don't keep their file open while they are
+// idle, only the sources that are queued or playing hold on to one
+static OPEN: AtomicUsize = AtomicUsize::new(0);
+
+// a file that is counted for as long as it is open
+pub struct Handle {
+    file: File,
+    counter: &'static AtomicUsize,
+    // the download that is still writing the file, which is waited for at its end
+    download: Option<Arc<Download>>,
+}
+
+impl Handle {
+    fn new(file: File, counter: &'static AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self {
+            file,
+            counter,
+            download: None,
+        }
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// the end of a file that is still being downloaded isn't the end of the sound, so reading waits
+// there for more
+impl Read for Handle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.file.read(buf)?;
+            match &self.download {
+                Some(download) if read == 0 && !buf.is_empty() && !download.is_finished() => {
+                    download.wait_for_more()
+                }
+                _ => return Ok(read),
+            }
+        }
+    }
+}
+
+impl Seek for Handle {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let (SeekFrom::End(_), Some(download)) = (pos, &self.download) {
+            while !download.is_finished() {
+                download.wait_for_more();
+            }
+        }
+        self.file.seek(pos)
+    }
+}
+
+// every sound file should be opened through here, so the diagnostics add up. A decoded copy in
+// the cache is opened instead of the sound itself, when there is one
+pub fn open(path: &Path) -> io::Result<BufReader<Handle>> {
+    // a sound that is still coming in is read from where it is written. Once it is complete, it is
+    // moved to the path, but the file stays open
+    if let Some(download) = download::in_progress(path) {
+        if let Ok(file) = File::open(download::readable(path)) {
+            let mut handle = Handle::new(file, &OPEN);
+            handle.download = Some(download);
+            return Ok(BufReader::new(handle));
+        }
+    }
+    let cached = cache::lookup(path);
+    let file = File::open(cached.as_deref().unwrap_or(path))?;
+    Ok(BufReader::new(Handle::new(file, &OPEN)))
+}
+
+pub fn open_sound_files() -> usize {
+    OPEN.load(Ordering::SeqCst)
+}
+
+// all files, sockets and devices the process has open, and how many it is allowed to have. Only
+// known on linux
+pub fn process_descriptors() -> Option<(usize, Option<u64>)> {
+    let open = fs::read_dir("/proc/self/fd").ok()?.count();
+    let limit = fs::read_to_string("/proc/self/limits")
+        .ok()
+        .and_then(|limits| {
+            limits
+                .lines()
+                .find(|line| line.starts_with("Max open files"))?
+                .split_whitespace()
+                .nth(3)?
+                .parse()
+                .ok()
+        });
+    Some((open, limit))
+}
+
+#[test]
+fn handles_are_counted_until_dropped() {
+    // a counter of its own, since other tests open sound files at the same time
+    static COUNTED: AtomicUsize = AtomicUsize::new(0);
+    let dir = crate::testing::TempDir::new("handle");
+    let path = dir.join("rain.ogg");
+    fs::write(&path, b"not really a sound").unwrap();
+    let first = Handle::new(File::open(&path).unwrap(), &COUNTED);
+    let second = Handle::new(File::open(&path).unwrap(), &COUNTED);
+    assert_eq!(COUNTED.load(Ordering::SeqCst), 2);
+    drop(first);
+    assert_eq!(COUNTED.load(Ordering::SeqCst), 1);
+    drop(second);
+    assert_eq!(COUNTED.load(Ordering::SeqCst), 0);
+    assert!(open(&path).is_ok());
+}
@@ -0,0 +1,79 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::schedule;
+use crate::{respond, AppState, INTERACTIVE};
+
+// short, because looping groups restart when the watcher notices that they ended
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+// hooks that trigger each other could otherwise keep going forever
+const MAX_HOOK_ROUNDS: usize = 16;
+
+// set when a command that the watcher ran quits, like at 23:00 exit. Whatever waits for input
+// sees it, and quits the way it would for an exit that was typed
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+pub fn quit_is_requested() -> bool {
+    QUIT.load(Ordering::SeqCst)
+}
+
+pub fn take_quit_request() -> bool {
+    QUIT.swap(false, Ordering::SeqCst)
+}
+
+// runs the commands of the hooks that match the pending events. Returns whether any of them mutated the state
+pub fn run_hooks(state: &mut AppState, has_been_saved: bool) -> bool {
+    let mut mutated = false;
+    for _ in 0..MAX_HOOK_ROUNDS {
+        let events = std::mem::take(&mut state.events);
+        if events.is_empty() {
+            return mutated;
+        }
+        for event in events {
+            let commands: Vec<String> = state
+                .config
+                .hooks
+                .iter()
+                .filter(|hook| hook.matches(&event))
+                .map(|hook| hook.run.clone())
+                .collect();
+            for command in commands {
+                match respond(state, &command, has_been_saved && !mutated) {
+                    Ok(result) => mutated |= result.mutated,
+                    Err(err) => println!("{err}"),
+                }
+            }
+        }
+    }
+    state.events.clear();
+    println!("warning: stopped running hooks, because they keep triggering each other.");
+    mutated
+}
+
+// watches for things that happen while nobody is typing, like sounds that finish and scheduled
+// commands that come due
+pub fn spawn_watcher(state: Arc<Mutex<AppState>>, background_mutated: Arc<AtomicBool>) {
+    thread::spawn(move || {
+        // there is nobody to answer prompts on this thread
+        INTERACTIVE.set(false);
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let mut state = state.lock().unwrap();
+            state.poll_events();
+            let due = schedule::run_due(&mut state);
+            let mutated = due.mutated | run_hooks(&mut state, false);
+            if mutated {
+                background_mutated.store(true, Ordering::SeqCst);
+            }
+            if due.quit {
+                QUIT.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+}
@@ -0,0 +1,256 @@
+use anyhow::Error;
+use livesplit_hotkey::{Hook, Hotkey, KeyCode, Modifiers};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::config::config_dir;
+use crate::server::Session;
+use crate::{AppState, INTERACTIVE};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Binding {
+    keys: String,
+    run: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct HotkeysFile {
+    #[serde(default, rename = "hotkey")]
+    hotkeys: Vec<Binding>,
+}
+
+fn hotkeys_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("hotkeys.toml"))
+}
+
+// takes keys the way people write them, like ctrl+alt+f1 or Shift + A
+pub fn parse_keys(keys: &str) -> Result<Hotkey, Error> {
+    let invalid = || Error::msg(format!("error: {keys} is not a key combination"));
+    let mut parts: Vec<&str> = keys.split('+').map(str::trim).collect();
+    let key = parts
+        .pop()
+        .filter(|key| !key.is_empty())
+        .ok_or_else(invalid)?;
+    let mut modifiers = Modifiers::empty();
+    for modifier in parts {
+        modifiers.insert(match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "alt" | "option" => Modifiers::ALT,
+            "shift" => Modifiers::SHIFT,
+            "meta" | "super" | "win" | "cmd" => Modifiers::META,
+            _ => {
+                return Err(Error::msg(format!(
+                    "error: {modifier} is not a modifier. Use ctrl, alt, shift or meta"
+                )))
+            }
+        });
+    }
+    let mut chars = key.chars();
+    let capitalized: String = chars
+        .next()
+        .into_iter()
+        .flat_map(char::to_uppercase)
+        .chain(chars)
+        .collect();
+    let key_code: KeyCode = key
+        .parse()
+        .or_else(|_| capitalized.parse())
+        .or_else(|_| key.to_uppercase().parse())
+        .map_err(|_| {
+            Error::msg(format!(
+                "error: {key} is not a key. Keys are named like A, 1, F1, Space, Numpad1 or ArrowUp"
+            ))
+        })?;
+    Ok(key_code.with_modifiers(modifiers))
+}
+
+// keys that run a command, even while another window has the focus, like the virtual tabletop.
+// They are kept in hotkeys.toml in the config folder, so they don't change with the soundscape
+pub struct Hotkeys {
+    bindings: Vec<(Hotkey, String)>,
+    // only made once there is something to listen for, because it needs the display server or
+    // access to the keyboard devices
+    hook: Option<Hook>,
+    commands: Sender<String>,
+    path: Option<PathBuf>,
+}
+
+impl Hotkeys {
+    pub fn new(commands: Sender<String>) -> Self {
+        Self {
+            bindings: Vec::new(),
+            hook: None,
+            commands,
+            path: hotkeys_path(),
+        }
+    }
+
+    // a missing file means nothing is bound yet
+    pub fn load(&mut self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file: HotkeysFile = match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|err| {
+                Error::msg(format!(
+                    "error: could not read the hotkeys at {}. {err}",
+                    path.display()
+                ))
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "error: could not open the hotkeys at {}. {err}",
+                    path.display()
+                )))
+            }
+        };
+        for binding in file.hotkeys {
+            let keys = parse_keys(&binding.keys)?;
+            self.register(keys, binding.run)?;
+        }
+        Ok(())
+    }
+
+    fn persist(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = HotkeysFile {
+            hotkeys: self
+                .bindings
+                .iter()
+                .map(|(keys, command)| Binding {
+                    keys: keys.to_string(),
+                    run: command.clone(),
+                })
+                .collect(),
+        };
+        let text = toml::to_string(&file)?;
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(path, text))
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not save the hotkeys to {}. {err}",
+                    path.display()
+                ))
+            })
+    }
+
+    fn register(&mut self, keys: Hotkey, command: String) -> Result<(), Error> {
+        if self.hook.is_none() {
+            self.hook = Some(Hook::new().map_err(|err| {
+                Error::msg(format!(
+                    "error: global hotkeys can't be used here. On Linux, they need an X11 session, or access to the keyboards in /dev/input. {err}"
+                ))
+            })?);
+        }
+        let commands = self.commands.clone();
+        let run = command.clone();
+        self.hook
+            .as_ref()
+            .unwrap()
+            .register(keys, move || {
+                let _ = commands.send(run.clone());
+            })
+            .map_err(|err| Error::msg(format!("error: could not bind {keys}. {err}")))?;
+        self.bindings.push((keys, command));
+        Ok(())
+    }
+
+    pub fn bindings(&self) -> &[(Hotkey, String)] {
+        &self.bindings
+    }
+
+    // binding the same keys again replaces what they did. When the new binding can't be made, the
+    // old one is put back
+    pub fn bind(&mut self, keys: Hotkey, command: String) -> Result<(), Error> {
+        let Some(index) = self.bindings.iter().position(|(bound, _)| *bound == keys) else {
+            self.register(keys, command)?;
+            return self.persist();
+        };
+        self.unregister(keys)?;
+        let (_, old) = self.bindings.remove(index);
+        if let Err(err) = self.register(keys, command) {
+            self.register(keys, old)?;
+            let restored = self.bindings.pop().unwrap();
+            self.bindings.insert(index, restored);
+            return Err(err);
+        }
+        self.persist()
+    }
+
+    pub fn unbind(&mut self, keys: Hotkey) -> Result<(), Error> {
+        let Some(index) = self.bindings.iter().position(|(bound, _)| *bound == keys) else {
+            return Err(Error::msg(format!("error: nothing is bound to {keys}")));
+        };
+        self.unregister(keys)?;
+        self.bindings.remove(index);
+        self.persist()
+    }
+
+    fn unregister(&self, keys: Hotkey) -> Result<(), Error> {
+        if let Some(hook) = &self.hook {
+            hook.unregister(keys)
+                .map_err(|err| Error::msg(format!("error: could not unbind {keys}. {err}")))?;
+        }
+        Ok(())
+    }
+}
+
+// runs the commands of the hotkeys that are pressed
+pub fn spawn(
+    state: Arc<Mutex<AppState>>,
+    commands: Receiver<String>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        // there is nobody to answer prompts on this thread
+        INTERACTIVE.set(false);
+        let session = Session::new(state, saved, background_mutated);
+        for command in commands {
+            session.run_quietly(&command);
+        }
+    });
+}
+
+#[test]
+fn keys_are_parsed_loosely() {
+    assert_eq!(
+        parse_keys("ctrl+alt+f1").unwrap().to_string(),
+        "Ctrl + Alt + F1"
+    );
+    assert_eq!(
+        parse_keys("Shift + a").unwrap(),
+        KeyCode::KeyA.with_modifiers(Modifiers::SHIFT)
+    );
+    assert_eq!(parse_keys("space").unwrap(), KeyCode::Space.into());
+    assert_eq!(
+        parse_keys("Meta+Numpad1").unwrap(),
+        KeyCode::Numpad1.with_modifiers(Modifiers::META)
+    );
+    assert!(parse_keys("hyper+a").is_err());
+    assert!(parse_keys("ctrl+").is_err());
+    assert!(parse_keys("ctrl+nokey").is_err());
+}
+
+#[test]
+fn a_bad_binding_keeps_the_old_one() {
+    crate::INTERACTIVE.set(false);
+    let mut state = crate::testing::app_state();
+    let keys = parse_keys("ctrl+f1").unwrap();
+    state.hotkeys.bindings.push((keys, "play rain".to_string()));
+    assert!(crate::operations::hotkey_bind(&mut state, keys, "plya rain".to_string()).is_err());
+    assert!(crate::operations::hotkey_bind(&mut state, keys, " ".to_string()).is_err());
+    assert_eq!(state.hotkeys.bindings(), [(keys, "play rain".to_string())]);
+}
@@ -0,0 +1,313 @@
+use anyhow::Error;
+use serde_json::{json, Value};
+use std::{
+    borrow::Cow,
+    net::SocketAddr,
+    sync::{atomic::AtomicBool, Arc, Mutex},
+    thread,
+    time::Instant,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::pairing::{is_local_host, is_local_origin};
+use crate::server::Session;
+use crate::{AppState, INTERACTIVE};
+
+fn quote(text: &str) -> Cow<'_, str> {
+    shlex::try_quote(text).unwrap_or(Cow::Borrowed(text))
+}
+
+// names in the path can have spaces and such in them, written as %20
+pub fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+pub fn query_value(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(name, _)| *name == key)
+        // only in the query, a + is a space
+        .and_then(|(_, value)| percent_decode(&value.replace('+', " ")))
+}
+
+// what a request asks for
+#[derive(Debug, PartialEq)]
+enum Route {
+    State,
+    Command(String),
+    // asks for a code with a name, or sends the code back
+    Pair {
+        name: Option<String>,
+        code: Option<String>,
+    },
+}
+
+// turns a request into the command that does the same at the prompt
+fn route(method: &Method, url: &str, body: &str) -> Result<Route, (u16, String)> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode(segment).ok_or((400, format!("{segment} is not valid"))))
+        .collect::<Result<_, _>>()?;
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let value = |key: &str| {
+        query_value(query, key).ok_or_else(|| (400, format!("{key} is missing from the query")))
+    };
+    let command = match (method, segments.as_slice()) {
+        (Method::Get, ["state"]) => return Ok(Route::State),
+        (Method::Post, ["pair"]) => {
+            return Ok(Route::Pair {
+                name: query_value(query, "name"),
+                code: query_value(query, "code"),
+            })
+        }
+        (Method::Post, ["command"]) if !body.trim().is_empty() => body.trim().to_string(),
+        (Method::Post, ["players", id, action @ ("play" | "stop" | "pause")]) => {
+            format!("{action} {}", quote(id))
+        }
+        (Method::Post, ["players", id, "volume"]) => {
+            format!("volume {} -v {}", quote(id), quote(&value("value")?))
+        }
+        (Method::Post, ["groups", group, action @ ("play" | "stop" | "pause")]) => {
+            format!("{action} -g {}", quote(group))
+        }
+        (Method::Post, ["groups", group, "volume"]) => {
+            format!("volume -g {} -v {}", quote(group), quote(&value("value")?))
+        }
+        (Method::Post, ["scenes", name, "switch"]) => match query_value(query, "fade") {
+            Some(fade) => format!("scene switch {} -f {}", quote(name), quote(&fade)),
+            None => format!("scene switch {}", quote(name)),
+        },
+        (Method::Get | Method::Post, _) => {
+            return Err((404, format!("there is nothing at {path}")))
+        }
+        _ => return Err((405, format!("{method} is not supported"))),
+    };
+    Ok(Route::Command(command))
+}
+
+fn json_response(status: u16, body: Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header)
+}
+
+// the token goes in an Authorization header, or in the query for clients that can't set one
+fn token(request: &Request) -> Option<String> {
+    let header = header(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let query = request.url().split_once('?').map(|(_, query)| query);
+    header.or_else(|| query.and_then(|query| query_value(query, "token")))
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv(name))
+        .map(|header| header.value.as_str())
+}
+
+// a page in a browser on this machine can send requests here too, without being asked first
+// whether it may, as long as they look like a form. Such requests can't have an X-Troubadour
+// header, and the page can't make its own name look like this machine's, so a client here has to
+// send that header to get in without a token
+fn is_local(request: &Request, peer: &SocketAddr) -> bool {
+    peer.ip().is_loopback()
+        && header(request, "X-Troubadour").is_some()
+        && header(request, "Host").is_some_and(is_local_host)
+        && header(request, "Origin").is_none_or(is_local_origin)
+}
+
+// pairing is the one thing a client can do before it is paired
+fn authorize(
+    request: &Request,
+    state: &Mutex<AppState>,
+    route: &Route,
+) -> Result<(), (u16, String)> {
+    if let Route::Pair { .. } = route {
+        return Ok(());
+    }
+    let peer = request.remote_addr().ok_or((
+        401,
+        "could not tell where the request came from".to_string(),
+    ))?;
+    state
+        .lock()
+        .unwrap()
+        .pairing
+        .authorize(is_local(request, peer), token(request).as_deref())
+        .map(|_| ())
+        .map_err(|err| (401, err.to_string()))
+}
+
+fn pair(
+    state: &Mutex<AppState>,
+    peer: Option<SocketAddr>,
+    name: Option<String>,
+    code: Option<String>,
+) -> (u16, Value) {
+    let Some(peer) = peer.map(|peer| peer.ip()) else {
+        return (
+            401,
+            json!({"error": "could not tell where the request came from"}),
+        );
+    };
+    let mut state = state.lock().unwrap();
+    let pairing = &mut state.pairing;
+    match (name, code) {
+        (_, Some(code)) => match pairing.complete(peer, &code, Instant::now()) {
+            Ok((name, token)) => (200, json!({"name": name, "token": token})),
+            Err(err) => (403, json!({"error": err.to_string()})),
+        },
+        (Some(name), None) => match pairing.start(peer, &name, Instant::now()) {
+            Ok(_) => {
+                let output = "Enter the code that is shown in the terminal, with code=<CODE>.";
+                (202, json!({ "output": output }))
+            }
+            Err(err) => (429, json!({"error": err.to_string()})),
+        },
+        (None, None) => (400, json!({"error": "name is missing from the query"})),
+    }
+}
+
+fn handle(mut request: Request, state: &Mutex<AppState>, session: &Session) {
+    let mut body = String::new();
+    let routed = request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|err| (400, format!("could not read the body. {err}")))
+        .and_then(|_| route(request.method(), request.url(), &body))
+        .and_then(|route| authorize(&request, state, &route).map(|_| route));
+    let (status, reply) = match routed {
+        Ok(Route::State) => (200, json!(state.lock().unwrap().snapshot())),
+        Ok(Route::Pair { name, code }) => pair(state, request.remote_addr().copied(), name, code),
+        Ok(Route::Command(command)) => match session.run(&command).0 {
+            Ok(said) => (200, json!({"output": said})),
+            Err((said, err)) => (400, json!({"error": err, "output": said})),
+        },
+        Err((status, err)) => (status, json!({"error": err})),
+    };
+    if let Err(err) = request.respond(json_response(status, reply)) {
+        println!("warning: could not answer an HTTP request. {err}");
+    }
+}
+
+// a small HTTP API for tools that can't keep a connection open, like home automation. Requests
+// are handled one at a time
+pub fn spawn(
+    state: Arc<Mutex<AppState>>,
+    address: SocketAddr,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let server = Server::http(address).map_err(|err| {
+        Error::msg(format!(
+            "error: could not listen for HTTP requests on {address}. {err}"
+        ))
+    })?;
+    println!("Listening for HTTP requests on http://{address}.");
+    thread::spawn(move || {
+        // there is nobody at this end to answer prompts
+        INTERACTIVE.set(false);
+        let session = Session::new(state.clone(), saved, background_mutated);
+        for request in server.incoming_requests() {
+            handle(request, &state, &session);
+        }
+    });
+    Ok(())
+}
+
+#[test]
+fn requests_are_routed_to_commands() {
+    let command = |method, url| match route(&method, url, "") {
+        Ok(Route::Command(command)) => command,
+        other => panic!("{url} routed to {other:?}"),
+    };
+    assert_eq!(route(&Method::Get, "/state", ""), Ok(Route::State));
+    assert_eq!(
+        command(Method::Post, "/players/night%20rain/play"),
+        "play 'night rain'"
+    );
+    assert_eq!(
+        command(Method::Post, "/groups/forest/volume?value=40"),
+        "volume -g forest -v 40"
+    );
+    assert_eq!(
+        command(Method::Post, "/scenes/combat/switch?fade=2s"),
+        "scene switch combat -f 2s"
+    );
+    assert_eq!(
+        route(&Method::Post, "/command", "stop all\n"),
+        Ok(Route::Command("stop all".to_string()))
+    );
+    assert_eq!(
+        route(&Method::Post, "/players/rain/volume", "")
+            .unwrap_err()
+            .0,
+        400
+    );
+    assert_eq!(route(&Method::Get, "/players", "").unwrap_err().0, 404);
+    assert_eq!(
+        route(&Method::Post, "/pair?code=123456", ""),
+        Ok(Route::Pair {
+            name: None,
+            code: Some("123456".to_string())
+        })
+    );
+    assert_eq!(route(&Method::Delete, "/state", "").unwrap_err().0, 405);
+}
+
+#[test]
+fn pages_in_a_browser_need_a_token() {
+    use std::io::{Read, Write};
+    let state = Arc::new(Mutex::new(crate::testing::app_state()));
+    let session = Session::new(state.clone(), Arc::default(), Arc::default());
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let address = server.server_addr().to_ip().unwrap();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle(request, &state, &session);
+        }
+    });
+    let status = |headers: &str| {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET /state HTTP/1.1\r\n{headers}Connection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response[9..12].to_string()
+    };
+    assert_eq!(status("Host: 127.0.0.1\r\nX-Troubadour: 1\r\n"), "200");
+    // what a page can send without asking first
+    assert_eq!(status("Host: 127.0.0.1\r\n"), "401");
+    // a page whose name was pointed at this machine
+    assert_eq!(status("Host: evil.example\r\nX-Troubadour: 1\r\n"), "401");
+    assert_eq!(
+        status("Host: localhost\r\nX-Troubadour: 1\r\nOrigin: https://evil.example\r\n"),
+        "401"
+    );
+}
@@ -0,0 +1,265 @@
+use anyhow::Error;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+// soundscapes from other programs, like the soundpad manifests that soundboard apps and Syrinscape
+// style sets can be written out as. Tracks become players, and moods become scenes, with the
+// volume every track plays at in that mood
+pub struct ImportedTrack {
+    pub name: String,
+    pub path: PathBuf,
+    pub volume: u32,
+    pub looping: bool,
+    pub group: Option<String>,
+}
+
+pub struct Imported {
+    pub tracks: Vec<ImportedTrack>,
+    // the tracks in every mood, with their volume
+    pub moods: IndexMap<String, IndexMap<String, u32>>,
+}
+
+fn full_volume() -> u32 {
+    100
+}
+
+// {"tracks": [{"name": "rain", "file": "rain.ogg", "volume": 40, "loop": true, "group": "weather"}],
+//  "moods": [{"name": "storm", "tracks": ["rain", {"name": "thunder", "volume": 80}]}]}
+#[derive(Deserialize)]
+struct JsonSoundpad {
+    #[serde(alias = "elements", alias = "sounds")]
+    tracks: Vec<JsonTrack>,
+    #[serde(default)]
+    moods: Vec<JsonMood>,
+}
+
+#[derive(Deserialize)]
+struct JsonTrack {
+    name: String,
+    #[serde(alias = "path")]
+    file: PathBuf,
+    #[serde(default = "full_volume")]
+    volume: u32,
+    #[serde(default, rename = "loop")]
+    looping: bool,
+    #[serde(default, alias = "category")]
+    group: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonMood {
+    name: String,
+    #[serde(alias = "elements", alias = "sounds")]
+    tracks: Vec<JsonMoodTrack>,
+}
+
+// without a volume, the track plays at its own volume in the mood
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonMoodTrack {
+    Name(String),
+    WithVolume { name: String, volume: u32 },
+}
+
+// name, file, volume, loop, group, moods. The moods are separated by semicolons, and can have a
+// volume after a colon, like "storm; calm:40"
+#[derive(Deserialize)]
+struct CsvRow {
+    name: String,
+    #[serde(alias = "path")]
+    file: PathBuf,
+    volume: Option<u32>,
+    #[serde(rename = "loop")]
+    looping: Option<String>,
+    group: Option<String>,
+    moods: Option<String>,
+}
+
+fn parse_json(reader: impl Read) -> Result<Imported, Error> {
+    let soundpad: JsonSoundpad = serde_json::from_reader(reader)
+        .map_err(|err| Error::msg(format!("error: could not read the soundscape. {err}")))?;
+    let tracks: Vec<ImportedTrack> = soundpad
+        .tracks
+        .into_iter()
+        .map(|track| ImportedTrack {
+            name: track.name,
+            path: track.file,
+            volume: track.volume,
+            looping: track.looping,
+            group: track.group.filter(|group| !group.is_empty()),
+        })
+        .collect();
+    let mut moods = IndexMap::new();
+    for mood in soundpad.moods {
+        let mut members = IndexMap::new();
+        for track in mood.tracks {
+            let (name, volume) = match track {
+                JsonMoodTrack::Name(name) => {
+                    let volume = tracks
+                        .iter()
+                        .find(|track| track.name == name)
+                        .map(|track| track.volume)
+                        .unwrap_or(100);
+                    (name, volume)
+                }
+                JsonMoodTrack::WithVolume { name, volume } => (name, volume),
+            };
+            members.insert(name, volume);
+        }
+        moods.insert(mood.name, members);
+    }
+    Ok(Imported { tracks, moods })
+}
+
+fn parse_csv(reader: impl Read) -> Result<Imported, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let mut imported = Imported {
+        tracks: Vec::new(),
+        moods: IndexMap::new(),
+    };
+    for row in reader.deserialize::<CsvRow>() {
+        let row: CsvRow = row.map_err(|err| {
+            let line = err.position().map(|pos| pos.line()).unwrap_or_default();
+            Error::msg(format!(
+                "error: could not read line {line} of the soundscape. {err}"
+            ))
+        })?;
+        let volume = row.volume.unwrap_or(100);
+        let looping = match row.looping.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("" | "no" | "false" | "off") => false,
+            Some("yes" | "true" | "on") => true,
+            Some(other) => {
+                return Err(Error::msg(format!(
+                    "error: in the row for {}: {other} is not yes or no.",
+                    row.name
+                )))
+            }
+        };
+        for mood in row.moods.iter().flat_map(|moods| moods.split(';')) {
+            let (mood, mood_volume) = match mood.split_once(':') {
+                Some((mood, mood_volume)) => {
+                    let mood_volume = mood_volume.trim().parse().map_err(|_| {
+                        Error::msg(format!(
+                            "error: in the row for {}: {mood_volume} is not a volume.",
+                            row.name
+                        ))
+                    })?;
+                    (mood.trim(), mood_volume)
+                }
+                None => (mood.trim(), volume),
+            };
+            if !mood.is_empty() {
+                imported
+                    .moods
+                    .entry(mood.to_string())
+                    .or_default()
+                    .insert(row.name.clone(), mood_volume);
+            }
+        }
+        imported.tracks.push(ImportedTrack {
+            name: row.name,
+            path: row.file,
+            volume,
+            looping,
+            group: row.group.filter(|group| !group.is_empty()),
+        });
+    }
+    Ok(imported)
+}
+
+// the names have to work as players, and the moods can only point at tracks that are there
+fn validate(imported: &Imported) -> Result<(), Error> {
+    let mut names = HashSet::new();
+    for track in &imported.tracks {
+        if track.name.is_empty() || track.name.to_lowercase() == "all" {
+            return Err(Error::msg(format!(
+                "error: '{}' is not a valid name for a player.",
+                track.name
+            )));
+        }
+        if !names.insert(&track.name) {
+            return Err(Error::msg(format!(
+                "error: there is more than one track called {}.",
+                track.name
+            )));
+        }
+    }
+    for (mood, members) in &imported.moods {
+        if let Some(missing) = members.keys().find(|name| !names.contains(name)) {
+            return Err(Error::msg(format!(
+                "error: the mood {mood} has a track called {missing}, but there is no such track."
+            )));
+        }
+    }
+    Ok(())
+}
+
+// json and csv files are told apart by their extension. Paths are relative to the file
+pub fn parse(path: &Path) -> Result<Imported, Error> {
+    let file = File::open(path)
+        .map_err(|err| Error::msg(format!("error: could not open {}. {err}", path.display())))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let mut imported = match extension.as_deref() {
+        Some("json") => parse_json(file)?,
+        Some("csv") => parse_csv(file)?,
+        _ => {
+            return Err(Error::msg(
+                "error: only .json and .csv soundscapes can be imported.",
+            ))
+        }
+    };
+    validate(&imported)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    for track in &mut imported.tracks {
+        track.path = base.join(&track.path);
+    }
+    Ok(imported)
+}
+
+#[test]
+fn parse_soundpads() {
+    let json = r#"{
+        "elements": [
+            {"name": "rain", "file": "weather/rain.ogg", "volume": 40, "loop": true, "category": "weather"},
+            {"name": "thunder", "path": "weather/thunder.ogg"}
+        ],
+        "moods": [{"name": "storm", "tracks": ["rain", {"name": "thunder", "volume": 80}]}]
+    }"#;
+    let imported = parse_json(json.as_bytes()).unwrap();
+    assert_eq!(imported.tracks.len(), 2);
+    assert_eq!(imported.tracks[0].group.as_deref(), Some("weather"));
+    assert!(imported.tracks[0].looping);
+    assert_eq!(imported.tracks[1].volume, 100);
+    assert_eq!(imported.moods["storm"]["rain"], 40);
+    assert_eq!(imported.moods["storm"]["thunder"], 80);
+    assert!(validate(&imported).is_ok());
+
+    let csv = "\
+name, file, volume, loop, group, moods
+rain, weather/rain.ogg, 40, yes, weather, storm; calm:20
+thunder, weather/thunder.ogg, , , , storm:80
+";
+    let imported = parse_csv(csv.as_bytes()).unwrap();
+    assert_eq!(imported.tracks[1].group, None);
+    assert!(!imported.tracks[1].looping);
+    assert_eq!(imported.moods["storm"]["rain"], 40);
+    assert_eq!(imported.moods["calm"]["rain"], 20);
+    assert_eq!(imported.moods["storm"]["thunder"], 80);
+
+    let csv = "name, file, moods\nrain, rain.ogg, storm\nrain, drizzle.ogg,\n";
+    assert!(validate(&parse_csv(csv.as_bytes()).unwrap()).is_err());
+    let json = r#"{"tracks": [], "moods": [{"name": "storm", "tracks": ["rain"]}]}"#;
+    assert!(validate(&parse_json(json.as_bytes()).unwrap()).is_err());
+}
@@ -0,0 +1,399 @@
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::config::config_dir;
+use crate::player::{is_supported, probe_length};
+
+fn library_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("library.json"))
+}
+
+// a sound that was found in one of the folders of the library
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub length: Option<Duration>,
+    // the folders between the root and the file, like weather for weather/rain.ogg
+    pub tags: Vec<String>,
+    // the size and the time of the last change when the file was indexed, so a scan can skip the
+    // files that didn't change, without decoding them again
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Entry {
+    pub fn name(&self) -> String {
+        self.path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn matches(&self, words: &[String]) -> bool {
+        let name = self.name().to_lowercase();
+        words.iter().all(|word| {
+            name.contains(word.as_str())
+                || self
+                    .tags
+                    .iter()
+                    .any(|tag| tag.to_lowercase().contains(word.as_str()))
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Index {
+    roots: Vec<PathBuf>,
+    sounds: Vec<Entry>,
+}
+
+// the folders that sounds are kept in, and the sounds in them, so a sound can be found by its name
+// instead of typing out its path. They are kept in library.json in the config folder
+pub struct Library {
+    index: Index,
+    // the sounds the last search found, which library add picks from by their number
+    results: Vec<Entry>,
+    path: Option<PathBuf>,
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self {
+            index: Index::default(),
+            results: Vec::new(),
+            path: library_path(),
+        }
+    }
+
+    // a missing file means there is no library yet
+    pub fn load(&mut self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        self.index = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|err| {
+                Error::msg(format!(
+                    "error: could not read the library at {}. {err}",
+                    path.display()
+                ))
+            })?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "error: could not open the library at {}. {err}",
+                    path.display()
+                )))
+            }
+        };
+        Ok(())
+    }
+
+    fn persist(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let text = serde_json::to_string(&self.index)?;
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(path, text))
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not save the library to {}. {err}",
+                    path.display()
+                ))
+            })
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.index.roots
+    }
+
+    pub fn sounds(&self) -> &[Entry] {
+        &self.index.sounds
+    }
+
+    pub fn sound_count(&self) -> usize {
+        self.index.sounds.len()
+    }
+
+    pub fn add_root(&mut self, folder: &Path) -> Result<PathBuf, Error> {
+        let folder = folder.canonicalize().map_err(|err| {
+            Error::msg(format!(
+                "error: could not find the folder {}. {err}",
+                folder.display()
+            ))
+        })?;
+        if !folder.is_dir() {
+            return Err(Error::msg(format!(
+                "error: {} is not a folder.",
+                folder.display()
+            )));
+        }
+        if let Some(root) = self
+            .index
+            .roots
+            .iter()
+            .find(|root| folder.starts_with(root))
+        {
+            let part_of = if *root == folder {
+                String::new()
+            } else {
+                format!(", as part of {}", root.display())
+            };
+            return Err(Error::msg(format!(
+                "error: {} is in the library already{part_of}.",
+                folder.display()
+            )));
+        }
+        // the folders inside it are part of it now
+        self.index.roots.retain(|root| !root.starts_with(&folder));
+        self.index.roots.push(folder.clone());
+        self.persist()?;
+        Ok(folder)
+    }
+
+    // the sounds that were found in the folder are forgotten with it
+    pub fn remove_root(&mut self, folder: &Path) -> Result<PathBuf, Error> {
+        let folder = folder
+            .canonicalize()
+            .unwrap_or_else(|_| folder.to_path_buf());
+        let Some(index) = self.index.roots.iter().position(|root| *root == folder) else {
+            return Err(Error::msg(format!(
+                "error: {} is not in the library.",
+                folder.display()
+            )));
+        };
+        self.index.roots.remove(index);
+        let roots = &self.index.roots;
+        self.index
+            .sounds
+            .retain(|sound| roots.iter().any(|root| sound.path.starts_with(root)));
+        self.results.clear();
+        self.persist()?;
+        Ok(folder)
+    }
+
+    // what a scan needs to know, so it can look through the folders without holding on to the
+    // library
+    pub fn start_scan(&self) -> Scan {
+        Scan {
+            roots: outermost(&self.index.roots),
+            known: self
+                .index
+                .sounds
+                .iter()
+                .map(|sound| (sound.path.clone(), sound.clone()))
+                .collect(),
+        }
+    }
+
+    // takes in the sounds that a scan found. The sounds of folders that it didn't look through
+    // stay, and those of folders that were removed in the meantime go. Returns how many sounds
+    // there are
+    pub fn finish_scan(&mut self, scanned: Scanned) -> Result<usize, Error> {
+        let Scanned { roots, mut sounds } = scanned;
+        sounds.extend(
+            self.index
+                .sounds
+                .drain(..)
+                .filter(|sound| !roots.iter().any(|root| sound.path.starts_with(root))),
+        );
+        let roots = &self.index.roots;
+        sounds.retain(|sound| roots.iter().any(|root| sound.path.starts_with(root)));
+        sounds.sort_by_key(|sound| sound.name().to_lowercase());
+        self.index.sounds = sounds;
+        self.results.clear();
+        self.persist()?;
+        Ok(self.index.sounds.len())
+    }
+
+    // the sounds whose name or tags have all the words in them, ignoring case. They are numbered
+    // from 1 for library add
+    pub fn search(&mut self, query: &[String]) -> &[Entry] {
+        let words: Vec<String> = query.iter().map(|word| word.to_lowercase()).collect();
+        self.results = self
+            .index
+            .sounds
+            .iter()
+            .filter(|sound| sound.matches(&words))
+            .cloned()
+            .collect();
+        &self.results
+    }
+
+    pub fn result(&self, number: usize) -> Result<&Entry, Error> {
+        if self.results.is_empty() {
+            return Err(Error::msg(
+                "error: there are no search results. Search with library search first.",
+            ));
+        }
+        number
+            .checked_sub(1)
+            .and_then(|index| self.results.get(index))
+            .ok_or_else(|| {
+                Error::msg(format!(
+                    "error: there is no result {number}. The last search found {}.",
+                    self.results.len()
+                ))
+            })
+    }
+}
+
+pub struct Scan {
+    roots: Vec<PathBuf>,
+    known: HashMap<PathBuf, Entry>,
+}
+
+pub struct Scanned {
+    roots: Vec<PathBuf>,
+    sounds: Vec<Entry>,
+}
+
+impl Scan {
+    // looks through the folders again, for sounds that were added, changed or removed. Returns
+    // the sounds, and how many of them had to be decoded
+    pub fn run(self, progress: &mut dyn FnMut(usize, usize, &str)) -> (Scanned, usize) {
+        let mut files = Vec::new();
+        for root in &self.roots {
+            find_sounds(root, root, &mut files);
+        }
+        let mut sounds = Vec::with_capacity(files.len());
+        let mut decoded = 0;
+        for (done, (path, tags)) in files.iter().enumerate() {
+            progress(done, files.len(), &path.display().to_string());
+            let metadata = fs::metadata(path).ok();
+            let size = metadata.as_ref().map_or(0, |meta| meta.len());
+            let modified = metadata.and_then(|meta| meta.modified().ok());
+            let length = match self.known.get(path) {
+                Some(sound) if sound.size == size && sound.modified == modified => sound.length,
+                _ => {
+                    decoded += 1;
+                    probe_length(path, None)
+                }
+            };
+            sounds.push(Entry {
+                path: path.clone(),
+                length,
+                tags: tags.clone(),
+                size,
+                modified,
+            });
+        }
+        let roots = self.roots;
+        (Scanned { roots, sounds }, decoded)
+    }
+}
+
+// the roots without the ones that are inside another, so no sound is found twice. Roots from before
+// they were made canonical are made so here
+fn outermost(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = roots
+        .iter()
+        .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+        .collect();
+    roots.sort();
+    roots.dedup();
+    let all = roots.clone();
+    roots.retain(|root| {
+        !all.iter()
+            .any(|other| other != root && root.starts_with(other))
+    });
+    roots
+}
+
+// the sounds in a folder and the folders in it, with the names of the folders between root and
+// them. Links to folders are not followed, so a link to a folder above can't loop forever
+fn find_sounds(root: &Path, folder: &Path, found: &mut Vec<(PathBuf, Vec<String>)>) {
+    let mut entries: Vec<_> = fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(kind) if kind.is_dir() => find_sounds(root, &path, found),
+            Ok(_) if is_supported(&path) => {
+                let tags = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(root).ok())
+                    .into_iter()
+                    .flat_map(|parent| parent.components())
+                    .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                found.push((path, tags));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn nested_folders_are_scanned_once() {
+    let dir = crate::testing::TempDir::new("library");
+    let weather = dir.join("sounds/weather");
+    fs::create_dir_all(&weather).unwrap();
+    crate::testing::write_tone(&weather.join("rain.wav"), Duration::from_secs(1));
+    let mut library = Library {
+        index: Index::default(),
+        results: Vec::new(),
+        path: None,
+    };
+    library.add_root(&weather).unwrap();
+    assert!(library.add_root(&weather).is_err());
+    let sounds = library.add_root(&dir.join("sounds")).unwrap();
+    assert_eq!(library.roots().len(), 1);
+    assert!(library.add_root(&weather).is_err());
+    // older libraries could have both
+    library.index.roots.push(weather.join("."));
+    let (scanned, decoded) = library.start_scan().run(&mut |_, _, _| {});
+    assert_eq!(decoded, 1);
+    assert_eq!(library.finish_scan(scanned).unwrap(), 1);
+    assert_eq!(library.index.sounds[0].tags, ["weather"]);
+    library.index.roots.pop();
+    // a folder that is removed while it is scanned doesn't come back
+    let scan = library.start_scan();
+    library.remove_root(&sounds).unwrap();
+    let (scanned, decoded) = scan.run(&mut |_, _, _| {});
+    assert_eq!(decoded, 0);
+    assert_eq!(library.finish_scan(scanned).unwrap(), 0);
+}
+
+#[test]
+fn sounds_are_found_by_name_and_folder() {
+    let entry = |path: &str, tags: &[&str]| Entry {
+        path: PathBuf::from(path),
+        length: None,
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        size: 0,
+        modified: None,
+    };
+    let mut library = Library {
+        index: Index {
+            roots: Vec::new(),
+            sounds: vec![
+                entry("/sounds/weather/Heavy Rain.ogg", &["weather"]),
+                entry("/sounds/weather/thunder.ogg", &["weather"]),
+                entry("/sounds/town/rain on roof.mp3", &["town"]),
+            ],
+        },
+        results: Vec::new(),
+        path: None,
+    };
+    let search = |library: &mut Library, query: &str| -> Vec<String> {
+        let words: Vec<String> = query.split_whitespace().map(String::from).collect();
+        library.search(&words).iter().map(Entry::name).collect()
+    };
+    assert_eq!(search(&mut library, "RAIN"), ["Heavy Rain", "rain on roof"]);
+    assert_eq!(search(&mut library, "weather rain"), ["Heavy Rain"]);
+    assert_eq!(library.result(1).unwrap().name(), "Heavy Rain");
+    assert!(library.result(2).is_err());
+    assert!(search(&mut library, "owl").is_empty());
+    assert!(library.result(1).is_err());
+}
@@ -0,0 +1,215 @@
+use rodio::Source;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::control::SAMPLES_PER_UPDATE;
+// how long it takes for the gain to recover after a peak
+const RELEASE: Duration = Duration::from_millis(200);
+// how fast the reported peak falls back, in dB per second, so that short peaks can still be read
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+
+pub fn to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+pub fn from_db(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[derive(Clone, Copy)]
+struct LimiterSettings {
+    enabled: bool,
+    threshold: f32,
+}
+
+// what the limiter has seen lately
+#[derive(Clone, Copy)]
+pub struct Levels {
+    // the loudest recent sample going into the limiter
+    pub peak: f32,
+    // what the limiter is taking off, as a gain. 1 means it isn't doing anything
+    pub gain: f32,
+}
+
+struct LimiterState {
+    settings: LimiterSettings,
+    levels: Levels,
+}
+
+#[derive(Clone)]
+pub struct LimiterControl(Arc<Mutex<LimiterState>>);
+
+impl LimiterControl {
+    pub fn new(threshold: f32, enabled: bool) -> Self {
+        Self(Arc::new(Mutex::new(LimiterState {
+            settings: LimiterSettings { enabled, threshold },
+            levels: Levels {
+                peak: 0.0,
+                gain: 1.0,
+            },
+        })))
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.0.lock().unwrap().settings.enabled = enabled;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.0.lock().unwrap().settings.enabled
+    }
+
+    pub fn set_threshold(&self, threshold: f32) {
+        self.0.lock().unwrap().settings.threshold = threshold;
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.0.lock().unwrap().settings.threshold
+    }
+
+    pub fn levels(&self) -> Levels {
+        self.0.lock().unwrap().levels
+    }
+
+    fn settings(&self) -> LimiterSettings {
+        self.0.lock().unwrap().settings
+    }
+
+    // hands over the levels, and gets the latest settings back
+    fn exchange(&self, levels: Levels) -> LimiterSettings {
+        let mut state = self.0.lock().unwrap();
+        state.levels = levels;
+        state.settings
+    }
+}
+
+// a peak limiter with an instant attack, so nothing gets past the threshold
+pub struct Limiter<S> {
+    input: S,
+    control: LimiterControl,
+    settings: LimiterSettings,
+    until_update: usize,
+    envelope: f32,
+    release: f32,
+    // the levels since the last update
+    window_peak: f32,
+    window_gain: f32,
+    held_peak: f32,
+    peak_fall: f32,
+}
+
+impl<S> Limiter<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: LimiterControl) -> Self {
+        let samples_per_second = input.sample_rate() as f32 * input.channels() as f32;
+        Self {
+            settings: control.settings(),
+            input,
+            control,
+            until_update: SAMPLES_PER_UPDATE,
+            envelope: 0.0,
+            release: (-1.0 / (RELEASE.as_secs_f32() * samples_per_second)).exp(),
+            window_peak: 0.0,
+            window_gain: 1.0,
+            held_peak: 0.0,
+            peak_fall: 10f32.powf(
+                -PEAK_FALL_DB_PER_SECOND * SAMPLES_PER_UPDATE as f32 / samples_per_second / 20.0,
+            ),
+        }
+    }
+}
+
+impl<S> Iterator for Limiter<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        if self.until_update == 0 {
+            self.held_peak = self.window_peak.max(self.held_peak * self.peak_fall);
+            self.settings = self.control.exchange(Levels {
+                peak: self.held_peak,
+                gain: self.window_gain,
+            });
+            self.window_peak = 0.0;
+            self.window_gain = 1.0;
+            self.until_update = SAMPLES_PER_UPDATE;
+        }
+        self.until_update -= 1;
+
+        self.window_peak = self.window_peak.max(sample.abs());
+        self.envelope = sample.abs().max(self.envelope * self.release);
+        if !self.settings.enabled || self.envelope <= self.settings.threshold {
+            return Some(sample);
+        }
+        let gain = self.settings.threshold / self.envelope;
+        self.window_gain = self.window_gain.min(gain);
+        Some(sample * gain)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Limiter<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[test]
+fn limiter_keeps_peaks_below_threshold() {
+    let samples = vec![0.1, 0.9, -1.5, 0.3, 0.05];
+    let input = rodio::buffer::SamplesBuffer::new(1, 44_100, samples.clone());
+    let control = LimiterControl::new(0.5, true);
+    let limited: Vec<f32> = Limiter::new(input, control).collect();
+    assert_eq!(limited[0], 0.1);
+    assert!(limited
+        .iter()
+        .all(|sample| sample.abs() <= 0.5 + f32::EPSILON));
+
+    let input = rodio::buffer::SamplesBuffer::new(1, 44_100, samples.clone());
+    let control = LimiterControl::new(0.5, false);
+    let untouched: Vec<f32> = Limiter::new(input, control).collect();
+    assert_eq!(untouched, samples);
+}
+
+#[test]
+fn limiter_reports_levels() {
+    let mut samples = vec![0.1f32; 1024];
+    samples[10] = 1.0;
+    let input = rodio::buffer::SamplesBuffer::new(1, 44_100, samples);
+    let control = LimiterControl::new(0.5, true);
+    let mut limiter = Limiter::new(input, control.clone());
+    limiter.by_ref().take(SAMPLES_PER_UPDATE + 1).for_each(drop);
+    let levels = control.levels();
+    assert_eq!(levels.peak, 1.0);
+    assert!((levels.gain - 0.5).abs() < 1e-6);
+
+    // the peak falls back slowly once it has passed
+    limiter.for_each(drop);
+    let levels = control.levels();
+    assert!(levels.peak < 1.0 && levels.peak > 0.9);
+    assert!(levels.gain > 0.5);
+}
@@ -1,70 +1,519 @@
+use aliases::Aliases;
 use anyhow::Error;
-use clap::Parser;
+use chrono::{Local, NaiveTime};
+use clap::{builder::BoolishValueParser, CommandFactory, Parser, Subcommand, ValueEnum};
+use completion::{CommandHelper, Names};
+use config::Config;
 use const_format::formatcp;
+use cue::CueList;
+use discord::Discord;
+use discovery::Api;
+use ducking::Duck;
+use events::{AppEvent, EventBus, Observer, SubscriptionId};
+use group_loop::GroupLoop;
+use hotkeys::Hotkeys;
 use indexmap::{IndexMap, IndexSet};
+use library::Library;
+use limiter::LimiterControl;
+use livesplit_hotkey::Hotkey;
+use midi::{ControlKind, Midi};
+use mixer::Mixer;
+use oneshot::OneShot;
 use operations::{
-    add, delay, exit, group, load, pause, play, remove, save, set_end, set_start, set_volume, show,
-    stop, toggle_loop, ungroup, unloop, RespondResult,
+    add, add_from_web, add_video, alias, apply_manifest, archive, balance, cache_build,
+    cache_clear, cancel, config_get, config_set, cue_add, cue_go, cue_list, cue_reset, delay,
+    diagnostics, discord_join, discord_leave, duck, dump, edit_mode, exit, export_script,
+    fade_volume, foreach, group, group_defaults, hotkey_bind, hotkey_list, hotkey_unbind,
+    import_soundscape, library_add, library_add_root, library_remove_root, library_roots,
+    library_scan, library_search, link, list_variables, load, log, loop_group, mark_add, mark_list,
+    mark_remove, master_limit, midi_connect, midi_disconnect, midi_list, midi_map, midi_ports,
+    midi_unmap, monitor, mono_check, normalize, open_dashboard, output_devices, output_use,
+    pairing_forget, pairing_list, paste_add, pause, play, playlist, preflight, quiet_hours,
+    remap_add, remap_list, remove, render, reopen_last, replay_last, resume, run_alias, save,
+    scene_capture, scene_list, scene_save, scene_switch, scene_volume, schedule_after, schedule_at,
+    schedule_cancel, schedule_list, seek, set_cooldown, set_default_group, set_end, set_fades,
+    set_loop_region, set_pan, set_speed, set_start, set_stretch, set_variable, set_volume,
+    set_weight, set_width, show, show_board, show_ducks, show_meter, show_pending, show_waveform,
+    stop, suggest, suspend, switch, tag_add, tag_list, tag_remove, toggle_loop, unalias, unarchive,
+    unduck, ungroup, unlink, unloop, unloop_group, unplaylist, unset_variable, variant, version,
+    Point, RespondResult,
 };
-use player::Player;
+use pairing::Pairing;
+use player::{parse_timestamp, parse_volume, Player};
+use playlist::Playlist;
+use progress::Spinner;
 use rustyline::error::ReadlineError;
 use rustyline::history::FileHistory;
-use rustyline::{DefaultEditor, Editor};
-use std::cell::RefCell;
+use rustyline::Editor;
+use scene::Scene;
+use schedule::Schedule;
+use session_log::SessionLog;
+use settings::{Bus, Effective, Settings};
+use snapshot::StateSnapshot;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::{path::PathBuf, time::Duration};
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::{
+    fs,
+    fs::File,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+// like println, but it goes to whoever gave the command. The macro has to come before the
+// modules that use it
+macro_rules! say {
+    ($($arg:tt)*) => {
+        $crate::output::say(&format!($($arg)*))
+    };
+}
 
+mod aliases;
+mod analysis;
+mod batch;
+mod board;
+mod cache;
+mod clipboard;
+mod completion;
+mod config;
+mod control;
+mod cooldown;
+mod cue;
+mod discord;
+mod discovery;
+mod download;
+mod ducking;
+mod effects;
+mod events;
+mod fade;
+mod fuzzy;
+mod group_loop;
+mod handles;
+mod hooks;
+mod hotkeys;
+mod http;
+mod import;
+mod library;
+mod limiter;
+mod manifest;
+mod metadata;
+mod meter;
+mod midi;
+mod mixer;
+mod oneshot;
 mod operations;
+mod output;
+mod pairing;
+mod pending;
 mod player;
+mod playhead;
+mod playlist;
+mod progress;
+mod project;
+mod remap;
+mod remote;
+mod render;
+mod repeat;
+mod save_format;
+mod scene;
+mod schedule;
+mod script;
+mod server;
+mod session_log;
+mod settings;
+mod shutdown;
+mod snapshot;
+mod stretch;
+mod suggest;
+#[cfg(test)]
+mod testing;
+mod tui;
+mod typeahead;
+mod variables;
+mod watch_folder;
+mod waveform;
 
-//TODO: Implement a sound length feature, based on amount samples
 //TODO: add fades toggle
 //TODO: make a nice GUI
 //VERY FAR FUTURE: add a special mapping feature (dungeon vtt-esque)
 
-const ADD_USAGE: &str = "add -p <PATH> -n <NAME>";
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const ADD_USAGE: &str = "add (-p <PATH> | --youtube <URL>) [-n <NAME>]";
+const PASTE_ADD_USAGE: &str = "paste-add [-n <NAME>]";
 const REMOVE_USAGE: &str = "remove [IDs]";
-const SHOW_USAGE: &str = "show [IDs] [-g <GROUPS>]";
-const PLAY_USAGE: &str = "play [IDs] [-g <GROUPS>]";
-const STOP_USAGE: &str = "stop [IDs] [-g <GROUPS>]";
-const PAUSE_USAGE: &str = "pause [IDs] [-g <GROUPS>]";
+const SHOW_USAGE: &str = "show [IDs] [-g <GROUPS>] [-l] [-e] [-v]";
+const ARCHIVE_USAGE: &str = "archive <IDs>";
+const UNARCHIVE_USAGE: &str = "unarchive <IDs>";
+const PLAY_USAGE: &str = "play [IDs] [-g <GROUPS>] [-f <FROM>]";
+const STOP_USAGE: &str = "stop [IDs] [-g <GROUPS>] [-f <DURATION>]";
+const PAUSE_USAGE: &str = "pause [IDs] [-g <GROUPS>] [-f <DURATION>]";
 const VOLUME_USAGE: &str = "volume [IDs] [-g <GROUPS>] -v <VOLUME>";
-const LOOP_USAGE: &str = "loop [IDs] [-g <GROUPS>] [-d <DURATION>]";
+const FADE_USAGE: &str = "fade [IDs] [-g <GROUPS>] -v <VOLUME> -d <DURATION>";
+const WIDTH_USAGE: &str = "width [IDs] [-g <GROUPS>] -w <WIDTH>";
+const NORMALIZE_USAGE: &str = "normalize [IDs] [-g <GROUPS>]";
+const BALANCE_USAGE: &str = "balance -g <GROUP> [-t <TARGET>] [--reset-relative]";
+const PAN_USAGE: &str = "pan [IDs] [-g <GROUPS>] -p <PAN>";
+const SPEED_USAGE: &str = "speed [IDs] [-g <GROUPS>] -s <SPEED>";
+const STRETCH_USAGE: &str = "stretch [IDs] [-g <GROUPS>] [-l <LENGTH>]";
+const LOOP_USAGE: &str = "loop [IDs] [-g <GROUPS>] [-d <DURATION> | -a] [-n <COUNT>]";
+const SET_LOOP_REGION_USAGE: &str = "set-loop-region [IDs] [-g <GROUPS>] [-a <POS> -b <POS>]";
 const UNLOOP_USAGE: &str = "unloop [IDs] [-g <GROUPS>]";
 const SET_START_USAGE: &str = "set-start [IDs] [-g <GROUPS>] -p <POS>";
 const SET_END_USAGE: &str = "set-end [IDs] [-g <GROUPS>] [-p <POS>]";
 const DELAY_USAGE: &str = "delay [IDs] [-g <GROUPS>] -d <DURATION>";
+const COOLDOWN_USAGE: &str = "cooldown [IDs] [-g <GROUPS>] -d <DURATION> [-q]";
+const FADES_USAGE: &str = "fades [IDs] [-g <GROUPS>] [-i <DURATION>] [-o <DURATION>] [--soft-start <on|off>] [--bus <master|edit>] [--inherit]";
+const SEEK_USAGE: &str = "seek [IDs] [-g <GROUPS>] (-t <POS> | -m <MARKER>)";
+const MARK_ADD_USAGE: &str = "mark add <ID> <NAME> <POS>";
+const MARK_REMOVE_USAGE: &str = "mark remove <ID> <NAME>";
+const MARK_LIST_USAGE: &str = "mark list [IDs]";
+const MARK_USAGE: &str =
+    formatcp!("{MARK_ADD_USAGE}\n       {MARK_REMOVE_USAGE}\n       {MARK_LIST_USAGE}");
+const TAG_ADD_USAGE: &str = "tag add <ID> <TAGS>";
+const TAG_REMOVE_USAGE: &str = "tag remove <ID> <TAGS>";
+const TAG_LIST_USAGE: &str = "tag list";
+const TAG_USAGE: &str =
+    formatcp!("{TAG_ADD_USAGE}\n       {TAG_REMOVE_USAGE}\n       {TAG_LIST_USAGE}");
+const EDIT_MODE_USAGE: &str = "edit-mode [IDs] [-g <GROUPS>]";
 const GROUP_USAGE: &str = "group [IDs] -g <GROUP>";
+const VARIANT_USAGE: &str = "variant -g <GROUP> -s <SUFFIX> [--volume-scale <SCALE>]";
 const UNGROUP_USAGE: &str = "ungroup [IDs] -g <GROUP>";
-const SAVE_USAGE: &str = "save -p <PATH>";
-const LOAD_USAGE: &str = "load -p <PATH>";
+const SET_DEFAULT_GROUP_USAGE: &str = "set default-group [GROUP]";
+const SET_VARIABLE_USAGE: &str = "set [$NAME] [VALUE]";
+const SET_USAGE: &str = formatcp!("{SET_DEFAULT_GROUP_USAGE}\n       {SET_VARIABLE_USAGE}");
+const UNSET_USAGE: &str = "unset <$NAME>";
+const LOOP_GROUP_USAGE: &str = "loop-group -g <GROUP> [-l <LEADER> | -d <DURATION> | --lock]";
+const UNLOOP_GROUP_USAGE: &str = "unloop-group -g <GROUP>";
+const GROUP_DEFAULTS_USAGE: &str =
+    "group-defaults -g <GROUP> [-i <DURATION>] [-o <DURATION>] [--soft-start <on|off>] [--bus <master|edit>] [--clear]";
+const PLAYLIST_USAGE: &str = "playlist -g <GROUP> [-c <COOLDOWN>]";
+const UNPLAYLIST_USAGE: &str = "unplaylist -g <GROUP>";
+const WEIGHT_USAGE: &str = "weight <IDs> -w <WEIGHT>";
+const QUIET_HOURS_USAGE: &str = "quiet-hours [on|off|auto]";
+const MASTER_LIMIT_USAGE: &str = "master-limit [on|off] [-t <THRESHOLD>]";
+const DUCK_USAGE: &str = "duck [-w <GROUP> -t <GROUP> -a <AMOUNT> [-r <DURATION>]]";
+const UNDUCK_USAGE: &str = "unduck -t <GROUP> [-w <GROUP>]";
+const LINK_USAGE: &str = "link <IDs> -l <LINK>";
+const UNLINK_USAGE: &str = "unlink <IDs>";
+const PENDING_USAGE: &str = "pending";
+const CANCEL_USAGE: &str = "cancel <IDs>";
+const AFTER_USAGE: &str = "after <DURATION> <COMMAND>";
+const AT_USAGE: &str = "at <TIME> <COMMAND>";
+const SCHEDULE_LIST_USAGE: &str = "schedule list";
+const SCHEDULE_CANCEL_USAGE: &str = "schedule cancel <IDs>";
+const SCHEDULE_USAGE: &str = formatcp!("{SCHEDULE_LIST_USAGE}\n       {SCHEDULE_CANCEL_USAGE}");
+const CUE_ADD_USAGE: &str = "cue add <COMMANDS>";
+const CUE_LIST_USAGE: &str = "cue list";
+const CUE_GO_USAGE: &str = "cue go";
+const CUE_RESET_USAGE: &str = "cue reset";
+const CUE_USAGE: &str = formatcp!(
+    "{CUE_ADD_USAGE}\n       {CUE_LIST_USAGE}\n       {CUE_GO_USAGE}\n       {CUE_RESET_USAGE}"
+);
+const FOREACH_USAGE: &str = "foreach [-y] [--dry-run] <REGEX> -- <COMMAND>";
+const VERSION_USAGE: &str = "version";
+const SUSPEND_USAGE: &str = "suspend";
+const RESUME_USAGE: &str = "resume";
+const SUGGEST_USAGE: &str = "suggest [-n <COUNT>]";
+const SCENE_CAPTURE_USAGE: &str = "scene capture <NAME>";
+const SCENE_SAVE_USAGE: &str = "scene save <NAME>";
+const SCENE_SWITCH_USAGE: &str = "scene switch <NAME> [-f <DURATION>]";
+const SCENE_VOLUME_USAGE: &str = "scene volume <NAME> <IDs> [-v <VOLUME>]";
+const SCENE_LIST_USAGE: &str = "scene list";
+const SCENE_USAGE: &str = formatcp!("{SCENE_CAPTURE_USAGE}\n       {SCENE_SAVE_USAGE}\n       {SCENE_SWITCH_USAGE}\n       {SCENE_VOLUME_USAGE}\n       {SCENE_LIST_USAGE}");
+const WAVEFORM_USAGE: &str = "waveform [IDs] [-g <GROUPS>] [-w <WIDTH>]";
+const MONO_CHECK_USAGE: &str = "mono-check [IDs] [-g <GROUPS>]";
+const METER_USAGE: &str = "meter [IDs] [-g <GROUPS>] [-d <DURATION>]";
+const BOARD_USAGE: &str = "board [-d <DURATION>]";
+const MONITOR_USAGE: &str = "monitor";
+const DUMP_USAGE: &str = "dump";
+const DIAGNOSTICS_USAGE: &str = "diagnostics";
+const TUI_USAGE: &str = "tui";
+const PREFLIGHT_USAGE: &str = "preflight";
+const PAIRING_LIST_USAGE: &str = "pairing list";
+const PAIRING_FORGET_USAGE: &str = "pairing forget <NAME>";
+const PAIRING_USAGE: &str = formatcp!("{PAIRING_LIST_USAGE}\n       {PAIRING_FORGET_USAGE}");
+const ALIAS_USAGE: &str = "alias [NAME] [COMMANDS]";
+const UNALIAS_USAGE: &str = "unalias <NAME>";
+const HOTKEY_BIND_USAGE: &str = "hotkey bind <KEYS> <COMMAND>";
+const HOTKEY_UNBIND_USAGE: &str = "hotkey unbind <KEYS>";
+const HOTKEY_LIST_USAGE: &str = "hotkey list";
+const HOTKEY_USAGE: &str =
+    formatcp!("{HOTKEY_BIND_USAGE}\n       {HOTKEY_UNBIND_USAGE}\n       {HOTKEY_LIST_USAGE}");
+const MIDI_PORTS_USAGE: &str = "midi ports";
+const MIDI_CONNECT_USAGE: &str = "midi connect [PORT]";
+const MIDI_DISCONNECT_USAGE: &str = "midi disconnect";
+const MIDI_MAP_USAGE: &str = "midi map <note|cc> <NUMBER> <COMMAND> [-c <CHANNEL>]";
+const MIDI_UNMAP_USAGE: &str = "midi unmap <note|cc> <NUMBER> [-c <CHANNEL>]";
+const MIDI_LIST_USAGE: &str = "midi list";
+const MIDI_USAGE: &str = formatcp!("{MIDI_PORTS_USAGE}\n       {MIDI_CONNECT_USAGE}\n       {MIDI_DISCONNECT_USAGE}\n       {MIDI_MAP_USAGE}\n       {MIDI_UNMAP_USAGE}\n       {MIDI_LIST_USAGE}");
+const DISCORD_JOIN_USAGE: &str = "discord join <GUILD> <CHANNEL>";
+const DISCORD_LEAVE_USAGE: &str = "discord leave";
+const DISCORD_USAGE: &str = formatcp!("{DISCORD_JOIN_USAGE}\n       {DISCORD_LEAVE_USAGE}");
+const OUTPUT_DEVICES_USAGE: &str = "output devices";
+const OUTPUT_USE_USAGE: &str = "output use [DEVICE]";
+const OUTPUT_USAGE: &str = formatcp!("{OUTPUT_DEVICES_USAGE}\n       {OUTPUT_USE_USAGE}");
+const CACHE_BUILD_USAGE: &str = "cache build";
+const CACHE_CLEAR_USAGE: &str = "cache clear";
+const CACHE_USAGE: &str = formatcp!("{CACHE_BUILD_USAGE}\n       {CACHE_CLEAR_USAGE}");
+const LIBRARY_ROOTS_USAGE: &str = "library roots";
+const LIBRARY_ADD_ROOT_USAGE: &str = "library add-root <FOLDER>";
+const LIBRARY_REMOVE_ROOT_USAGE: &str = "library remove-root <FOLDER>";
+const LIBRARY_SCAN_USAGE: &str = "library scan";
+const LIBRARY_SEARCH_USAGE: &str = "library search <QUERY>...";
+const LIBRARY_ADD_USAGE: &str = "library add <RESULT> [-n <NAME>]";
+const LIBRARY_USAGE: &str = formatcp!("{LIBRARY_ROOTS_USAGE}\n       {LIBRARY_ADD_ROOT_USAGE}\n       {LIBRARY_REMOVE_ROOT_USAGE}\n       {LIBRARY_SCAN_USAGE}\n       {LIBRARY_SEARCH_USAGE}\n       {LIBRARY_ADD_USAGE}");
+const APPLY_MANIFEST_USAGE: &str = "apply-manifest -p <PATH>";
+const LOG_USAGE: &str = "log [TEXT] [-e <PATH>]";
+const EXPORT_SCRIPT_USAGE: &str = "export-script -p <PATH>";
+const RENDER_USAGE: &str = "render -d <DURATION> -p <PATH>";
+const SAVE_USAGE: &str = "save -p <PATH> [--project]";
+const LOAD_USAGE: &str = "load -p <PATH> [--resume-playback]";
+const SWITCH_USAGE: &str = "switch -p <PATH> [-f <DURATION>]";
+const IMPORT_USAGE: &str = "import -p <PATH> -o <OUT>";
+const REMAP_ADD_USAGE: &str = "remap add <FROM> <TO>";
+const REMAP_LIST_USAGE: &str = "remap list";
+const REMAP_USAGE: &str = formatcp!("{REMAP_ADD_USAGE}\n       {REMAP_LIST_USAGE}");
+const CONFIG_GET_USAGE: &str = "config get [KEY]";
+const CONFIG_SET_USAGE: &str = "config set <KEY> <VALUE>";
+const CONFIG_USAGE: &str = formatcp!("{CONFIG_GET_USAGE}\n       {CONFIG_SET_USAGE}");
 
 const NO_ID_ADDENDUM: &str = "When called without ID, this will select the last added sound.";
 
 const ABOUT_ADD: &str = "Adds a sound to the soundscape.";
 const ABOUT_ADD_LONG: &str =
-    "Adds a sound to the soundscape. Added sounds will not start playing until you call play.";
+    "Adds a sound to the soundscape. Added sounds will not start playing until you call play. PATH can be a web address, like https://example.com/rain.ogg, which is downloaded once and kept. With --youtube, the sound of a video is downloaded with yt-dlp instead. Without NAME, the sound is named after the title in its tags, or else after the file.";
+const ABOUT_PASTE_ADD: &str = "Adds the sound whose path is on the clipboard.";
+const ABOUT_PASTE_ADD_LONG: &str = "Adds the sound whose path is on the clipboard, like one copied from a file manager. Asks for the name when NAME is not supplied, with the file name as the default.";
 const ABOUT_REMOVE: &str = "Removes sounds from the soundscape.";
-const ABOUT_VOLUME: &str = "Sets the volume as a percentage. Can be higher than 100%";
+const ABOUT_ARCHIVE: &str =
+    "Puts sounds aside: they leave the soundscape, but their settings are kept in the save.";
+const ABOUT_ARCHIVE_LONG: &str = "Puts sounds aside: they leave the soundscape, but their settings are kept in the save. They are taken out of their scenes, but return to their group when they are unarchived.";
+const ABOUT_UNARCHIVE: &str = "Brings archived sounds back into the soundscape.";
+const ABOUT_VOLUME: &str =
+    "Sets the volume as a percentage, or in dB, like -6dB. Can be higher than 100% or 0dB";
+const ABOUT_FADE: &str = "Changes the volume of sounds gradually over DURATION, while they keep playing. A fade that is still going is replaced by the new one.";
+const ABOUT_WIDTH: &str =
+    "Sets the stereo width as a percentage. 0% is mono, 100% is unchanged and 200% is extra wide.";
+const ABOUT_NORMALIZE: &str = "Turns normalization on or off for sounds. Normalized sounds are made louder or quieter, so that they all play at a comparable level before their volume is applied.";
+const ABOUT_BALANCE: &str = "Measures how loud the sounds in a group are heard, and changes their volumes to bring the group to a target level in dB (-20 by default). The differences you made between them are kept, unless --reset-relative is given, which puts every sound on the target.";
+const ABOUT_PAN: &str =
+    "Places sounds in the stereo field, from -1 for all the way left to 1 for all the way right.";
+const ABOUT_STRETCH: &str = "Stretches sounds to LENGTH without changing their pitch, like a 58s loop to exactly 60s, to line it up with another. One pass through the sound is stretched, with its cuts and loop length. It can be made at most twice as long or half as long. Reset by omitting LENGTH.";
+const ABOUT_SPEED: &str = "Sets how fast sounds play, from 0.25 to 4. The pitch changes with it: 0.8 makes rain sound heavier, 1.2 lighter.";
 const ABOUT_SHOW: &str = "Shows the status and configuration of sounds.";
+const ABOUT_SHOW_LONG: &str = "Shows the status and configuration of sounds. With -l, the output levels are shown too. With -e, so are the settings that are in effect for each sound, and whether they are its own or come from its group. With -v, the file of each sound is shown, with the title, artist and album in its tags.";
 const ABOUT_PLAY: &str = "Plays sounds.";
-const ABOUT_STOP: &str = "Stops sounds and resets the play heads to the start of each sound.";
-const ABOUT_PAUSE: &str = "Pauses sounds.";
-const ABOUT_LOOP: &str = "Loops sounds at the end of their play length or DURATION, if supplied.";
-const ABOUT_LOOP_LONG: &str = "Loops sounds the end of their play length or the DURATION, if supplied. DURATION can be longer than the sounds lengths.";
+const ABOUT_PLAY_LONG: &str = "Plays sounds. With FROM, they play from that marker, or from that position, like 1:32, even when they are already playing.";
+const ABOUT_STOP: &str = "Stops sounds and resets the play heads to the start of each sound. With --fade, they fade out over DURATION first, instead of over their own fade out.";
+const ABOUT_PAUSE: &str = "Pauses sounds. With --fade, they fade out over DURATION first, and are resumed at their volume.";
+const ABOUT_LOOP: &str = "Loops sounds at the end of their play length or DURATION, if supplied. With COUNT, they play that many times and then stop.";
+const ABOUT_LOOP_LONG: &str = "Loops sounds the end of their play length or the DURATION, if supplied. DURATION can be longer than the sounds lengths. With --auto, the start and end are moved a little, to where the sound loops without a click. With --count, they play COUNT times and then stop, instead of looping forever.";
+const ABOUT_SET_LOOP_REGION: &str = "Loops sounds between POS A and POS B, after an intro that plays once from the start up to B. Reset by omitting the positions.";
+const ABOUT_SET_LOOP_REGION_LONG: &str = "Loops sounds between POS A and POS B, after an intro that plays once from the start up to B, like a piece of music with an intro and a part that repeats. The positions are counted from the start of the file, like 1:32 or 92s. It turns looping on, and a loop count counts the intro as the first pass. The region ends where set-end cuts the sound off, if that is sooner, and a loop length can't be set while it is there. Reset by omitting the positions, after which the sound loops as a whole again.";
 const ABOUT_UNLOOP: &str = "Turns of looping for these sounds.";
 const ABOUT_SET_START: &str = "Clips the start of sounds by selecting the starting position.";
 const ABOUT_SET_END: &str =
     "Clips the end of sounds by selecting the ending position. Reset by omitting POS.";
 const ABOUT_DELAY: &str =
     "Delays playing the sound after the play command. Useful when you play multiple sounds at once.";
+const ABOUT_COOLDOWN: &str = "Keeps sounds from being played again within DURATION of the last play, like a one-shot on a pad that is hit twice. Plays during the cooldown are ignored, or with -q, play the sound once it is over. A DURATION of 0 removes the cooldown.";
+const ABOUT_FADES: &str = "Sets how long sounds fade in when they are played and fade out when they are stopped, whether they start softly so they don't click, and whether they play into the master output or the edit bus. What isn't set comes from the defaults of their group. --inherit takes all of it from the group again.";
+const ABOUT_SEEK: &str = "Moves sounds that are playing or paused to a position like 1:32, or to a marker, without playing or pausing them.";
+const ABOUT_MARK: &str = "Manages markers: named points in a sound that it can be played from.";
+const ABOUT_MARK_ADD: &str =
+    "Marks POS in a sound as NAME, so it can be played from there with play -f.";
+const ABOUT_MARK_ADD_LONG: &str = "Marks POS in a sound as NAME, so it can be played from there with play -f, or moved there with seek -m, like the chorus of a long track. POS is counted from the start of the file, like 1:32 or 92s. A marker with the same name is moved. NAME can't look like a position, since play -f takes both.";
+const ABOUT_MARK_REMOVE: &str = "Removes a marker from a sound.";
+const ABOUT_MARK_LIST: &str = "Lists the markers of sounds, or of all sounds.";
+const ABOUT_TAG: &str = "Manages tags, which select sounds with @TAG wherever IDs go.";
+const ABOUT_TAG_ADD: &str =
+    "Tags a sound, so that @TAG selects it along with the other sounds with that tag.";
+const ABOUT_TAG_ADD_LONG: &str = "Tags a sound, so that @TAG selects it along with the other sounds with that tag, like play @battle. Unlike a group, a sound can have many tags.";
+const ABOUT_TAG_REMOVE: &str = "Takes tags off a sound.";
+const ABOUT_TAG_LIST: &str = "Lists the tags, with the sounds that have them.";
+const ABOUT_EDIT_MODE: &str =
+    "Toggles edit mode, in which sounds keep playing but can't be heard, while you change them.";
+const ABOUT_EDIT_MODE_LONG: &str = "Toggles edit mode, in which sounds keep playing but can't be heard, so you can adjust cuts and effects in the middle of a session without the audience hearing the half-finished sound. Running it again brings the sounds back where they are. When some of the sounds are in edit mode and some aren't, all of them go into edit mode.";
 const ABOUT_GROUP: &str =
     "Adds sounds to a group. If the group doesn't exists yet, a new one will be made.";
+const ABOUT_SET: &str = "Changes settings of the soundscape, or sets variables.";
+const ABOUT_SET_DEFAULT_GROUP: &str = "Puts sounds that are added from now on in a group.";
+const ABOUT_SET_DEFAULT_GROUP_LONG: &str = "Puts sounds that are added from now on in GROUP, instead of at the top level. The group is made when the first sound is added to it. Without GROUP, added sounds go to the top level again.";
+const ABOUT_SET_VARIABLE: &str = "Makes $NAME stand for VALUE in the commands after it, like play $NAME. Without VALUE, shows what $NAME stands for, and without $NAME, lists the variables.";
+const ABOUT_UNSET: &str = "Removes a variable.";
 const ABOUT_UNGROUP: &str =
     "Removes sounds from a group. If the group is empty after this operation, it will be removed.";
-const ABOUT_SAVE: &str = "Saves the current configuration to a file.";
-const ABOUT_LOAD: &str =
-    "Loads a saved configuration. You can choose to replace or add to current configuration.";
+const ABOUT_VARIANT: &str =
+    "Copies a group, with its members renamed and adjusted, like a night version of a forest.";
+const ABOUT_VARIANT_LONG: &str = "Copies a group, with its members renamed and adjusted, like a night version of a forest. The copy of a group and its members get -SUFFIX after their names. Their volumes are multiplied by SCALE, 1 by default. A group loop or playlist is copied along.";
+const ABOUT_PLAYLIST: &str =
+    "Plays the sounds in a group one at a time, in a random order, like background music.";
+const ABOUT_PLAYLIST_LONG: &str = "Plays the sounds in a group one at a time, in a random order, like background music. A sound doesn't come back until COOLDOWN others have played, 1 by default. Use weight to make some sounds come up more often. The sounds in the group stop looping by themselves.";
+const ABOUT_UNPLAYLIST: &str =
+    "Stops picking new sounds for a playlist group. The sound that is playing plays to its end.";
+const ABOUT_WEIGHT: &str = "Sets how often sounds come up in their playlist, compared to the others. The default is 1, and 0 takes a sound out of the rotation.";
+const ABOUT_QUIET_HOURS: &str =
+    "Shows whether it is quiet hours, or overrides the quiet hours schedule from the config.";
+const ABOUT_QUIET_HOURS_LONG: &str = "Shows whether it is quiet hours, or overrides the quiet hours schedule from the config. During quiet hours, the master volume is capped. on caps it right away and off lifts the cap, whatever the time. auto goes back to the schedule.";
+const ABOUT_MASTER_LIMIT: &str =
+    "Shows or changes the limiter on the master output, which keeps many loud sounds from distorting.";
+const ABOUT_MASTER_LIMIT_LONG: &str = "Shows or changes the limiter on the master output, which keeps many loud sounds from distorting. THRESHOLD is in dBFS, between -30 and 0, and -1 by default. The limiter is on by default.";
+const ABOUT_DUCK: &str =
+    "Lowers a group while another group can be heard, like music under a voice.";
+const ABOUT_DUCK_LONG: &str = "Lowers a group while another group can be heard, like music under a voice. The target group (-t) goes down by AMOUNT dB while anything in the -w group is heard, and comes back up over the release, 300ms by default. Without arguments, lists the ducking rules.";
+const ABOUT_UNDUCK: &str =
+    "Removes the ducking rules for a group, or only the one for the group it ducks under.";
+const ABOUT_LINK: &str = "Links the volumes of sounds, so that changing the volume of one changes the others in proportion.";
+const ABOUT_LINK_LONG: &str = "Links the volumes of sounds, so that changing the volume of one changes the others in proportion. Useful for layers of the same thing, like close and far rain. Adds the sounds to LINK if it already exists. A sound can only be in one link.";
+const ABOUT_UNLINK: &str = "Unlinks the volumes of sounds from the sounds they were linked to.";
+const ABOUT_LOOP_GROUP: &str =
+    "Loops a group as a whole. All its sounds restart together, so they stay in sync.";
+const ABOUT_LOOP_GROUP_LONG: &str = "Loops a group as a whole. All its sounds restart together, so they stay in sync. The group restarts when its longest sound ends, or when LEADER ends, if supplied. The sounds in the group stop looping by themselves. With DURATION, the sounds are locked to that loop length instead, padded with silence or cut off, so they line up at every round without any gap. --lock does the same with the length of the longest sound.";
+const ABOUT_UNLOOP_GROUP: &str = "Turns off looping for a group as a whole. Sounds that were locked to a loop length stop looping too.";
+const ABOUT_GROUP_DEFAULTS: &str =
+    "Sets the fades, soft start and bus that the sounds in a group have, unless they set their own with fades.";
+const ABOUT_GROUP_DEFAULTS_LONG: &str = "Sets the fades, soft start and bus that the sounds in a group have, unless they set their own with fades, so a big group is configured in one go. What isn't given is kept. --clear removes the defaults of the group. Without options, shows them.";
+const ABOUT_PENDING: &str = "Lists what is about to happen by itself, like sounds waiting out their delay and running fades, with the time left.";
+const ABOUT_CANCEL: &str = "Cancels things that are about to happen, by their ID in pending, or everything pending for a sound, by its name.";
+const ABOUT_CANCEL_LONG: &str = "Cancels things that are about to happen, by their ID in pending, or everything pending for a sound, by its name. A sound waiting out its delay is stopped. A sound that is fading goes back to its volume and keeps playing.";
+const ABOUT_AFTER: &str = "Runs a command once DURATION has passed, like: after 10m play owl.";
+const ABOUT_AFTER_LONG: &str = "Runs a command once DURATION has passed, like: after 10m play owl. The command is written as at the prompt, and is checked right away. Scheduled commands are kept in the save.";
+const ABOUT_AT: &str =
+    "Runs a command when the clock shows TIME, like: at 21:30 scene switch night.";
+const ABOUT_AT_LONG: &str = "Runs a command when the clock shows TIME, like: at 21:30 scene switch night. TIME is written like 21:30 or 21:30:15, and is tomorrow if it has passed today. Scheduled commands are kept in the save, but ones whose time has passed by the time the save is loaded are skipped.";
+const ABOUT_SCHEDULE: &str = "Manages the commands scheduled with at and after.";
+const ABOUT_SCHEDULE_LIST: &str =
+    "Lists the scheduled commands, the soonest first, with their IDs and when they run.";
+const ABOUT_SCHEDULE_CANCEL: &str = "Takes scheduled commands off the schedule, by their ID.";
+const ABOUT_FOREACH: &str = "Runs a command once for every sound whose name matches REGEX, after showing what it would run.";
+const ABOUT_FOREACH_LONG: &str = "Runs a command once for every sound whose name matches REGEX, like foreach '^forest' -- volume -v 40. The name goes where {} is in COMMAND, or at the end when there is no {}. The commands that would run are listed first, and only run once that is confirmed, or right away with -y. --dry-run only lists them.";
+const ABOUT_CUE: &str =
+    "Manages the cue list: the steps of a show, which are run one after the other with go.";
+const ABOUT_CUE_ADD: &str =
+    "Adds a cue to the end of the cue list, which runs COMMANDS together, like: cue add \"play storm\" \"stop music\".";
+const ABOUT_CUE_ADD_LONG: &str = "Adds a cue to the end of the cue list, which runs COMMANDS together, like: cue add \"play storm\" \"stop music\". Every command is written as at the prompt, in quotes, and is checked right away. The cue list is kept in the save.";
+const ABOUT_CUE_LIST: &str = "Lists the cues, with the one that go runs next marked.";
+const ABOUT_CUE_GO: &str = "Runs the next cue, and stands by for the one after it.";
+const ABOUT_CUE_RESET: &str = "Goes back to the first cue, so the show can be run again.";
+const ABOUT_VERSION: &str = "Shows the version of troubadour, the save versions it can read and the version of the loaded save.";
+const ABOUT_SUSPEND: &str = "Pauses the whole soundscape, remembering which sounds were playing.";
+const ABOUT_RESUME: &str =
+    "Resumes the sounds that were playing when the soundscape was suspended.";
+const ABOUT_SUGGEST: &str =
+    "Suggests sounds that are not in the soundscape yet, but match what is playing.";
+const ABOUT_SUGGEST_LONG: &str = "Suggests sounds that are not in the soundscape yet, but match what is playing. Looks in the folders of the playing sounds and in the library, for files that share tags or words in their names with them. The folders a sound is in in the library count as its tags. Shows at most COUNT suggestions, 10 by default.";
+const ABOUT_SCENE: &str = "Manages scenes: named snapshots of which sounds are playing.";
+const ABOUT_SCENE_CAPTURE: &str =
+    "Captures which sounds are playing right now, and at what volume, as a new scene.";
+const ABOUT_SCENE_CAPTURE_LONG: &str = "Captures which sounds are playing right now, and at what volume, as a new scene. Playback is not interrupted.";
+const ABOUT_SCENE_SAVE: &str =
+    "Saves which sounds are playing right now, with their volume and loop settings, as a new scene.";
+const ABOUT_SCENE_SWITCH: &str =
+    "Switches to a scene. Sounds in the scene start playing, all other sounds are stopped.";
+const ABOUT_SCENE_VOLUME: &str = "Sets the volume the sounds play at in a scene, as a percentage or in dB, like -6dB, without changing their own volume. Without VOLUME, they play at their own volume in the scene.";
+const ABOUT_SCENE_VOLUME_LONG: &str = "Sets the volume the sounds play at in a scene, as a percentage or in dB, like -6dB, without changing their own volume, so the same sound can be quiet in one scene and loud in another. Without VOLUME, they play at their own volume in the scene. When the scene is playing, the sounds change right away. Setting the volume of a sound with volume overrides the scene until the next switch.";
+const ABOUT_SCENE_LIST: &str = "Lists the scenes, with the one that was switched to last marked.";
+const ABOUT_SCENE_SWITCH_LONG: &str = "Switches to a scene. Sounds in the scene start playing, all other sounds are stopped. When DURATION is supplied, the sounds crossfade over that duration.";
+const ABOUT_WAVEFORM: &str =
+    "Draws the waveforms of sounds, with the part that is left after set-start and set-end.";
+const ABOUT_WAVEFORM_LONG: &str = "Draws the waveforms of sounds, with the part that is left after set-start and set-end marked below it. Useful to see where to cut. WIDTH is the number of characters, 60 by default.";
+const ABOUT_MONO_CHECK: &str =
+    "Checks whether sounds hold up on mono speakers, or whether their channels cancel out.";
+const ABOUT_MONO_CHECK_LONG: &str = "Checks whether sounds hold up on mono speakers, or whether their channels cancel out. A lot of venue speaker setups are effectively mono, and sounds with phase problems can almost disappear on them.";
+const ABOUT_BOARD: &str =
+    "Shows the soundscape as a live soundboard, with a coloured section for every group.";
+const ABOUT_BOARD_LONG: &str = "Shows the soundscape as a live soundboard, with a coloured section for every group, for DURATION, 10 seconds by default. Every sound shows whether it is playing, and the hotkeys and MIDI controls that are bound to it.";
+const ABOUT_METER: &str = "Shows live level meters for sounds and the master output.";
+const ABOUT_METER_LONG: &str = "Shows live level meters for sounds and the master output, for DURATION, 5 seconds by default. The levels are in dBFS, where 0 is the loudest the output can go.";
+const ABOUT_DUMP: &str = "Prints the whole state of the soundscape as JSON: the sounds with their settings and whether they are playing, the groups, the scenes, the buses and what is about to happen. For scripts and other programs.";
+const ABOUT_MONITOR: &str =
+    "Shows what happens to sounds as it happens, like sounds that finish or loop. Call again to stop.";
+const ABOUT_PAIRING: &str =
+    "Manages the remotes on other machines that are paired with troubadour.";
+const ABOUT_PAIRING_LIST: &str = "Lists the paired remotes.";
+const ABOUT_PAIRING_FORGET: &str =
+    "Forgets a paired remote. It has to pair again before it can control anything.";
+const ABOUT_ALIAS: &str = "Makes NAME a command of its own, which runs COMMANDS. Without COMMANDS, shows what NAME runs, and without NAME, lists the aliases.";
+const ABOUT_ALIAS_LONG: &str = "Makes NAME a command of its own, which runs COMMANDS, like: alias battle \"play drums horns; volume music -v 30\". The commands are separated by ;. $1, $2 and so on are replaced by what is written after NAME, and $@ by all of it, so after alias loud \"volume $1 -v 150\", loud rain turns rain up. Without COMMANDS, shows what NAME runs, and without NAME, lists the aliases. The aliases are kept in aliases.toml in the config folder.";
+const ABOUT_UNALIAS: &str = "Removes an alias.";
+const ABOUT_HOTKEY: &str =
+    "Manages global hotkeys, which run commands even while another window has the focus.";
+const ABOUT_HOTKEY_BIND: &str = "Runs a command when a key combination is pressed, anywhere.";
+const ABOUT_HOTKEY_BIND_LONG: &str = "Runs a command when a key combination is pressed, even while another window has the focus, like: hotkey bind ctrl+alt+t \"play thunder\". KEYS are modifiers (ctrl, alt, shift, meta) and a key (A, 1, F1, Space, Numpad1, ArrowUp), joined with +. The command is checked right away, and keys that are bound already keep what they did when it isn't right. The hotkeys are kept in hotkeys.toml in the config folder.";
+const ABOUT_HOTKEY_UNBIND: &str = "Removes the command from a key combination.";
+const ABOUT_HOTKEY_LIST: &str = "Lists the global hotkeys.";
+const ABOUT_DISCORD: &str =
+    "Streams the master output into a Discord voice channel, so online players hear the same mix.";
+const ABOUT_DISCORD_JOIN: &str = "Streams the master output into a Discord voice channel.";
+const ABOUT_DISCORD_JOIN_LONG: &str = "Streams the master output into a Discord voice channel, so online players hear the same mix. GUILD and CHANNEL are the ids of the server and the voice channel, which Discord shows with developer mode on. The bot token is read from the TROUBADOUR_DISCORD_TOKEN environment variable. Only available when troubadour is built with --features discord.";
+const ABOUT_DISCORD_LEAVE: &str = "Stops streaming and leaves the Discord voice channel.";
+const ABOUT_CACHE: &str =
+    "Manages the cache of decoded sounds, which load and play without decoding.";
+const ABOUT_CACHE_BUILD: &str =
+    "Decodes all sounds into the cache, so the soundscape loads fast on a slow machine.";
+const ABOUT_CACHE_BUILD_LONG: &str = "Decodes all sounds, archived ones included, into the cache, so the soundscape loads fast on a slow machine. Sounds are played from the cache from then on. A sound that changes is decoded again on the next build, until then the sound itself is played.";
+const ABOUT_CACHE_CLEAR: &str = "Removes all decoded sounds from the cache.";
+const ABOUT_LIBRARY: &str =
+    "Finds sounds in the folders they are kept in, so they can be added without typing their path.";
+const ABOUT_LIBRARY_ROOTS: &str = "Lists the folders of the library.";
+const ABOUT_LIBRARY_ADD_ROOT: &str = "Adds a folder to the library, and scans it.";
+const ABOUT_LIBRARY_ADD_ROOT_LONG: &str = "Adds a folder to the library, and scans it. The sounds in the folders inside it are found too, and are tagged with the names of those folders.";
+const ABOUT_LIBRARY_REMOVE_ROOT: &str =
+    "Removes a folder from the library, with the sounds that were found in it.";
+const ABOUT_LIBRARY_SCAN: &str =
+    "Scans the folders of the library again, for sounds that were added, changed or removed.";
+const ABOUT_LIBRARY_SEARCH: &str =
+    "Lists the sounds in the library whose name or folders have all the words of QUERY in them.";
+const ABOUT_LIBRARY_ADD: &str = "Adds a sound that the last search found, by its number.";
+const ABOUT_LIBRARY_ADD_LONG: &str = "Adds a sound that the last search found, by its number, like library add 2. Without NAME, it is named after the file.";
+const ABOUT_OUTPUT: &str = "Chooses the audio device that the master output is sent to.";
+const ABOUT_OUTPUT_DEVICES: &str = "Lists the audio devices that the master output can be sent to.";
+const ABOUT_OUTPUT_USE: &str = "Sends the master output to another audio device, by its number in output devices or a part of its name. Goes back to the default device when DEVICE is not supplied.";
+const ABOUT_OUTPUT_USE_LONG: &str = "Sends the master output to another audio device, by its number in output devices or a part of its name, like: output use cable. Goes back to the default device when DEVICE is not supplied. Sending the output to a loopback or virtual device, like VB-Cable or a PulseAudio null sink, lets OBS or a video call pick up the mix.";
+const ABOUT_MIDI: &str = "Controls the soundscape from the pads and knobs of a MIDI controller.";
+const ABOUT_MIDI_PORTS: &str = "Lists the MIDI devices that can be connected.";
+const ABOUT_MIDI_CONNECT: &str = "Connects to a MIDI device, by its number in midi ports or a part of its name. Connects to the first one when PORT is not supplied.";
+const ABOUT_MIDI_DISCONNECT: &str = "Disconnects from the MIDI device.";
+const ABOUT_MIDI_MAP: &str = "Runs a command when a note is hit or a control is moved.";
+const ABOUT_MIDI_MAP_LONG: &str = "Runs a command when a note is hit or a control (cc) is moved. {value} in the command becomes how far the control is turned, or how hard the note was hit, from 0 to 100, like: midi map cc 7 \"volume rain -v {value}\". When CHANNEL is supplied, only listens on that channel, 1 to 16.";
+const ABOUT_MIDI_UNMAP: &str = "Removes the command from a note or control.";
+const ABOUT_MIDI_LIST: &str = "Lists the commands that are mapped to the MIDI controller.";
+const ABOUT_DIAGNOSTICS: &str =
+    "Shows how many files are open, to find out whether the system limit is getting close.";
+const ABOUT_TUI: &str = "Switches to a dashboard that fills the terminal, with every player, how far along it is and its volume. The up and down arrows pick a player, left and right turn it down or up, space plays or stops it and p pauses it. Commands are typed after a colon, like :scene switch night. q goes back to the prompt.";
+const ABOUT_PREFLIGHT: &str = "Checks everything before a session: that every sound file is there and decodes, that the audio device responds quickly enough, and that the scenes, cues and scheduled commands only refer to things that exist. Prints a checklist, and fails when any check does.";
+const ABOUT_APPLY_MANIFEST: &str =
+    "Applies the settings in a CSV manifest to the players in it, adding the ones that don't exist yet.";
+const ABOUT_APPLY_MANIFEST_LONG: &str = "Applies the settings in a CSV manifest to the players in it, adding the ones that don't exist yet. The first row names the columns: name, path, volume, loop, start, end, delay, group and tags. Only name is required. Empty cells leave a setting as it is. loop can be yes, no or a duration, end can be none and tags are separated by semicolons.";
+const ABOUT_LOG: &str = "Writes a note in the session log, or shows the log.";
+const ABOUT_LOG_LONG: &str = "Writes TEXT in the session log, with the time. Sounds starting and stopping and scene switches are logged too, to help writing a recap after the game. Shows the log when TEXT is not supplied. When PATH is supplied, writes the log of this session to that file.";
+const ABOUT_EXPORT_SCRIPT: &str =
+    "Writes the commands that rebuild the current soundscape from scratch to a file.";
+const ABOUT_RENDER: &str =
+    "Renders the sounds that are playing into a wav file, faster than real-time.";
+const ABOUT_RENDER_LONG: &str = "Renders the sounds that are playing into a wav file, faster than real-time, as they would sound when they were all started at once, with their loops, delays, cuts and volumes. Useful to export a loop to use in other tools. Sounds that end before DURATION leave silence behind.";
+const ABOUT_SAVE: &str = "Saves the current configuration to a file. It is written as TOML when PATH ends in .toml, which is easier to edit by hand. With --project, or when PATH is a folder, it is saved as a project: a folder with soundscape.json and the sounds in it.";
+const ABOUT_SWITCH: &str = "Switches to another saved soundscape in one go, crossfading from what is playing to its default scene.";
+const ABOUT_SWITCH_LONG: &str = "Switches to another saved soundscape in one go, crossfading from what is playing to its default scene over DURATION. The default scene is the one that was active when the soundscape was saved, or else its first scene. PATH can be a project folder too.";
+const ABOUT_LOAD: &str = "Loads a saved configuration. You can choose to replace or add to current configuration. PATH can be a project folder too. With --resume-playback, the sounds that were playing when it was saved play again, from where they were.";
+const ABOUT_IMPORT: &str =
+    "Converts a soundscape from another program into a save, which can then be loaded.";
+const ABOUT_IMPORT_LONG: &str = "Converts a soundscape from another program into a save at OUT, which can then be loaded. PATH is a soundpad manifest in JSON or CSV, with tracks and moods, like the ones soundboard apps and Syrinscape-style sets can be written out as. Tracks become sounds, their groups become groups, and moods become scenes. The readme shows what the files look like.";
+const ABOUT_REMAP: &str =
+    "Manages where sounds are looked for when a save from another machine is loaded.";
+const ABOUT_REMAP_ADD: &str = "Looks for sounds under FROM in TO instead, when loading a save.";
+const ABOUT_REMAP_ADD_LONG: &str = "Looks for sounds under FROM in TO instead, when loading a save, like: remap add 'D:\\Sounds' /home/me/sounds. Slashes and backslashes are the same to a remap, so a save made on Windows opens on Linux and the other way around. The remaps are saved with the soundscape. Remaps for every soundscape go in the config file.";
+const ABOUT_REMAP_LIST: &str = "Lists the remaps of the soundscape and the config file.";
+const ABOUT_CONFIG: &str = "Shows and changes the settings in the config file.";
+const ABOUT_CONFIG_GET: &str = "Shows a setting of the config file, or all of them without KEY.";
+const ABOUT_CONFIG_SET: &str = "Changes a setting in the config file.";
+const ABOUT_CONFIG_SET_LONG: &str = "Changes a setting in the config file, like: config set default_volume 80. VALUE is written the way it is in the file, but text doesn't need quotes. Settings in a table are written with a dot, like safe_start.ramp. The rest of the file is kept the way it is, comments and all.";
 const ABOUT_HELP: &str = "Shows this help message.";
 const ABOUT_EXIT: &str = "Exits the program.";
 
@@ -72,8 +521,14 @@ const USAGE: &str = formatcp!(
     "
 \t{ADD_USAGE}\n\t\t{ABOUT_ADD}
 
+\t{PASTE_ADD_USAGE}\n\t\t{ABOUT_PASTE_ADD}
+
 \t{REMOVE_USAGE}\n\t\t{ABOUT_REMOVE}
 
+\t{ARCHIVE_USAGE}\n\t\t{ABOUT_ARCHIVE}
+
+\t{UNARCHIVE_USAGE}\n\t\t{ABOUT_UNARCHIVE}
+
 \t{SHOW_USAGE}\n\t\t{ABOUT_SHOW}
 
 \t{PLAY_USAGE}\n\t\t{ABOUT_PLAY}
@@ -84,8 +539,24 @@ const USAGE: &str = formatcp!(
 
 \t{VOLUME_USAGE}\n\t\t{ABOUT_VOLUME}
 
+\t{FADE_USAGE}\n\t\t{ABOUT_FADE}
+
+\t{WIDTH_USAGE}\n\t\t{ABOUT_WIDTH}
+
+\t{NORMALIZE_USAGE}\n\t\t{ABOUT_NORMALIZE}
+
+\t{BALANCE_USAGE}\n\t\t{ABOUT_BALANCE}
+
+\t{PAN_USAGE}\n\t\t{ABOUT_PAN}
+
+\t{SPEED_USAGE}\n\t\t{ABOUT_SPEED}
+
+\t{STRETCH_USAGE}\n\t\t{ABOUT_STRETCH}
+
 \t{LOOP_USAGE}\n\t\t{ABOUT_LOOP}
 
+\t{SET_LOOP_REGION_USAGE}\n\t\t{ABOUT_SET_LOOP_REGION}
+
 \t{UNLOOP_USAGE}\n\t\t{ABOUT_UNLOOP}
 
 \t{SET_START_USAGE}\n\t\t{ABOUT_SET_START}
@@ -94,14 +565,194 @@ const USAGE: &str = formatcp!(
 
 \t{DELAY_USAGE}\n\t\t{ABOUT_DELAY}
 
+\t{COOLDOWN_USAGE}\n\t\t{ABOUT_COOLDOWN}
+
+\t{FADES_USAGE}\n\t\t{ABOUT_FADES}
+
+\t{SEEK_USAGE}\n\t\t{ABOUT_SEEK}
+
+\t{MARK_ADD_USAGE}\n\t\t{ABOUT_MARK_ADD}
+
+\t{MARK_REMOVE_USAGE}\n\t\t{ABOUT_MARK_REMOVE}
+
+\t{MARK_LIST_USAGE}\n\t\t{ABOUT_MARK_LIST}
+
+\t{TAG_ADD_USAGE}\n\t\t{ABOUT_TAG_ADD}
+
+\t{TAG_REMOVE_USAGE}\n\t\t{ABOUT_TAG_REMOVE}
+
+\t{TAG_LIST_USAGE}\n\t\t{ABOUT_TAG_LIST}
+
+\t{EDIT_MODE_USAGE}\n\t\t{ABOUT_EDIT_MODE}
+
 \t{GROUP_USAGE}\n\t\t{ABOUT_GROUP}
 
 \t{UNGROUP_USAGE}\n\t\t{ABOUT_UNGROUP}
 
+\t{SET_DEFAULT_GROUP_USAGE}\n\t\t{ABOUT_SET_DEFAULT_GROUP}
+
+\t{SET_VARIABLE_USAGE}\n\t\t{ABOUT_SET_VARIABLE}
+
+\t{UNSET_USAGE}\n\t\t{ABOUT_UNSET}
+
+\t{VARIANT_USAGE}\n\t\t{ABOUT_VARIANT}
+
+\t{LOOP_GROUP_USAGE}\n\t\t{ABOUT_LOOP_GROUP}
+
+\t{UNLOOP_GROUP_USAGE}\n\t\t{ABOUT_UNLOOP_GROUP}
+
+\t{GROUP_DEFAULTS_USAGE}\n\t\t{ABOUT_GROUP_DEFAULTS}
+
+\t{PLAYLIST_USAGE}\n\t\t{ABOUT_PLAYLIST}
+
+\t{UNPLAYLIST_USAGE}\n\t\t{ABOUT_UNPLAYLIST}
+
+\t{WEIGHT_USAGE}\n\t\t{ABOUT_WEIGHT}
+
+\t{QUIET_HOURS_USAGE}\n\t\t{ABOUT_QUIET_HOURS}
+
+\t{MASTER_LIMIT_USAGE}\n\t\t{ABOUT_MASTER_LIMIT}
+
+\t{DUCK_USAGE}\n\t\t{ABOUT_DUCK}
+
+\t{UNDUCK_USAGE}\n\t\t{ABOUT_UNDUCK}
+
+\t{LINK_USAGE}\n\t\t{ABOUT_LINK}
+
+\t{UNLINK_USAGE}\n\t\t{ABOUT_UNLINK}
+
+\t{PENDING_USAGE}\n\t\t{ABOUT_PENDING}
+
+\t{CANCEL_USAGE}\n\t\t{ABOUT_CANCEL}
+
+\t{AFTER_USAGE}\n\t\t{ABOUT_AFTER}
+
+\t{AT_USAGE}\n\t\t{ABOUT_AT}
+
+\t{SCHEDULE_LIST_USAGE}\n\t\t{ABOUT_SCHEDULE_LIST}
+
+\t{SCHEDULE_CANCEL_USAGE}\n\t\t{ABOUT_SCHEDULE_CANCEL}
+
+\t{FOREACH_USAGE}\n\t\t{ABOUT_FOREACH}
+
+\t{CUE_ADD_USAGE}\n\t\t{ABOUT_CUE_ADD}
+
+\t{CUE_LIST_USAGE}\n\t\t{ABOUT_CUE_LIST}
+
+\t{CUE_GO_USAGE}\n\t\t{ABOUT_CUE_GO}
+
+\t{CUE_RESET_USAGE}\n\t\t{ABOUT_CUE_RESET}
+
+\t{SUSPEND_USAGE}\n\t\t{ABOUT_SUSPEND}
+
+\t{RESUME_USAGE}\n\t\t{ABOUT_RESUME}
+
+\t{SUGGEST_USAGE}\n\t\t{ABOUT_SUGGEST}
+
+\t{SCENE_CAPTURE_USAGE}\n\t\t{ABOUT_SCENE_CAPTURE}
+
+\t{SCENE_SAVE_USAGE}\n\t\t{ABOUT_SCENE_SAVE}
+
+\t{SCENE_SWITCH_USAGE}\n\t\t{ABOUT_SCENE_SWITCH}
+
+\t{SCENE_VOLUME_USAGE}\n\t\t{ABOUT_SCENE_VOLUME}
+
+\t{SCENE_LIST_USAGE}\n\t\t{ABOUT_SCENE_LIST}
+
+\t{WAVEFORM_USAGE}\n\t\t{ABOUT_WAVEFORM}
+
+\t{MONO_CHECK_USAGE}\n\t\t{ABOUT_MONO_CHECK}
+
+\t{METER_USAGE}\n\t\t{ABOUT_METER}
+
+\t{BOARD_USAGE}\n\t\t{ABOUT_BOARD}
+
+\t{TUI_USAGE}\n\t\t{ABOUT_TUI}
+
+\t{MONITOR_USAGE}\n\t\t{ABOUT_MONITOR}
+
+\t{DUMP_USAGE}\n\t\t{ABOUT_DUMP}
+
+\t{DIAGNOSTICS_USAGE}\n\t\t{ABOUT_DIAGNOSTICS}
+
+\t{PREFLIGHT_USAGE}\n\t\t{ABOUT_PREFLIGHT}
+
+\t{PAIRING_LIST_USAGE}\n\t\t{ABOUT_PAIRING_LIST}
+
+\t{PAIRING_FORGET_USAGE}\n\t\t{ABOUT_PAIRING_FORGET}
+
+\t{ALIAS_USAGE}\n\t\t{ABOUT_ALIAS}
+
+\t{UNALIAS_USAGE}\n\t\t{ABOUT_UNALIAS}
+
+\t{HOTKEY_BIND_USAGE}\n\t\t{ABOUT_HOTKEY_BIND}
+
+\t{HOTKEY_UNBIND_USAGE}\n\t\t{ABOUT_HOTKEY_UNBIND}
+
+\t{HOTKEY_LIST_USAGE}\n\t\t{ABOUT_HOTKEY_LIST}
+
+\t{MIDI_PORTS_USAGE}\n\t\t{ABOUT_MIDI_PORTS}
+
+\t{MIDI_CONNECT_USAGE}\n\t\t{ABOUT_MIDI_CONNECT}
+
+\t{MIDI_DISCONNECT_USAGE}\n\t\t{ABOUT_MIDI_DISCONNECT}
+
+\t{MIDI_MAP_USAGE}\n\t\t{ABOUT_MIDI_MAP}
+
+\t{MIDI_UNMAP_USAGE}\n\t\t{ABOUT_MIDI_UNMAP}
+
+\t{MIDI_LIST_USAGE}\n\t\t{ABOUT_MIDI_LIST}
+
+\t{DISCORD_JOIN_USAGE}\n\t\t{ABOUT_DISCORD_JOIN}
+
+\t{DISCORD_LEAVE_USAGE}\n\t\t{ABOUT_DISCORD_LEAVE}
+
+\t{OUTPUT_DEVICES_USAGE}\n\t\t{ABOUT_OUTPUT_DEVICES}
+
+\t{OUTPUT_USE_USAGE}\n\t\t{ABOUT_OUTPUT_USE}
+
+\t{CACHE_BUILD_USAGE}\n\t\t{ABOUT_CACHE_BUILD}
+
+\t{CACHE_CLEAR_USAGE}\n\t\t{ABOUT_CACHE_CLEAR}
+
+\t{LIBRARY_ROOTS_USAGE}\n\t\t{ABOUT_LIBRARY_ROOTS}
+
+\t{LIBRARY_ADD_ROOT_USAGE}\n\t\t{ABOUT_LIBRARY_ADD_ROOT}
+
+\t{LIBRARY_REMOVE_ROOT_USAGE}\n\t\t{ABOUT_LIBRARY_REMOVE_ROOT}
+
+\t{LIBRARY_SCAN_USAGE}\n\t\t{ABOUT_LIBRARY_SCAN}
+
+\t{LIBRARY_SEARCH_USAGE}\n\t\t{ABOUT_LIBRARY_SEARCH}
+
+\t{LIBRARY_ADD_USAGE}\n\t\t{ABOUT_LIBRARY_ADD}
+
+\t{APPLY_MANIFEST_USAGE}\n\t\t{ABOUT_APPLY_MANIFEST}
+
+\t{LOG_USAGE}\n\t\t{ABOUT_LOG}
+
+\t{EXPORT_SCRIPT_USAGE}\n\t\t{ABOUT_EXPORT_SCRIPT}
+
+\t{RENDER_USAGE}\n\t\t{ABOUT_RENDER}
+
 \t{SAVE_USAGE}\n\t\t{ABOUT_SAVE}
 
 \t{LOAD_USAGE}\n\t\t{ABOUT_LOAD}
 
+\t{SWITCH_USAGE}\n\t\t{ABOUT_SWITCH}
+
+\t{IMPORT_USAGE}\n\t\t{ABOUT_IMPORT}
+
+\t{REMAP_ADD_USAGE}\n\t\t{ABOUT_REMAP_ADD}
+
+\t{REMAP_LIST_USAGE}\n\t\t{ABOUT_REMAP_LIST}
+
+\t{CONFIG_GET_USAGE}\n\t\t{ABOUT_CONFIG_GET}
+
+\t{CONFIG_SET_USAGE}\n\t\t{ABOUT_CONFIG_SET}
+
+\t{VERSION_USAGE}\n\t\t{ABOUT_VERSION}
+
 \thelp\n\t\t{ABOUT_HELP}
 
 \texit\n\t\t{ABOUT_EXIT}
@@ -109,7 +760,8 @@ const USAGE: &str = formatcp!(
 Note that:
 \t- [..] indicates an optional value.
 \t- Most commands will select the last added sound if ID is not supplied.
-\t- ID can be a name or 'all'. For instance: 'play horn' or 'play all'\
+\t- ID can be a name or 'all'. For instance: 'play horn' or 'play all'
+\t- ID can also be a pattern, where * stands for any letters and ? for one, or a tag. For instance: 'play forest*' or 'play @battle'\
 "
 );
 
@@ -140,53 +792,151 @@ macro_rules! build {
 build! {
     #[command(override_usage=ADD_USAGE, about=ABOUT_ADD_LONG)]
     Add {
+        #[arg(long, short, required_unless_present = "youtube")]
+        path: Option<PathBuf>,
+        #[arg(long, conflicts_with = "path")]
+        youtube: Option<String>,
         #[arg(long, short)]
-        path: PathBuf,
+        name: Option<String>
+    },
+    #[command(override_usage=PASTE_ADD_USAGE, about=ABOUT_PASTE_ADD_LONG)]
+    PasteAdd {
         #[arg(long, short)]
-        name: String
+        name: Option<String>,
     },
     #[command(override_usage=REMOVE_USAGE, about=ABOUT_REMOVE)]
     Remove {
         ids: Vec<String>,
     },
-    #[command(override_usage=PLAY_USAGE, about=format!("{ABOUT_PLAY} {NO_ID_ADDENDUM}"))]
+    #[command(override_usage=ARCHIVE_USAGE, about=ABOUT_ARCHIVE_LONG)]
+    Archive {
+        ids: Vec<String>,
+    },
+    #[command(override_usage=UNARCHIVE_USAGE, about=ABOUT_UNARCHIVE)]
+    Unarchive {
+        ids: Vec<String>,
+    },
+    #[command(override_usage=PLAY_USAGE, about=format!("{ABOUT_PLAY_LONG} {NO_ID_ADDENDUM}"))]
     Play {
         ids: Vec<String>,
         #[arg(long, short)]
-        groups: Vec<String>
+        groups: Vec<String>,
+        #[arg(long, short)]
+        from: Option<String>
     },
     #[command(override_usage=STOP_USAGE, about=format!("{ABOUT_STOP} {NO_ID_ADDENDUM}"))]
     Stop {
         ids: Vec<String>,
         #[arg(long, short)]
-        groups: Vec<String>
+        groups: Vec<String>,
+        #[arg(long, short, value_parser = parse_duration)]
+        fade: Option<Duration>
     },
     #[command(override_usage=PAUSE_USAGE, about=format!("{ABOUT_PAUSE} {NO_ID_ADDENDUM}"))]
     Pause {
         ids: Vec<String>,
         #[arg(long, short)]
-        groups: Vec<String>
+        groups: Vec<String>,
+        #[arg(long, short, value_parser = parse_duration)]
+        fade: Option<Duration>
     },
     #[command(override_usage=VOLUME_USAGE, about=format!("{ABOUT_VOLUME} {NO_ID_ADDENDUM}"))]
     Volume {
         ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_volume, allow_hyphen_values = true)]
+        volume: u32,
         #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=FADE_USAGE, about=format!("{ABOUT_FADE} {NO_ID_ADDENDUM}"))]
+    Fade {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_volume, allow_hyphen_values = true)]
         volume: u32,
+        #[arg(long, short, value_parser = parse_duration)]
+        duration: Duration,
         #[arg(long, short)]
         groups: Vec<String>
     },
-    #[command(override_usage=SHOW_USAGE, about=format!("{ABOUT_SHOW} {NO_ID_ADDENDUM}"))]
-    Show {
+    #[command(override_usage=WIDTH_USAGE, about=format!("{ABOUT_WIDTH} {NO_ID_ADDENDUM}"))]
+    Width {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = clap::value_parser!(u32).range(0..=200))]
+        width: u32,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=NORMALIZE_USAGE, about=format!("{ABOUT_NORMALIZE} {NO_ID_ADDENDUM}"))]
+    Normalize {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=BALANCE_USAGE, about=ABOUT_BALANCE)]
+    Balance {
+        #[arg(long, short)]
+        group: String,
+        #[arg(long, short, value_parser = parse_balance_target, allow_negative_numbers = true)]
+        target: Option<f32>,
+        #[arg(long)]
+        reset_relative: bool
+    },
+    #[command(override_usage=PAN_USAGE, about=format!("{ABOUT_PAN} {NO_ID_ADDENDUM}"))]
+    Pan {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_pan, allow_negative_numbers = true)]
+        pan: f32,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=STRETCH_USAGE, about=format!("{ABOUT_STRETCH} {NO_ID_ADDENDUM}"))]
+    Stretch {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_duration)]
+        length: Option<Duration>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=SPEED_USAGE, about=format!("{ABOUT_SPEED} {NO_ID_ADDENDUM}"))]
+    Speed {
         ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_speed)]
+        speed: f32,
         #[arg(long, short)]
         groups: Vec<String>
     },
+    #[command(override_usage=SHOW_USAGE, about=format!("{ABOUT_SHOW_LONG} {NO_ID_ADDENDUM}"))]
+    Show {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>,
+        #[arg(long, short)]
+        levels: bool,
+        #[arg(long, short)]
+        effective: bool,
+        #[arg(long, short)]
+        verbose: bool
+    },
     #[command(override_usage=LOOP_USAGE, about=format!("{ABOUT_LOOP_LONG} {NO_ID_ADDENDUM}"))]
     Loop {
         ids: Vec<String>,
-        #[arg(long, short, value_parser = parse_duration)]
+        #[arg(long, short, value_parser = parse_duration, conflicts_with = "auto")]
         duration: Option<Duration>,
         #[arg(long, short)]
+        auto: bool,
+        #[arg(long = "count", short = 'n', value_parser = clap::value_parser!(u32).range(1..))]
+        count: Option<u32>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=SET_LOOP_REGION_USAGE, about=format!("{ABOUT_SET_LOOP_REGION} {NO_ID_ADDENDUM}"), long_about=format!("{ABOUT_SET_LOOP_REGION_LONG} {NO_ID_ADDENDUM}"))]
+    SetLoopRegion {
+        ids: Vec<String>,
+        #[arg(long = "start", short = 'a', value_parser = parse_position, requires = "end")]
+        start: Option<Duration>,
+        #[arg(long = "end", short = 'b', value_parser = parse_position, requires = "start")]
+        end: Option<Duration>,
+        #[arg(long, short)]
         groups: Vec<String>
     },
     #[command(override_usage=UNLOOP_USAGE, about=format!("{ABOUT_UNLOOP} {NO_ID_ADDENDUM}"))]
@@ -219,6 +969,59 @@ build! {
         #[arg(long, short)]
         groups: Vec<String>
     },
+    #[command(override_usage=COOLDOWN_USAGE, about=format!("{ABOUT_COOLDOWN} {NO_ID_ADDENDUM}"))]
+    Cooldown {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_duration)]
+        duration: Duration,
+        #[arg(long, short)]
+        queue: bool,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=FADES_USAGE, about=format!("{ABOUT_FADES} {NO_ID_ADDENDUM}"))]
+    Fades {
+        ids: Vec<String>,
+        #[arg(long = "in", short = 'i', value_parser = parse_duration, conflicts_with = "inherit")]
+        fade_in: Option<Duration>,
+        #[arg(long = "out", short = 'o', value_parser = parse_duration, conflicts_with = "inherit")]
+        fade_out: Option<Duration>,
+        #[arg(long, value_parser = BoolishValueParser::new(), conflicts_with = "inherit")]
+        soft_start: Option<bool>,
+        #[arg(long, conflicts_with = "inherit")]
+        bus: Option<Bus>,
+        #[arg(long)]
+        inherit: bool,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=SEEK_USAGE, about=format!("{ABOUT_SEEK} {NO_ID_ADDENDUM}"))]
+    Seek {
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_position, required_unless_present = "marker")]
+        to: Option<Duration>,
+        #[arg(long, short, conflicts_with = "to")]
+        marker: Option<String>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    // bookmark is what the markers were called first
+    #[command(override_usage=MARK_USAGE, about=ABOUT_MARK, alias = "bookmark")]
+    Mark {
+        #[command(subcommand)]
+        action: MarkCommands,
+    },
+    #[command(override_usage=TAG_USAGE, about=ABOUT_TAG)]
+    Tag {
+        #[command(subcommand)]
+        action: TagCommands,
+    },
+    #[command(override_usage=EDIT_MODE_USAGE, about=format!("{ABOUT_EDIT_MODE_LONG} {NO_ID_ADDENDUM}"))]
+    EditMode {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
     #[command(override_usage=GROUP_USAGE, about=ABOUT_GROUP)]
     Group {
         #[arg(long, short)]
@@ -231,57 +1034,1265 @@ build! {
         group: String,
         ids: Vec<String>,
     },
-    #[command(override_usage=SAVE_USAGE, about=ABOUT_SAVE)]
-    Save {
+    #[command(override_usage=SET_USAGE, about=ABOUT_SET)]
+    Set {
+        #[command(subcommand)]
+        action: Option<SetCommands>,
+    },
+    #[command(override_usage=UNSET_USAGE, about=ABOUT_UNSET)]
+    Unset { name: String },
+    #[command(override_usage=VARIANT_USAGE, about=ABOUT_VARIANT_LONG)]
+    Variant {
+        #[arg(long, short)]
+        group: String,
+        #[arg(long, short)]
+        suffix: String,
+        #[arg(long, default_value_t = 1.0, value_parser = parse_volume_scale)]
+        volume_scale: f32,
+    },
+    #[command(override_usage=PLAYLIST_USAGE, about=ABOUT_PLAYLIST_LONG)]
+    Playlist {
+        #[arg(long, short)]
+        group: String,
+        #[arg(long, short, default_value_t = 1)]
+        cooldown: usize,
+    },
+    #[command(override_usage=UNPLAYLIST_USAGE, about=ABOUT_UNPLAYLIST)]
+    Unplaylist {
+        #[arg(long, short)]
+        group: String,
+    },
+    #[command(override_usage=WEIGHT_USAGE, about=ABOUT_WEIGHT)]
+    Weight {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        weight: u32,
+    },
+    #[command(override_usage=QUIET_HOURS_USAGE, about=ABOUT_QUIET_HOURS_LONG)]
+    QuietHours {
+        mode: Option<QuietMode>,
+    },
+    #[command(override_usage=MASTER_LIMIT_USAGE, about=ABOUT_MASTER_LIMIT_LONG)]
+    MasterLimit {
+        mode: Option<LimitMode>,
+        #[arg(long, short, value_parser = parse_threshold, allow_negative_numbers = true)]
+        threshold: Option<f32>,
+    },
+    #[command(override_usage=DUCK_USAGE, about=ABOUT_DUCK_LONG)]
+    Duck {
+        #[arg(long, short, requires_all = ["target", "amount"])]
+        when: Option<String>,
+        #[arg(long, short, requires = "when")]
+        target: Option<String>,
+        #[arg(long, short, requires = "when", value_parser = parse_duck_amount)]
+        amount: Option<f32>,
+        #[arg(long, short, requires = "when", value_parser = parse_duration)]
+        release: Option<Duration>,
+    },
+    #[command(override_usage=UNDUCK_USAGE, about=ABOUT_UNDUCK)]
+    Unduck {
+        #[arg(long, short)]
+        target: String,
+        #[arg(long, short)]
+        when: Option<String>,
+    },
+    #[command(override_usage=LINK_USAGE, about=ABOUT_LINK_LONG)]
+    Link {
+        #[arg(long, short)]
+        link: String,
+        ids: Vec<String>,
+    },
+    #[command(override_usage=UNLINK_USAGE, about=ABOUT_UNLINK)]
+    Unlink {
+        ids: Vec<String>,
+    },
+    #[command(override_usage=LOOP_GROUP_USAGE, about=ABOUT_LOOP_GROUP_LONG)]
+    LoopGroup {
+        #[arg(long, short)]
+        group: String,
+        #[arg(long, short, conflicts_with_all = ["duration", "lock"])]
+        leader: Option<String>,
+        #[arg(long, short, value_parser = parse_duration, conflicts_with = "lock")]
+        duration: Option<Duration>,
+        #[arg(long)]
+        lock: bool,
+    },
+    #[command(override_usage=UNLOOP_GROUP_USAGE, about=ABOUT_UNLOOP_GROUP)]
+    UnloopGroup {
+        #[arg(long, short)]
+        group: String,
+    },
+    #[command(override_usage=GROUP_DEFAULTS_USAGE, about=ABOUT_GROUP_DEFAULTS_LONG)]
+    GroupDefaults {
+        #[arg(long, short)]
+        group: String,
+        #[arg(long = "in", short = 'i', value_parser = parse_duration, conflicts_with = "clear")]
+        fade_in: Option<Duration>,
+        #[arg(long = "out", short = 'o', value_parser = parse_duration, conflicts_with = "clear")]
+        fade_out: Option<Duration>,
+        #[arg(long, value_parser = BoolishValueParser::new(), conflicts_with = "clear")]
+        soft_start: Option<bool>,
+        #[arg(long, conflicts_with = "clear")]
+        bus: Option<Bus>,
+        #[arg(long)]
+        clear: bool,
+    },
+    #[command(override_usage=PENDING_USAGE, about=ABOUT_PENDING)]
+    Pending,
+    #[command(override_usage=CANCEL_USAGE, about=ABOUT_CANCEL_LONG)]
+    Cancel {
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    #[command(override_usage=AFTER_USAGE, about=ABOUT_AFTER_LONG)]
+    After {
+        #[arg(value_parser = parse_duration)]
+        duration: Duration,
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    #[command(override_usage=AT_USAGE, about=ABOUT_AT_LONG)]
+    At {
+        #[arg(value_parser = schedule::parse_time_of_day)]
+        time: NaiveTime,
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    #[command(override_usage=SCHEDULE_USAGE, about=ABOUT_SCHEDULE)]
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleCommands,
+    },
+    #[command(override_usage=FOREACH_USAGE, about=ABOUT_FOREACH_LONG)]
+    Foreach {
+        regex: String,
+        #[arg(short, long = "yes")]
+        yes: bool,
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(required = true, last = true)]
+        command: Vec<String>,
+    },
+    #[command(override_usage=CUE_USAGE, about=ABOUT_CUE)]
+    Cue {
+        #[command(subcommand)]
+        action: CueCommands,
+    },
+    #[command(override_usage=VERSION_USAGE, about=ABOUT_VERSION)]
+    Version,
+    #[command(override_usage=SUSPEND_USAGE, about=ABOUT_SUSPEND)]
+    Suspend,
+    #[command(override_usage=RESUME_USAGE, about=ABOUT_RESUME)]
+    Resume,
+    #[command(override_usage=SUGGEST_USAGE, about=ABOUT_SUGGEST_LONG)]
+    Suggest {
+        #[arg(short = 'n', long, default_value_t = 10)]
+        count: usize,
+    },
+    #[command(override_usage=SCENE_USAGE, about=ABOUT_SCENE)]
+    Scene {
+        #[command(subcommand)]
+        action: SceneCommands,
+    },
+    #[command(override_usage=WAVEFORM_USAGE, about=format!("{ABOUT_WAVEFORM_LONG} {NO_ID_ADDENDUM}"))]
+    Waveform {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>,
+        #[arg(long, short, default_value_t = 60, value_parser = clap::value_parser!(u16).range(10..))]
+        width: u16,
+    },
+    #[command(override_usage=MONO_CHECK_USAGE, about=format!("{ABOUT_MONO_CHECK_LONG} {NO_ID_ADDENDUM}"))]
+    MonoCheck {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>
+    },
+    #[command(override_usage=METER_USAGE, about=format!("{ABOUT_METER_LONG} {NO_ID_ADDENDUM}"))]
+    Meter {
+        ids: Vec<String>,
+        #[arg(long, short)]
+        groups: Vec<String>,
+        #[arg(long, short, value_parser = parse_duration, default_value = "5s")]
+        duration: Duration,
+    },
+    #[command(override_usage=BOARD_USAGE, about=ABOUT_BOARD_LONG)]
+    Board {
+        #[arg(long, short, value_parser = parse_duration, default_value = "10s")]
+        duration: Duration,
+    },
+    #[command(override_usage=TUI_USAGE, about=ABOUT_TUI)]
+    Tui,
+    #[command(override_usage=MONITOR_USAGE, about=ABOUT_MONITOR)]
+    Monitor,
+    #[command(override_usage=DUMP_USAGE, about=ABOUT_DUMP)]
+    Dump,
+    #[command(override_usage=DIAGNOSTICS_USAGE, about=ABOUT_DIAGNOSTICS)]
+    Diagnostics,
+    #[command(override_usage=PREFLIGHT_USAGE, about=ABOUT_PREFLIGHT)]
+    Preflight,
+    #[command(override_usage=PAIRING_USAGE, about=ABOUT_PAIRING)]
+    Pairing {
+        #[command(subcommand)]
+        action: PairingCommands,
+    },
+    #[command(override_usage=ALIAS_USAGE, about=ABOUT_ALIAS_LONG)]
+    Alias {
+        name: Option<String>,
+        commands: Option<String>,
+    },
+    #[command(override_usage=UNALIAS_USAGE, about=ABOUT_UNALIAS)]
+    Unalias { name: String },
+    #[command(override_usage=HOTKEY_USAGE, about=ABOUT_HOTKEY)]
+    Hotkey {
+        #[command(subcommand)]
+        action: HotkeyCommands,
+    },
+    #[command(override_usage=MIDI_USAGE, about=ABOUT_MIDI)]
+    Midi {
+        #[command(subcommand)]
+        action: MidiCommands,
+    },
+    #[command(override_usage=DISCORD_USAGE, about=ABOUT_DISCORD)]
+    Discord {
+        #[command(subcommand)]
+        action: DiscordCommands,
+    },
+    #[command(override_usage=OUTPUT_USAGE, about=ABOUT_OUTPUT)]
+    Output {
+        #[command(subcommand)]
+        action: OutputCommands,
+    },
+    #[command(override_usage=CACHE_USAGE, about=ABOUT_CACHE)]
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+    #[command(override_usage=LIBRARY_USAGE, about=ABOUT_LIBRARY)]
+    Library {
+        #[command(subcommand)]
+        action: LibraryCommands,
+    },
+    #[command(override_usage=APPLY_MANIFEST_USAGE, about=ABOUT_APPLY_MANIFEST_LONG)]
+    ApplyManifest {
         #[arg(long, short)]
         path: PathBuf,
     },
-    #[command(override_usage=LOAD_USAGE, about=ABOUT_LOAD)]
-    Load {
+    #[command(override_usage=LOG_USAGE, about=ABOUT_LOG_LONG)]
+    Log {
+        text: Vec<String>,
+        #[arg(long, short)]
+        export: Option<PathBuf>,
+    },
+    #[command(override_usage=EXPORT_SCRIPT_USAGE, about=ABOUT_EXPORT_SCRIPT)]
+    ExportScript {
         #[arg(long, short)]
         path: PathBuf,
     },
-    #[command(about=ABOUT_EXIT)]
-    Exit
-}
-
+    #[command(override_usage=RENDER_USAGE, about=ABOUT_RENDER_LONG)]
+    Render {
+        #[arg(long, short, value_parser = parse_duration)]
+        duration: Duration,
+        #[arg(long, short)]
+        path: PathBuf,
+    },
+    #[command(override_usage=SAVE_USAGE, about=ABOUT_SAVE)]
+    Save {
+        #[arg(long, short)]
+        path: PathBuf,
+        #[arg(long)]
+        project: bool,
+    },
+    #[command(override_usage=LOAD_USAGE, about=ABOUT_LOAD)]
+    Load {
+        #[arg(long, short)]
+        path: PathBuf,
+        #[arg(long)]
+        resume_playback: bool,
+    },
+    #[command(override_usage=SWITCH_USAGE, about=ABOUT_SWITCH_LONG)]
+    Switch {
+        #[arg(long, short)]
+        path: PathBuf,
+        #[arg(long, short, value_parser = parse_duration)]
+        fade: Option<Duration>,
+    },
+    #[command(override_usage=IMPORT_USAGE, about=ABOUT_IMPORT_LONG)]
+    Import {
+        #[arg(long, short)]
+        path: PathBuf,
+        #[arg(long, short)]
+        out: PathBuf,
+    },
+    #[command(override_usage=REMAP_USAGE, about=ABOUT_REMAP)]
+    Remap {
+        #[command(subcommand)]
+        action: RemapCommands,
+    },
+    #[command(override_usage=CONFIG_USAGE, about=ABOUT_CONFIG)]
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommands,
+    },
+    #[command(about=ABOUT_EXIT)]
+    Exit
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum QuietMode {
+    // caps the volume now, whatever the time
+    On,
+    // lifts the cap, whatever the time
+    Off,
+    // follows the schedule in the config
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum LimitMode {
+    On,
+    Off,
+}
+
+#[derive(Debug, Subcommand)]
+enum PairingCommands {
+    #[command(override_usage=PAIRING_LIST_USAGE, about=ABOUT_PAIRING_LIST, help_template = COMMAND_HELP)]
+    List,
+    #[command(override_usage=PAIRING_FORGET_USAGE, about=ABOUT_PAIRING_FORGET, help_template = COMMAND_HELP)]
+    Forget { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum RemapCommands {
+    #[command(override_usage=REMAP_ADD_USAGE, about=ABOUT_REMAP_ADD_LONG, help_template = COMMAND_HELP)]
+    Add { from: String, to: String },
+    #[command(override_usage=REMAP_LIST_USAGE, about=ABOUT_REMAP_LIST, help_template = COMMAND_HELP)]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    #[command(override_usage=CONFIG_GET_USAGE, about=ABOUT_CONFIG_GET, help_template = COMMAND_HELP)]
+    Get { key: Option<String> },
+    #[command(override_usage=CONFIG_SET_USAGE, about=ABOUT_CONFIG_SET_LONG, help_template = COMMAND_HELP)]
+    Set {
+        key: String,
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SetCommands {
+    #[command(override_usage=SET_DEFAULT_GROUP_USAGE, about=ABOUT_SET_DEFAULT_GROUP_LONG, help_template = COMMAND_HELP)]
+    DefaultGroup { group: Option<String> },
+    // a variable, like set $battle "drums horns", which clap sees as a subcommand it doesn't know
+    #[command(external_subcommand)]
+    Variable(Vec<String>),
+}
+
+#[derive(Debug, Subcommand)]
+enum HotkeyCommands {
+    #[command(override_usage=HOTKEY_BIND_USAGE, about=ABOUT_HOTKEY_BIND_LONG, help_template = COMMAND_HELP)]
+    Bind {
+        #[arg(value_parser = hotkeys::parse_keys)]
+        keys: Hotkey,
+        command: String,
+    },
+    #[command(override_usage=HOTKEY_UNBIND_USAGE, about=ABOUT_HOTKEY_UNBIND, help_template = COMMAND_HELP)]
+    Unbind {
+        #[arg(value_parser = hotkeys::parse_keys)]
+        keys: Hotkey,
+    },
+    #[command(override_usage=HOTKEY_LIST_USAGE, about=ABOUT_HOTKEY_LIST, help_template = COMMAND_HELP)]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum MidiCommands {
+    #[command(override_usage=MIDI_PORTS_USAGE, about=ABOUT_MIDI_PORTS, help_template = COMMAND_HELP)]
+    Ports,
+    #[command(override_usage=MIDI_CONNECT_USAGE, about=ABOUT_MIDI_CONNECT, help_template = COMMAND_HELP)]
+    Connect { port: Option<String> },
+    #[command(override_usage=MIDI_DISCONNECT_USAGE, about=ABOUT_MIDI_DISCONNECT, help_template = COMMAND_HELP)]
+    Disconnect,
+    #[command(override_usage=MIDI_MAP_USAGE, about=ABOUT_MIDI_MAP_LONG, help_template = COMMAND_HELP)]
+    Map {
+        kind: ControlKind,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        number: u8,
+        command: String,
+        #[arg(long, short, value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Option<u8>,
+    },
+    #[command(override_usage=MIDI_UNMAP_USAGE, about=ABOUT_MIDI_UNMAP, help_template = COMMAND_HELP)]
+    Unmap {
+        kind: ControlKind,
+        #[arg(value_parser = clap::value_parser!(u8).range(0..=127))]
+        number: u8,
+        #[arg(long, short, value_parser = clap::value_parser!(u8).range(1..=16))]
+        channel: Option<u8>,
+    },
+    #[command(override_usage=MIDI_LIST_USAGE, about=ABOUT_MIDI_LIST, help_template = COMMAND_HELP)]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum DiscordCommands {
+    #[command(override_usage=DISCORD_JOIN_USAGE, about=ABOUT_DISCORD_JOIN_LONG, help_template = COMMAND_HELP)]
+    Join {
+        guild: NonZeroU64,
+        channel: NonZeroU64,
+    },
+    #[command(override_usage=DISCORD_LEAVE_USAGE, about=ABOUT_DISCORD_LEAVE, help_template = COMMAND_HELP)]
+    Leave,
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommands {
+    #[command(override_usage=CACHE_BUILD_USAGE, about=ABOUT_CACHE_BUILD_LONG, help_template = COMMAND_HELP)]
+    Build,
+    #[command(override_usage=CACHE_CLEAR_USAGE, about=ABOUT_CACHE_CLEAR, help_template = COMMAND_HELP)]
+    Clear,
+}
+
+#[derive(Debug, Subcommand)]
+enum LibraryCommands {
+    #[command(override_usage=LIBRARY_ROOTS_USAGE, about=ABOUT_LIBRARY_ROOTS, help_template = COMMAND_HELP)]
+    Roots,
+    #[command(override_usage=LIBRARY_ADD_ROOT_USAGE, about=ABOUT_LIBRARY_ADD_ROOT_LONG, help_template = COMMAND_HELP)]
+    AddRoot { folder: PathBuf },
+    #[command(override_usage=LIBRARY_REMOVE_ROOT_USAGE, about=ABOUT_LIBRARY_REMOVE_ROOT, help_template = COMMAND_HELP)]
+    RemoveRoot { folder: PathBuf },
+    #[command(override_usage=LIBRARY_SCAN_USAGE, about=ABOUT_LIBRARY_SCAN, help_template = COMMAND_HELP)]
+    Scan,
+    #[command(override_usage=LIBRARY_SEARCH_USAGE, about=ABOUT_LIBRARY_SEARCH, help_template = COMMAND_HELP)]
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    #[command(override_usage=LIBRARY_ADD_USAGE, about=ABOUT_LIBRARY_ADD_LONG, help_template = COMMAND_HELP)]
+    Add {
+        #[arg(value_parser = clap::value_parser!(usize))]
+        result: usize,
+        #[arg(long, short)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum OutputCommands {
+    #[command(override_usage=OUTPUT_DEVICES_USAGE, about=ABOUT_OUTPUT_DEVICES, help_template = COMMAND_HELP)]
+    Devices,
+    #[command(override_usage=OUTPUT_USE_USAGE, about=ABOUT_OUTPUT_USE_LONG, help_template = COMMAND_HELP)]
+    Use { device: Option<String> },
+}
+
+#[derive(Debug, Subcommand)]
+enum SceneCommands {
+    #[command(override_usage=SCENE_CAPTURE_USAGE, about=ABOUT_SCENE_CAPTURE_LONG, help_template = COMMAND_HELP)]
+    Capture { name: String },
+    #[command(override_usage=SCENE_SAVE_USAGE, about=ABOUT_SCENE_SAVE, help_template = COMMAND_HELP)]
+    Save { name: String },
+    #[command(override_usage=SCENE_SWITCH_USAGE, about=ABOUT_SCENE_SWITCH_LONG, help_template = COMMAND_HELP)]
+    Switch {
+        name: String,
+        #[arg(long, short, value_parser = parse_duration)]
+        fade: Option<Duration>,
+    },
+    #[command(override_usage=SCENE_VOLUME_USAGE, about=ABOUT_SCENE_VOLUME_LONG, help_template = COMMAND_HELP)]
+    Volume {
+        name: String,
+        #[arg(required = true)]
+        ids: Vec<String>,
+        #[arg(long, short, value_parser = parse_volume, allow_hyphen_values = true)]
+        volume: Option<u32>,
+    },
+    #[command(override_usage=SCENE_LIST_USAGE, about=ABOUT_SCENE_LIST, help_template = COMMAND_HELP)]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum MarkCommands {
+    #[command(override_usage=MARK_ADD_USAGE, about=ABOUT_MARK_ADD_LONG, help_template = COMMAND_HELP)]
+    Add {
+        id: String,
+        name: String,
+        #[arg(value_parser = parse_position)]
+        pos: Duration,
+    },
+    #[command(override_usage=MARK_REMOVE_USAGE, about=ABOUT_MARK_REMOVE, help_template = COMMAND_HELP)]
+    Remove { id: String, name: String },
+    #[command(override_usage=MARK_LIST_USAGE, about=ABOUT_MARK_LIST, help_template = COMMAND_HELP)]
+    List { ids: Vec<String> },
+}
+
+#[derive(Debug, Subcommand)]
+enum TagCommands {
+    #[command(override_usage=TAG_ADD_USAGE, about=ABOUT_TAG_ADD_LONG, help_template = COMMAND_HELP)]
+    Add {
+        id: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    #[command(override_usage=TAG_REMOVE_USAGE, about=ABOUT_TAG_REMOVE, help_template = COMMAND_HELP)]
+    Remove {
+        id: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    #[command(override_usage=TAG_LIST_USAGE, about=ABOUT_TAG_LIST, help_template = COMMAND_HELP)]
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum ScheduleCommands {
+    #[command(override_usage=SCHEDULE_LIST_USAGE, about=ABOUT_SCHEDULE_LIST, help_template = COMMAND_HELP)]
+    List,
+    #[command(override_usage=SCHEDULE_CANCEL_USAGE, about=ABOUT_SCHEDULE_CANCEL, help_template = COMMAND_HELP)]
+    Cancel {
+        #[arg(required = true)]
+        ids: Vec<u32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CueCommands {
+    #[command(override_usage=CUE_ADD_USAGE, about=ABOUT_CUE_ADD_LONG, help_template = COMMAND_HELP)]
+    Add {
+        #[arg(required = true)]
+        commands: Vec<String>,
+    },
+    #[command(override_usage=CUE_LIST_USAGE, about=ABOUT_CUE_LIST, help_template = COMMAND_HELP)]
+    List,
+    #[command(override_usage=CUE_GO_USAGE, about=ABOUT_CUE_GO, help_template = COMMAND_HELP)]
+    Go,
+    #[command(override_usage=CUE_RESET_USAGE, about=ABOUT_CUE_RESET, help_template = COMMAND_HELP)]
+    Reset,
+}
+
 fn parse_duration(dur: &str) -> Result<Duration, Error> {
     Ok(duration_str::parse(dur)?)
 }
 
+// a point in a sound, written like on a timeline or as a duration
+fn parse_position(pos: &str) -> Result<Duration, Error> {
+    parse_timestamp(pos).map_or_else(|| parse_duration(pos), Ok)
+}
+
+fn parse_pan(pan: &str) -> Result<f32, Error> {
+    let pan: f32 = pan.parse()?;
+    if !(-1.0..=1.0).contains(&pan) {
+        return Err(Error::msg("the pan must be between -1 and 1"));
+    }
+    Ok(pan)
+}
+
+fn parse_balance_target(target: &str) -> Result<f32, Error> {
+    let target: f32 = target.parse()?;
+    if !(-60.0..=0.0).contains(&target) {
+        return Err(Error::msg("the target must be between -60 and 0 dB"));
+    }
+    Ok(target)
+}
+
+fn parse_threshold(threshold: &str) -> Result<f32, Error> {
+    let threshold: f32 = threshold.parse()?;
+    if !(-30.0..=0.0).contains(&threshold) {
+        return Err(Error::msg("the threshold must be between -30 and 0 dBFS"));
+    }
+    Ok(threshold)
+}
+
+fn parse_duck_amount(amount: &str) -> Result<f32, Error> {
+    let amount: f32 = amount.parse()?;
+    if !(amount > 0.0 && amount <= 60.0) {
+        return Err(Error::msg("the amount must be above 0 and at most 60 dB"));
+    }
+    Ok(amount)
+}
+
+fn parse_volume_scale(scale: &str) -> Result<f32, Error> {
+    let scale: f32 = scale.parse()?;
+    if !(0.0..=10.0).contains(&scale) {
+        return Err(Error::msg("the volume scale must be between 0 and 10"));
+    }
+    Ok(scale)
+}
+
+fn parse_speed(speed: &str) -> Result<f32, Error> {
+    let speed: f32 = speed.parse()?;
+    if !(0.25..=4.0).contains(&speed) {
+        return Err(Error::msg("the speed must be between 0.25 and 4"));
+    }
+    Ok(speed)
+}
+
 // FIXME: this only works if the app stays single threaded. Also, when I write the GUI version, this should probably be refactored.
 // additionally, It prevents any debugger from working;
-thread_local! {static READLINE: RefCell<Editor<(), FileHistory>> = RefCell::new(DefaultEditor::new().expect("error: could not get access to the stdin."))}
+thread_local! {static READLINE: RefCell<Editor<CommandHelper, FileHistory>> = RefCell::new(Editor::new().expect("error: could not get access to the stdin."))}
+// time spent waiting for the user to type something. This doesn't count towards how long a command took
+thread_local! {static INPUT_WAIT: Cell<Duration> = const { Cell::new(Duration::ZERO) }}
+// threads other than the main one have nobody to answer their prompts
+thread_local! {static INTERACTIVE: Cell<bool> = const { Cell::new(true) }}
+
+// commands that take longer than this report how long they took
+const SLOW_COMMAND_THRESHOLD: Duration = Duration::from_secs(1);
 
 pub struct AppState {
     pub players: HashMap<String, Player>,
     pub top_group: IndexSet<String>,
     pub groups: IndexMap<String, IndexSet<String>>,
+    pub scenes: IndexMap<String, Scene>,
+    // the scene that was switched to last
+    pub active_scene: Option<String>,
+    // the group that added sounds go in
+    pub default_group: Option<String>,
+    // where the sounds of saves from other machines are, see remap
+    pub remaps: IndexMap<String, String>,
+    // the project folder the soundscape was last saved to or loaded from, see project
+    pub project: Option<PathBuf>,
+    // the players that were playing when the soundscape got suspended
+    pub suspended: IndexSet<String>,
+    // players that were removed while they were playing. They are dropped once they have faded out
+    pub removed: Vec<Player>,
+    pub group_loops: IndexMap<String, GroupLoop>,
+    // the settings that the members of a group take, unless they set their own
+    pub group_defaults: IndexMap<String, Settings>,
+    // players whose volumes move together, by the name of the link
+    pub volume_links: IndexMap<String, IndexSet<String>>,
+    // groups that play their members one at a time, by the name of the group
+    pub playlists: IndexMap<String, Playlist>,
+    // groups that go down while other groups are heard
+    pub ducks: Vec<Duck>,
+    // commands that run by themselves later, see at and after
+    pub schedule: Schedule,
+    pub cues: CueList,
+    // set by the quiet-hours command. None follows the schedule in the config
+    pub quiet_override: Option<bool>,
+    pub archived: IndexMap<String, player::Serializable>,
+    pub mixer: Mixer,
+    pub config: Config,
+    // events that happened, but that haven't been handled by the hooks yet
+    pub events: Vec<AppEvent>,
+    pub bus: EventBus,
+    observer: Observer,
+    // the subscription that prints events, when monitor is on
+    pub monitor: Option<SubscriptionId>,
+    // the version of the save the soundscape was last loaded from or saved to
+    pub save_version: Option<u32>,
+    // the remotes on other machines that may control the soundscape
+    pub pairing: Pairing,
+    pub midi: Midi,
+    pub discord: Discord,
+    pub hotkeys: Hotkeys,
+    pub aliases: Aliases,
+    pub library: Library,
+    // what the variables stand for, by their name without the $
+    pub variables: IndexMap<String, String>,
+    pub session_log: SessionLog,
+}
+
+impl AppState {
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot::new(self)
+    }
+
+    // the settings that are in effect for a player, with what it doesn't set taken from its group
+    pub fn effective_settings(&self, id: &str) -> Effective<'_> {
+        let player = &self.players[id];
+        let group = player.group.as_ref().and_then(|group| {
+            self.group_defaults
+                .get(group)
+                .map(|defaults| (group.as_str(), defaults))
+        });
+        player.settings.resolve(group, &self.default_settings())
+    }
+
+    // the fades of the config, for players that don't set them and whose group doesn't either
+    pub fn default_settings(&self) -> Settings {
+        Settings {
+            fade_in: Some(self.config.default_fade_in),
+            fade_out: Some(self.config.default_fade_out),
+            soft_start: None,
+            bus: None,
+        }
+    }
+
+    // so that the next session can play them again, see --resume
+    pub fn remember_playing(&self) {
+        if let Some(save) = &self.config.last_save {
+            let playing = self
+                .ordered_ids()
+                .filter(|id| self.players[*id].get_is_playing())
+                .cloned()
+                .collect();
+            config::remember_playing(save, playing);
+        }
+    }
+
+    // walks the players in the same order that show uses
+    pub fn ordered_ids(&self) -> impl Iterator<Item = &String> {
+        self.top_group.iter().chain(self.groups.values().flatten())
+    }
+
+    // pauses everything, but remembers what was actually playing, so resume_all only restarts those
+    pub fn pause_all(&mut self) -> Vec<String> {
+        let playing: Vec<String> = self
+            .ordered_ids()
+            .filter(|id| self.players.get(*id).is_some_and(|p| p.get_is_playing()))
+            .cloned()
+            .collect();
+        for id in &playing {
+            self.players.get_mut(id).unwrap().pause();
+        }
+        self.suspended.extend(playing.iter().cloned());
+        playing
+    }
+
+    // players that have been stopped or started by hand in the meantime are left alone
+    pub fn resume_all(&mut self) -> Result<Vec<String>, Error> {
+        let mut resumed = Vec::new();
+        for id in std::mem::take(&mut self.suspended) {
+            if let Some(player) = self.players.get_mut(&id) {
+                if player.get_is_paused() {
+                    player.play()?;
+                    resumed.push(id);
+                }
+            }
+        }
+        Ok(resumed)
+    }
+
+    // the sounds that are playing, mixed from their start like the master output would, into a wav
+    // file. Returns how many sounds went into it
+    pub fn render(&self, duration: Duration, path: &Path) -> Result<usize, Error> {
+        let sounds = self
+            .ordered_ids()
+            .map(|id| &self.players[id])
+            .filter(|player| player.get_is_playing())
+            .map(Player::render_source)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let count = sounds.len();
+        let limiter = &self.mixer.master_limit;
+        let limiter = LimiterControl::new(limiter.threshold(), limiter.is_enabled());
+        render::render(sounds, limiter, duration, path)?;
+        Ok(count)
+    }
+
+    pub fn emit(&mut self, event: AppEvent) {
+        self.session_log.record(&event);
+        self.bus.publish(&event);
+        self.events.push(event);
+    }
+
+    pub fn is_quiet_hours(&self) -> bool {
+        self.quiet_override.unwrap_or_else(|| {
+            self.config
+                .quiet_hours
+                .as_ref()
+                .is_some_and(|quiet_hours| quiet_hours.contains(Local::now().time()))
+        })
+    }
+
+    // the volume that the master output is capped at during quiet hours, in percent
+    pub fn quiet_hours_volume(&self) -> u32 {
+        self.config
+            .quiet_hours
+            .as_ref()
+            .map(|quiet_hours| quiet_hours.volume)
+            .unwrap_or(config::DEFAULT_QUIET_VOLUME)
+    }
+
+    fn update_quiet_hours(&mut self) {
+        let volume = self.quiet_hours_volume();
+        let quiet = self.is_quiet_hours();
+        let cap = quiet.then(|| player::real_volume(volume));
+        if self.mixer.set_quiet_hours_cap(cap) {
+            progress::suspend();
+            if quiet {
+                println!("Quiet hours: the master volume is capped at {volume}%.");
+            } else {
+                println!("Quiet hours are over.");
+            }
+        }
+    }
+
+    fn update_ducking(&mut self) {
+        let now = Instant::now();
+        // a group under more than one rule goes as far down as the deepest one
+        let mut targets: HashMap<String, f32> = HashMap::new();
+        let mut releases: HashMap<String, Duration> = HashMap::new();
+        for duck in &mut self.ducks {
+            let heard = self.groups.get(&duck.when).is_some_and(|members| {
+                members.iter().any(|id| {
+                    self.players.get(id).is_some_and(|p| {
+                        !p.is_editing() && p.current_level().rms > ducking::HEARD_LEVEL
+                    })
+                })
+            });
+            let release = releases.entry(duck.target.clone()).or_default();
+            *release = (*release).max(duck.release);
+            if duck.poll(heard, now) {
+                let gain = targets.entry(duck.target.clone()).or_insert(1.0);
+                *gain = gain.min(duck.gain());
+            }
+        }
+        for player in self.players.values_mut() {
+            let group = player.group.as_ref();
+            let gain = group.and_then(|g| targets.get(g)).copied().unwrap_or(1.0);
+            let release = group
+                .and_then(|g| releases.get(g))
+                .copied()
+                .unwrap_or(ducking::DEFAULT_RELEASE);
+            player.duck(gain, ducking::ATTACK, release);
+        }
+    }
+
+    pub fn poll_events(&mut self) {
+        self.update_quiet_hours();
+        self.update_ducking();
+        self.removed.retain(|player| player.get_is_playing());
+        let mut finished = Vec::new();
+        for (id, player) in &mut self.players {
+            if player.poll_finished() {
+                finished.push(id.clone());
+            }
+        }
+
+        for player in self.players.values_mut() {
+            player.poll_pausing();
+            if let Err(err) = player.poll_queued() {
+                println!("{err}");
+            }
+        }
+
+        for (group_name, group_loop) in &mut self.group_loops {
+            let Some(members) = self.groups.get(group_name) else {
+                continue;
+            };
+            let players = &self.players;
+            let restart = group_loop.poll(members, &finished, |id| {
+                players
+                    .get(id)
+                    .is_some_and(|p| p.get_is_playing() || p.get_is_paused())
+            });
+            for id in restart.into_iter().flatten() {
+                let player = self.players.get_mut(&id).unwrap();
+                player.stop();
+                if let Err(err) = player.play() {
+                    println!("{err}");
+                }
+            }
+        }
+
+        for (group_name, playlist) in &mut self.playlists {
+            let Some(members) = self.groups.get(group_name) else {
+                continue;
+            };
+            let players = &self.players;
+            let next = playlist.poll(
+                members,
+                &finished,
+                |id| {
+                    players
+                        .get(id)
+                        .is_some_and(|p| p.get_is_playing() || p.get_is_paused())
+                },
+                playlist::random(),
+            );
+            if let Some(id) = next {
+                if let Err(err) = self.players.get_mut(&id).unwrap().play() {
+                    println!("{err}");
+                }
+            }
+        }
+
+        for event in self.observer.observe(&self.players) {
+            if matches!(event, AppEvent::Started(_)) {
+                self.mixer.release_safe_start();
+            }
+            self.emit(event);
+        }
+        for id in finished {
+            self.emit(AppEvent::PlayerFinished(id));
+        }
+    }
+}
+
+// the arguments troubadour itself is started with, as opposed to the commands at the prompt
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "A simple audio looping application for the creation of soundscapes."
+)]
+struct Args {
+    #[arg(
+        long,
+        help = "Keeps the config and history in a troubadour-data folder next to the executable, instead of in the config directory."
+    )]
+    portable: bool,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Also takes commands from browsers over WebSocket on this address, like 127.0.0.1:7879, and pushes what happens to them."
+    )]
+    remote: Option<SocketAddr>,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Also takes requests over a small HTTP API on this address, like 127.0.0.1:7880."
+    )]
+    http: Option<SocketAddr>,
+    #[arg(
+        long,
+        help = "Advertises the server, remote and HTTP endpoints on the network over mDNS, so phones and other machines can find them."
+    )]
+    advertise: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Runs the commands in FILE, one per line, instead of showing the prompt. Stops at the first command that fails, with exit code 1. Use - to read them from stdin, which also happens when stdin is not a terminal."
+    )]
+    script: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "With a command, like troubadour play horn --save scape.json: loads the save, runs the command on it and saves the changes."
+    )]
+    save: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "With a command, like troubadour play horn --connect 127.0.0.1:7878: sends it to troubadour running with serve."
+    )]
+    connect: Option<String>,
+    #[arg(
+        long,
+        help = "Starts in the dashboard instead of at the prompt, see the tui command."
+    )]
+    tui: bool,
+    #[arg(
+        long,
+        conflicts_with = "save",
+        help = "Opens the save that was loaded or saved last again, and offers to play the sounds that were playing when troubadour was closed."
+    )]
+    resume: bool,
+    #[command(subcommand)]
+    mode: Option<Mode>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Mode {
+    #[command(
+        about = "Runs the soundscape without a prompt, and takes commands over the network instead. Send one command per line, written as at the prompt, or as a JSON-RPC request."
+    )]
+    Serve {
+        #[arg(long, short, default_value_t = 7878)]
+        port: u16,
+        #[arg(
+            long,
+            default_value = "127.0.0.1",
+            help = "The address to listen on. Use 0.0.0.0 to take commands from other machines, which needs --token or a paired remote."
+        )]
+        bind: String,
+        #[arg(
+            long,
+            value_name = "TOKEN",
+            help = "Clients on other machines have to send auth TOKEN before their commands are taken."
+        )]
+        token: Option<String>,
+    },
+    // anything else is a command, which is run once before troubadour exits
+    #[command(external_subcommand)]
+    Command(Vec<String>),
 }
 
 fn main() -> Result<(), String> {
-    println!(
-        r"Troubadour Copyright (C) 2024 J.P Hagedoorn AKA Dexterdy Krataigos
+    let args = Args::parse();
+    let one_shot = match &args.mode {
+        Some(Mode::Command(words)) => {
+            let mut words = words.clone();
+            // the options can come before the command too
+            if let Some(path) = &args.save {
+                words.extend(["--save".to_string(), path.to_string_lossy().into_owned()]);
+            }
+            if let Some(address) = &args.connect {
+                words.extend(["--connect".to_string(), address.clone()]);
+            }
+            Some(oneshot::parse(&words).map_err(|err| err.to_string())?)
+        }
+        _ if args.save.is_some() || args.connect.is_some() => {
+            return Err("--save and --connect go with a command, like: troubadour play horn --save scape.json".to_string());
+        }
+        _ => None,
+    };
+    // the output of a one-off command might be read by a script
+    if one_shot.is_none() {
+        println!(
+            r"Troubadour Copyright (C) 2024 J.P Hagedoorn AKA Dexterdy Krataigos
 This program comes with ABSOLUTELY NO WARRANTY.
 This is free software, and you are welcome to redistribute it
 under the conditions of the GPL v3."
-    );
+        );
+    }
+    if let Some(OneShot {
+        command,
+        connect: Some(address),
+        ..
+    }) = &one_shot
+    {
+        match oneshot::send(address, command) {
+            Ok(said) => {
+                print!("{said}");
+                return Ok(());
+            }
+            Err(err) => {
+                println!("{err}");
+                process::exit(1);
+            }
+        }
+    }
+
+    if args.portable {
+        match config::use_portable_dir() {
+            Ok(dir) => println!("Portable mode: keeping settings in {}.", dir.display()),
+            Err(err) => return Err(err.to_string()),
+        }
+    }
+    if let Some(path) = config::history_path() {
+        READLINE.with_borrow_mut(|rl| rl.load_history(&path).unwrap_or_default());
+    }
 
-    let mut state = AppState {
+    let config = Config::load().unwrap_or_else(|err| {
+        println!("{err}");
+        Config::default()
+    });
+    let pairing = Pairing::load().unwrap_or_else(|err| {
+        println!("{err}");
+        Pairing::default()
+    });
+    let mixer = Mixer::new();
+    mixer
+        .headphone_protect
+        .set_enabled(config.headphone_protect);
+    if config.headphone_protect {
+        println!("Headphone protection is on.");
+    }
+    mixer.set_safe_start(config.safe_start.enabled.then_some(config.safe_start.ramp));
+
+    let (midi_messages, midi_receiver) = mpsc::channel();
+    let (hotkey_commands, hotkey_receiver) = mpsc::channel();
+    let mut hotkeys = Hotkeys::new(hotkey_commands);
+    if let Err(err) = hotkeys.load() {
+        println!("{err}");
+    }
+    let mut aliases = Aliases::new();
+    if let Err(err) = aliases.load() {
+        println!("{err}");
+    }
+    let mut library = Library::new();
+    if let Err(err) = library.load() {
+        println!("{err}");
+    }
+    let state = AppState {
         players: HashMap::new(),
         top_group: IndexSet::new(),
         groups: IndexMap::new(),
+        scenes: IndexMap::new(),
+        active_scene: None,
+        default_group: None,
+        remaps: IndexMap::new(),
+        project: None,
+        suspended: IndexSet::new(),
+        removed: Vec::new(),
+        group_loops: IndexMap::new(),
+        group_defaults: IndexMap::new(),
+        volume_links: IndexMap::new(),
+        playlists: IndexMap::new(),
+        ducks: Vec::new(),
+        schedule: Schedule::default(),
+        cues: CueList::default(),
+        quiet_override: None,
+        archived: IndexMap::new(),
+        mixer,
+        config,
+        events: Vec::new(),
+        bus: EventBus::default(),
+        observer: Observer::default(),
+        monitor: None,
+        save_version: None,
+        pairing,
+        midi: Midi::new(midi_messages),
+        discord: Discord::new(),
+        hotkeys,
+        aliases,
+        library,
+        variables: IndexMap::new(),
+        session_log: SessionLog::new(),
     };
+    let state = Arc::new(Mutex::new(state));
+    READLINE.with_borrow_mut(|rl| rl.set_helper(Some(CommandHelper::new(state.clone()))));
+    let background_mutated = Arc::new(AtomicBool::new(false));
+    hooks::spawn_watcher(state.clone(), background_mutated.clone());
+    if let Some(folder) = &state.lock().unwrap().config.watch_folder {
+        if folder.path.is_dir() {
+            println!(
+                "Watching {} for new sounds. They are added to the group {}.",
+                folder.path.display(),
+                folder.group
+            );
+            watch_folder::spawn_watcher(
+                state.clone(),
+                folder.path.clone(),
+                folder.group.clone(),
+                background_mutated.clone(),
+            );
+        } else {
+            println!(
+                "warning: the watch folder {} does not exist.",
+                folder.path.display()
+            );
+        }
+    }
 
     let mut has_been_saved = true;
+    let saved = Arc::new(AtomicBool::new(true));
+    shutdown::install(state.clone(), saved.clone(), background_mutated.clone());
+    let autosave_interval = state.lock().unwrap().config.autosave_interval;
+    if !autosave_interval.is_zero() {
+        shutdown::autosave_every(
+            autosave_interval,
+            state.clone(),
+            saved.clone(),
+            background_mutated.clone(),
+        );
+    }
+    midi::spawn(
+        state.clone(),
+        midi_receiver,
+        saved.clone(),
+        background_mutated.clone(),
+    );
+    hotkeys::spawn(
+        state.clone(),
+        hotkey_receiver,
+        saved.clone(),
+        background_mutated.clone(),
+    );
+
+    if let Some(address) = args.remote {
+        remote::spawn(
+            state.clone(),
+            address,
+            saved.clone(),
+            background_mutated.clone(),
+        )
+        .map_err(|err| err.to_string())?;
+    }
+    if let Some(address) = args.http {
+        http::spawn(
+            state.clone(),
+            address,
+            saved.clone(),
+            background_mutated.clone(),
+        )
+        .map_err(|err| err.to_string())?;
+    }
+    // kept until troubadour stops, because dropping it stops the advertising
+    let _advertiser = if args.advertise {
+        let mut endpoints = Vec::new();
+        if let Some(address) = args.remote {
+            endpoints.push((Api::WebSocket, address.ip(), address.port()));
+        }
+        if let Some(address) = args.http {
+            endpoints.push((Api::Http, address.ip(), address.port()));
+        }
+        if let Some(Mode::Serve { port, bind, .. }) = &args.mode {
+            // a host name instead of an address is taken to be reachable
+            let ip = bind.parse().unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+            endpoints.push((Api::Lines, ip, *port));
+        }
+        if endpoints.is_empty() {
+            println!("warning: there is nothing to advertise. Use --advertise with serve, --remote or --http.");
+        }
+        discovery::advertise(&endpoints)
+            .map_err(|err| println!("{err}"))
+            .ok()
+    } else {
+        None
+    };
+    if args.resume || offer_resume(&state.lock().unwrap(), &args, one_shot.is_some()) {
+        let mut state = state.lock().unwrap();
+        let spinner = Spinner::start("loading");
+        let reopened = reopen_last(&mut state, &mut |done, total, name| {
+            spinner.progress(done, total, &format!("loading {name}"))
+        });
+        drop(spinner);
+        if let Err(err) = reopened.and_then(|_| replay_last(&mut state)) {
+            println!("{err}");
+        }
+    }
+    if let Some(Mode::Serve { port, bind, token }) = &args.mode {
+        return server::serve(
+            state,
+            (bind, *port),
+            token.clone(),
+            saved,
+            background_mutated,
+        )
+        .map_err(|err| err.to_string());
+    }
+    if let Some(shot) = &one_shot {
+        if let Err(err) = oneshot::run_headless(&state, shot) {
+            println!("{err}");
+            process::exit(1);
+        }
+        batch::wait_for_sounds(&state);
+        return Ok(());
+    }
 
+    let script: Option<(Box<dyn BufRead>, String)> = match &args.script {
+        Some(path) if path == Path::new("-") => {
+            Some((Box::new(io::stdin().lock()), "stdin".to_string()))
+        }
+        Some(path) => match File::open(path) {
+            Ok(file) => Some((Box::new(BufReader::new(file)), path.display().to_string())),
+            Err(err) => {
+                println!("error: could not open {}. {err}", path.display());
+                process::exit(2);
+            }
+        },
+        None if !io::stdin().is_terminal() => {
+            Some((Box::new(io::stdin().lock()), "stdin".to_string()))
+        }
+        None => None,
+    };
+    if let Some((script, source)) = script {
+        match batch::run(&state, script, &source, &saved, &background_mutated) {
+            Ok(true) => return Ok(()),
+            Ok(false) => {
+                batch::wait_for_sounds(&state);
+                return Ok(());
+            }
+            Err(err) => {
+                println!("{err}");
+                process::exit(1);
+            }
+        }
+    }
+
+    if args.tui {
+        tui::request();
+    }
     loop {
         let mut should_quit = false;
 
-        let response = readline("$ ").and_then(|line| {
-            let line = line.trim();
-            respond(&mut state, &line, has_been_saved)
-        });
+        let response = if tui::take_request() {
+            tui::run(&state, has_been_saved)
+        } else {
+            prompt(&state, has_been_saved)
+        };
 
         match response {
             Ok(RespondResult {
@@ -298,10 +2309,18 @@ under the conditions of the GPL v3."
                 Err(err) => println!("{err}"),
             },
         }
+        // hooks and the watch folder can change things in the background, while waiting for input
+        if background_mutated.swap(false, Ordering::SeqCst) {
+            has_been_saved = false;
+        }
+        saved.store(has_been_saved, Ordering::SeqCst);
+        if hooks::take_quit_request() {
+            should_quit = true;
+        }
 
         if should_quit {
             let quit = has_been_saved
-                || get_confirmation("Are you sure you want to exit without saving?")
+                || confirm_destructive("Are you sure you want to exit without saving?")
                     .unwrap_or_else(|e| {
                         matches!(
                             e.downcast::<ReadlineError>(),
@@ -309,41 +2328,319 @@ under the conditions of the GPL v3."
                         )
                     });
             if quit {
+                state.lock().unwrap().remember_playing();
                 break Ok(());
             }
         }
     }
 }
 
+// whether to open the last save again, when the config says to ask at the start
+fn offer_resume(state: &AppState, args: &Args, one_shot: bool) -> bool {
+    let Some(save) = &state.config.last_save else {
+        return false;
+    };
+    state.config.ask_to_resume
+        && !one_shot
+        && args.script.is_none()
+        && io::stdin().is_terminal()
+        && save.exists()
+        && get_confirmation(&format!("Open {} again?", save.display())).unwrap_or(false)
+}
+
+// reads a command at the prompt and runs it
+fn prompt(state: &Arc<Mutex<AppState>>, has_been_saved: bool) -> Result<RespondResult, Error> {
+    // what was typed during the last command goes first
+    let line = match typeahead::next() {
+        Some(line) => {
+            println!("$ {line}");
+            Ok(line)
+        }
+        // a scheduled exit can come in while nothing is typed
+        None if !typeahead::wait_for_key("$ ", hooks::quit_is_requested) => Ok(String::new()),
+        None => readline("$ "),
+    };
+    line.and_then(|line| {
+        let line = line.trim();
+        let started = Instant::now();
+        let waited_before = INPUT_WAIT.get();
+        let response = run_line(state, line, has_been_saved);
+        let busy = started
+            .elapsed()
+            .saturating_sub(INPUT_WAIT.get() - waited_before);
+        if busy > SLOW_COMMAND_THRESHOLD {
+            println!("(took {})", player::duration_to_string(busy, false));
+        }
+        response
+    })
+}
+
+// runs a command, and then whatever it set off, like hooks
+pub fn run_command(
+    state: &mut AppState,
+    line: &str,
+    has_been_saved: bool,
+) -> Result<RespondResult, Error> {
+    respond(state, line, has_been_saved).map(|mut result| {
+        state.poll_events();
+        result.mutated |= hooks::run_hooks(state, has_been_saved && !result.mutated);
+        result
+    })
+}
+
+// the state for commands that take a while, like the meter. A shared one is only locked for each
+// step, so the server and the watchers in the background aren't held up in between
+pub enum Shared<'a> {
+    Locked(&'a Mutex<AppState>),
+    Borrowed(&'a mut AppState),
+}
+
+impl Shared<'_> {
+    pub fn with<T>(&mut self, step: impl FnOnce(&mut AppState) -> T) -> T {
+        match self {
+            Shared::Locked(state) => step(&mut state.lock().unwrap()),
+            Shared::Borrowed(state) => step(state),
+        }
+    }
+}
+
+// runs a command like run_command, but without holding the lock through the commands that take a
+// while. Commands that are run by other commands, like aliases, hold it the whole time
+pub fn run_line(
+    state: &Mutex<AppState>,
+    line: &str,
+    has_been_saved: bool,
+) -> Result<RespondResult, Error> {
+    let command = {
+        let mut state = state.lock().unwrap();
+        let is_alias = state
+            .aliases
+            .expand(line)
+            .is_ok_and(|alias| alias.is_some());
+        let command = (!line.is_empty() && !line.starts_with('#') && !is_alias)
+            .then(|| expand_words(&state, line, &Names::of(&state)).ok())
+            .flatten()
+            .and_then(|words| Commands::try_parse_from(words).ok())
+            .filter(Commands::takes_a_while);
+        match command {
+            Some(command) => command,
+            None => return run_command(&mut state, line, has_been_saved),
+        }
+    };
+    let result = respond_shared(&mut Shared::Locked(state), command)?;
+    let mut state = state.lock().unwrap();
+    state.poll_events();
+    Ok(RespondResult {
+        mutated: result.mutated | hooks::run_hooks(&mut state, has_been_saved && !result.mutated),
+        ..result
+    })
+}
+
+// the commands that take a while, see run_line
+fn respond_shared(state: &mut Shared, command: Commands) -> Result<RespondResult, Error> {
+    match command {
+        Commands::Meter {
+            ids,
+            groups,
+            duration,
+        } => show_meter(state, ids, groups, duration),
+        Commands::Board { duration } => show_board(state, duration),
+        Commands::Preflight => {
+            let spinner = Spinner::start("checking");
+            preflight(state, &mut |done, total, message| {
+                spinner.progress(done, total, message)
+            })
+        }
+        Commands::Add {
+            path,
+            youtube,
+            name,
+        } => {
+            let spinner = Spinner::start("downloading");
+            let mut progress = |_, _, message: &str| spinner.update(message);
+            match (path, youtube) {
+                (_, Some(url)) => add_video(state, url, name, &mut progress),
+                (Some(path), None) => add_from_web(
+                    state,
+                    path.to_string_lossy().into_owned(),
+                    name,
+                    &mut progress,
+                ),
+                (None, None) => unreachable!(),
+            }
+        }
+        Commands::Library {
+            action: LibraryCommands::AddRoot { folder },
+        } => {
+            let spinner = Spinner::start("scanning");
+            library_add_root(state, folder, &mut |done, total, name| {
+                spinner.progress(done, total, &format!("scanning {name}"))
+            })
+        }
+        Commands::Library {
+            action: LibraryCommands::Scan,
+        } => {
+            let spinner = Spinner::start("scanning");
+            library_scan(state, &mut |done, total, name| {
+                spinner.progress(done, total, &format!("scanning {name}"))
+            })
+        }
+        _ => unreachable!(),
+    }
+}
+
+impl Commands {
+    fn takes_a_while(&self) -> bool {
+        match self {
+            Commands::Meter { .. } | Commands::Board { .. } | Commands::Preflight => true,
+            Commands::Add {
+                youtube: Some(_), ..
+            } => true,
+            Commands::Library {
+                action: LibraryCommands::AddRoot { .. } | LibraryCommands::Scan,
+            } => true,
+            Commands::Add {
+                path: Some(path), ..
+            } => download::is_url(path),
+            _ => false,
+        }
+    }
+}
+
+fn split_command(line: &str) -> Result<Vec<String>, Error> {
+    shlex::split(line).ok_or_else(|| {
+        Error::msg("error: cannot parse input. Perhaps you have erroneous quotation(\"\")?")
+    })
+}
+
+// a command as it is written at the prompt
+fn parse_command(line: &str) -> Result<Commands, Error> {
+    Ok(Commands::try_parse_from(split_command(line)?)?)
+}
+
+// the words of a command, with the variables filled in and the names it matches written out
+fn expand_words(state: &AppState, line: &str, names: &Names) -> Result<Vec<String>, Error> {
+    let mut words = variables::expand(&state.variables, split_command(line)?)?;
+    let root = Commands::command();
+    if state.config.match_prefixes {
+        words = completion::expand_prefixes(&root, words, names);
+    }
+    completion::expand_patterns(&root, words, names)
+}
+
 fn respond(state: &mut AppState, line: &str, has_been_saved: bool) -> Result<RespondResult, Error> {
-    if line.is_empty() {
+    // lines starting with # are comments, so that scripts can explain themselves
+    if line.is_empty() || line.starts_with('#') {
         return Ok(RespondResult {
             saved: false,
             mutated: false,
             quit: false,
         });
     }
-    let args = shlex::split(line).ok_or_else(|| {
-        Error::msg("error: cannot parse input. Perhaps you have erroneous quotation(\"\")?")
-    })?;
-    let matches = Commands::try_parse_from(args)?;
-    match matches {
-        Commands::Add { path, name } => add(state, path, name),
+    if let Some(commands) = state.aliases.expand(line)? {
+        return run_alias(state, commands, has_been_saved);
+    }
+    match Commands::try_parse_from(expand_words(state, line, &Names::of(state))?)? {
+        command @ Commands::Add { .. } if command.takes_a_while() => {
+            respond_shared(&mut Shared::Borrowed(state), command)
+        }
+        Commands::Add { path, name, .. } => {
+            let spinner = Spinner::start(format!("adding {}", name.as_deref().unwrap_or("")));
+            add(state, path.unwrap(), name, &mut |_, _, name| {
+                spinner.update(format!("adding {name}"))
+            })
+        }
+        Commands::PasteAdd { name } => {
+            let spinner = Spinner::start("adding");
+            paste_add(state, name, &mut |_, _, name| {
+                spinner.update(format!("adding {name}"))
+            })
+        }
         Commands::Remove { ids } => remove(state, ids),
-        Commands::Play { ids, groups } => play(state, ids, groups),
-        Commands::Stop { ids, groups } => stop(state, ids, groups),
-        Commands::Pause { ids, groups } => pause(state, ids, groups),
+        Commands::Archive { ids } => archive(state, ids),
+        Commands::Unarchive { ids } => unarchive(state, ids),
+        Commands::Play { ids, groups, from } => {
+            // a marker can't be named like a position, see mark_add
+            let from =
+                from.map(|from| parse_position(&from).map_or(Point::Marker(from), Point::At));
+            play(state, ids, groups, from)
+        }
+        Commands::Stop { ids, groups, fade } => stop(state, ids, groups, fade),
+        Commands::Pause { ids, groups, fade } => pause(state, ids, groups, fade),
         Commands::Volume {
             ids,
             groups,
             volume,
         } => set_volume(state, ids, groups, volume),
-        Commands::Show { ids, groups } => show(state, ids, groups),
+        Commands::Fade {
+            ids,
+            groups,
+            volume,
+            duration,
+        } => fade_volume(state, ids, groups, volume, duration),
+        Commands::Width { ids, groups, width } => set_width(state, ids, groups, width),
+        Commands::Normalize { ids, groups } => {
+            let spinner = Spinner::start("measuring");
+            normalize(state, ids, groups, &mut |done, total, name| {
+                spinner.progress(done, total, &format!("measuring {name}"))
+            })
+        }
+        Commands::Balance {
+            group,
+            target,
+            reset_relative,
+        } => {
+            let spinner = Spinner::start("measuring");
+            balance(
+                state,
+                group,
+                target,
+                reset_relative,
+                &mut |done, total, name| {
+                    spinner.progress(done, total, &format!("measuring {name}"))
+                },
+            )
+        }
+        Commands::Pan { ids, groups, pan } => set_pan(state, ids, groups, pan),
+        Commands::Speed { ids, groups, speed } => set_speed(state, ids, groups, speed),
+        Commands::Stretch {
+            ids,
+            groups,
+            length,
+        } => set_stretch(state, ids, groups, length),
+        Commands::Show {
+            ids,
+            groups,
+            levels,
+            effective,
+            verbose,
+        } => show(state, ids, groups, levels, effective, verbose),
         Commands::Loop {
             ids,
             groups,
             duration,
-        } => toggle_loop(state, ids, groups, duration),
+            auto,
+            count,
+        } => {
+            let spinner = Spinner::start("finding loop points");
+            toggle_loop(
+                state,
+                ids,
+                groups,
+                duration,
+                auto,
+                count,
+                &mut |done, total, name| {
+                    spinner.progress(done, total, &format!("finding loop points for {name}"))
+                },
+            )
+        }
+        Commands::SetLoopRegion {
+            ids,
+            groups,
+            start,
+            end,
+        } => set_loop_region(state, ids, groups, start.zip(end)),
         Commands::Unloop { ids, groups } => unloop(state, ids, groups),
         Commands::SetStart {
             ids,
@@ -360,23 +2657,294 @@ fn respond(state: &mut AppState, line: &str, has_been_saved: bool) -> Result<Res
             groups,
             duration,
         } => delay(state, ids, groups, duration),
+        Commands::Cooldown {
+            ids,
+            groups,
+            duration,
+            queue,
+        } => set_cooldown(state, ids, groups, duration, queue),
+        Commands::Fades {
+            ids,
+            groups,
+            fade_in,
+            fade_out,
+            soft_start,
+            bus,
+            inherit,
+        } => {
+            let settings = Settings {
+                fade_in,
+                fade_out,
+                soft_start,
+                bus,
+            };
+            set_fades(state, ids, groups, settings, inherit)
+        }
+        Commands::EditMode { ids, groups } => edit_mode(state, ids, groups),
         Commands::Group {
             group: group_name,
             ids,
         } => group(state, group_name, ids),
         Commands::Ungroup { group, ids } => ungroup(state, group, ids),
-        Commands::Save { path } => save(state, &path),
-        Commands::Load { path } => load(state, &path, has_been_saved),
+        Commands::Set { action } => match action {
+            Some(SetCommands::DefaultGroup { group }) => set_default_group(state, group),
+            Some(SetCommands::Variable(words)) => set_variable(state, words),
+            None => list_variables(state),
+        },
+        Commands::Unset { name } => unset_variable(state, name),
+        Commands::Variant {
+            group,
+            suffix,
+            volume_scale,
+        } => variant(state, group, suffix, volume_scale),
+        Commands::Playlist { group, cooldown } => playlist(state, group, cooldown),
+        Commands::Unplaylist { group } => unplaylist(state, group),
+        Commands::Duck {
+            when,
+            target,
+            amount,
+            release,
+        } => match (when, target, amount) {
+            (Some(when), Some(target), Some(amount)) => duck(state, when, target, amount, release),
+            _ => show_ducks(state),
+        },
+        Commands::Unduck { target, when } => unduck(state, target, when),
+        Commands::Weight { ids, weight } => set_weight(state, ids, weight),
+        Commands::QuietHours { mode } => quiet_hours(state, mode),
+        Commands::MasterLimit { mode, threshold } => master_limit(state, mode, threshold),
+        Commands::Link { link: name, ids } => link(state, name, ids),
+        Commands::Unlink { ids } => unlink(state, ids),
+        Commands::LoopGroup {
+            group,
+            leader,
+            duration,
+            lock,
+        } => loop_group(state, group, leader, duration, lock),
+        Commands::UnloopGroup { group } => unloop_group(state, group),
+        Commands::GroupDefaults {
+            group,
+            fade_in,
+            fade_out,
+            soft_start,
+            bus,
+            clear,
+        } => {
+            let settings = Settings {
+                fade_in,
+                fade_out,
+                soft_start,
+                bus,
+            };
+            group_defaults(state, group, settings, clear)
+        }
+        Commands::Suspend => suspend(state),
+        Commands::Resume => resume(state),
+        Commands::Suggest { count } => suggest(state, count),
+        Commands::Waveform { ids, groups, width } => {
+            let spinner = Spinner::start("reading");
+            show_waveform(
+                state,
+                ids,
+                groups,
+                width as usize,
+                &mut |done, total, name| spinner.progress(done, total, &format!("reading {name}")),
+            )
+        }
+        Commands::MonoCheck { ids, groups } => {
+            let spinner = Spinner::start("analysing");
+            mono_check(state, ids, groups, &mut |done, total, name| {
+                spinner.progress(done, total, &format!("analysing {name}"))
+            })
+        }
+        command @ (Commands::Meter { .. } | Commands::Board { .. } | Commands::Preflight) => {
+            respond_shared(&mut Shared::Borrowed(state), command)
+        }
+        Commands::Tui => open_dashboard(),
+        Commands::Monitor => monitor(state),
+        Commands::Dump => dump(state),
+        Commands::Pending => show_pending(state),
+        Commands::Version => version(state),
+        Commands::Cancel { ids } => cancel(state, ids),
+        Commands::After { duration, command } => schedule_after(state, duration, command),
+        Commands::At { time, command } => schedule_at(state, time, command),
+        Commands::Schedule { action } => match action {
+            ScheduleCommands::List => schedule_list(state),
+            ScheduleCommands::Cancel { ids } => schedule_cancel(state, ids),
+        },
+        Commands::Foreach {
+            regex,
+            yes,
+            dry_run,
+            command,
+        } => foreach(state, regex, command, yes, dry_run, has_been_saved),
+        Commands::Cue { action } => match action {
+            CueCommands::Add { commands } => cue_add(state, commands),
+            CueCommands::List => cue_list(state),
+            CueCommands::Go => cue_go(state, has_been_saved),
+            CueCommands::Reset => cue_reset(state),
+        },
+        Commands::Diagnostics => diagnostics(state),
+        Commands::Pairing { action } => match action {
+            PairingCommands::List => pairing_list(state),
+            PairingCommands::Forget { name } => pairing_forget(state, name),
+        },
+        Commands::Alias { name, commands } => alias(state, name, commands),
+        Commands::Unalias { name } => unalias(state, name),
+        Commands::Hotkey { action } => match action {
+            HotkeyCommands::Bind { keys, command } => hotkey_bind(state, keys, command),
+            HotkeyCommands::Unbind { keys } => hotkey_unbind(state, keys),
+            HotkeyCommands::List => hotkey_list(state),
+        },
+        Commands::Midi { action } => match action {
+            MidiCommands::Ports => midi_ports(state),
+            MidiCommands::Connect { port } => midi_connect(state, port),
+            MidiCommands::Disconnect => midi_disconnect(state),
+            MidiCommands::Map {
+                kind,
+                number,
+                command,
+                channel,
+            } => midi_map(state, kind, number, channel, command),
+            MidiCommands::Unmap {
+                kind,
+                number,
+                channel,
+            } => midi_unmap(state, kind, number, channel),
+            MidiCommands::List => midi_list(state),
+        },
+        Commands::Discord { action } => match action {
+            DiscordCommands::Join { guild, channel } => discord_join(state, guild, channel),
+            DiscordCommands::Leave => discord_leave(state),
+        },
+        Commands::Output { action } => match action {
+            OutputCommands::Devices => output_devices(state),
+            OutputCommands::Use { device } => output_use(state, device),
+        },
+        Commands::Cache { action } => match action {
+            CacheCommands::Build => {
+                let spinner = Spinner::start("decoding");
+                cache_build(state, &mut |done, total, name| {
+                    spinner.progress(done, total, &format!("decoding {name}"))
+                })
+            }
+            CacheCommands::Clear => cache_clear(),
+        },
+        Commands::Library { action } => match action {
+            LibraryCommands::Roots => library_roots(state),
+            action @ (LibraryCommands::AddRoot { .. } | LibraryCommands::Scan) => {
+                respond_shared(&mut Shared::Borrowed(state), Commands::Library { action })
+            }
+            LibraryCommands::RemoveRoot { folder } => library_remove_root(state, folder),
+            LibraryCommands::Search { query } => library_search(state, query),
+            LibraryCommands::Add { result, name } => {
+                let spinner = Spinner::start("adding");
+                library_add(state, result, name, &mut |_, _, name| {
+                    spinner.update(format!("adding {name}"))
+                })
+            }
+        },
+        Commands::Seek {
+            ids,
+            groups,
+            to,
+            marker,
+        } => {
+            // clap makes sure one of them is there
+            let to = to.map(Point::At).or(marker.map(Point::Marker)).unwrap();
+            seek(state, ids, groups, to)
+        }
+        Commands::Mark { action } => match action {
+            MarkCommands::Add { id, name, pos } => mark_add(state, id, name, pos),
+            MarkCommands::Remove { id, name } => mark_remove(state, id, name),
+            MarkCommands::List { ids } => mark_list(state, ids),
+        },
+        Commands::Tag { action } => match action {
+            TagCommands::Add { id, tags } => tag_add(state, id, tags),
+            TagCommands::Remove { id, tags } => tag_remove(state, id, tags),
+            TagCommands::List => tag_list(state),
+        },
+        Commands::Scene { action } => match action {
+            SceneCommands::Capture { name } => scene_capture(state, name),
+            SceneCommands::Save { name } => scene_save(state, name),
+            SceneCommands::Switch { name, fade } => scene_switch(state, name, fade),
+            SceneCommands::Volume { name, ids, volume } => scene_volume(state, name, ids, volume),
+            SceneCommands::List => scene_list(state),
+        },
+        Commands::ApplyManifest { path } => {
+            let spinner = Spinner::start("applying manifest");
+            apply_manifest(state, &path, &mut |done, total, name| {
+                spinner.progress(done, total, &format!("applying manifest to {name}"))
+            })
+        }
+        Commands::Log { text, export } => log(state, text.join(" "), export),
+        Commands::ExportScript { path } => export_script(state, &path),
+        Commands::Render { duration, path } => {
+            let _spinner = Spinner::start("rendering");
+            render(state, duration, &path)
+        }
+        Commands::Save { path, project } => save(state, &path, project),
+        Commands::Load {
+            path,
+            resume_playback,
+        } => {
+            let spinner = Spinner::start("loading");
+            load(
+                state,
+                &path,
+                has_been_saved,
+                resume_playback,
+                &mut |done, total, name| spinner.progress(done, total, &format!("loading {name}")),
+            )
+        }
+        Commands::Switch { path, fade } => {
+            let spinner = Spinner::start("loading");
+            switch(
+                state,
+                &path,
+                fade,
+                has_been_saved,
+                &mut |done, total, name| spinner.progress(done, total, &format!("loading {name}")),
+            )
+        }
+        Commands::Import { path, out } => import_soundscape(&path, &out),
+        Commands::Remap { action } => match action {
+            RemapCommands::Add { from, to } => remap_add(state, from, to),
+            RemapCommands::List => remap_list(state),
+        },
+        Commands::Config { action } => match action {
+            ConfigCommands::Get { key } => config_get(state, key),
+            ConfigCommands::Set { key, value } => config_set(state, key, value),
+        },
         Commands::Exit => exit(),
     }
 }
 
 pub fn readline(prompt: &str) -> Result<String, Error> {
+    if !INTERACTIVE.get() {
+        return Err(Error::msg(
+            "error: this command needs an answer, but it was not run from the prompt.",
+        ));
+    }
     READLINE.with_borrow_mut(|rl| {
+        progress::suspend();
+        // the answer goes to the prompt, not to the queue
+        typeahead::stop();
+        let started = Instant::now();
         let line = rl.readline(prompt);
+        INPUT_WAIT.set(INPUT_WAIT.get() + started.elapsed());
+        if progress::is_spinning() {
+            typeahead::listen();
+        }
+        progress::resume();
         match line {
             Ok(line) => {
                 rl.add_history_entry(line.as_str()).unwrap_or_default();
+                // losing the history is not worth bothering anyone about
+                if let Some(path) = config::history_path() {
+                    let _ = fs::create_dir_all(path.parent().unwrap())
+                        .map_err(ReadlineError::from)
+                        .and_then(|_| rl.save_history(&path));
+                }
                 Ok(line)
             }
             Err(ReadlineError::Eof) => Err(Error::msg("error: unexpected EOF.")),
@@ -387,6 +2955,15 @@ pub fn readline(prompt: &str) -> Result<String, Error> {
     })
 }
 
+// asks before something that can't be undone. These are the only questions that the confirm
+// setting answers with yes
+fn confirm_destructive(prompt: &str) -> Result<bool, Error> {
+    if !config::confirms() {
+        return Ok(true);
+    }
+    get_confirmation(prompt)
+}
+
 fn get_confirmation(prompt: &str) -> Result<bool, Error> {
     let mut result = None;
 
@@ -0,0 +1,134 @@
+use anyhow::Error;
+use serde::Deserialize;
+use std::{io::Read, path::PathBuf, time::Duration};
+
+// one row of a manifest, as it is written in the spreadsheet. Empty cells leave a setting as it is
+#[derive(Deserialize)]
+struct Row {
+    name: String,
+    path: Option<PathBuf>,
+    volume: Option<u32>,
+    #[serde(rename = "loop")]
+    looping: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+    delay: Option<String>,
+    group: Option<String>,
+    tags: Option<String>,
+}
+
+pub struct ManifestEntry {
+    pub name: String,
+    // players that don't exist yet are added from here
+    pub path: Option<PathBuf>,
+    pub volume: Option<u32>,
+    // whether to loop, and the loop length
+    pub looping: Option<(bool, Option<Duration>)>,
+    pub start: Option<Duration>,
+    // "none" resets the end, so that the sound plays to its end again
+    pub end: Option<Option<Duration>>,
+    pub delay: Option<Duration>,
+    pub group: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    duration_str::parse(text).map_err(|_| format!("{text} is not a valid duration"))
+}
+
+fn parse_loop(text: &str) -> Result<(bool, Option<Duration>), String> {
+    match text.to_lowercase().as_str() {
+        "yes" | "true" | "on" => Ok((true, None)),
+        "no" | "false" | "off" => Ok((false, None)),
+        _ => Ok((true, Some(parse_duration(text)?))),
+    }
+}
+
+impl ManifestEntry {
+    fn parse(row: Row) -> Result<Self, String> {
+        Ok(Self {
+            looping: row.looping.as_deref().map(parse_loop).transpose()?,
+            start: row.start.as_deref().map(parse_duration).transpose()?,
+            end: row
+                .end
+                .as_deref()
+                .map(|end| match end.to_lowercase().as_str() {
+                    "none" => Ok(None),
+                    _ => parse_duration(end).map(Some),
+                })
+                .transpose()?,
+            delay: row.delay.as_deref().map(parse_duration).transpose()?,
+            tags: row.tags.map(|tags| {
+                tags.split([';', ','])
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            }),
+            name: row.name,
+            path: row.path,
+            volume: row.volume,
+            group: row.group,
+        })
+    }
+}
+
+// the first row holds the column names. Only the name column is required, and the columns can be
+// in any order. All rows are checked before anything is applied, so a typo doesn't leave the
+// soundscape half changed
+pub fn parse(reader: impl Read) -> Result<Vec<ManifestEntry>, Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let mut entries = Vec::new();
+    for row in reader.deserialize::<Row>() {
+        let row = row.map_err(|err| {
+            let line = err.position().map(|pos| pos.line()).unwrap_or_default();
+            Error::msg(format!(
+                "error: could not read line {line} of the manifest. {err}"
+            ))
+        })?;
+        let name = row.name.clone();
+        if name.is_empty() || name.to_lowercase() == "all" {
+            return Err(Error::msg(format!(
+                "error: '{name}' is not a valid name for a player in the manifest."
+            )));
+        }
+        entries.push(
+            ManifestEntry::parse(row)
+                .map_err(|err| Error::msg(format!("error: in the row for {name}: {err}.")))?,
+        );
+    }
+    Ok(entries)
+}
+
+#[test]
+fn parse_manifest() {
+    let csv = "\
+name, path, volume, loop, start, end, group, tags
+rain, sounds/rain.ogg, 40, yes, , , weather, calm; outside
+horn, , , 30s, 1s, none, ,
+";
+    let entries = parse(csv.as_bytes()).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].path, Some(PathBuf::from("sounds/rain.ogg")));
+    assert_eq!(entries[0].volume, Some(40));
+    assert_eq!(entries[0].looping, Some((true, None)));
+    assert_eq!(entries[0].start, None);
+    assert_eq!(entries[0].group.as_deref(), Some("weather"));
+    assert_eq!(
+        entries[0].tags,
+        Some(vec!["calm".to_string(), "outside".to_string()])
+    );
+    assert_eq!(entries[1].path, None);
+    assert_eq!(
+        entries[1].looping,
+        Some((true, Some(Duration::from_secs(30))))
+    );
+    assert_eq!(entries[1].start, Some(Duration::from_secs(1)));
+    assert_eq!(entries[1].end, Some(None));
+    assert_eq!(entries[1].group, None);
+
+    assert!(parse("name, volume\nrain, loud\n".as_bytes()).is_err());
+    assert!(parse("name, loop\nrain, sometimes\n".as_bytes()).is_err());
+}
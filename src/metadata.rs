@@ -0,0 +1,344 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+
+// the tags that a sound file can have, like the ID3 tags of an mp3 or the comments of an ogg
+#[derive(Default, Debug, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+impl Metadata {
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        let field = match key.to_uppercase().as_str() {
+            "TITLE" | "INAM" => &mut self.title,
+            "ARTIST" | "IART" => &mut self.artist,
+            "ALBUM" | "IPRD" => &mut self.album,
+            _ => return,
+        };
+        field.get_or_insert_with(|| value.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Display for Metadata {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "\n\tno tags");
+        }
+        for (name, value) in [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("album", &self.album),
+        ] {
+            if let Some(value) = value {
+                write!(f, "\n\t{name}: {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// reads the tags of a sound. Each format keeps them in its own way, so they are read by the crate
+// that decodes it. A file that can't be read just has no tags
+pub fn read(path: &Path) -> Metadata {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let mut metadata = Metadata::default();
+    match extension.as_str() {
+        "mp3" => read_id3(path, &mut metadata),
+        "ogg" => read_vorbis_comments(path, &mut metadata),
+        "flac" => read_flac(path, &mut metadata),
+        "wav" => read_riff_info(path, &mut metadata),
+        _ => None,
+    };
+    metadata
+}
+
+fn read_id3(path: &Path, metadata: &mut Metadata) -> Option<()> {
+    let stream = MediaSourceStream::new(Box::new(File::open(path).ok()?), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("mp3");
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    // ID3v2 tags come before the audio, so they are found by the probe. Others are in the stream
+    let mut read_revision = |revision: &MetadataRevision| {
+        for tag in revision.tags() {
+            let key = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => "TITLE",
+                Some(StandardTagKey::Artist) => "ARTIST",
+                Some(StandardTagKey::Album) => "ALBUM",
+                _ => continue,
+            };
+            metadata.set(key, &tag.value.to_string());
+        }
+    };
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|log| log.current()) {
+        read_revision(revision);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        read_revision(revision);
+    }
+    Some(())
+}
+
+// the comments are the second packet of the stream, after the identification header, so the rest
+// of the stream doesn't have to be read
+fn read_vorbis_comments(path: &Path, metadata: &mut Metadata) -> Option<()> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let packet = read_ogg_packets(&mut file, 2)?.pop()?;
+    let comments = lewton::header::read_header_comment(&packet).ok()?;
+    for (key, value) in &comments.comment_list {
+        metadata.set(key, value);
+    }
+    Some(())
+}
+
+// the first packets of an ogg stream. The packets are cut into segments of at most 255 bytes, and a
+// shorter segment ends a packet
+fn read_ogg_packets(file: &mut impl Read, count: usize) -> Option<Vec<Vec<u8>>> {
+    let (mut packets, mut packet) = (Vec::new(), Vec::new());
+    while packets.len() < count {
+        let mut header = [0; 27];
+        file.read_exact(&mut header).ok()?;
+        if &header[0..4] != b"OggS" {
+            return None;
+        }
+        let mut segments = vec![0; header[26] as usize];
+        file.read_exact(&mut segments).ok()?;
+        for length in segments {
+            let start = packet.len();
+            packet.resize(start + length as usize, 0);
+            file.read_exact(&mut packet[start..]).ok()?;
+            if length < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+    }
+    packets.truncate(count);
+    Some(packets)
+}
+
+fn read_flac(path: &Path, metadata: &mut Metadata) -> Option<()> {
+    let reader = claxon::FlacReader::open(path).ok()?;
+    for (key, value) in reader.tags() {
+        metadata.set(key, value);
+    }
+    Some(())
+}
+
+// wav files keep their tags in a LIST chunk of the INFO kind, whose chunks are named like INAM for
+// the title
+fn read_riff_info(path: &Path, metadata: &mut Metadata) -> Option<()> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let mut header = [0; 12];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+    loop {
+        let (id, size) = read_chunk_header(&mut file)?;
+        // chunks are padded to an even size
+        let padded = size + size % 2;
+        if &id != b"LIST" {
+            file.seek(SeekFrom::Current(padded as i64)).ok()?;
+            continue;
+        }
+        let mut list = vec![0; padded as usize];
+        file.read_exact(&mut list).ok()?;
+        if list.get(0..4) == Some(b"INFO") {
+            read_info_list(&list[4..], metadata);
+            return Some(());
+        }
+    }
+}
+
+fn read_chunk_header(file: &mut impl Read) -> Option<([u8; 4], u32)> {
+    let mut header = [0; 8];
+    file.read_exact(&mut header).ok()?;
+    let id = header[0..4].try_into().ok()?;
+    let size = u32::from_le_bytes(header[4..8].try_into().ok()?);
+    Some((id, size))
+}
+
+fn read_info_list(mut list: &[u8], metadata: &mut Metadata) {
+    while let Some((id, size)) = read_chunk_header(&mut list) {
+        let size = size as usize;
+        let Some(value) = list.get(..size) else {
+            return;
+        };
+        let value = String::from_utf8_lossy(value);
+        metadata.set(&String::from_utf8_lossy(&id), value.trim_end_matches('\0'));
+        list = list.get(size + size % 2..).unwrap_or_default();
+    }
+}
+
+// the comments of an ogg or a flac, which are the same thing in both
+#[cfg(test)]
+fn comment_list(comments: &[&[u8]]) -> Vec<u8> {
+    let mut list = Vec::new();
+    let mut add = |text: &[u8]| {
+        list.extend((text.len() as u32).to_le_bytes());
+        list.extend(text);
+    };
+    add(b"troubadour");
+    list.extend((comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        list.extend((comment.len() as u32).to_le_bytes());
+        list.extend(*comment);
+    }
+    list
+}
+
+#[cfg(test)]
+fn check_tags(path: &Path, file: &[u8]) {
+    std::fs::write(path, file).unwrap();
+    assert_eq!(
+        read(path),
+        Metadata {
+            title: Some("Heavy Rain".to_string()),
+            artist: Some("Tavern Sounds".to_string()),
+            album: None,
+        }
+    );
+}
+
+#[test]
+fn mp3_tags_are_read() {
+    let frame = |id: &[u8], text: &[u8]| {
+        let mut frame = id.to_vec();
+        frame.extend((text.len() as u32 + 1).to_be_bytes());
+        frame.extend([0, 0, 0]);
+        frame.extend(text);
+        frame
+    };
+    let frames = [
+        frame(b"TIT2", b"Heavy Rain"),
+        frame(b"TPE1", b"Tavern Sounds"),
+    ]
+    .concat();
+    // ID3v2.3, whose size is written in 7 bits a byte
+    let size = frames.len() as u32;
+    let mut file = b"ID3\x03\0\0".to_vec();
+    file.extend([size >> 21, size >> 14, size >> 7, size].map(|part| (part & 0x7f) as u8));
+    file.extend(frames);
+    // silent frames of MPEG 1 layer 3 at 128 kbps and 44.1 kHz, which are 417 bytes
+    for _ in 0..4 {
+        file.extend([0xff, 0xfb, 0x90, 0x00]);
+        file.extend([0; 413]);
+    }
+    let dir = crate::testing::TempDir::new("mp3 tags");
+    check_tags(&dir.join("rain.mp3"), &file);
+}
+
+#[test]
+fn ogg_tags_are_read() {
+    let page = |sequence: u32, packet: &[u8]| {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend([0; 8]);
+        page.extend([0; 4]);
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        let segments = packet.len() / 255 + 1;
+        page.push(segments as u8);
+        page.extend(std::iter::repeat_n(255, segments - 1));
+        page.push((packet.len() % 255) as u8);
+        page.extend(packet);
+        page
+    };
+    // a title that is long enough to go over more than one segment
+    let title = [b"TITLE=Heavy Rain".as_slice(), &[b' '; 300]].concat();
+    let comments = [
+        b"\x03vorbis".as_slice(),
+        &comment_list(&[&title, b"artist=Tavern Sounds"]),
+        &[1],
+    ]
+    .concat();
+    let file = [page(0, b"\x01vorbis"), page(1, &comments)].concat();
+    let dir = crate::testing::TempDir::new("ogg tags");
+    check_tags(&dir.join("rain.ogg"), &file);
+}
+
+#[test]
+fn flac_tags_are_read() {
+    let block = |kind: u8, data: &[u8]| {
+        let mut block = vec![kind];
+        block.extend(&(data.len() as u32).to_be_bytes()[1..]);
+        block.extend(data);
+        block
+    };
+    // blocks of 4096 samples, at 44.1 kHz in stereo and 16 bits
+    let mut info = vec![0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0];
+    info.extend([0x0a, 0xc4, 0x42, 0xf0, 0, 0, 0, 0]);
+    info.extend([0; 16]);
+    let comments = comment_list(&[b"TITLE=Heavy Rain", b"Artist=Tavern Sounds"]);
+    let file = [b"fLaC".to_vec(), block(0, &info), block(0x84, &comments)].concat();
+    let dir = crate::testing::TempDir::new("flac tags");
+    check_tags(&dir.join("rain.flac"), &file);
+}
+
+#[test]
+fn wav_tags_are_read() {
+    let chunk = |id: &[u8], data: &[u8]| {
+        let mut chunk = id.to_vec();
+        chunk.extend((data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    };
+    let info = [
+        b"INFO".to_vec(),
+        chunk(b"INAM", b"Heavy Rain\0"),
+        chunk(b"IART", b"Tavern Sounds\0"),
+    ]
+    .concat();
+    let wave = [
+        b"WAVE".to_vec(),
+        chunk(b"fmt ", &[0; 16]),
+        chunk(b"data", &[0; 3]),
+        chunk(b"LIST", &info),
+    ]
+    .concat();
+    let dir = crate::testing::TempDir::new("tags");
+    let path = dir.join("rain.wav");
+    std::fs::write(&path, chunk(b"RIFF", &wave)).unwrap();
+    let metadata = read(&path);
+    assert_eq!(
+        metadata,
+        Metadata {
+            title: Some("Heavy Rain".to_string()),
+            artist: Some("Tavern Sounds".to_string()),
+            album: None,
+        }
+    );
+    assert!(read(Path::new("missing.mp3")).is_empty());
+}
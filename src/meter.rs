@@ -0,0 +1,175 @@
+use rodio::Source;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// the levels are averaged over this long, which is about what the eye can follow
+const WINDOW: Duration = Duration::from_millis(50);
+// how fast the peak falls back, in dB per second, like the needle of a meter
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Level {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+impl Level {
+    pub fn scaled(self, gain: f32) -> Self {
+        Self {
+            peak: self.peak * gain,
+            rms: self.rms * gain,
+        }
+    }
+}
+
+// the level of whatever went through the meter last, shared with whoever wants to read it
+#[derive(Clone, Default)]
+pub struct MeterControl(Arc<Mutex<Level>>);
+
+impl MeterControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn level(&self) -> Level {
+        *self.0.lock().unwrap()
+    }
+
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = Level::default();
+    }
+}
+
+// passes the sound through untouched, and measures it on the way
+pub struct Meter<S> {
+    input: S,
+    control: MeterControl,
+    samples_per_window: usize,
+    in_window: usize,
+    sum_of_squares: f32,
+    window_peak: f32,
+    held_peak: f32,
+    peak_fall: f32,
+}
+
+impl<S> Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, control: MeterControl) -> Self {
+        let samples_per_second = input.sample_rate() as f32 * input.channels() as f32;
+        let samples_per_window = ((samples_per_second * WINDOW.as_secs_f32()) as usize).max(1);
+        Self {
+            input,
+            control,
+            samples_per_window,
+            in_window: 0,
+            sum_of_squares: 0.0,
+            window_peak: 0.0,
+            held_peak: 0.0,
+            peak_fall: 10f32.powf(-PEAK_FALL_DB_PER_SECOND * WINDOW.as_secs_f32() / 20.0),
+        }
+    }
+
+    fn publish(&mut self) {
+        self.held_peak = self.window_peak.max(self.held_peak * self.peak_fall);
+        *self.control.0.lock().unwrap() = Level {
+            peak: self.held_peak,
+            rms: (self.sum_of_squares / self.in_window as f32).sqrt(),
+        };
+        self.in_window = 0;
+        self.sum_of_squares = 0.0;
+        self.window_peak = 0.0;
+    }
+}
+
+impl<S> Iterator for Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let Some(sample) = self.input.next() else {
+            // a sound that ended shouldn't keep showing its last level
+            self.control.reset();
+            return None;
+        };
+        self.sum_of_squares += sample * sample;
+        self.window_peak = self.window_peak.max(sample.abs());
+        self.in_window += 1;
+        if self.in_window == self.samples_per_window {
+            self.publish();
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+// from -60 dBFS up to 0 dBFS, after which the bar is full
+pub fn render_bar(level: f32, width: usize) -> String {
+    const FLOOR_DB: f32 = -60.0;
+    let db = if level > 0.0 {
+        20.0 * level.log10()
+    } else {
+        FLOOR_DB
+    };
+    let filled = (((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0) * width as f32).round() as usize;
+    format!("{}{}", "#".repeat(filled), "-".repeat(width - filled))
+}
+
+#[test]
+fn meter_measures_windows() {
+    // 50ms of a square wave at half scale, then a single loud sample in the next window
+    let mut samples: Vec<f32> = (0..2205)
+        .map(|i| if i % 2 == 0 { 0.5 } else { -0.5 })
+        .collect();
+    samples.extend([0.0; 2205]);
+    samples[3000] = 1.0;
+    let control = MeterControl::new();
+    let mut meter = Meter::new(
+        rodio::buffer::SamplesBuffer::new(1, 44_100, samples.clone()),
+        control.clone(),
+    );
+    let passed: Vec<f32> = meter.by_ref().take(2205).collect();
+    assert_eq!(passed, samples[..2205]);
+    assert_eq!(control.level().peak, 0.5);
+    assert!((control.level().rms - 0.5).abs() < 1e-6);
+
+    meter.by_ref().take(2205).for_each(drop);
+    assert_eq!(control.level().peak, 1.0);
+    assert!(control.level().rms < 0.1);
+
+    meter.for_each(drop);
+    assert_eq!(control.level(), Level::default());
+
+    assert_eq!(render_bar(1.0, 4), "####");
+    assert_eq!(render_bar(0.01, 4), "#---");
+    assert_eq!(render_bar(0.0, 4), "----");
+}
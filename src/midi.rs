@@ -0,0 +1,250 @@
+use anyhow::Error;
+use clap::ValueEnum;
+use midir::{MidiInput, MidiInputConnection};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::server::Session;
+use crate::{AppState, INTERACTIVE};
+
+const CLIENT_NAME: &str = "troubadour";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControlKind {
+    Note,
+    Cc,
+}
+
+// a pad or a knob on the controller, and the command it runs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub kind: ControlKind,
+    pub number: u8,
+    // 1 to 16. None listens on every channel
+    pub channel: Option<u8>,
+    pub command: String,
+}
+
+// a message from the controller, as far as bindings are concerned. Channels count from 1
+#[derive(Debug, PartialEq)]
+pub struct Input {
+    pub kind: ControlKind,
+    pub channel: u8,
+    pub number: u8,
+    pub value: u8,
+}
+
+// only pressing a pad counts. Releasing it, or a note on with no velocity, which a lot of
+// controllers send instead of a note off, does nothing
+pub fn parse(message: &[u8]) -> Option<Input> {
+    let (&status, data) = message.split_first()?;
+    let channel = (status & 0x0f) + 1;
+    let (kind, number, value) = match (status & 0xf0, data) {
+        (0x90, [note, velocity, ..]) if *velocity > 0 => (ControlKind::Note, *note, *velocity),
+        (0xb0, [controller, value, ..]) => (ControlKind::Cc, *controller, *value),
+        _ => return None,
+    };
+    Some(Input {
+        kind,
+        channel,
+        number,
+        value,
+    })
+}
+
+impl Binding {
+    pub fn is_for(&self, kind: ControlKind, number: u8, channel: Option<u8>) -> bool {
+        self.kind == kind && self.number == number && self.channel == channel
+    }
+
+    pub fn matches(&self, input: &Input) -> bool {
+        self.kind == input.kind
+            && self.number == input.number
+            && self.channel.is_none_or(|channel| channel == input.channel)
+    }
+
+    // {value} in the command becomes the position of the knob or how hard the pad was hit, from 0
+    // to 100, so a fader can drive a volume
+    pub fn command_for(&self, input: &Input) -> String {
+        let value = (input.value as u32 * 100 + 63) / 127;
+        self.command.replace("{value}", &value.to_string())
+    }
+
+    // the control the way midi map takes it, like note 36
+    pub fn control(&self) -> String {
+        let kind = match self.kind {
+            ControlKind::Note => "note",
+            ControlKind::Cc => "cc",
+        };
+        format!("{kind} {}", self.number)
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.control())?;
+        if let Some(channel) = self.channel {
+            write!(f, " on channel {channel}")?;
+        }
+        Ok(())
+    }
+}
+
+fn midi_input() -> Result<MidiInput, Error> {
+    MidiInput::new(CLIENT_NAME)
+        .map_err(|err| Error::msg(format!("error: could not get at the MIDI devices. {err}")))
+}
+
+pub fn ports() -> Result<Vec<String>, Error> {
+    let input = midi_input()?;
+    Ok(input
+        .ports()
+        .iter()
+        .map(|port| input.port_name(port).unwrap_or_default())
+        .collect())
+}
+
+// the controller that is connected, and the bindings. Messages from the controller are handed to
+// the thread from spawn, which runs the commands
+pub struct Midi {
+    pub bindings: Vec<Binding>,
+    connection: Option<(String, MidiInputConnection<()>)>,
+    messages: Sender<Vec<u8>>,
+}
+
+impl Midi {
+    pub fn new(messages: Sender<Vec<u8>>) -> Self {
+        Self {
+            bindings: Vec::new(),
+            connection: None,
+            messages,
+        }
+    }
+
+    pub fn connected(&self) -> Option<&str> {
+        self.connection.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    // the port can be given by its number in the list, or by a part of its name. Without one, the
+    // first port is used
+    pub fn connect(&mut self, port: Option<&str>) -> Result<String, Error> {
+        let input = midi_input()?;
+        let ports = input.ports();
+        let names: Vec<String> = ports
+            .iter()
+            .map(|port| input.port_name(port).unwrap_or_default())
+            .collect();
+        let index = match port {
+            None => (!ports.is_empty()).then_some(0),
+            Some(port) => match port.parse::<usize>() {
+                Ok(number) => (1..=ports.len()).contains(&number).then(|| number - 1),
+                Err(_) => names
+                    .iter()
+                    .position(|name| name.to_lowercase().contains(&port.to_lowercase())),
+            },
+        };
+        let Some(index) = index else {
+            return Err(Error::msg(match port {
+                Some(port) => format!("error: no MIDI device found that matches {port}"),
+                None => "error: no MIDI devices found".to_string(),
+            }));
+        };
+        let messages = self.messages.clone();
+        let connection = input
+            .connect(
+                &ports[index],
+                CLIENT_NAME,
+                move |_, message, _| {
+                    let _ = messages.send(message.to_vec());
+                },
+                (),
+            )
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not connect to {}. {err}",
+                    names[index]
+                ))
+            })?;
+        self.connection = Some((names[index].clone(), connection));
+        Ok(names[index].clone())
+    }
+
+    pub fn disconnect(&mut self) -> Option<String> {
+        let (name, connection) = self.connection.take()?;
+        connection.close();
+        Some(name)
+    }
+}
+
+// runs the commands that are bound to whatever comes in from the controller
+pub fn spawn(
+    state: Arc<Mutex<AppState>>,
+    messages: Receiver<Vec<u8>>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        // there is nobody to answer prompts on this thread
+        INTERACTIVE.set(false);
+        let session = Session::new(state.clone(), saved, background_mutated);
+        for message in messages {
+            let Some(input) = parse(&message) else {
+                continue;
+            };
+            let commands: Vec<String> = state
+                .lock()
+                .unwrap()
+                .midi
+                .bindings
+                .iter()
+                .filter(|binding| binding.matches(&input))
+                .map(|binding| binding.command_for(&input))
+                .collect();
+            for command in commands {
+                session.run_quietly(&command);
+            }
+        }
+    });
+}
+
+#[test]
+fn controller_messages_run_bindings() {
+    let pad = Binding {
+        kind: ControlKind::Note,
+        number: 36,
+        channel: Some(10),
+        command: "play thunder".to_string(),
+    };
+    let fader = Binding {
+        kind: ControlKind::Cc,
+        number: 7,
+        channel: None,
+        command: "volume rain -v {value}".to_string(),
+    };
+
+    let hit = parse(&[0x99, 36, 100]).unwrap();
+    assert_eq!(hit.channel, 10);
+    assert!(pad.matches(&hit));
+    assert!(!pad.matches(&parse(&[0x90, 36, 100]).unwrap()));
+    // a note on without velocity is a release
+    assert_eq!(parse(&[0x99, 36, 0]), None);
+    assert_eq!(parse(&[0x89, 36, 64]), None);
+
+    let moved = parse(&[0xb3, 7, 127]).unwrap();
+    assert!(fader.matches(&moved));
+    assert_eq!(fader.command_for(&moved), "volume rain -v 100");
+    assert_eq!(
+        fader.command_for(&parse(&[0xb0, 7, 64]).unwrap()),
+        "volume rain -v 50"
+    );
+    assert_eq!(pad.to_string(), "note 36 on channel 10");
+}
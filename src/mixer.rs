@@ -0,0 +1,503 @@
+use anyhow::Error;
+use rodio::{
+    cpal::traits::{DeviceTrait, HostTrait},
+    dynamic_mixer::{self, DynamicMixer, DynamicMixerController},
+    source::Zero,
+    OutputStream, Sink, Source,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config;
+use crate::fade::{FadeControl, Faded};
+use crate::limiter::{Limiter, LimiterControl};
+use crate::meter::{Level, Meter, MeterControl};
+use crate::progress;
+
+pub const CHANNELS: u16 = 2;
+pub const SAMPLE_RATE: u32 = 44_100;
+// the master output takes this many samples at once from the master chain
+const CHUNK_SIZE: usize = 1024;
+// -1 dBFS. Keeps a lot of loud sounds at once from clipping the output
+pub const MASTER_LIMIT_THRESHOLD: f32 = 0.891;
+// -12 dBFS. Quiet enough for headphones, even with a lot of sounds stacked on top of each other
+const HEADPHONE_PROTECT_THRESHOLD: f32 = 0.25;
+// how many chunks a tap can fall behind before it misses some. About a second and a half
+const TAP_BACKLOG: usize = 64;
+// where a safe start ramps up from. -20 dB
+const SAFE_START_LEVEL: f32 = 0.1;
+
+type MasterChain = Meter<Limiter<Limiter<Limiter<Faded<DynamicMixer<f32>>>>>>;
+type Taps = Arc<Mutex<Vec<mpsc::SyncSender<Vec<f32>>>>>;
+// the device lives on its own thread, which shuts down when the sender is dropped
+type OpenDevice = (mpsc::Sender<()>, thread::JoinHandle<()>);
+
+// every player plays into the mixer, and the result goes through the master chain before it is
+// sent to the audio device
+pub struct Mixer {
+    controller: Arc<DynamicMixerController<f32>>,
+    chain: Arc<Mutex<MasterChain>>,
+    // get a copy of everything that is sent to the audio device
+    taps: Taps,
+    pub headphone_protect: LimiterControl,
+    // catches the peaks when a lot of sounds play at once
+    pub master_limit: LimiterControl,
+    // what is sent to the audio device
+    master_meter: MeterControl,
+    master_fade: FadeControl,
+    // caps the master output during quiet hours. Whatever is quieter than the cap is left alone
+    quiet_hours: LimiterControl,
+    // how long a safe start takes, if safe starts are turned on
+    safe_start: Mutex<Option<Duration>>,
+    // the master output is held at a low level until the next sound starts
+    safe_start_armed: AtomicBool,
+    device: Mutex<Option<OpenDevice>>,
+    // players in edit mode play into this, and it is never heard. It is only started once it is needed
+    edit_controller: Arc<DynamicMixerController<f32>>,
+    edit_bus: Mutex<Option<DynamicMixer<f32>>>,
+    edit_keep_alive: Mutex<Option<mpsc::Sender<()>>>,
+    device_name: Mutex<Option<String>>,
+    // the device that was chosen with set_output_device, or None for the default one
+    chosen_device: Mutex<Option<String>>,
+}
+
+impl Mixer {
+    pub fn new() -> Self {
+        let (controller, mixer) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+        // without any sounds the mixer would end, and it can't be restarted after that
+        controller.add(Zero::<f32>::new(CHANNELS, SAMPLE_RATE));
+        let (edit_controller, edit_bus) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+        edit_controller.add(Zero::<f32>::new(CHANNELS, SAMPLE_RATE));
+        let headphone_protect = LimiterControl::new(HEADPHONE_PROTECT_THRESHOLD, false);
+        let master_fade = FadeControl::new();
+        let master_limit = LimiterControl::new(MASTER_LIMIT_THRESHOLD, true);
+        let quiet_hours = LimiterControl::new(1.0, false);
+        let master_meter = MeterControl::new();
+        let chain = Meter::new(
+            Limiter::new(
+                Limiter::new(
+                    Limiter::new(Faded::new(mixer, master_fade.clone()), quiet_hours.clone()),
+                    master_limit.clone(),
+                ),
+                headphone_protect.clone(),
+            ),
+            master_meter.clone(),
+        );
+        Self {
+            controller,
+            chain: Arc::new(Mutex::new(chain)),
+            taps: Arc::new(Mutex::new(Vec::new())),
+            headphone_protect,
+            master_limit,
+            master_meter,
+            master_fade,
+            quiet_hours,
+            safe_start: Mutex::new(None),
+            safe_start_armed: AtomicBool::new(false),
+            device: Mutex::new(None),
+            edit_controller,
+            edit_bus: Mutex::new(Some(edit_bus)),
+            edit_keep_alive: Mutex::new(None),
+            device_name: Mutex::new(None),
+            chosen_device: Mutex::new(None),
+        }
+    }
+
+    pub fn current_level(&self) -> Level {
+        self.master_meter.level()
+    }
+
+    pub fn set_safe_start(&self, ramp: Option<Duration>) {
+        *self.safe_start.lock().unwrap() = ramp;
+    }
+
+    // does nothing when safe starts are turned off
+    pub fn arm_safe_start(&self) {
+        if self.safe_start.lock().unwrap().is_none()
+            || self.safe_start_armed.swap(true, Ordering::SeqCst)
+        {
+            return;
+        }
+        self.master_fade
+            .start(SAFE_START_LEVEL, SAFE_START_LEVEL, Duration::ZERO, false);
+        progress::suspend();
+        println!("The audio device is different from last time, so the next sound will start quietly and ramp up.");
+    }
+
+    // called when a sound starts. Ramps the master output up, if a safe start was armed
+    pub fn release_safe_start(&self) {
+        if !self.safe_start_armed.swap(false, Ordering::SeqCst) {
+            return;
+        }
+        let ramp = self.safe_start.lock().unwrap().unwrap_or_default();
+        self.master_fade.start(SAFE_START_LEVEL, 1.0, ramp, false);
+    }
+
+    // the gain the master output is capped at, or None to lift the cap. Returns whether anything changed
+    pub fn set_quiet_hours_cap(&self, cap: Option<f32>) -> bool {
+        let current = self
+            .quiet_hours
+            .is_enabled()
+            .then(|| self.quiet_hours.threshold());
+        if current == cap {
+            return false;
+        }
+        self.quiet_hours.set_threshold(cap.unwrap_or(1.0));
+        self.quiet_hours.set_enabled(cap.is_some());
+        true
+    }
+
+    // the master output, interleaved, in chunks, as it is sent to the audio device. A tap that
+    // can't keep up misses chunks rather than holding up the device, and it is closed by dropping
+    // the receiver
+    pub fn tap(&self) -> mpsc::Receiver<Vec<f32>> {
+        let (sender, receiver) = mpsc::sync_channel(TAP_BACKLOG);
+        self.taps.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn device_name(&self) -> Option<String> {
+        self.device_name.lock().unwrap().clone()
+    }
+
+    // opens the device if it isn't yet, and watches the master output for a moment. Returns the
+    // longest the device left it waiting between two chunks, which is about how far behind the
+    // speakers are. None when the device didn't take anything at all
+    pub fn probe(&self, window: Duration) -> Result<Option<Duration>, Error> {
+        self.ensure_device()?;
+        let tap = self.tap();
+        let started = Instant::now();
+        let mut last: Option<Instant> = None;
+        let mut longest = Duration::ZERO;
+        while let Some(left) = window.checked_sub(started.elapsed()) {
+            if tap.recv_timeout(left).is_err() {
+                break;
+            }
+            let now = Instant::now();
+            if let Some(last) = last {
+                longest = longest.max(now - last);
+            }
+            last = Some(now);
+        }
+        Ok(last.map(|_| longest))
+    }
+
+    // the device is only opened when the first sound is added
+    fn ensure_device(&self) -> Result<(), Error> {
+        let mut device = self.device.lock().unwrap();
+        if device.is_none() {
+            let chosen = self.chosen_device.lock().unwrap().clone();
+            *device = Some(self.open_device(chosen)?);
+        }
+        Ok(())
+    }
+
+    // sends the master output to another device, like a loopback or virtual device (VB-Cable, a
+    // PulseAudio null sink) that OBS or a video call can record from. None goes back to the
+    // default device. The sounds keep playing while the device is swapped out, and when the new
+    // one can't be opened, the one from before is kept. Returns the name of the device, or None
+    // when there is no device and the sounds play silently
+    pub fn set_output_device(&self, device: Option<String>) -> Result<Option<String>, Error> {
+        let mut open = self.device.lock().unwrap();
+        let previous = open.take();
+        let was_open = previous.is_some();
+        if let Some((keep_alive, device_thread)) = previous {
+            drop(keep_alive);
+            device_thread.join().unwrap_or_default();
+        }
+        match self.open_device(device.clone()) {
+            Ok(opened) => {
+                *open = Some(opened);
+                *self.chosen_device.lock().unwrap() = device;
+                Ok(self.device_name())
+            }
+            Err(error) => {
+                if was_open {
+                    let chosen = self.chosen_device.lock().unwrap().clone();
+                    *open = self.open_device(chosen).ok();
+                }
+                Err(error)
+            }
+        }
+    }
+
+    // without a chosen device, the master output is played silently when there is no device at
+    // all, like on a headless server. Everything keeps working as if it could be heard
+    fn open_device(&self, chosen: Option<String>) -> Result<OpenDevice, Error> {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (keep_alive_tx, keep_alive_rx) = mpsc::channel::<()>();
+        let (chain, taps) = (self.chain.clone(), self.taps.clone());
+        let device_thread = thread::spawn(move || {
+            let host = rodio::cpal::default_host();
+            let device = match &chosen {
+                None => host.default_output_device(),
+                Some(chosen) => host.output_devices().ok().and_then(|devices| {
+                    let mut devices: Vec<_> = devices.collect();
+                    let names: Vec<String> = devices
+                        .iter()
+                        .map(|device| device.name().unwrap_or_default())
+                        .collect();
+                    find_device(&names, chosen).map(|index| devices.swap_remove(index))
+                }),
+            };
+            let stream = device.and_then(|device| {
+                let (stream, handle) = OutputStream::try_from_device(&device).ok()?;
+                handle
+                    .play_raw(MasterOutput::new(chain.clone(), taps.clone()))
+                    .ok()?;
+                Some((stream, device.name().unwrap_or_default()))
+            });
+            match (stream, chosen) {
+                (Some((_stream, name)), _) => {
+                    ready_tx.send(Ok(Some(name))).unwrap_or_default();
+                    // returns once the mixer is dropped, or the device is swapped out
+                    keep_alive_rx.recv().unwrap_or_default();
+                }
+                (None, None) => {
+                    ready_tx.send(Ok(None)).unwrap_or_default();
+                    play_silently(MasterOutput::new(chain, taps), keep_alive_rx);
+                }
+                (None, Some(chosen)) => ready_tx
+                    .send(Err(format!(
+                        "error: failed to set up an audio device that matches {chosen}."
+                    )))
+                    .unwrap_or_default(),
+            }
+        });
+
+        let name = ready_rx
+            .recv()
+            .unwrap_or_else(|_| Err("error: failed to set up your audio device.".to_string()))
+            .map_err(Error::msg)?;
+        match &name {
+            Some(name) => {
+                if config::last_device().as_ref() != Some(name) {
+                    self.arm_safe_start();
+                }
+                config::remember_device(name);
+            }
+            None => {
+                progress::suspend();
+                println!("No audio device was found, so the sounds play silently.");
+            }
+        }
+        *self.device_name.lock().unwrap() = name;
+        Ok((keep_alive_tx, device_thread))
+    }
+
+    // fades the master output out and closes the device, so that the sound doesn't cut off with a
+    // pop when troubadour is shut down
+    pub fn shutdown(&self, fade: Duration) {
+        let Some((keep_alive, device_thread)) = self.device.lock().unwrap().take() else {
+            return;
+        };
+        self.master_fade.start_from_current(0.0, fade, false);
+        thread::sleep(fade);
+        drop(keep_alive);
+        device_thread.join().unwrap_or_default();
+    }
+
+    pub fn new_sink(&self) -> Result<Sink, Error> {
+        self.ensure_device()?;
+        let (sink, output) = Sink::new_idle();
+        self.controller.add(output);
+        Ok(sink)
+    }
+
+    // a sink that plays like any other, but into the edit bus instead of the master output
+    pub fn new_edit_sink(&self) -> Sink {
+        if let Some(bus) = self.edit_bus.lock().unwrap().take() {
+            let (keep_alive_tx, keep_alive_rx) = mpsc::channel();
+            thread::spawn(move || play_silently(bus, keep_alive_rx));
+            *self.edit_keep_alive.lock().unwrap() = Some(keep_alive_tx);
+        }
+        let (sink, output) = Sink::new_idle();
+        self.edit_controller.add(output);
+        sink
+    }
+}
+
+impl Default for Mixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// takes the output as fast as a device would, without playing it, so that sounds still end, meters
+// move and taps get the mix. Returns once the sender of keep_alive is dropped
+fn play_silently(mut output: impl Iterator<Item = f32>, keep_alive: mpsc::Receiver<()>) {
+    let chunk = Duration::from_secs_f64(CHUNK_SIZE as f64 / (CHANNELS as u32 * SAMPLE_RATE) as f64);
+    let mut next = Instant::now();
+    loop {
+        output.by_ref().take(CHUNK_SIZE).for_each(drop);
+        next += chunk;
+        let wait = next.saturating_duration_since(Instant::now());
+        if !matches!(
+            keep_alive.recv_timeout(wait),
+            Err(mpsc::RecvTimeoutError::Timeout)
+        ) {
+            return;
+        }
+    }
+}
+
+// the devices the master output can be sent to, including loopback and virtual devices
+pub fn output_devices() -> Result<Vec<String>, Error> {
+    let devices = rodio::cpal::default_host()
+        .output_devices()
+        .map_err(|_| Error::msg("error: failed to list the audio devices."))?;
+    Ok(devices
+        .map(|device| device.name().unwrap_or_default())
+        .collect())
+}
+
+// the device can be given by its number in the list, or by a part of its name
+fn find_device(names: &[String], device: &str) -> Option<usize> {
+    match device.parse::<usize>() {
+        Ok(number) => (1..=names.len()).contains(&number).then(|| number - 1),
+        Err(_) => names
+            .iter()
+            .position(|name| name.to_lowercase().contains(&device.to_lowercase())),
+    }
+}
+
+struct MasterOutput {
+    chain: Arc<Mutex<MasterChain>>,
+    taps: Taps,
+    buffer: Vec<f32>,
+    position: usize,
+}
+
+impl MasterOutput {
+    fn new(chain: Arc<Mutex<MasterChain>>, taps: Taps) -> Self {
+        Self {
+            chain,
+            taps,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for MasterOutput {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.buffer.len() {
+            let mut chain = self.chain.lock().unwrap();
+            self.buffer.clear();
+            self.buffer.extend((&mut *chain).take(CHUNK_SIZE));
+            self.position = 0;
+            self.taps.lock().unwrap().retain(|tap| {
+                !matches!(
+                    tap.try_send(self.buffer.clone()),
+                    Err(mpsc::TrySendError::Disconnected(_))
+                )
+            });
+        }
+        let sample = self.buffer.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl Source for MasterOutput {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        CHANNELS
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[test]
+fn taps_get_the_master_output() {
+    let mixer = Mixer::new();
+    let tap = mixer.tap();
+    let closed = mixer.tap();
+    drop(closed);
+    let mut output = MasterOutput::new(mixer.chain.clone(), mixer.taps.clone());
+    let samples: Vec<f32> = output.by_ref().take(CHUNK_SIZE).collect();
+    assert_eq!(tap.try_recv().unwrap(), samples);
+    assert!(tap.try_recv().is_err());
+    assert_eq!(mixer.taps.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn devices_are_found_by_number_or_name() {
+    let names = ["Speakers".to_string(), "CABLE Input (VB-Audio)".to_string()];
+    assert_eq!(find_device(&names, "2"), Some(1));
+    assert_eq!(find_device(&names, "3"), None);
+    assert_eq!(find_device(&names, "cable"), Some(1));
+    assert_eq!(find_device(&names, "headphones"), None);
+}
+
+#[test]
+fn silent_output_takes_the_mix() {
+    let mixer = Mixer::new();
+    let tap = mixer.tap();
+    let level = 0.2;
+    let sound = rodio::buffer::SamplesBuffer::new(CHANNELS, SAMPLE_RATE, vec![level; CHUNK_SIZE]);
+    mixer.controller.add(sound);
+    // without a keep alive, one chunk is taken before it returns
+    let (keep_alive, keep_alive_rx) = mpsc::channel();
+    drop(keep_alive);
+    play_silently(
+        MasterOutput::new(mixer.chain.clone(), mixer.taps.clone()),
+        keep_alive_rx,
+    );
+    let chunk = tap.try_recv().unwrap();
+    assert_eq!(chunk.len(), CHUNK_SIZE);
+    assert!(chunk.iter().all(|sample| (sample - level).abs() < 1e-6));
+    assert!(tap.try_recv().is_err());
+}
+
+#[test]
+fn quiet_hours_cap_the_output() {
+    use crate::control::SAMPLES_PER_UPDATE;
+    let mixer = Mixer::new();
+    let (quiet, loud) = (0.2, 0.8);
+    let sound = rodio::buffer::SamplesBuffer::new(
+        CHANNELS,
+        SAMPLE_RATE,
+        [vec![quiet; CHUNK_SIZE], vec![loud; CHUNK_SIZE]].concat(),
+    );
+    mixer.controller.add(sound);
+    assert!(mixer.set_quiet_hours_cap(Some(0.5)));
+    assert!(!mixer.set_quiet_hours_cap(Some(0.5)));
+    let mut output = MasterOutput::new(mixer.chain.clone(), mixer.taps.clone());
+    // the cap is picked up at the next update
+    let samples: Vec<f32> = output
+        .by_ref()
+        .take(2 * CHUNK_SIZE)
+        .skip(SAMPLES_PER_UPDATE)
+        .collect();
+    let (below, above) = samples.split_at(CHUNK_SIZE - SAMPLES_PER_UPDATE);
+    assert!(below.iter().all(|sample| (sample - quiet).abs() < 1e-6));
+    assert!(above.iter().all(|sample| (sample - 0.5).abs() < 1e-6));
+}
+
+#[test]
+fn the_edit_bus_isnt_heard() {
+    let mixer = Mixer::new();
+    let sink = mixer.new_edit_sink();
+    let sound = rodio::buffer::SamplesBuffer::new(CHANNELS, SAMPLE_RATE, vec![0.5; CHUNK_SIZE]);
+    sink.append(sound);
+    let mut output = MasterOutput::new(mixer.chain.clone(), mixer.taps.clone());
+    assert!(output.by_ref().take(CHUNK_SIZE).all(|sample| sample == 0.0));
+}
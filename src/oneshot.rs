@@ -0,0 +1,193 @@
+use anyhow::Error;
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use crate::operations::{load, save};
+use crate::{run_command, AppState, INTERACTIVE};
+
+// a single command given on the command line, like troubadour play horn --save scape.json. It
+// runs against the save, or against a running server with --connect, and then troubadour exits
+#[derive(Debug, PartialEq)]
+pub struct OneShot {
+    pub command: String,
+    pub save: Option<PathBuf>,
+    pub connect: Option<String>,
+}
+
+// the options can go anywhere between the words of the command
+pub fn parse(words: &[String]) -> Result<OneShot, Error> {
+    let mut command = Vec::new();
+    let (mut save, mut connect) = (None, None);
+    let mut words = words.iter();
+    while let Some(word) = words.next() {
+        let (option, value) = match word.split_once('=') {
+            Some((option, value)) => (option, Some(value.to_string())),
+            None => (word.as_str(), None),
+        };
+        let target = match option {
+            "--save" => &mut save,
+            "--connect" => &mut connect,
+            _ => {
+                command.push(word.as_str());
+                continue;
+            }
+        };
+        let value = value
+            .or_else(|| words.next().cloned())
+            .ok_or_else(|| Error::msg(format!("error: {option} needs a value.")))?;
+        *target = Some(value);
+    }
+    if save.is_some() && connect.is_some() {
+        return Err(Error::msg(
+            "error: a running server has its own soundscape, so --save can't go with --connect.",
+        ));
+    }
+    let command = shlex::try_join(command)
+        .map_err(|_| Error::msg("error: the command can't have NUL characters in it."))?;
+    Ok(OneShot {
+        command,
+        save: save.map(PathBuf::from),
+        connect,
+    })
+}
+
+// sends the command to a server as a JSON-RPC request, and returns what it said
+pub fn send(address: &str, command: &str) -> Result<String, Error> {
+    let mut stream = TcpStream::connect(address).map_err(|err| {
+        Error::msg(format!(
+            "error: could not connect to troubadour at {address}. Is it running with serve? {err}"
+        ))
+    })?;
+    let request = json!({"jsonrpc": "2.0", "id": 1, "method": "run", "params": [command]});
+    writeln!(stream, "{request}")?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    let reply: Value = serde_json::from_str(&reply).map_err(|err| {
+        Error::msg(format!(
+            "error: the server gave an answer that makes no sense. {err}"
+        ))
+    })?;
+    if let Some(output) = reply["result"]["output"].as_str() {
+        return Ok(output.to_string());
+    }
+    let said = reply["error"]["data"]["output"]
+        .as_str()
+        .unwrap_or_default();
+    let err = reply["error"]["message"]
+        .as_str()
+        .unwrap_or("error: the command failed.");
+    Err(Error::msg(format!("{said}{err}")))
+}
+
+// runs the command on a soundscape of its own. Changes are saved back, so a one-off like a
+// volume change sticks
+pub fn run_headless(state: &Mutex<AppState>, shot: &OneShot) -> Result<(), Error> {
+    INTERACTIVE.set(false);
+    let mut state = state.lock().unwrap();
+    if let Some(path) = &shot.save {
+        load(&mut state, path, true, false, &mut |_, _, _| {})?;
+    }
+    let result = run_command(&mut state, &shot.command, true)?;
+    if let (true, Some(path)) = (result.mutated, &shot.save) {
+        save(&mut state, path, false)?;
+        say!("Saved the changes to {}.", path.display());
+    }
+    Ok(())
+}
+
+#[test]
+fn one_shot_options_are_taken_out_of_the_command() {
+    let words = |words: &[&str]| {
+        words
+            .iter()
+            .map(|word| word.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        parse(&words(&["play", "horn", "--save", "scape.json"])).unwrap(),
+        OneShot {
+            command: "play horn".to_string(),
+            save: Some(PathBuf::from("scape.json")),
+            connect: None,
+        }
+    );
+    assert_eq!(
+        parse(&words(&[
+            "--connect=127.0.0.1:7878",
+            "volume",
+            "big rain",
+            "-v",
+            "50"
+        ]))
+        .unwrap(),
+        OneShot {
+            command: "volume 'big rain' -v 50".to_string(),
+            save: None,
+            connect: Some("127.0.0.1:7878".to_string()),
+        }
+    );
+    assert!(parse(&words(&["play", "--save"])).is_err());
+}
+
+#[test]
+fn one_shots_save_their_changes() {
+    use crate::player::Player;
+    use std::time::Duration;
+
+    let dir = crate::testing::TempDir::new("one shot");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(1));
+    let path = dir.join("scape.json");
+    let mut state = crate::testing::app_state();
+    let horn = Player::new(tone, "horn".to_string(), &state.mixer).unwrap();
+    state.players.insert("horn".to_string(), horn);
+    state.top_group.insert("horn".to_string());
+    save(&mut state, &path, false).unwrap();
+    let shot = parse(&[
+        "volume".to_string(),
+        "horn".to_string(),
+        "-v".to_string(),
+        "30".to_string(),
+        format!("--save={}", path.display()),
+    ])
+    .unwrap();
+    run_headless(&Mutex::new(crate::testing::app_state()), &shot).unwrap();
+    let mut loaded = crate::testing::app_state();
+    load(&mut loaded, &path, true, false, &mut |_, _, _| {}).unwrap();
+    assert_eq!(loaded.players["horn"].get_volume(), 30);
+    let shot = parse(&["volume".to_string(), "owl".to_string(), "-v".to_string()]).unwrap();
+    assert!(run_headless(&Mutex::new(crate::testing::app_state()), &shot).is_err());
+}
+
+#[test]
+fn one_shots_are_answered_by_a_server() {
+    use crate::server::Session;
+    use std::{net::TcpListener, sync::atomic::AtomicBool, sync::Arc, thread};
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let session = Session::new(
+        Arc::new(Mutex::new(crate::testing::app_state())),
+        Arc::new(AtomicBool::new(true)),
+        Arc::new(AtomicBool::new(false)),
+    );
+    let server = thread::spawn(move || {
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            let (reply, _) = session.reply(&line);
+            stream.write_all(reply.as_bytes()).unwrap();
+        }
+    });
+    let said = send(&address, "version").unwrap();
+    assert!(said.starts_with(&format!("troubadour {}", crate::VERSION)));
+    let err = send(&address, "play owl").unwrap_err().to_string();
+    assert!(err.contains("owl"));
+    server.join().unwrap();
+}
@@ -1,15 +1,80 @@
 use anyhow::Error;
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use clap::{CommandFactory, Parser};
 use indexmap::{IndexMap, IndexSet};
+use livesplit_hotkey::Hotkey;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroU64;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::player::Player;
+use crate::analysis;
+use crate::board;
+use crate::cache;
+use crate::clipboard;
+use crate::completion::{self, NameKind, Names};
+use crate::config::{self, Config, LoadConflicts};
+use crate::cooldown::{Cooldown, Trigger};
+use crate::cue::{Cue, CueList};
+use crate::download;
+use crate::ducking::{self, Duck};
+use crate::events::AppEvent;
+use crate::fuzzy;
+use crate::group_loop::GroupLoop;
+use crate::handles;
+use crate::import;
+use crate::limiter::{from_db, to_db, LimiterControl};
+use crate::manifest;
+use crate::metadata;
+use crate::meter::{self, Level};
+use crate::midi::{self, Binding, ControlKind};
+use crate::mixer;
+use crate::output;
+use crate::pending::{self, Action};
 use crate::player::Serializable;
-use crate::{get_confirmation, get_option, readline, AppState};
+use crate::player::{
+    check_media, duration_to_string, parse_timestamp, real_volume, volume_for_gain, Player,
+};
+use crate::playlist::Playlist;
+use crate::project;
+use crate::save_format::SaveFormat;
+use crate::scene::{LoopSettings, Scene, SceneEntry};
+use crate::schedule::{self, Schedule, Scheduled};
+use crate::script;
+use crate::settings::{Bus, Effective, Settings};
+use crate::stretch::STRETCH_RANGE;
+use crate::suggest::find_suggestions;
+use crate::tui;
+use crate::variables;
+use crate::watch_folder::player_name;
+use crate::waveform;
+use crate::{
+    confirm_destructive, expand_words, get_confirmation, get_option, parse_command, progress,
+    readline, respond, AppState, Commands, LimitMode, QuietMode, Shared, INTERACTIVE,
+};
+
+fn no_player(state: &AppState, id: &str) -> Error {
+    Error::msg(format!(
+        "error: no player found with name {id}{}",
+        fuzzy::did_you_mean(id, state.players.keys())
+    ))
+}
+
+fn no_group(state: &AppState, name: &str) -> Error {
+    Error::msg(format!(
+        "error: no group found with name {name}{}",
+        fuzzy::did_you_mean(name, state.groups.keys())
+    ))
+}
 
 fn validate_selection(
     state: &AppState,
@@ -18,10 +83,7 @@ fn validate_selection(
 ) -> Result<(), Error> {
     for group_id in group_ids {
         if !state.groups.contains_key(group_id) {
-            return Err(Error::msg(format!(
-                "error: no group found with name {}",
-                group_id
-            )));
+            return Err(no_group(state, group_id));
         }
     }
     if ids.len() == 1 && ids[0].to_lowercase() == "all" {
@@ -35,13 +97,10 @@ fn validate_selection(
         }
 
         if !state.players.contains_key(id) {
-            return Err(Error::msg(format!(
-                "error: no player found with name {}",
-                id
-            )));
+            return Err(no_player(state, id));
         }
     }
-    if state.top_group.len() == 0 {
+    if state.top_group.is_empty() {
         return Err(Error::msg(
             "error: no players to select. Add a player first",
         ));
@@ -49,12 +108,12 @@ fn validate_selection(
     Ok(())
 }
 
-fn apply_selection(
-    state: &mut AppState,
+// the selected ids, in the order that show uses
+fn selected_ids(
+    state: &AppState,
     ids: &Vec<String>,
     group_ids: &Vec<String>,
-    callback: impl Fn(&mut Player) -> Result<(), Error>,
-) -> Result<(), Error> {
+) -> Result<Vec<String>, Error> {
     validate_selection(state, ids, group_ids)?;
     let mut selection = HashSet::new();
 
@@ -73,12 +132,25 @@ fn apply_selection(
             }
         }
 
-        if ids.len() == 0 && group_ids.len() == 0 && state.top_group.len() > 0 {
+        if ids.is_empty() && group_ids.is_empty() && !state.top_group.is_empty() {
             add_id(state.top_group.last().ok_or(Error::msg("error: internal reference to player that does not exist. This is a bug. Contact the developer"))?);
         }
     }
 
-    for id in selection {
+    Ok(state
+        .ordered_ids()
+        .filter(|id| selection.contains(*id))
+        .cloned()
+        .collect())
+}
+
+fn apply_selection(
+    state: &mut AppState,
+    ids: &Vec<String>,
+    group_ids: &Vec<String>,
+    mut callback: impl FnMut(&mut Player) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for id in selected_ids(state, ids, group_ids)? {
         callback(state.players.get_mut(&id).unwrap())?;
     }
     Ok(())
@@ -88,6 +160,18 @@ fn show_selection(
     state: &AppState,
     ids: &Vec<String>,
     group_ids: &Vec<String>,
+) -> Result<(), Error> {
+    show_players(state, ids, group_ids, false, false)
+}
+
+// with effective, the settings that are in effect for each player are shown too, and where they
+// come from
+fn show_players(
+    state: &AppState,
+    ids: &Vec<String>,
+    group_ids: &Vec<String>,
+    effective: bool,
+    verbose: bool,
 ) -> Result<(), Error> {
     validate_selection(state, ids, group_ids)?;
     let mut selected_top_group = IndexSet::new();
@@ -124,19 +208,34 @@ fn show_selection(
         }
     }
     let print_player = |id: &String| -> Result<(), Error> {
-        println!("{}", state.players.get(id).ok_or(Error::msg("error: internal reference to player that does not exist. This is a bug. Contact the developer"))?.to_string());
+        let player = state.players.get(id).ok_or(Error::msg("error: internal reference to player that does not exist. This is a bug. Contact the developer"))?;
+        let mut text = player.to_string();
+        if verbose {
+            text += &format!(
+                "\n\tfile: {}{}",
+                player.get_media().display(),
+                metadata::read(player.get_media())
+            );
+        }
+        if effective {
+            text += &format!("\n\tin effect:{}", state.effective_settings(id));
+        }
+        say!("{text}");
         Ok(())
     };
     for id in selected_top_group {
         print_player(id)?;
     }
     for (group_name, group) in selected_groups {
-        println!("\n{}\n", group_name);
+        match state.group_defaults.get(group_name) {
+            Some(defaults) => say!("\n{group_name}{defaults}\n"),
+            None => say!("\n{group_name}\n"),
+        }
         for id in group {
             print_player(id)?;
         }
     }
-    if ids.len() == 0 && group_ids.len() == 0 && state.top_group.len() > 0 {
+    if ids.is_empty() && group_ids.is_empty() && !state.top_group.is_empty() {
         print_player(state.top_group.last().unwrap())?;
     }
     Ok(())
@@ -148,21 +247,106 @@ pub struct RespondResult {
     pub quit: bool,
 }
 
-pub fn add(state: &mut AppState, path: PathBuf, name: String) -> Result<RespondResult, Error> {
+fn check_new_name(state: &AppState, name: &str) -> Result<(), Error> {
     if &name.to_lowercase() == "all" {
         return Err(Error::msg(
             "error: you cannot use the name 'all', because it is a keyword.",
         ));
     }
-    if state.players.contains_key(&name) {
+    if state.players.contains_key(name) {
         return Err(Error::msg(format!(
             "error: you cannot use the name '{name}', because it is already used."
         )));
     }
-    let new_player = Player::new(path, name.clone())?;
-    println!("{}", new_player.to_string());
+    Ok(())
+}
+
+// without a name, the sound is named after the title in its tags, or else after the file
+pub fn add(
+    state: &mut AppState,
+    path: PathBuf,
+    name: Option<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    if let Some(name) = &name {
+        check_new_name(state, name)?;
+    }
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let name = player_name(&path, &state.players.keys().collect());
+            check_new_name(state, &name)?;
+            name
+        }
+    };
+    let path = match &state.project {
+        Some(dir) if state.config.copy_to_project => project::copy_in(dir, &path)?,
+        _ => path,
+    };
+    progress(1, 1, &name);
+    let mut new_player = Player::new(path, name.clone(), &state.mixer)?;
+    // a sound that can't be measured can still be played, it just can't be normalized
+    new_player.measure_loudness().unwrap_or_default();
+    insert_player(state, name, new_player)
+}
+
+// a sound from the web is downloaded first, and played from the download. That takes a while, so
+// it is done without holding on to the soundscape, and so is decoding the start of it. The sound
+// can be played as soon as its start is in
+pub fn add_from_web(
+    state: &mut Shared,
+    url: String,
+    name: Option<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let copy_to = state.with(|state| {
+        if let Some(name) = &name {
+            check_new_name(state, name)?;
+        }
+        Ok::<_, Error>(
+            state
+                .project
+                .clone()
+                .filter(|_| state.config.copy_to_project),
+        )
+    })?;
+    progress(0, 1, &format!("downloading {url}"));
+    let mut path = download::fetch(&url)?;
+    // the copy in the project is made of the whole sound
+    if let Some(dir) = copy_to {
+        download::wait(&path)?;
+        path = project::copy_in(&dir, &path)?;
+    }
+    let name = match name {
+        Some(name) => name,
+        None => state.with(|state| player_name(&path, &state.players.keys().collect())),
+    };
+    progress(1, 1, &format!("adding {name}"));
+    let sink = state.with(|state| state.mixer.new_sink())?;
+    let mut new_player = Player::with_sink(path, name.clone(), sink)?;
+    new_player.source_url = Some(url);
+    new_player.measure_loudness().unwrap_or_default();
+    state.with(|state| {
+        // another sound might have gotten the name in the meantime
+        check_new_name(state, &name)?;
+        insert_player(state, name, new_player)
+    })
+}
+
+fn insert_player(
+    state: &mut AppState,
+    name: String,
+    mut new_player: Player,
+) -> Result<RespondResult, Error> {
+    new_player.volume(state.config.default_volume);
+    progress::suspend();
+    say!("{}", new_player);
     state.players.insert(name.clone(), new_player);
-    state.top_group.insert(name);
+    state.top_group.insert(name.clone());
+    if let Some(group) = state.default_group.clone() {
+        move_to_group(state, &name, &group)?;
+        say!("It was put in group {group}.");
+    }
     Ok(RespondResult {
         mutated: true,
         saved: false,
@@ -170,26 +354,192 @@ pub fn add(state: &mut AppState, path: PathBuf, name: String) -> Result<RespondR
     })
 }
 
-pub fn remove(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
-    validate_selection(state, &ids, &vec![])?;
-    if ids.len() == 0 {
-        return Err(Error::msg(
-            "error: please provide the ids of the players that you want to remove",
-        ));
+// for ambience that is found on YouTube and the like. The sound of the video is downloaded into the
+// media folder of the project, so it travels with it, or into the cache. Like other downloads, that
+// is done without holding on to the soundscape
+pub fn add_video(
+    state: &mut Shared,
+    url: String,
+    name: Option<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let dir = state.with(|state| {
+        if let Some(name) = &name {
+            check_new_name(state, name)?;
+        }
+        Ok::<_, Error>(
+            state
+                .project
+                .as_ref()
+                .map(|dir| dir.join(project::MEDIA_DIR)),
+        )
+    })?;
+    progress(0, 1, &format!("downloading {url}"));
+    let path = download::fetch_video(&url, dir.as_deref(), &mut |percent, _, url| {
+        progress(percent, 100, &format!("downloading {url} ({percent}%)"))
+    })?;
+    state.with(|state| {
+        let name = name.unwrap_or_else(|| player_name(&path, &state.players.keys().collect()));
+        let result = add(state, path, Some(name.clone()), &mut |done, total, name| {
+            progress(done, total, &format!("adding {name}"))
+        })?;
+        state.players.get_mut(&name).unwrap().video_url = Some(url);
+        Ok(result)
+    })
+}
+
+// for grabbing a sound that was just found in the file manager, without typing out its path
+pub fn paste_add(
+    state: &mut AppState,
+    name: Option<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let path = clipboard::to_path(&clipboard::read()?)?;
+    if !path.is_file() {
+        return Err(Error::msg(format!(
+            "error: the clipboard holds {}, which is not a file.",
+            path.display()
+        )));
     }
-    for id in &ids {
+    let suggested = player_name(&path, &state.players.keys().collect());
+    let name = match name {
+        Some(name) => name,
+        None if INTERACTIVE.get() => {
+            let answer = readline(&format!("name [{suggested}]: "))?;
+            match answer.trim() {
+                "" => suggested,
+                answer => answer.to_string(),
+            }
+        }
+        None => suggested,
+    };
+    add(state, path, Some(name), progress)
+}
+
+// ids that have to name players explicitly, so no 'all' and no falling back to the last added sound
+fn validate_explicit_ids(state: &AppState, ids: &Vec<String>, action: &str) -> Result<(), Error> {
+    validate_selection(state, ids, &vec![])?;
+    if ids.is_empty() {
+        return Err(Error::msg(format!(
+            "error: please provide the ids of the players that you want to {action}"
+        )));
+    }
+    for id in ids {
         if id.to_lowercase() == "all" {
             return Err(Error::msg(
                 "error: 'all' is not a valid id for this command",
             ));
         }
     }
-    if get_confirmation("Are you sure you want to remove these players?")? {
-        println!("Removed {}", ids.join(", "));
-        state.players.retain(|k, _| !ids.contains(k));
-        state.top_group.retain(|n| !ids.contains(n));
-        for (_, group) in &mut state.groups {
-            group.retain(|n| !ids.contains(n));
+    Ok(())
+}
+
+// takes players out of the soundscape, and out of everything that refers to them
+fn forget_players(state: &mut AppState, ids: &[String]) -> Vec<(String, Player)> {
+    let players = ids
+        .iter()
+        .filter_map(|id| state.players.remove_entry(id))
+        .collect();
+    state.top_group.retain(|n| !ids.contains(n));
+    for (_, group) in &mut state.groups {
+        group.retain(|n| !ids.contains(n));
+    }
+    state.groups.retain(|_, group| !group.is_empty());
+    for (_, scene) in &mut state.scenes {
+        scene.players.retain(|n, _| !ids.contains(n));
+    }
+    state.suspended.retain(|n| !ids.contains(n));
+    for (_, group_loop) in &mut state.group_loops {
+        ids.iter().for_each(|id| group_loop.forget(id));
+    }
+    state
+        .group_loops
+        .retain(|name, _| state.groups.contains_key(name));
+    for (_, playlist) in &mut state.playlists {
+        ids.iter().for_each(|id| playlist.forget(id));
+    }
+    state
+        .playlists
+        .retain(|name, _| state.groups.contains_key(name));
+    state
+        .group_defaults
+        .retain(|name, _| state.groups.contains_key(name));
+    unlink_players(state, ids);
+    players
+}
+
+// a sound that is still playing is faded out before it is dropped, instead of cutting off
+fn fade_out_removed(state: &mut AppState, mut player: Player) {
+    if player.get_is_playing() {
+        player.fade_out(state.config.remove_fade.0);
+        state.removed.push(player);
+    }
+}
+
+// a link of one player doesn't link anything, so those are dropped
+fn unlink_players(state: &mut AppState, ids: &[String]) {
+    for (_, link) in &mut state.volume_links {
+        link.retain(|n| !ids.contains(n));
+    }
+    let players = &mut state.players;
+    state.volume_links.retain(|_, link| {
+        if link.len() < 2 {
+            for id in link.iter() {
+                if let Some(player) = players.get_mut(id) {
+                    player.link = None;
+                }
+            }
+        }
+        link.len() >= 2
+    });
+    for id in ids {
+        if let Some(player) = state.players.get_mut(id) {
+            player.link = None;
+        }
+    }
+}
+
+fn linked_volume(volume: u32, before: u32, after: u32) -> u32 {
+    // a silent player gives no proportion to go by, so the others move by the same amount
+    if before == 0 {
+        volume + after
+    } else {
+        (volume as f64 * after as f64 / before as f64).round() as u32
+    }
+}
+
+// the players that are linked to the changed ones follow along, in proportion to how much the volume
+// changed, over the same fade. Takes the changed players with their volume from before the change
+fn follow_volume_links(state: &mut AppState, changed: &[(String, u32)], fade: Duration) {
+    let mut followers = Vec::new();
+    for link in state.volume_links.values() {
+        let Some((leader, before)) = changed.iter().find(|(id, _)| link.contains(id)) else {
+            continue;
+        };
+        let after = state.players[leader].get_volume();
+        for id in link {
+            if changed.iter().any(|(changed, _)| changed == id) {
+                continue;
+            }
+            let volume = state.players[id].get_volume();
+            followers.push((id.clone(), linked_volume(volume, *before, after)));
+        }
+    }
+    for (id, volume) in followers {
+        state
+            .players
+            .get_mut(&id)
+            .unwrap()
+            .fade_volume(volume, fade);
+    }
+}
+
+pub fn remove(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    validate_explicit_ids(state, &ids, "remove")?;
+    if confirm_destructive("Are you sure you want to remove these players?")? {
+        say!("Removed {}", ids.join(", "));
+        for (_, player) in forget_players(state, &ids) {
+            fade_out_removed(state, player);
         }
         Ok(RespondResult {
             mutated: true,
@@ -205,41 +555,88 @@ pub fn remove(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, E
     }
 }
 
-pub fn play(
-    state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| p.play())?;
-    show_selection(state, &ids, &group_ids)?;
+pub fn archive(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    validate_explicit_ids(state, &ids, "archive")?;
+    for (id, player) in forget_players(state, &ids) {
+        state.archived.insert(id, player.to_serializable());
+        fade_out_removed(state, player);
+    }
+    say!("Archived {}", ids.join(", "));
     Ok(RespondResult {
-        mutated: false,
+        mutated: true,
         saved: false,
         quit: false,
     })
 }
 
-pub fn stop(
-    state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| Ok(p.stop()))?;
-    show_selection(state, &ids, &group_ids)?;
+pub fn unarchive(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    if ids.is_empty() {
+        return Err(Error::msg(if state.archived.is_empty() {
+            "error: there are no archived players.".to_string()
+        } else {
+            let names: Vec<&str> = state.archived.keys().map(|id| id.as_str()).collect();
+            format!(
+                "error: please provide the ids of the players that you want to unarchive. Archived: {}",
+                names.join(", ")
+            )
+        }));
+    }
+    for id in &ids {
+        if !state.archived.contains_key(id) {
+            return Err(Error::msg(format!(
+                "error: no archived player found with name {id}"
+            )));
+        }
+        if state.players.contains_key(id) {
+            return Err(Error::msg(format!(
+                "error: you cannot unarchive {id}, because the name is already used."
+            )));
+        }
+    }
+    for id in &ids {
+        let mut player =
+            Player::from_serializable(&state.archived[id], &state.mixer, state.project.as_deref())?;
+        // it may have been renamed when it was loaded next to a player with the same name
+        player.name = id.clone();
+        let group = player.group.clone();
+        state.archived.shift_remove(id);
+        state.players.insert(id.clone(), player);
+        state.top_group.insert(id.clone());
+        if let Some(group) = group {
+            move_to_group(state, id, &group)?;
+        }
+    }
+    show_selection(state, &ids, &vec![])?;
     Ok(RespondResult {
-        mutated: false,
+        mutated: true,
         saved: false,
         quit: false,
     })
 }
 
-pub fn pause(
+pub fn play(
     state: &mut AppState,
     ids: Vec<String>,
     group_ids: Vec<String>,
+    from: Option<Point>,
 ) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| Ok(p.pause()))?;
+    let fades = effective_fades(state, |effective| effective.start_fade());
+    if let Some(from) = from {
+        return play_from(state, ids, group_ids, &from, &fades);
+    }
+    let mut held = Vec::new();
+    for id in selected_ids(state, &ids, &group_ids)? {
+        let p = state.players.get_mut(&id).unwrap();
+        match p.trigger(fades.get(&id).copied().unwrap_or_default())? {
+            Trigger::Play => {}
+            Trigger::Queue => held.push(format!("{} plays once its cooldown is over.", p.name)),
+            Trigger::Ignore => held.push(format!("{} is cooling down, so it didn't play.", p.name)),
+        }
+    }
     show_selection(state, &ids, &group_ids)?;
+    for message in held {
+        say!("{message}");
+    }
     Ok(RespondResult {
         mutated: false,
         saved: false,
@@ -247,26 +644,78 @@ pub fn pause(
     })
 }
 
-pub fn set_volume(
+// where sounds are played or moved to: a position, or a marker that each of them has
+pub enum Point {
+    At(Duration),
+    Marker(String),
+}
+
+// where the point is in each of the sounds
+fn marker_positions(
+    state: &AppState,
+    selected: &[String],
+    point: &Point,
+) -> Result<Vec<Duration>, Error> {
+    let name = match point {
+        Point::At(position) => return Ok(vec![*position; selected.len()]),
+        Point::Marker(name) => name,
+    };
+    let mut positions = Vec::new();
+    for id in selected {
+        let player = &state.players[id];
+        let Some(position) = player.markers.get(name) else {
+            return Err(Error::msg(format!(
+                "error: {id} has no marker named {name}{}",
+                fuzzy::did_you_mean(name, player.markers.keys())
+            )));
+        };
+        positions.push(*position);
+    }
+    Ok(positions)
+}
+
+// plays from a position, or from a marker
+fn play_from(
     state: &mut AppState,
     ids: Vec<String>,
     group_ids: Vec<String>,
-    volume: u32,
+    from: &Point,
+    fades: &HashMap<String, Duration>,
 ) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| Ok(p.volume(volume)))?;
+    let selected = selected_ids(state, &ids, &group_ids)?;
+    let positions = marker_positions(state, &selected, from)?;
+    for (id, position) in selected.iter().zip(positions) {
+        let player = state.players.get_mut(id).unwrap();
+        player.play_from(position, fades.get(id).copied().unwrap_or_default())?;
+    }
     show_selection(state, &ids, &group_ids)?;
     Ok(RespondResult {
-        mutated: true,
+        mutated: false,
         saved: false,
         quit: false,
     })
 }
 
-pub fn show(
-    state: &AppState,
+// moves the play heads to a position or a marker, without playing or pausing anything
+pub fn seek(
+    state: &mut AppState,
     ids: Vec<String>,
     group_ids: Vec<String>,
+    to: Point,
 ) -> Result<RespondResult, Error> {
+    let selected = selected_ids(state, &ids, &group_ids)?;
+    let positions = marker_positions(state, &selected, &to)?;
+    if let Some(id) = selected.iter().find(|id| {
+        let player = &state.players[*id];
+        !player.get_is_playing() && !player.get_is_paused()
+    }) {
+        return Err(Error::msg(format!(
+            "error: {id} isn't playing. Use play -f to play it from there."
+        )));
+    }
+    for (id, position) in selected.iter().zip(positions) {
+        state.players.get_mut(id).unwrap().seek(position)?;
+    }
     show_selection(state, &ids, &group_ids)?;
     Ok(RespondResult {
         mutated: false,
@@ -275,38 +724,55 @@ pub fn show(
     })
 }
 
-pub fn toggle_loop(
+pub fn mark_add(
     state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-    duration: Option<Duration>,
+    id: String,
+    name: String,
+    position: Duration,
 ) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| {
-        p.toggle_loop(true);
-        p.loop_length(duration);
-        p.apply_settings_in_place(false)?;
-        Ok(())
-    })?;
-
-    show_selection(state, &ids, &group_ids)?;
+    if !state.players.contains_key(&id) {
+        return Err(no_player(state, &id));
+    }
+    if parse_timestamp(&name).is_some() || duration_str::parse(&name).is_ok() {
+        return Err(Error::msg(format!(
+            "error: {name} looks like a position, so play -f would take it for one. Give the marker a name."
+        )));
+    }
+    let player = state.players.get_mut(&id).unwrap();
+    if let Some(length) = player.get_media_length() {
+        if position >= length {
+            return Err(Error::msg(format!(
+                "error: {id} is only {} long",
+                duration_to_string(length, false)
+            )));
+        }
+    }
+    player.markers.insert(name.clone(), position);
+    player
+        .markers
+        .sort_by(|_, first, _, second| first.cmp(second));
+    say!(
+        "{id} can now be played from {name}, at {}.",
+        duration_to_string(position, false)
+    );
     Ok(RespondResult {
         mutated: true,
         saved: false,
         quit: false,
     })
 }
-pub fn unloop(
-    state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| {
-        p.toggle_loop(false);
-        p.apply_settings_in_place(false)?;
-        Ok(())
-    })?;
 
-    show_selection(state, &ids, &group_ids)?;
+pub fn mark_remove(state: &mut AppState, id: String, name: String) -> Result<RespondResult, Error> {
+    if !state.players.contains_key(&id) {
+        return Err(no_player(state, &id));
+    }
+    let player = state.players.get_mut(&id).unwrap();
+    if player.markers.shift_remove(&name).is_none() {
+        return Err(Error::msg(format!(
+            "error: {id} has no marker named {name}{}",
+            fuzzy::did_you_mean(&name, player.markers.keys())
+        )));
+    }
     Ok(RespondResult {
         mutated: true,
         saved: false,
@@ -314,19 +780,52 @@ pub fn unloop(
     })
 }
 
-pub fn set_start(
+pub fn mark_list(state: &AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    if let Some(id) = ids.iter().find(|id| !state.players.contains_key(*id)) {
+        return Err(no_player(state, id));
+    }
+    let ids: Vec<String> = if ids.is_empty() {
+        state.ordered_ids().cloned().collect()
+    } else {
+        ids
+    };
+    let mut any = false;
+    for id in &ids {
+        let markers = &state.players[id].markers;
+        if markers.is_empty() {
+            continue;
+        }
+        any = true;
+        say!("{id}:");
+        for (name, position) in markers {
+            say!("\t{name}: {}", duration_to_string(*position, false));
+        }
+    }
+    if !any {
+        say!("There are no markers yet. Add one with mark add.");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn tag_add(
     state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-    duration: Duration,
+    id: String,
+    tags: Vec<String>,
 ) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| {
-        p.skip_duration(duration);
-        p.apply_settings_in_place(false)?;
-        Ok(())
-    })?;
-
-    show_selection(state, &ids, &group_ids)?;
+    if !state.players.contains_key(&id) {
+        return Err(no_player(state, &id));
+    }
+    let player = state.players.get_mut(&id).unwrap();
+    // @battle is how a tag is used, so it is taken to mean battle here too
+    player.tags.extend(
+        tags.iter()
+            .map(|tag| tag.strip_prefix('@').unwrap_or(tag).to_string()),
+    );
+    say!("{player}");
     Ok(RespondResult {
         mutated: true,
         saved: false,
@@ -334,19 +833,27 @@ pub fn set_start(
     })
 }
 
-pub fn set_end(
+pub fn tag_remove(
     state: &mut AppState,
-    ids: Vec<String>,
-    group_ids: Vec<String>,
-    duration: Option<Duration>,
+    id: String,
+    tags: Vec<String>,
 ) -> Result<RespondResult, Error> {
-    apply_selection(state, &ids, &group_ids, |p| {
-        p.take_duration(duration);
-        p.apply_settings_in_place(false)?;
-        Ok(())
-    })?;
-
-    show_selection(state, &ids, &group_ids)?;
+    if !state.players.contains_key(&id) {
+        return Err(no_player(state, &id));
+    }
+    let player = state.players.get_mut(&id).unwrap();
+    let tags: Vec<&str> = tags
+        .iter()
+        .map(|tag| tag.strip_prefix('@').unwrap_or(tag))
+        .collect();
+    if let Some(tag) = tags.iter().find(|tag| !player.tags.contains(**tag)) {
+        return Err(Error::msg(format!(
+            "error: {id} is not tagged {tag}{}",
+            fuzzy::did_you_mean(tag, &player.tags)
+        )));
+    }
+    player.tags.retain(|tag| !tags.contains(&tag.as_str()));
+    say!("{player}");
     Ok(RespondResult {
         mutated: true,
         saved: false,
@@ -354,18 +861,208 @@ pub fn set_end(
     })
 }
 
-pub fn delay(
-    state: &mut AppState,
+pub fn tag_list(state: &AppState) -> Result<RespondResult, Error> {
+    let tags = Names::of(state).tags;
+    if tags.is_empty() {
+        say!("There are no tags yet. Add one with tag add.");
+    }
+    for (tag, ids) in tags {
+        say!("@{tag}: {}", ids.join(", "));
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// moves a player to the bus that is in effect for it, see Settings
+fn apply_bus(state: &mut AppState, id: &str) -> Result<(), Error> {
+    let editing = state.effective_settings(id).bus.0 == Bus::Edit;
+    state
+        .players
+        .get_mut(id)
+        .unwrap()
+        .set_editing(editing, &state.mixer)
+}
+
+// one of the settings in effect, for every player, so it can be used while the players change
+fn effective_fades(
+    state: &AppState,
+    pick: impl Fn(Effective) -> Duration,
+) -> HashMap<String, Duration> {
+    state
+        .players
+        .keys()
+        .map(|id| (id.clone(), pick(state.effective_settings(id))))
+        .collect()
+}
+
+pub fn set_fades(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    settings: Settings,
+    inherit: bool,
+) -> Result<RespondResult, Error> {
+    if !inherit && settings.is_empty() {
+        return Err(Error::msg(
+            "error: give a fade in, a fade out, or --inherit.",
+        ));
+    }
+    for id in selected_ids(state, &ids, &group_ids)? {
+        let p = state.players.get_mut(&id).unwrap();
+        if inherit {
+            p.settings = Settings::default();
+        } else {
+            p.settings.update(settings);
+        }
+        apply_bus(state, &id)?;
+    }
+    show_players(state, &ids, &group_ids, true, false)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_cooldown(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    length: Duration,
+    queue: bool,
+) -> Result<RespondResult, Error> {
+    let cooldown = (!length.is_zero()).then_some(Cooldown { length, queue });
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.set_cooldown(cooldown);
+        Ok(())
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+// with a fade, it is used instead of the fade outs of the sounds
+pub fn stop(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    fade: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    let fades = effective_fades(state, |effective| effective.fade_out.0);
+    for id in selected_ids(state, &ids, &group_ids)? {
+        let length = fade.unwrap_or_else(|| fades.get(&id).copied().unwrap_or_default());
+        state.players.get_mut(&id).unwrap().fade_out(length);
+    }
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn pause(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    fade: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.fade_pause(fade.unwrap_or_default());
+        Ok(())
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_volume(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    volume: u32,
+) -> Result<RespondResult, Error> {
+    let before: Vec<(String, u32)> = selected_ids(state, &ids, &group_ids)?
+        .into_iter()
+        .map(|id| {
+            let volume = state.players[&id].get_volume();
+            (id, volume)
+        })
+        .collect();
+    // what is asked for is what is heard, also in a scene with its own volume for the sound
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.volume(volume);
+        p.fade_scene_volume(None, Duration::ZERO);
+        Ok(())
+    })?;
+    follow_volume_links(state, &before, Duration::ZERO);
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+// like set_volume, but the sounds that are playing get there gradually
+pub fn fade_volume(
+    state: &mut AppState,
     ids: Vec<String>,
     group_ids: Vec<String>,
+    volume: u32,
     duration: Duration,
 ) -> Result<RespondResult, Error> {
+    let before: Vec<(String, u32)> = selected_ids(state, &ids, &group_ids)?
+        .into_iter()
+        .map(|id| {
+            let volume = state.players[&id].get_volume();
+            (id, volume)
+        })
+        .collect();
     apply_selection(state, &ids, &group_ids, |p| {
-        p.set_delay(duration);
-        p.apply_settings_in_place(false)?;
+        p.fade_volume(volume, duration);
+        p.fade_scene_volume(None, duration);
         Ok(())
     })?;
+    follow_volume_links(state, &before, duration);
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
 
+pub fn normalize(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let selection = selected_ids(state, &ids, &group_ids)?;
+    for (done, id) in selection.iter().enumerate() {
+        let player = state.players.get_mut(id).unwrap();
+        // sounds from older saves haven't been measured yet
+        if !player.get_normalize() && player.get_loudness().is_none() {
+            progress(done + 1, selection.len(), id);
+            player.measure_loudness()?;
+            if player.get_loudness().is_none() {
+                progress::suspend();
+                say!("{id} is silent, so there is nothing to normalize.");
+            }
+        }
+        player.set_normalize(!player.get_normalize());
+    }
+    progress::suspend();
     show_selection(state, &ids, &group_ids)?;
     Ok(RespondResult {
         mutated: true,
@@ -374,28 +1071,43 @@ pub fn delay(
     })
 }
 
-pub fn group(state: &mut AppState, name: String, ids: Vec<String>) -> Result<RespondResult, Error> {
-    validate_selection(state, &ids, &vec![])?;
-    for id in &ids {
-        state.top_group.shift_remove(id);
+pub fn balance(
+    state: &mut AppState,
+    group: String,
+    target: Option<f32>,
+    reset_relative: bool,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let groups = vec![group];
+    let selection = selected_ids(state, &vec![], &groups)?;
+    let mut levels = Vec::new();
+    for (done, id) in selection.iter().enumerate() {
         let player = state.players.get_mut(id).unwrap();
-        if let Some(group) = &player.group {
-            state
-                .groups
-                .get_mut(group)
-                .ok_or(Error::msg("error: player carries reference to non-existent group. This is a bug. Contact the developer"))?
-                .shift_remove(id);
+        if player.get_loudness().is_none() {
+            progress(done + 1, selection.len(), id);
+            player.measure_loudness()?;
+        }
+        match player.heard_loudness() {
+            Some(level) => levels.push((id, level)),
+            None => {
+                progress::suspend();
+                say!("{id} is silent, so it is left as it is.");
+            }
         }
-        player.group = Some(name.clone());
     }
-    if state.groups.contains_key(&name) {
-        let group = state.groups.get_mut(&name).unwrap();
-        group.extend(ids);
-    } else {
-        let mut group = IndexSet::new();
-        group.extend(ids);
-        state.groups.insert(name, group);
-    };
+    progress::suspend();
+    let target = target.unwrap_or(analysis::NORMALIZE_TARGET_DB);
+    let changes = analysis::balance(
+        &levels.iter().map(|&(_, level)| level).collect::<Vec<_>>(),
+        target,
+        !reset_relative,
+    );
+    for ((id, _), change) in levels.into_iter().zip(changes) {
+        let player = state.players.get_mut(id).unwrap();
+        let gain = real_volume(player.get_volume()) * from_db(change);
+        player.volume(volume_for_gain(gain));
+    }
+    show_selection(state, &vec![], &groups)?;
     Ok(RespondResult {
         mutated: true,
         saved: false,
@@ -403,60 +1115,2921 @@ pub fn group(state: &mut AppState, name: String, ids: Vec<String>) -> Result<Res
     })
 }
 
-pub fn ungroup(
+pub fn set_pan(
     state: &mut AppState,
-    name: String,
     ids: Vec<String>,
+    group_ids: Vec<String>,
+    pan: f32,
 ) -> Result<RespondResult, Error> {
-    validate_selection(state, &ids, &vec![name.clone()])?;
-    let group = state.groups.get_mut(&name).unwrap();
-    for id in &ids {
-        if !group.contains(id) {
-            return Err(Error::msg(format!(
-                "error: {id} is not part of the group {name}"
-            )));
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.set_pan(pan);
+        Ok(())
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_speed(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    speed: f32,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.set_speed(speed);
+        Ok(())
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_stretch(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    length: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        if let Some(length) = length {
+            let natural = p.get_play_length().filter(|natural| !natural.is_zero());
+            let Some(natural) = natural else {
+                return Err(Error::msg(format!(
+                    "error: the length of {} is unknown, so it can't be stretched.",
+                    p.name
+                )));
+            };
+            if !STRETCH_RANGE.contains(&(length.as_secs_f64() / natural.as_secs_f64())) {
+                return Err(Error::msg(format!(
+                    "error: {} is {} long. It can be stretched to at most twice or half that.",
+                    p.name,
+                    duration_to_string(natural, false)
+                )));
+            }
         }
+        Ok(())
+    })?;
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.set_stretch(length);
+        p.apply_settings_in_place(false)
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_width(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    width: u32,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.width(width);
+        Ok(())
+    })?;
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+fn show_limiter(name: &str, limiter: &LimiterControl) {
+    if !limiter.is_enabled() {
+        say!("{name}: off");
+        return;
     }
-    let ids: IndexSet<String> = ids.into_iter().collect();
-    if ids.len() == group.len() {
-        state.groups.shift_remove(&name);
+    let threshold = to_db(limiter.threshold());
+    say!("{name}: threshold {threshold:.1} dBFS");
+    let levels = limiter.levels();
+    if levels.peak <= 0.0 {
+        say!("    peak: silent");
+        return;
+    }
+    let peak = to_db(levels.peak);
+    say!("    peak: {peak:.1} dBFS");
+    if levels.gain < 1.0 {
+        say!("    limiting by {:.1} dB", -to_db(levels.gain));
     } else {
-        for id in &ids {
-            group.shift_remove(id);
+        say!("    headroom: {:.1} dB", threshold - peak);
+    }
+}
+
+fn show_levels(state: &AppState) {
+    show_limiter("master limiter", &state.mixer.master_limit);
+    show_limiter("headphone protection", &state.mixer.headphone_protect);
+}
+
+pub fn show(
+    state: &AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    levels: bool,
+    effective: bool,
+    verbose: bool,
+) -> Result<RespondResult, Error> {
+    if levels {
+        show_levels(state);
+        // the levels are worth a look before any sounds are added
+        if state.players.is_empty() && ids.is_empty() && group_ids.is_empty() {
+            return Ok(RespondResult {
+                mutated: false,
+                saved: false,
+                quit: false,
+            });
+        }
+    }
+    show_players(state, &ids, &group_ids, effective, verbose)?;
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn toggle_loop(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    duration: Option<Duration>,
+    auto: bool,
+    count: Option<u32>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    // a loop region says where the loop is already, so a loop length would be ignored
+    if duration.is_some() {
+        for id in selected_ids(state, &ids, &group_ids)? {
+            if state.players[&id].has_loop_region() {
+                return Err(Error::msg(format!(
+                    "error: {id} loops between the points of its loop region, so it can't loop for a set length. Reset the region with set-loop-region first."
+                )));
+            }
         }
     }
-    for id in &ids {
-        let player = state.players.get_mut(id).unwrap();
-        player.group = None;
-        state.top_group.insert(id.clone());
+    if auto {
+        // finding the points takes a while, so all of them are found before anything changes
+        let selection = selected_ids(state, &ids, &group_ids)?;
+        let mut points = Vec::new();
+        for (done, id) in selection.iter().enumerate() {
+            progress(done + 1, selection.len(), id);
+            points.push((id, state.players[id].find_loop_points()?));
+        }
+        progress::suspend();
+        for (id, points) in points {
+            let player = state.players.get_mut(id).unwrap();
+            match points {
+                Some((start, end)) => {
+                    player.skip_duration(start);
+                    player.take_duration(Some(end));
+                }
+                None => {
+                    say!("{id} is too short to find loop points for. Leaving its cuts as they are.")
+                }
+            }
+        }
+    }
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.toggle_loop(true);
+        p.loop_length(duration);
+        p.loop_count(count);
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_loop_region(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    region: Option<(Duration, Duration)>,
+) -> Result<RespondResult, Error> {
+    if let Some((start, end)) = region {
+        if start >= end {
+            return Err(Error::msg(
+                "error: the loop region has to start before it ends.",
+            ));
+        }
+    }
+    apply_selection(state, &ids, &group_ids, |p| {
+        if let (Some((_, end)), Some(take)) = (region, p.get_end().filter(|take| !take.is_zero())) {
+            if end > take {
+                return Err(Error::msg(format!(
+                    "error: {} ends at {}, so the loop region can't end after that. Move its end with set-end first.",
+                    p.name,
+                    duration_to_string(take, false)
+                )));
+            }
+        }
+        if let (Some((_, end)), Some(length)) = (region, p.get_media_length()) {
+            if end > length {
+                return Err(Error::msg(format!(
+                    "error: {} is only {} long, so the loop region can't end after that.",
+                    p.name,
+                    duration_to_string(length, false)
+                )));
+            }
+        }
+        p.loop_region(region);
+        if region.is_some() {
+            p.toggle_loop(true);
+        }
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unloop(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.toggle_loop(false);
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_start(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    duration: Duration,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.skip_duration(duration);
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_end(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    duration: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.take_duration(duration);
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn delay(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    duration: Duration,
+) -> Result<RespondResult, Error> {
+    apply_selection(state, &ids, &group_ids, |p| {
+        p.set_delay(duration);
+        p.apply_settings_in_place(false)?;
+        Ok(())
+    })?;
+
+    show_selection(state, &ids, &group_ids)?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+// when only some of the sounds are in edit mode, all of them go in
+pub fn edit_mode(
+    state: &mut AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+) -> Result<RespondResult, Error> {
+    let selected = selected_ids(state, &ids, &group_ids)?;
+    let editing = !selected.iter().all(|id| state.players[id].is_editing());
+    for id in &selected {
+        state
+            .players
+            .get_mut(id)
+            .unwrap()
+            .set_editing(editing, &state.mixer)?;
+        if editing {
+            say!("{id} is in edit mode, and can't be heard.");
+        } else {
+            say!("{id} can be heard again.");
+        }
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+fn move_to_group(state: &mut AppState, id: &String, name: &str) -> Result<(), Error> {
+    state.top_group.shift_remove(id);
+    let player = state.players.get_mut(id).unwrap();
+    if let Some(group) = &player.group {
+        state
+            .groups
+            .get_mut(group)
+            .ok_or(Error::msg("error: player carries reference to non-existent group. This is a bug. Contact the developer"))?
+            .shift_remove(id);
+    }
+    if let Some(group_loop) = player
+        .group
+        .as_ref()
+        .and_then(|group| state.group_loops.get_mut(group))
+    {
+        group_loop.forget(id);
+    }
+    player.group = Some(name.to_string());
+    state
+        .groups
+        .entry(name.to_string())
+        .or_default()
+        .insert(id.clone());
+    apply_bus(state, id)
+}
+
+pub fn set_default_group(
+    state: &mut AppState,
+    group: Option<String>,
+) -> Result<RespondResult, Error> {
+    if group
+        .as_ref()
+        .is_some_and(|group| group.to_lowercase() == "all")
+    {
+        return Err(Error::msg(
+            "error: you cannot use the name 'all', because it is a keyword.",
+        ));
+    }
+    match &group {
+        Some(group) => say!("Added sounds go in group {group} from now on."),
+        None => say!("Added sounds go to the top level from now on."),
+    }
+    state.default_group = group;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+// takes the words after set, which start with the name of the variable
+pub fn set_variable(state: &mut AppState, words: Vec<String>) -> Result<RespondResult, Error> {
+    let (name, value) = words.split_first().unwrap();
+    if !variables::is_variable(name) {
+        return Err(Error::msg(format!(
+            "error: {name} can't be set. Variables start with $ and a letter, like set $battle \"drums horns\"."
+        )));
+    }
+    let name = &name[1..];
+    if value.is_empty() {
+        let value = state.variables.get(name).ok_or_else(|| {
+            Error::msg(format!(
+                "error: there is no variable ${name}{}",
+                fuzzy::did_you_mean(name, state.variables.keys())
+            ))
+        })?;
+        say!("${name}: {value}");
+    } else {
+        let value = shlex::try_join(value.iter().map(String::as_str))
+            .map_err(|_| Error::msg("error: the value can't have NUL characters in it."))?;
+        say!("${name} now stands for: {value}");
+        state.variables.insert(name.to_string(), value);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn list_variables(state: &AppState) -> Result<RespondResult, Error> {
+    if state.variables.is_empty() {
+        say!("There are no variables.");
+    }
+    for (name, value) in &state.variables {
+        say!("${name}: {value}");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unset_variable(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    let bare = name.strip_prefix('$').unwrap_or(&name);
+    if state.variables.shift_remove(bare).is_none() {
+        return Err(Error::msg(format!(
+            "error: there is no variable ${bare}{}",
+            fuzzy::did_you_mean(bare, state.variables.keys())
+        )));
+    }
+    say!("${bare} is no longer set.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn config_get(state: &AppState, key: Option<String>) -> Result<RespondResult, Error> {
+    match key {
+        Some(key) => {
+            let value = state.config.get(&key).ok_or_else(|| {
+                Error::msg(format!("error: {key} is not a setting, or isn't set."))
+            })?;
+            say!("{key} = {value}");
+        }
+        None => say!("{}", toml::to_string(&state.config)?.trim_end()),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn config_set(
+    state: &mut AppState,
+    key: String,
+    value: String,
+) -> Result<RespondResult, Error> {
+    state.config = Config::set(&key, &value)?;
+    say!("{key} = {}", state.config.get(&key).unwrap_or_default());
+    if Config::read_at_start(&key) {
+        say!("It takes effect the next time troubadour starts.");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn remap_add(state: &mut AppState, from: String, to: String) -> Result<RespondResult, Error> {
+    say!("Sounds under {from} are looked for in {to} from now on, when a save is loaded.");
+    state.remaps.insert(from, to);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn remap_list(state: &AppState) -> Result<RespondResult, Error> {
+    if state.remaps.is_empty() && state.config.remaps.is_empty() {
+        say!("There are no remaps.");
+    }
+    for (from, to) in &state.remaps {
+        say!("{from} -> {to}");
+    }
+    for (from, to) in &state.config.remaps {
+        say!("{from} -> {to} (config)");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn group(state: &mut AppState, name: String, ids: Vec<String>) -> Result<RespondResult, Error> {
+    validate_selection(state, &ids, &vec![])?;
+    for id in &ids {
+        move_to_group(state, id, &name)?;
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+// copies a group under a new name, with its members renamed the same way, like forest-night
+pub fn variant(
+    state: &mut AppState,
+    name: String,
+    suffix: String,
+    volume_scale: f32,
+) -> Result<RespondResult, Error> {
+    let members = state
+        .groups
+        .get(&name)
+        .ok_or_else(|| no_group(state, &name))?
+        .clone();
+    let rename = |id: &String| format!("{id}-{suffix}");
+    let new_name = rename(&name);
+    if state.groups.contains_key(&new_name) {
+        return Err(Error::msg(format!(
+            "error: a group with the name {new_name} already exists"
+        )));
+    }
+    for id in &members {
+        let new_id = rename(id);
+        if state.players.contains_key(&new_id) || state.archived.contains_key(&new_id) {
+            return Err(Error::msg(format!(
+                "error: a player with the name {new_id} already exists"
+            )));
+        }
+    }
+
+    // every copy is made before any of them is added, so a failure leaves the soundscape as it was
+    let mut copies = Vec::new();
+    for id in &members {
+        let mut copy = Player::from_serializable(
+            &state.players[id].to_serializable(),
+            &state.mixer,
+            state.project.as_deref(),
+        )?;
+        copy.name = rename(id);
+        copy.group = Some(new_name.clone());
+        copy.volume((copy.get_volume() as f32 * volume_scale).round() as u32);
+        copies.push(copy);
+    }
+    for copy in copies {
+        state.players.insert(copy.name.clone(), copy);
+    }
+    state
+        .groups
+        .insert(new_name.clone(), members.iter().map(rename).collect());
+    if let Some(group_loop) = state.group_loops.get(&name) {
+        let leader = group_loop.leader.as_ref().map(rename);
+        let length = group_loop.length;
+        state
+            .group_loops
+            .insert(new_name.clone(), GroupLoop::new(leader, length));
+    }
+    if let Some(playlist) = state.playlists.get(&name) {
+        let mut copy = Playlist::new(playlist.cooldown);
+        for (id, weight) in &playlist.weights {
+            copy.weights.insert(rename(id), *weight);
+        }
+        state.playlists.insert(new_name.clone(), copy);
+    }
+    if let Some(defaults) = state.group_defaults.get(&name) {
+        state.group_defaults.insert(new_name.clone(), *defaults);
+    }
+    for id in &members {
+        apply_bus(state, &rename(id))?;
+    }
+    show_selection(state, &vec![], &vec![new_name])?;
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn ungroup(
+    state: &mut AppState,
+    name: String,
+    ids: Vec<String>,
+) -> Result<RespondResult, Error> {
+    validate_selection(state, &ids, &vec![name.clone()])?;
+    let group = state.groups.get_mut(&name).unwrap();
+    for id in &ids {
+        if !group.contains(id) {
+            return Err(Error::msg(format!(
+                "error: {id} is not part of the group {name}"
+            )));
+        }
+    }
+    let ids: IndexSet<String> = ids.into_iter().collect();
+    if ids.len() == group.len() {
+        state.groups.shift_remove(&name);
+        state.group_loops.shift_remove(&name);
+        state.playlists.shift_remove(&name);
+        state.group_defaults.shift_remove(&name);
+        state
+            .ducks
+            .retain(|duck| duck.when != name && duck.target != name);
+    } else {
+        if let Some(group_loop) = state.group_loops.get_mut(&name) {
+            ids.iter().for_each(|id| group_loop.forget(id));
+        }
+        if let Some(playlist) = state.playlists.get_mut(&name) {
+            ids.iter().for_each(|id| playlist.forget(id));
+        }
+        for id in &ids {
+            group.shift_remove(id);
+        }
+    }
+    for id in &ids {
+        let player = state.players.get_mut(id).unwrap();
+        player.group = None;
+        state.top_group.insert(id.clone());
+        apply_bus(state, id)?;
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn link(state: &mut AppState, name: String, ids: Vec<String>) -> Result<RespondResult, Error> {
+    validate_explicit_ids(state, &ids, "link")?;
+    if name.to_lowercase() == "all" {
+        return Err(Error::msg("error: 'all' is not a valid name for a link"));
+    }
+    let mut members = state.volume_links.get(&name).cloned().unwrap_or_default();
+    members.extend(ids.iter().cloned());
+    if members.len() < 2 {
+        return Err(Error::msg(
+            "error: a link needs at least two players. Name another player to link to.",
+        ));
+    }
+    // a player can only follow one link
+    unlink_players(state, &ids);
+    for id in &members {
+        state.players.get_mut(id).unwrap().link = Some(name.clone());
+    }
+    say!(
+        "Linked the volumes of {}",
+        members.iter().cloned().collect::<Vec<_>>().join(", ")
+    );
+    state.volume_links.insert(name, members);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unlink(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    validate_explicit_ids(state, &ids, "unlink")?;
+    for id in &ids {
+        if state.players[id].link.is_none() {
+            return Err(Error::msg(format!("error: {id} is not linked to anything")));
+        }
+    }
+    unlink_players(state, &ids);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn loop_group(
+    state: &mut AppState,
+    name: String,
+    leader: Option<String>,
+    length: Option<Duration>,
+    lock: bool,
+) -> Result<RespondResult, Error> {
+    let group = state
+        .groups
+        .get(&name)
+        .ok_or_else(|| no_group(state, &name))?;
+    if let Some(leader) = &leader {
+        if !group.contains(leader) {
+            return Err(Error::msg(format!(
+                "error: {leader} is not part of the group {name}"
+            )));
+        }
+    }
+    if state.playlists.contains_key(&name) {
+        return Err(Error::msg(format!(
+            "error: group {name} is a playlist. Use unplaylist first."
+        )));
+    }
+    // the group restarts as a whole, so a member that loops by itself would never end
+    for id in group {
+        let player = state.players.get_mut(id).unwrap();
+        if player.get_looping() {
+            player.toggle_loop(false);
+            player.apply_settings_in_place(false)?;
+        }
+    }
+    let length = match length {
+        Some(length) => Some(length),
+        None if lock => Some(
+            group
+                .iter()
+                .filter_map(|id| state.players[id].get_play_length())
+                .max()
+                .ok_or_else(|| {
+                    Error::msg(format!(
+                        "error: the lengths of the sounds in group {name} are unknown. Give a duration instead."
+                    ))
+                })?,
+        ),
+        None => None,
+    };
+    match (&leader, length) {
+        (_, Some(length)) => {
+            // restarting the ones that play puts them in phase, after that they stay there
+            for id in group {
+                let player = state.players.get_mut(id).unwrap();
+                player.toggle_loop(true);
+                player.loop_length(Some(length));
+                if player.get_is_playing() {
+                    player.stop();
+                    player.play()?;
+                } else {
+                    player.apply_settings_in_place(false)?;
+                }
+            }
+            say!(
+                "Group {name} now loops every {}, and its sounds line up at every round.",
+                duration_to_string(length, false)
+            );
+        }
+        (Some(leader), None) => say!("Group {name} now restarts whenever {leader} ends."),
+        (None, None) => say!("Group {name} now restarts whenever its longest sound ends."),
+    }
+    state
+        .group_loops
+        .insert(name, GroupLoop::new(leader, length));
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unloop_group(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    if !state.groups.contains_key(&name) {
+        return Err(no_group(state, &name));
+    }
+    let Some(group_loop) = state.group_loops.shift_remove(&name) else {
+        return Err(Error::msg(format!("error: group {name} does not loop")));
+    };
+    // the members of a locked group loop by themselves, which they only did for the group
+    if group_loop.length.is_some() {
+        for id in &state.groups[&name] {
+            let player = state.players.get_mut(id).unwrap();
+            player.toggle_loop(false);
+            player.loop_length(None);
+            player.apply_settings_in_place(false)?;
+        }
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn group_defaults(
+    state: &mut AppState,
+    name: String,
+    settings: Settings,
+    clear: bool,
+) -> Result<RespondResult, Error> {
+    if !state.groups.contains_key(&name) {
+        return Err(no_group(state, &name));
+    }
+    let mutated = clear || !settings.is_empty();
+    if clear {
+        state.group_defaults.shift_remove(&name);
+    } else if !settings.is_empty() {
+        state
+            .group_defaults
+            .entry(name.clone())
+            .or_default()
+            .update(settings);
+    }
+    for id in state.groups[&name].clone() {
+        apply_bus(state, &id)?;
+    }
+    match state.group_defaults.get(&name) {
+        Some(defaults) => say!("{name}:{defaults}"),
+        None => say!("{name} has no defaults."),
+    }
+    Ok(RespondResult {
+        mutated,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn playlist(
+    state: &mut AppState,
+    name: String,
+    cooldown: usize,
+) -> Result<RespondResult, Error> {
+    let group = state
+        .groups
+        .get(&name)
+        .ok_or_else(|| no_group(state, &name))?;
+    if state.group_loops.contains_key(&name) {
+        return Err(Error::msg(format!(
+            "error: group {name} loops as a whole. Use unloop-group first."
+        )));
+    }
+    // the next sound is picked when the current one ends, so a sound that loops by itself would
+    // keep the playlist waiting forever
+    for id in group {
+        let player = state.players.get_mut(id).unwrap();
+        if player.get_looping() {
+            player.toggle_loop(false);
+            player.apply_settings_in_place(false)?;
+        }
+        player.stop();
+    }
+    // the weights are kept when a playlist is set up again, so only the cooldown changes
+    let mut playlist = state
+        .playlists
+        .shift_remove(&name)
+        .unwrap_or_else(|| Playlist::new(cooldown));
+    playlist.cooldown = cooldown;
+    match playlist.pick(group, crate::playlist::random()) {
+        Some(id) => {
+            state.players.get_mut(&id).unwrap().play()?;
+            say!("Playlist {name} is playing {id}.");
+        }
+        None => say!("Playlist {name} has no sounds with a weight above 0."),
+    }
+    state.playlists.insert(name, playlist);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn duck(
+    state: &mut AppState,
+    when: String,
+    target: String,
+    amount: f32,
+    release: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    for name in [&when, &target] {
+        if !state.groups.contains_key(name) {
+            return Err(no_group(state, name));
+        }
+    }
+    if when == target {
+        return Err(Error::msg("error: a group can't duck under itself."));
+    }
+    let release = release.unwrap_or(ducking::DEFAULT_RELEASE);
+    say!(
+        "{target} goes down by {amount} dB while {when} is heard, and comes back up over {}.",
+        duration_to_string(release, false)
+    );
+    // setting up the same pair again changes the rule that is already there
+    state
+        .ducks
+        .retain(|duck| duck.when != when || duck.target != target);
+    state.ducks.push(Duck::new(when, target, amount, release));
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn show_ducks(state: &AppState) -> Result<RespondResult, Error> {
+    if state.ducks.is_empty() {
+        say!("There are no ducking rules.");
+    }
+    for duck in &state.ducks {
+        say!(
+            "{} goes down by {} dB while {} is heard, release {}",
+            duck.target,
+            duck.amount,
+            duck.when,
+            duration_to_string(duck.release, false)
+        );
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unduck(
+    state: &mut AppState,
+    target: String,
+    when: Option<String>,
+) -> Result<RespondResult, Error> {
+    let before = state.ducks.len();
+    state.ducks.retain(|duck| {
+        duck.target != target || when.as_ref().is_some_and(|when| &duck.when != when)
+    });
+    if state.ducks.len() == before {
+        return Err(Error::msg(format!(
+            "error: group {target} doesn't duck under {}",
+            when.as_deref().unwrap_or("anything")
+        )));
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unplaylist(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    if !state.groups.contains_key(&name) {
+        return Err(no_group(state, &name));
+    }
+    if state.playlists.shift_remove(&name).is_none() {
+        return Err(Error::msg(format!("error: group {name} is not a playlist")));
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn set_weight(
+    state: &mut AppState,
+    ids: Vec<String>,
+    weight: u32,
+) -> Result<RespondResult, Error> {
+    validate_explicit_ids(state, &ids, "weigh")?;
+    let mut playlists = Vec::new();
+    for id in &ids {
+        let group = state.players[id].group.clone();
+        match group.filter(|group| state.playlists.contains_key(group)) {
+            Some(group) => playlists.push(group),
+            None => return Err(Error::msg(format!("error: {id} is not part of a playlist"))),
+        }
+    }
+    for (id, group) in ids.iter().zip(playlists) {
+        let playlist = state.playlists.get_mut(&group).unwrap();
+        playlist.weights.insert(id.clone(), weight);
+        say!("{id} now has a weight of {weight} in playlist {group}.");
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn quiet_hours(state: &mut AppState, mode: Option<QuietMode>) -> Result<RespondResult, Error> {
+    if let Some(mode) = mode {
+        state.quiet_override = match mode {
+            QuietMode::On => Some(true),
+            QuietMode::Off => Some(false),
+            QuietMode::Auto => None,
+        };
+        // the change is announced right away, instead of after the next command
+        state.poll_events();
+    }
+    match &state.config.quiet_hours {
+        Some(quiet_hours) => say!(
+            "Quiet hours are from {} to {}.",
+            quiet_hours.from.format("%H:%M"),
+            quiet_hours.to.format("%H:%M")
+        ),
+        None => say!("There are no quiet hours in the config."),
+    }
+    let overridden = if state.quiet_override.is_some() {
+        " Overridden until quiet-hours auto."
+    } else {
+        ""
+    };
+    if state.is_quiet_hours() {
+        say!(
+            "The master volume is capped at {}% now.{overridden}",
+            state.quiet_hours_volume()
+        );
+    } else {
+        say!("The master volume isn't capped now.{overridden}");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn master_limit(
+    state: &mut AppState,
+    mode: Option<LimitMode>,
+    threshold: Option<f32>,
+) -> Result<RespondResult, Error> {
+    let limiter = &state.mixer.master_limit;
+    if let Some(threshold) = threshold {
+        limiter.set_threshold(from_db(threshold));
+    }
+    match mode {
+        Some(LimitMode::On) => limiter.set_enabled(true),
+        Some(LimitMode::Off) => limiter.set_enabled(false),
+        None => {}
+    }
+    if limiter.is_enabled() {
+        say!(
+            "The master limiter is on, at {:.1} dBFS.",
+            to_db(limiter.threshold())
+        );
+    } else {
+        say!("The master limiter is off.");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn suspend(state: &mut AppState) -> Result<RespondResult, Error> {
+    let paused = state.pause_all();
+    if paused.is_empty() {
+        say!("Nothing is playing.");
+    } else {
+        say!("Suspended {}", paused.join(", "));
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn resume(state: &mut AppState) -> Result<RespondResult, Error> {
+    let resumed = state.resume_all()?;
+    if resumed.is_empty() {
+        say!("Nothing to resume.");
+    } else {
+        say!("Resumed {}", resumed.join(", "));
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn suggest(state: &AppState, count: usize) -> Result<RespondResult, Error> {
+    let (tags, suggestions) = find_suggestions(state);
+    if tags.is_empty() {
+        return Err(Error::msg(
+            "error: nothing is playing right now, so there is nothing to base suggestions on.",
+        ));
+    }
+    if suggestions.is_empty() {
+        say!("No sounds found that match {}.", tags.join(", "));
+    } else {
+        say!("Sounds that match {}:", tags.join(", "));
+        for suggestion in suggestions.iter().take(count) {
+            say!(
+                "\t{} ({})",
+                suggestion.path.display(),
+                suggestion.shared_tags.join(", ")
+            );
+        }
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+fn insert_scene(state: &mut AppState, scene: Scene) -> Result<RespondResult, Error> {
+    if scene.players.is_empty() {
+        return Err(Error::msg(
+            "error: nothing is playing right now, so there is nothing to capture.",
+        ));
+    }
+    if state.scenes.contains_key(&scene.name)
+        && !confirm_destructive(&format!(
+            "A scene with the name {} already exists. Do you want to overwrite it?",
+            scene.name
+        ))?
+    {
+        return Ok(RespondResult {
+            mutated: false,
+            saved: false,
+            quit: false,
+        });
+    }
+    say!("{}", scene);
+    state.scenes.insert(scene.name.clone(), scene);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn scene_capture(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    let scene = Scene::capture(state, name, false);
+    insert_scene(state, scene)
+}
+
+pub fn scene_save(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    let scene = Scene::capture(state, name, true);
+    insert_scene(state, scene)
+}
+
+pub fn scene_switch(
+    state: &mut AppState,
+    name: String,
+    fade: Option<Duration>,
+) -> Result<RespondResult, Error> {
+    let scene = state
+        .scenes
+        .get(&name)
+        .ok_or_else(|| Error::msg(format!("error: no scene found with name {name}")))?;
+    // the sounds that leave the scene don't cut off, even without a fade
+    let leave_fade = fade.unwrap_or(state.config.remove_fade.0);
+    let fade = fade.unwrap_or_default();
+    let diff = scene.diff(
+        state
+            .players
+            .iter()
+            .map(|(id, player)| (id, player.get_is_playing())),
+    );
+
+    let apply_entry = |id: &String, player: &mut Player| -> Result<(), Error> {
+        let entry = scene.players.get(id).unwrap();
+        if let Some(loop_settings) = &entry.loop_settings {
+            let current = LoopSettings {
+                looping: player.get_looping(),
+                length: player.get_loop_length(),
+            };
+            // re-applying restarts the source, so only do it when something changed
+            if &current != loop_settings {
+                player.toggle_loop(loop_settings.looping);
+                player.loop_length(loop_settings.length);
+                player.apply_settings_in_place(false)?;
+            }
+        }
+        player.fade_scene_volume(entry.volume, fade);
+        Ok(())
+    };
+
+    for id in &diff.start {
+        let player = state.players.get_mut(id).unwrap();
+        apply_entry(id, player)?;
+        player.fade_in(fade)?;
+    }
+    for id in &diff.keep {
+        apply_entry(id, state.players.get_mut(id).unwrap())?;
+    }
+    for id in diff.fade_out.iter().chain(&diff.rest) {
+        state.players.get_mut(id).unwrap().leave_scene(leave_fade);
+    }
+
+    say!("{}", scene);
+    state.active_scene = Some(name.clone());
+    state.emit(AppEvent::SceneActivated(name));
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn scene_volume(
+    state: &mut AppState,
+    name: String,
+    ids: Vec<String>,
+    volume: Option<u32>,
+) -> Result<RespondResult, Error> {
+    let scene = state
+        .scenes
+        .get_mut(&name)
+        .ok_or_else(|| Error::msg(format!("error: no scene found with name {name}")))?;
+    if let Some(id) = ids.iter().find(|id| !scene.players.contains_key(*id)) {
+        return Err(Error::msg(format!(
+            "error: {id} is not in the scene {name}."
+        )));
+    }
+    for id in &ids {
+        scene.players[id].volume = volume;
+    }
+    // the scene that plays right now changes right away
+    if state.active_scene.as_ref() == Some(&name) {
+        for id in &ids {
+            if let Some(player) = state.players.get_mut(id) {
+                player.fade_scene_volume(volume, Duration::ZERO);
+            }
+        }
+    }
+    say!("{}", scene);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn scene_list(state: &AppState) -> Result<RespondResult, Error> {
+    if state.scenes.is_empty() {
+        say!("There are no scenes yet. Make one with scene capture.");
+    }
+    for (name, scene) in &state.scenes {
+        let active = if state.active_scene.as_ref() == Some(name) {
+            " (active)"
+        } else {
+            ""
+        };
+        say!("{name}{active}: {} sounds", scene.players.len());
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn hotkey_bind(
+    state: &mut AppState,
+    keys: Hotkey,
+    command: String,
+) -> Result<RespondResult, Error> {
+    if command.trim().is_empty() {
+        return Err(Error::msg("error: the command is empty."));
+    }
+    // checked before anything is bound, so a typo doesn't take the place of what the keys did
+    if state.aliases.expand(&command)?.is_none() {
+        parse_command(&command)?;
+    }
+    state.hotkeys.bind(keys, command.clone())?;
+    say!("{keys} now runs: {command}");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn hotkey_unbind(state: &mut AppState, keys: Hotkey) -> Result<RespondResult, Error> {
+    state.hotkeys.unbind(keys)?;
+    say!("{keys} is no longer bound.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn hotkey_list(state: &AppState) -> Result<RespondResult, Error> {
+    if state.hotkeys.bindings().is_empty() {
+        say!("No hotkeys are bound.");
+    }
+    for (keys, command) in state.hotkeys.bindings() {
+        say!("{keys}: {command}");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn alias(
+    state: &mut AppState,
+    name: Option<String>,
+    commands: Option<String>,
+) -> Result<RespondResult, Error> {
+    match (name, commands) {
+        (None, _) => {
+            if state.aliases.aliases().is_empty() {
+                say!("There are no aliases.");
+            }
+            for (name, commands) in state.aliases.aliases() {
+                say!("{name}: {commands}");
+            }
+        }
+        (Some(name), None) => {
+            let commands = state.aliases.get(&name).ok_or_else(|| {
+                Error::msg(format!(
+                    "error: there is no alias {name}{}",
+                    fuzzy::did_you_mean(&name, state.aliases.aliases().keys())
+                ))
+            })?;
+            say!("{name}: {commands}");
+        }
+        (Some(name), Some(commands)) => {
+            if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+                return Err(Error::msg(format!(
+                    "error: {name} can't be the name of an alias."
+                )));
+            }
+            if Commands::command().find_subcommand(&name).is_some() {
+                return Err(Error::msg(format!(
+                    "error: {name} is a command already, so it can't be an alias."
+                )));
+            }
+            if commands.trim().is_empty() {
+                return Err(Error::msg("error: the command is empty."));
+            }
+            state.aliases.define(name.clone(), commands.clone())?;
+            say!("{name} now runs: {commands}");
+        }
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn unalias(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    state.aliases.remove(&name)?;
+    say!("{name} is no longer an alias.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// runs the commands of an alias one after the other. Like in a shell, a command that fails doesn't
+// keep the ones after it from running
+pub fn run_alias(
+    state: &mut AppState,
+    commands: Vec<String>,
+    has_been_saved: bool,
+) -> Result<RespondResult, Error> {
+    let mut result = RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    };
+    for command in commands {
+        match respond(state, &command, has_been_saved && !result.mutated) {
+            Ok(ran) => {
+                result.mutated |= ran.mutated;
+                result.saved |= ran.saved;
+                result.quit |= ran.quit;
+            }
+            Err(err) => say!("{command}: {err}"),
+        }
+        if result.quit {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+pub fn midi_ports(state: &AppState) -> Result<RespondResult, Error> {
+    let ports = midi::ports()?;
+    if ports.is_empty() {
+        say!("No MIDI devices found.");
+    }
+    for (number, name) in ports.iter().enumerate() {
+        let connected = if state.midi.connected() == Some(name.as_str()) {
+            " (connected)"
+        } else {
+            ""
+        };
+        say!("{}: {name}{connected}", number + 1);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn midi_connect(state: &mut AppState, port: Option<String>) -> Result<RespondResult, Error> {
+    state.midi.disconnect();
+    let name = state.midi.connect(port.as_deref())?;
+    say!("Connected to {name}.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn midi_disconnect(state: &mut AppState) -> Result<RespondResult, Error> {
+    match state.midi.disconnect() {
+        Some(name) => say!("Disconnected from {name}."),
+        None => say!("No MIDI device is connected."),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn discord_join(
+    state: &mut AppState,
+    guild: NonZeroU64,
+    channel: NonZeroU64,
+) -> Result<RespondResult, Error> {
+    let mix = state.mixer.tap();
+    state.discord.join(guild, channel, mix)?;
+    say!("Streaming into the voice channel {channel}.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn discord_leave(state: &mut AppState) -> Result<RespondResult, Error> {
+    let channel = state.discord.channel();
+    match (state.discord.leave()?, channel) {
+        (true, Some((_, channel))) => say!("Left the voice channel {channel}."),
+        _ => say!("Not in a voice channel."),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn output_devices(state: &AppState) -> Result<RespondResult, Error> {
+    let devices = mixer::output_devices()?;
+    if devices.is_empty() {
+        say!("No audio devices found.");
+    }
+    let current = state.mixer.device_name();
+    for (number, name) in devices.iter().enumerate() {
+        let in_use = if current.as_ref() == Some(name) {
+            " (in use)"
+        } else {
+            ""
+        };
+        say!("{}: {name}{in_use}", number + 1);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn output_use(state: &mut AppState, device: Option<String>) -> Result<RespondResult, Error> {
+    match state.mixer.set_output_device(device)? {
+        Some(name) => say!("The master output is sent to {name}."),
+        None => say!("There is no audio device, so the master output is not played."),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn midi_map(
+    state: &mut AppState,
+    kind: ControlKind,
+    number: u8,
+    channel: Option<u8>,
+    command: String,
+) -> Result<RespondResult, Error> {
+    if command.trim().is_empty() {
+        return Err(Error::msg("error: the command is empty."));
+    }
+    let binding = Binding {
+        kind,
+        number,
+        channel,
+        command,
+    };
+    say!("{binding} now runs: {}", binding.command);
+    // mapping the same control again replaces what it did
+    state
+        .midi
+        .bindings
+        .retain(|existing| !existing.is_for(kind, number, channel));
+    state.midi.bindings.push(binding);
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn midi_unmap(
+    state: &mut AppState,
+    kind: ControlKind,
+    number: u8,
+    channel: Option<u8>,
+) -> Result<RespondResult, Error> {
+    let before = state.midi.bindings.len();
+    state
+        .midi
+        .bindings
+        .retain(|binding| !binding.is_for(kind, number, channel));
+    if state.midi.bindings.len() == before {
+        return Err(Error::msg("error: nothing is mapped to that control"));
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn midi_list(state: &AppState) -> Result<RespondResult, Error> {
+    match state.midi.connected() {
+        Some(name) => say!("Connected to {name}."),
+        None => say!("No MIDI device is connected. Connect one with midi connect."),
+    }
+    if state.midi.bindings.is_empty() {
+        say!("Nothing is mapped yet.");
+    }
+    for binding in &state.midi.bindings {
+        say!("{binding}: {}", binding.command);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn pairing_list(state: &AppState) -> Result<RespondResult, Error> {
+    let mut clients = state.pairing.clients().peekable();
+    if clients.peek().is_none() {
+        say!("No remotes are paired. Remotes on this machine don't need to be.");
+    }
+    for client in clients {
+        say!("{}: paired {}", client.name, client.paired);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn pairing_forget(state: &mut AppState, name: String) -> Result<RespondResult, Error> {
+    state.pairing.forget(&name)?;
+    say!("Forgot {name}.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn mono_check(
+    state: &AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let selection = selected_ids(state, &ids, &group_ids)?;
+    let mut results = Vec::new();
+    for (done, id) in selection.iter().enumerate() {
+        progress(done + 1, selection.len(), id);
+        results.push((id, state.players[id].analyze_mono_compatibility()?));
+    }
+    progress::suspend();
+
+    let mut problems = 0;
+    for (id, result) in results {
+        match result {
+            None => say!("{id}: not stereo, or silent"),
+            Some(result) if result.is_problematic() => {
+                problems += 1;
+                say!(
+                    "warning: {id} cancels out heavily in mono (correlation {:.2}, {:.1} dB quieter). It might disappear on mono speakers.",
+                    result.correlation, result.loss_db
+                );
+            }
+            Some(result) => say!(
+                "{id}: correlation {:.2}, {:.1} dB quieter in mono",
+                result.correlation,
+                result.loss_db
+            ),
+        }
+    }
+    if problems == 0 {
+        say!("No phase problems found.");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn apply_manifest(
+    state: &mut AppState,
+    path: &Path,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let file = File::open(path).map_err(|err| {
+        Error::msg(format!(
+            "error: could not open the manifest at {}. {err}",
+            path.display()
+        ))
+    })?;
+    let entries = manifest::parse(file)?;
+    for entry in &entries {
+        if !state.players.contains_key(&entry.name) && entry.path.is_none() {
+            return Err(Error::msg(format!(
+                "error: there is no player named {}, and the manifest has no path to add it from.",
+                entry.name
+            )));
+        }
+    }
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let total = entries.len();
+    for (done, entry) in entries.into_iter().enumerate() {
+        progress(done + 1, total, &entry.name);
+        let name = entry.name;
+        if !state.players.contains_key(&name) {
+            let player = Player::new(entry.path.unwrap(), name.clone(), &state.mixer)?;
+            state.players.insert(name.clone(), player);
+            state.top_group.insert(name.clone());
+            added.push(name.clone());
+        } else {
+            changed.push(name.clone());
+        }
+
+        let player = state.players.get_mut(&name).unwrap();
+        if let Some(volume) = entry.volume {
+            player.volume(volume);
+        }
+        if let Some((looping, length)) = entry.looping {
+            player.toggle_loop(looping);
+            player.loop_length(length);
+        }
+        if let Some(start) = entry.start {
+            player.skip_duration(start);
+        }
+        if let Some(end) = entry.end {
+            player.take_duration(end);
+        }
+        if let Some(delay) = entry.delay {
+            player.set_delay(delay);
+        }
+        if let Some(tags) = entry.tags {
+            player.tags = tags.into_iter().collect();
+        }
+        player.apply_settings_in_place(false)?;
+        if let Some(group) = entry.group {
+            if player.group.as_ref() != Some(&group) {
+                move_to_group(state, &name, &group)?;
+            }
+        }
+    }
+    // moving players around can leave groups without members
+    state.groups.retain(|_, group| !group.is_empty());
+    state
+        .group_loops
+        .retain(|name, _| state.groups.contains_key(name));
+    state
+        .group_defaults
+        .retain(|name, _| state.groups.contains_key(name));
+
+    progress::suspend();
+    if !added.is_empty() {
+        say!("Added {}", added.join(", "));
+    }
+    if !changed.is_empty() {
+        say!("Updated {}", changed.join(", "));
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn show_waveform(
+    state: &AppState,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    width: usize,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let selection = selected_ids(state, &ids, &group_ids)?;
+    let mut shapes = Vec::new();
+    for (done, id) in selection.iter().enumerate() {
+        progress(done + 1, selection.len(), id);
+        let player = &state.players[id];
+        let envelope = waveform::envelope(player.get_media())?;
+        shapes.push((id, player, envelope));
+    }
+    progress::suspend();
+
+    for (id, player, envelope) in shapes {
+        let duration = envelope.duration();
+        say!("{id} ({})", duration_to_string(duration, true));
+        say!("{}", waveform::render(&envelope.downsample(width)));
+        // marks the part that is left after the cuts
+        let end = player.get_end().filter(|end| !end.is_zero());
+        if !player.get_start().is_zero() || end.is_some() {
+            let column = |at: Duration| {
+                ((at.as_secs_f64() / duration.as_secs_f64().max(f64::EPSILON)) * width as f64)
+                    .round()
+                    .min(width as f64) as usize
+            };
+            let start = column(player.get_start()).min(width.saturating_sub(1));
+            let end = end.map(column).unwrap_or(width).max(start + 1);
+            say!(
+                "{}[{}]",
+                " ".repeat(start),
+                " ".repeat(end.saturating_sub(start + 2))
+            );
+        }
+        let end_label = duration_to_string(duration, true);
+        say!(
+            "0s{}{end_label}\n",
+            " ".repeat(width.saturating_sub(2 + end_label.len()))
+        );
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn dump(state: &AppState) -> Result<RespondResult, Error> {
+    say!("{}", serde_json::to_string_pretty(&state.snapshot())?);
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn monitor(state: &mut AppState) -> Result<RespondResult, Error> {
+    match state.monitor.take() {
+        Some(subscription) => {
+            state.bus.unsubscribe(subscription);
+            say!("Stopped showing events.");
+        }
+        None => {
+            state.monitor = Some(state.bus.on(|event| {
+                progress::suspend();
+                say!("[{event}]");
+            }));
+            say!("Showing events as they happen. Call monitor again to stop.");
+        }
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn diagnostics(state: &AppState) -> Result<RespondResult, Error> {
+    let playing = state
+        .players
+        .values()
+        .filter(|player| player.get_is_playing())
+        .count();
+    say!(
+        "players: {} ({playing} playing), {} archived",
+        state.players.len(),
+        state.archived.len()
+    );
+    say!("open sound files: {}", handles::open_sound_files());
+    match handles::process_descriptors() {
+        Some((open, Some(limit))) => say!("open file descriptors: {open} of {limit}"),
+        Some((open, None)) => say!("open file descriptors: {open}"),
+        None => say!("open file descriptors: unknown on this system"),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// how long preflight listens to the audio device
+const PROBE_WINDOW: Duration = Duration::from_millis(500);
+// when the speakers are further behind than this, a sound that is started on cue is noticeably late
+const MAX_LATENCY: Duration = Duration::from_millis(100);
+
+// whether a command that runs later, like in a cue, is valid and refers to things that exist.
+// Players that earlier commands add count as existing
+fn check_command(state: &AppState, command: &str, added: &mut HashSet<String>) -> Option<String> {
+    // the names are written out the way respond does, with the sounds added before it counted in
+    let mut names = Names::of(state);
+    names.players.extend(added.iter().cloned());
+    let words = match expand_words(state, command, &names) {
+        Ok(words) => words,
+        Err(err) => {
+            return Some(format!(
+                "{command} can't run. {}",
+                err.to_string().trim_start_matches("error: ")
+            ))
+        }
+    };
+    let parsed = match Commands::try_parse_from(&words) {
+        Ok(parsed) => parsed,
+        Err(_) => return Some(format!("{command} is not a valid command")),
+    };
+    let missing = completion::referenced_names(&Commands::command(), &words)
+        .into_iter()
+        .find(|(kind, name)| match kind {
+            NameKind::Player => {
+                name.to_lowercase() != "all"
+                    && !state.players.contains_key(name)
+                    && !added.contains(name)
+            }
+            NameKind::Group => !state.groups.contains_key(name),
+            NameKind::Scene => !state.scenes.contains_key(name),
+            NameKind::Path => !Path::new(name).exists(),
+        });
+    // a sound that is added without a name gets the one add would give it
+    if let Commands::Add { path, name, .. } = parsed {
+        let name = name.or_else(|| {
+            let path = path.filter(|path| path.is_file())?;
+            let existing = state.players.keys().chain(added.iter()).collect();
+            Some(player_name(&path, &existing))
+        });
+        if let Some(name) = name {
+            added.insert(name);
+        }
+    }
+    missing.map(|(kind, name)| match kind {
+        NameKind::Player => format!("{command} refers to {name}, which doesn't exist"),
+        NameKind::Group => format!("{command} refers to the group {name}, which doesn't exist"),
+        NameKind::Scene => format!("{command} refers to the scene {name}, which doesn't exist"),
+        NameKind::Path => format!("{command} refers to {name}, which doesn't exist"),
+    })
+}
+
+pub fn cache_build(
+    state: &AppState,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let media: IndexSet<&Path> = state
+        .ordered_ids()
+        .map(|id| state.players[id].get_media())
+        .chain(state.archived.values().map(Serializable::media))
+        .collect();
+    let (mut built, mut failed) = (0, 0);
+    for (done, path) in media.iter().enumerate() {
+        progress(done, media.len(), &path.display().to_string());
+        match cache::build(path) {
+            Ok(true) => built += 1,
+            Ok(false) => {}
+            Err(err) => {
+                progress::suspend();
+                say!("{err}");
+                failed += 1;
+            }
+        }
+    }
+    progress::suspend();
+    say!(
+        "Decoded {built} sounds, {} were in the cache already.",
+        media.len() - built - failed
+    );
+    if failed > 0 {
+        return Err(Error::msg(format!(
+            "error: {failed} sounds could not be decoded."
+        )));
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn cache_clear() -> Result<RespondResult, Error> {
+    let freed = cache::clear()
+        .map_err(|err| Error::msg(format!("error: could not clear the cache. {err}")))?;
+    say!(
+        "Cleared the cache, which freed {:.1} MB.",
+        freed as f64 / 1e6
+    );
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn library_roots(state: &AppState) -> Result<RespondResult, Error> {
+    if state.library.roots().is_empty() {
+        say!("There are no folders in the library. Add one with library add-root.");
+    }
+    for root in state.library.roots() {
+        say!("{}", root.display());
+    }
+    say!("The library has {} sounds.", state.library.sound_count());
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// a folder is scanned as soon as it is added, so it can be searched right away
+pub fn library_add_root(
+    state: &mut Shared,
+    folder: PathBuf,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let folder = state.with(|state| state.library.add_root(&folder))?;
+    progress::suspend();
+    say!("Added {} to the library.", folder.display());
+    progress::resume();
+    library_scan(state, progress)
+}
+
+pub fn library_remove_root(state: &mut AppState, folder: PathBuf) -> Result<RespondResult, Error> {
+    let folder = state.library.remove_root(&folder)?;
+    say!(
+        "Removed {} from the library, which has {} sounds now.",
+        folder.display(),
+        state.library.sound_count()
+    );
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// the folders are looked through without holding on to the soundscape, since decoding the sounds
+// that are new takes a while
+pub fn library_scan(
+    state: &mut Shared,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let scan = state.with(|state| state.library.start_scan());
+    let (scanned, decoded) = scan.run(progress);
+    let found = state.with(|state| state.library.finish_scan(scanned))?;
+    progress::suspend();
+    say!("Found {found} sounds, {decoded} of which were new or changed.");
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn library_search(state: &mut AppState, query: Vec<String>) -> Result<RespondResult, Error> {
+    let results = state.library.search(&query);
+    if results.is_empty() {
+        say!("No sounds in the library match {}.", query.join(" "));
+    }
+    for (number, sound) in results.iter().enumerate() {
+        let length = sound
+            .length
+            .map(|length| format!(", {}", duration_to_string(length, true)))
+            .unwrap_or_default();
+        let tags = if sound.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", sound.tags.join("/"))
+        };
+        say!(
+            "{}. {}{length}{tags}\n\t{}",
+            number + 1,
+            sound.name(),
+            sound.path.display()
+        );
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// adds a sound that the last search found, by its number
+pub fn library_add(
+    state: &mut AppState,
+    result: usize,
+    name: Option<String>,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let path = state.library.result(result)?.path.clone();
+    add(state, path, name, progress)
+}
+
+// whether the audio device responds fast enough, and whether the scenes, cues and schedule only
+// use players that exist
+fn check_show(state: &AppState, checks: &mut Vec<(bool, String)>) {
+    match (state.mixer.probe(PROBE_WINDOW), state.mixer.device_name()) {
+        (Err(err), _) => checks.push((
+            false,
+            format!(
+                "audio device: {}",
+                err.to_string().trim_start_matches("error: ")
+            ),
+        )),
+        (Ok(_), None) => checks.push((
+            false,
+            "audio device: none was found, so the sounds would play silently".to_string(),
+        )),
+        (Ok(None), Some(name)) => {
+            checks.push((false, format!("audio device: {name} doesn't respond")))
+        }
+        (Ok(Some(latency)), Some(name)) => {
+            checks.push((true, format!("audio device: {name} responds")));
+            checks.push((
+                latency <= MAX_LATENCY,
+                format!(
+                    "latency: {} ms, at most {} ms is fine",
+                    latency.as_millis(),
+                    MAX_LATENCY.as_millis()
+                ),
+            ));
+        }
+    }
+
+    for scene in state.scenes.values() {
+        let missing: Vec<&String> = scene
+            .players
+            .keys()
+            .filter(|id| !state.players.contains_key(*id))
+            .collect();
+        checks.push(match missing.as_slice() {
+            [] => (true, format!("scene {}: all players exist", scene.name)),
+            missing => (
+                false,
+                format!(
+                    "scene {}: {} don't exist",
+                    scene.name,
+                    missing
+                        .iter()
+                        .map(|id| id.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+        });
+    }
+
+    let mut added = HashSet::new();
+    for (index, cue) in state.cues.cues.iter().enumerate() {
+        let problems: Vec<String> = cue
+            .commands
+            .iter()
+            .filter_map(|command| check_command(state, command, &mut added))
+            .collect();
+        checks.push(match problems.as_slice() {
+            [] => (true, format!("cue {}", index + 1)),
+            problems => (false, format!("cue {}: {}", index + 1, problems.join("; "))),
+        });
+    }
+    for (id, scheduled) in state.schedule.list() {
+        if let Some(problem) = check_command(state, &scheduled.command, &mut added) {
+            checks.push((false, format!("scheduled {id}: {problem}")));
+        }
+    }
+}
+
+// the last thing to run before a session. Goes through everything that could go wrong halfway,
+// and fails when any of it does
+pub fn preflight(
+    state: &mut Shared,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let mut checks: Vec<(bool, String)> = Vec::new();
+    let media: Vec<(String, PathBuf)> = state.with(|state| {
+        state
+            .ordered_ids()
+            .map(|id| (id.clone(), state.players[id].get_media().to_path_buf()))
+            .collect()
+    });
+    // decoding every sound takes a while, so it is done without the lock
+    for (done, (id, path)) in media.iter().enumerate() {
+        progress(done, media.len(), &format!("decoding {id}"));
+        checks.push(match check_media(path) {
+            Ok(()) => (true, format!("{id}: {} decodes", path.display())),
+            Err(err) => (
+                false,
+                format!("{id}: {}", err.to_string().trim_start_matches("error: ")),
+            ),
+        });
+    }
+
+    progress(media.len(), media.len(), "listening to the audio device");
+    state.with(|state| check_show(state, &mut checks));
+
+    progress::suspend();
+    for (passed, check) in &checks {
+        say!("{} {check}", if *passed { "[ok]  " } else { "[FAIL]" });
+    }
+    let failed = checks.iter().filter(|(passed, _)| !passed).count();
+    if failed > 0 {
+        return Err(Error::msg(format!(
+            "error: {failed} of {} checks failed.",
+            checks.len()
+        )));
+    }
+    say!("All {} checks passed.", checks.len());
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+const METER_WIDTH: usize = 30;
+const METER_REFRESH: Duration = Duration::from_millis(100);
+
+fn meter_line(name: &str, name_width: usize, level: Level) -> String {
+    let db = |level: f32| {
+        if level > 0.0 {
+            format!("{:5.1}", to_db(level))
+        } else {
+            "  -inf".to_string()
+        }
+    };
+    format!(
+        "{name:name_width$} [{}] rms {} dB, peak {} dB",
+        meter::render_bar(level.rms, METER_WIDTH),
+        db(level.rms),
+        db(level.peak)
+    )
+}
+
+pub fn show_meter(
+    state: &mut Shared,
+    ids: Vec<String>,
+    group_ids: Vec<String>,
+    duration: Duration,
+) -> Result<RespondResult, Error> {
+    let mut selection = state.with(|state| {
+        if state.players.is_empty() && ids.is_empty() && group_ids.is_empty() {
+            Ok(Vec::new())
+        } else {
+            selected_ids(state, &ids, &group_ids)
+        }
+    })?;
+    selection.sort();
+    let name_width = selection
+        .iter()
+        .map(|id| id.chars().count())
+        .chain(["master".len()])
+        .max()
+        .unwrap_or_default();
+    // sounds that are removed while the meter runs drop out of it
+    let lines = |state: &mut AppState| {
+        let mut lines = vec![meter_line(
+            "master",
+            name_width,
+            state.mixer.current_level(),
+        )];
+        for id in &selection {
+            if let Some(player) = state.players.get(id) {
+                lines.push(meter_line(id, name_width, player.current_level()));
+            }
+        }
+        lines
+    };
+
+    // without a terminal to redraw in, a single reading is all that makes sense
+    if !io::stdout().is_terminal() || output::is_captured() {
+        for line in state.with(lines) {
+            say!("{line}");
+        }
+        return Ok(RespondResult {
+            mutated: false,
+            saved: false,
+            quit: false,
+        });
+    }
+    let started = Instant::now();
+    let mut drawn = 0;
+    while drawn == 0 || started.elapsed() < duration {
+        if drawn > 0 {
+            print!("\x1b[{drawn}A");
+        }
+        let lines = state.with(lines);
+        for line in &lines {
+            say!("\r\x1b[2K{line}");
+        }
+        io::stdout().flush().unwrap_or_default();
+        drawn = lines.len();
+        // the state isn't held while waiting for the next frame
+        thread::sleep(METER_REFRESH);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn show_board(state: &mut Shared, duration: Duration) -> Result<RespondResult, Error> {
+    // without a terminal to redraw in, a single picture is all that makes sense
+    if !io::stdout().is_terminal() || output::is_captured() {
+        for line in state.with(|state| board::render(state, false)) {
+            say!("{line}");
+        }
+        return Ok(RespondResult {
+            mutated: false,
+            saved: false,
+            quit: false,
+        });
+    }
+    let started = Instant::now();
+    let mut drawn = 0;
+    while drawn == 0 || started.elapsed() < duration {
+        let lines = state.with(|state| {
+            // the prompt waits until the board is done, so sounds that end are picked up here
+            state.poll_events();
+            board::render(state, state.config.colours())
+        });
+        if drawn > 0 {
+            print!("\x1b[{drawn}A\x1b[J");
+        }
+        for line in &lines {
+            say!("\r\x1b[2K{line}");
+        }
+        io::stdout().flush().unwrap_or_default();
+        drawn = lines.len();
+        // the state isn't held while waiting for the next frame
+        thread::sleep(METER_REFRESH);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// the dashboard takes over the terminal once this command is done, see tui
+pub fn open_dashboard() -> Result<RespondResult, Error> {
+    if !cfg!(unix) {
+        return Err(Error::msg(tui::UNSUPPORTED));
+    }
+    if !INTERACTIVE.get() || output::is_captured() || !io::stdout().is_terminal() {
+        return Err(Error::msg(
+            "error: the dashboard can only be opened from the prompt, in a terminal.",
+        ));
+    }
+    tui::request();
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn show_pending(state: &AppState) -> Result<RespondResult, Error> {
+    let pending = pending::collect(&state.players);
+    if pending.is_empty() {
+        say!("Nothing is pending.");
+    }
+    for entry in pending {
+        say!("{}", entry.describe());
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn cancel(state: &mut AppState, ids: Vec<String>) -> Result<RespondResult, Error> {
+    let pending = pending::collect(&state.players);
+    for id in &ids {
+        if !pending.iter().any(|entry| entry.matches(id)) {
+            return Err(Error::msg(format!(
+                "error: nothing is pending with ID {id}. Use pending to see what is."
+            )));
+        }
+    }
+    for entry in pending
+        .iter()
+        .filter(|entry| ids.iter().any(|id| entry.matches(id)))
+    {
+        let player = state.players.get_mut(&entry.player).unwrap();
+        match entry.action {
+            // the sound hasn't been heard yet, so not starting it means stopping it
+            Action::DelayedStart => player.stop(),
+            Action::Fade { .. } | Action::PausingFade => player.cancel_fade(),
+            Action::QueuedTrigger => player.cancel_queued(),
+        }
+        say!("Cancelled {}.", entry.id());
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// whole seconds are precise enough for something that is minutes away
+fn time_until(at: DateTime<Local>) -> String {
+    let left = (at - Local::now()).to_std().unwrap_or_default();
+    duration_to_string(
+        Duration::from_secs(left.as_secs_f64().round() as u64),
+        false,
+    )
+}
+
+fn schedule_command(
+    state: &mut AppState,
+    at: DateTime<Local>,
+    command: Vec<String>,
+) -> Result<RespondResult, Error> {
+    let command = shlex::try_join(command.iter().map(String::as_str))
+        .map_err(|_| Error::msg("error: the command can't have NUL characters in it."))?;
+    // a typo should come out now, not when nobody is looking
+    parse_command(&command)?;
+    let left = time_until(at);
+    let id = state.schedule.add(Scheduled {
+        at,
+        command: command.clone(),
+    });
+    say!("Scheduled {id}: {command}, in {left}.");
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn schedule_after(
+    state: &mut AppState,
+    duration: Duration,
+    command: Vec<String>,
+) -> Result<RespondResult, Error> {
+    let duration = TimeDelta::from_std(duration)
+        .map_err(|_| Error::msg("error: that is too far in the future."))?;
+    schedule_command(state, Local::now() + duration, command)
+}
+
+pub fn schedule_at(
+    state: &mut AppState,
+    time: NaiveTime,
+    command: Vec<String>,
+) -> Result<RespondResult, Error> {
+    let at = schedule::next_time_of_day(time, Local::now().naive_local())
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| {
+            Error::msg(format!(
+                "error: the clock skips {time} because of daylight saving time."
+            ))
+        })?;
+    schedule_command(state, at, command)
+}
+
+pub fn schedule_list(state: &AppState) -> Result<RespondResult, Error> {
+    let list = state.schedule.list();
+    if list.is_empty() {
+        say!("Nothing is scheduled.");
+    }
+    let now = Local::now();
+    for (id, scheduled) in list {
+        let format = if scheduled.at.date_naive() == now.date_naive() {
+            "%H:%M:%S"
+        } else {
+            "%Y-%m-%d %H:%M:%S"
+        };
+        say!(
+            "{id}\n\t{}, at {} (in {})",
+            scheduled.command,
+            scheduled.at.format(format),
+            time_until(scheduled.at)
+        );
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn schedule_cancel(state: &mut AppState, ids: Vec<u32>) -> Result<RespondResult, Error> {
+    for id in &ids {
+        if !state.schedule.list().iter().any(|(other, _)| other == id) {
+            return Err(Error::msg(format!(
+                "error: nothing is scheduled with ID {id}. Use schedule list to see what is."
+            )));
+        }
+    }
+    for id in ids {
+        if let Some(scheduled) = state.schedule.remove(id) {
+            say!("Cancelled {id}: {}.", scheduled.command);
+        }
+    }
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn cue_add(state: &mut AppState, commands: Vec<String>) -> Result<RespondResult, Error> {
+    for command in &commands {
+        if command.trim().is_empty() {
+            return Err(Error::msg("error: a command of the cue is empty."));
+        }
+        parse_command(command)?;
+    }
+    let number = state.cues.add(Cue { commands });
+    say!("Added cue {number}.");
+    Ok(RespondResult {
+        mutated: true,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn cue_list(state: &AppState) -> Result<RespondResult, Error> {
+    if state.cues.cues.is_empty() {
+        say!("There are no cues. Use cue add to make some.");
+    }
+    let standby = state.cues.standby();
+    for (index, cue) in state.cues.cues.iter().enumerate() {
+        let marker = if standby == Some(index + 1) {
+            "> "
+        } else {
+            "  "
+        };
+        say!("{marker}cue {}: {}", index + 1, cue.commands.join("; "));
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// a command of the cue that fails doesn't hold up the rest of the show
+pub fn cue_go(state: &mut AppState, has_been_saved: bool) -> Result<RespondResult, Error> {
+    let Some((number, cue)) = state.cues.go() else {
+        return Err(Error::msg(
+            "error: there is no next cue. Use cue reset to go back to the first one.",
+        ));
+    };
+    say!("Cue {number}.");
+    let mut mutated = false;
+    for command in &cue.commands {
+        match respond(state, command, has_been_saved && !mutated) {
+            Ok(result) => mutated |= result.mutated,
+            Err(err) => say!("{err}"),
+        }
+    }
+    match state.cues.standby() {
+        Some(next) => say!("Standing by for cue {next}."),
+        None => say!("That was the last cue."),
+    }
+    Ok(RespondResult {
+        mutated,
+        saved: false,
+        quit: false,
+    })
+}
+
+// the command with the name where {} is, or at the end
+fn command_for(command: &[String], id: &str) -> Result<String, Error> {
+    let mut words: Vec<String> = command.iter().map(|word| word.replace("{}", id)).collect();
+    if !command.iter().any(|word| word.contains("{}")) {
+        words.push(id.to_string());
+    }
+    shlex::try_join(words.iter().map(String::as_str))
+        .map_err(|_| Error::msg("error: the command can't have NUL characters in it."))
+}
+
+pub fn foreach(
+    state: &mut AppState,
+    regex: String,
+    command: Vec<String>,
+    yes: bool,
+    dry_run: bool,
+    has_been_saved: bool,
+) -> Result<RespondResult, Error> {
+    let pattern = Regex::new(&regex).map_err(|err| {
+        Error::msg(format!(
+            "error: {regex} is not a valid regular expression. {err}"
+        ))
+    })?;
+    let commands = state
+        .ordered_ids()
+        .filter(|id| pattern.is_match(id))
+        .map(|id| command_for(&command, id))
+        .collect::<Result<Vec<String>, Error>>()?;
+    if commands.is_empty() {
+        return Err(Error::msg(format!("error: no player matches {regex}")));
+    }
+    // a typo should come out before anything has run
+    parse_command(&commands[0])?;
+    for command in &commands {
+        say!("{command}");
+    }
+    let run =
+        !dry_run && (yes || get_confirmation(&format!("Run these {} commands?", commands.len()))?);
+    let mut mutated = false;
+    let mut failed = 0;
+    for command in commands.iter().filter(|_| run) {
+        match respond(state, command, has_been_saved && !mutated) {
+            Ok(result) => mutated |= result.mutated,
+            Err(err) => {
+                say!("{command}: {err}");
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(Error::msg(format!(
+            "error: {failed} of {} commands failed.",
+            commands.len()
+        )));
+    }
+    Ok(RespondResult {
+        mutated,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn cue_reset(state: &mut AppState) -> Result<RespondResult, Error> {
+    state.cues.reset();
+    if !state.cues.cues.is_empty() {
+        say!("Standing by for cue 1.");
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// goes up whenever a save written now would not load correctly in an older troubadour. Saves
+// from before there were versions count as version 0
+pub const SAVE_VERSION: u32 = 1;
+
+pub fn supported_save_versions() -> RangeInclusive<u32> {
+    0..=SAVE_VERSION
+}
+
+// a save from a newer troubadour might not even parse, so the version is looked at first
+fn check_save_version(json: &serde_json::Value) -> Result<u32, Error> {
+    let version = json
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0);
+    match u32::try_from(version) {
+        Ok(version) if supported_save_versions().contains(&version) => Ok(version),
+        _ => Err(Error::msg(format!(
+            "error: this save is version {version}, but this troubadour ({}) only reads save versions up to {SAVE_VERSION}. It was made with a newer troubadour.",
+            crate::VERSION
+        ))),
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SerializableAppState {
+    #[serde(default)]
+    version: u32,
+    players: HashMap<String, Serializable>,
+    top_group: IndexSet<String>,
+    groups: IndexMap<String, IndexSet<String>>,
+    #[serde(default)]
+    scenes: IndexMap<String, Scene>,
+    #[serde(default)]
+    group_loops: IndexMap<String, GroupLoop>,
+    // the settings that the members of a group take, unless they set their own
+    #[serde(default)]
+    group_defaults: IndexMap<String, Settings>,
+    // players whose volumes move together
+    #[serde(default)]
+    volume_links: IndexMap<String, IndexSet<String>>,
+    #[serde(default)]
+    playlists: IndexMap<String, Playlist>,
+    #[serde(default)]
+    ducks: Vec<Duck>,
+    // the commands that are mapped to the MIDI controller
+    #[serde(default)]
+    midi: Vec<Binding>,
+    #[serde(default)]
+    default_group: Option<String>,
+    // where the sounds are on other machines, see remap
+    #[serde(default)]
+    remaps: IndexMap<String, String>,
+    // players that were put aside, with their settings and the group they were in
+    #[serde(default)]
+    archived: IndexMap<String, Serializable>,
+    // the audio device that the soundscape was last saved with
+    #[serde(default)]
+    device: Option<String>,
+    // the commands scheduled with at and after, the soonest first
+    #[serde(default)]
+    schedule: Vec<Scheduled>,
+    #[serde(default)]
+    cues: Vec<Cue>,
+    // the scene that was switched to last, which switch starts the soundscape in
+    #[serde(default)]
+    active_scene: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LoadWarning {
+    MissingPlayer { id: String, group: Option<String> },
+    DuplicateMembership { id: String, group: Option<String> },
+    EmptyGroup { group: String },
+    Unreferenced { id: String },
+    MissingSceneMember { id: String, scene: String },
+}
+
+fn group_to_string(group: &Option<String>) -> String {
+    match group {
+        Some(group) => format!("group {group}"),
+        None => "the top level".to_string(),
+    }
+}
+
+impl Display for LoadWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadWarning::MissingPlayer { id, group } => write!(
+                f,
+                "warning: {} refers to player {id}, which does not exist in the save file. The reference was dropped.",
+                group_to_string(group)
+            ),
+            LoadWarning::DuplicateMembership { id, group } => write!(
+                f,
+                "warning: player {id} is listed more than once. Its membership of {} was dropped.",
+                group_to_string(group)
+            ),
+            LoadWarning::EmptyGroup { group } => write!(
+                f,
+                "warning: group {group} has no members left and was dropped."
+            ),
+            LoadWarning::Unreferenced { id } => write!(
+                f,
+                "warning: player {id} is not part of any group. It was added to the top level."
+            ),
+            LoadWarning::MissingSceneMember { id, scene } => write!(
+                f,
+                "warning: scene {scene} refers to player {id}, which does not exist in the save file. The reference was dropped."
+            ),
+        }
+    }
+}
+
+// saves edited by hand or written by older versions can contain references that don't add up.
+// This makes sure that every player in the file is a member of exactly one group (or the top level)
+fn reconcile(json: &mut SerializableAppState) -> Vec<LoadWarning> {
+    let mut warnings = Vec::new();
+    let mut seen = HashSet::new();
+    let players = &json.players;
+
+    let mut keep = |id: &String, group: Option<&String>| {
+        if !players.contains_key(id) {
+            warnings.push(LoadWarning::MissingPlayer {
+                id: id.clone(),
+                group: group.cloned(),
+            });
+            false
+        } else if !seen.insert(id.clone()) {
+            warnings.push(LoadWarning::DuplicateMembership {
+                id: id.clone(),
+                group: group.cloned(),
+            });
+            false
+        } else {
+            true
+        }
+    };
+
+    json.top_group.retain(|id| keep(id, None));
+    for (group_name, group) in &mut json.groups {
+        group.retain(|id| keep(id, Some(group_name)));
+    }
+
+    json.groups.retain(|group_name, group| {
+        if group.is_empty() {
+            warnings.push(LoadWarning::EmptyGroup {
+                group: group_name.clone(),
+            });
+        }
+        !group.is_empty()
+    });
+
+    let mut unreferenced: Vec<&String> = players.keys().filter(|id| !seen.contains(*id)).collect();
+    unreferenced.sort();
+    for id in unreferenced {
+        warnings.push(LoadWarning::Unreferenced { id: id.clone() });
+        json.top_group.insert(id.clone());
+    }
+
+    for (scene_name, scene) in &mut json.scenes {
+        scene.players.retain(|id, _| {
+            if !players.contains_key(id) {
+                warnings.push(LoadWarning::MissingSceneMember {
+                    id: id.clone(),
+                    scene: scene_name.clone(),
+                });
+            }
+            players.contains_key(id)
+        });
+    }
+
+    warnings
+}
+
+pub fn export_script(state: &AppState, path: &Path) -> Result<RespondResult, Error> {
+    let script = script::export(state);
+    fs::write(path, &script).map_err(|err| {
+        Error::msg(format!(
+            "error: could not write the script to {}. {err}",
+            path.display()
+        ))
+    })?;
+    let commands = script.lines().filter(|line| !line.starts_with('#')).count();
+    say!("Wrote {commands} commands to {}", path.display());
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn render(state: &AppState, duration: Duration, path: &Path) -> Result<RespondResult, Error> {
+    if !state.players.values().any(|player| player.get_is_playing()) {
+        return Err(Error::msg(
+            "error: nothing is playing. Play the sounds that should be in the render first.",
+        ));
+    }
+    let count = state.render(duration, path)?;
+    say!(
+        "Rendered {} of {count} sounds to {}",
+        duration_to_string(duration, false),
+        path.display()
+    );
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+pub fn log(
+    state: &mut AppState,
+    text: String,
+    export: Option<PathBuf>,
+) -> Result<RespondResult, Error> {
+    if !text.trim().is_empty() {
+        state.session_log.note(text.trim().to_string());
+    }
+    if let Some(path) = export {
+        fs::write(&path, state.session_log.render()).map_err(|err| {
+            Error::msg(format!(
+                "error: could not write the log to {}. {err}",
+                path.display()
+            ))
+        })?;
+        say!("Wrote the session log to {}", path.display());
+    } else if text.trim().is_empty() {
+        if state.session_log.is_empty() {
+            say!("Nothing has been logged yet.");
+        } else {
+            say!("{}", state.session_log.render().trim_end());
+        }
     }
     Ok(RespondResult {
-        mutated: true,
+        mutated: false,
         saved: false,
         quit: false,
     })
 }
 
-#[derive(Serialize, Deserialize)]
-struct SerializableAppState {
-    players: HashMap<String, Serializable>,
-    top_group: IndexSet<String>,
-    groups: IndexMap<String, IndexSet<String>>,
+// the name, or the name with the first number after it that isn't taken yet
+pub fn unique_name(name: &str, existing: &IndexSet<&String>) -> String {
+    let taken = |name: &String| existing.contains(name) || name.to_lowercase() == "all";
+    let mut unique = name.to_string();
+    let mut number = 2;
+    while taken(&unique) {
+        unique = format!("{name}-{number}");
+        number += 1;
+    }
+    unique
 }
 
-pub fn save(state: &mut AppState, path: &Path) -> Result<RespondResult, Error> {
+pub fn to_serializable(state: &AppState) -> SerializableAppState {
     let serializable: HashMap<String, Serializable> = state
         .players
         .iter()
         .map(|(k, p)| (k.clone(), p.to_serializable()))
         .collect();
-    let ser_app_state = SerializableAppState {
+    SerializableAppState {
+        version: SAVE_VERSION,
         players: serializable,
         top_group: state.top_group.clone(),
         groups: state.groups.clone(),
-    };
-    let json = serde_json::to_string(&ser_app_state)?;
-    fs::write(path, json)?;
+        scenes: state.scenes.clone(),
+        group_loops: state.group_loops.clone(),
+        group_defaults: state.group_defaults.clone(),
+        volume_links: state.volume_links.clone(),
+        playlists: state.playlists.clone(),
+        ducks: state.ducks.clone(),
+        midi: state.midi.bindings.clone(),
+        remaps: state.remaps.clone(),
+        default_group: state.default_group.clone(),
+        archived: state
+            .archived
+            .iter()
+            .map(|(id, player)| (id.clone(), player.clone()))
+            .collect(),
+        device: state.mixer.device_name(),
+        schedule: state
+            .schedule
+            .list()
+            .into_iter()
+            .map(|(_, scheduled)| scheduled.clone())
+            .collect(),
+        cues: state.cues.cues.clone(),
+        active_scene: state.active_scene.clone(),
+    }
+}
+
+pub fn save(state: &mut AppState, path: &Path, as_project: bool) -> Result<RespondResult, Error> {
+    if as_project || path.is_dir() {
+        return save_project(state, path);
+    }
+    let save = SaveFormat::of(path).write(&to_serializable(state))?;
+    fs::write(path, save)?;
+    state.save_version = Some(SAVE_VERSION);
+    state.project = None;
+    state.config.remember_save(path);
+    Ok(RespondResult {
+        mutated: false,
+        saved: true,
+        quit: false,
+    })
+}
+
+fn save_project(state: &mut AppState, dir: &Path) -> Result<RespondResult, Error> {
+    fs::create_dir_all(dir).map_err(|err| {
+        Error::msg(format!(
+            "error: could not create the project folder at {}. {err}",
+            dir.display()
+        ))
+    })?;
+    let mut json = to_serializable(state);
+    let mut copied = 0;
+    for player in json.players.values_mut().chain(json.archived.values_mut()) {
+        let mut media = project::relative(dir, player.media());
+        // sounds outside of the project stay absolute
+        let downloading = download::in_progress(&media).is_some();
+        if state.config.copy_to_project && media.is_absolute() && (media.is_file() || downloading) {
+            // a sound that is still being downloaded is copied once it is complete
+            download::wait(&media)?;
+            media = project::relative(dir, &project::copy_in(dir, &media)?);
+            copied += 1;
+        }
+        player.set_media(media);
+    }
+    if copied > 0 {
+        say!("Copied {copied} sounds into the project.");
+    }
+    fs::write(project::save_file(dir), SaveFormat::Json.write(&json)?)?;
+    state.save_version = Some(SAVE_VERSION);
+    state.project = Some(dir.to_path_buf());
+    state.config.remember_save(dir);
     Ok(RespondResult {
         mutated: false,
         saved: true,
@@ -464,116 +4037,679 @@ pub fn save(state: &mut AppState, path: &Path) -> Result<RespondResult, Error> {
     })
 }
 
+// writes a save file, instead of adding to the soundscape, so the result can be looked over and
+// loaded like any other save
+pub fn import_soundscape(path: &Path, out: &Path) -> Result<RespondResult, Error> {
+    let imported = import::parse(path)?;
+    let mut json = SerializableAppState {
+        version: SAVE_VERSION,
+        ..Default::default()
+    };
+    for track in &imported.tracks {
+        match &track.group {
+            Some(group) => json.groups.entry(group.clone()).or_default(),
+            None => &mut json.top_group,
+        }
+        .insert(track.name.clone());
+        let player = Serializable::new(
+            track.path.clone(),
+            track.name.clone(),
+            track.group.clone(),
+            track.volume,
+            track.looping,
+        );
+        json.players.insert(track.name.clone(), player);
+    }
+    for (name, members) in &imported.moods {
+        let players = members
+            .iter()
+            .map(|(id, volume)| {
+                let entry = SceneEntry {
+                    volume: Some(*volume),
+                    loop_settings: None,
+                };
+                (id.clone(), entry)
+            })
+            .collect();
+        json.scenes.insert(
+            name.clone(),
+            Scene {
+                name: name.clone(),
+                players,
+            },
+        );
+    }
+    fs::write(out, SaveFormat::of(out).write(&json)?).map_err(|err| {
+        Error::msg(format!(
+            "error: could not write the save to {}. {err}",
+            out.display()
+        ))
+    })?;
+    say!(
+        "Imported {} sounds and {} moods into {}. Use load to open it.",
+        imported.tracks.len(),
+        imported.moods.len(),
+        out.display()
+    );
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
 pub fn load(
     state: &mut AppState,
     path: &Path,
     has_been_saved: bool,
+    resume_playback: bool,
+    progress: &mut dyn FnMut(usize, usize, &str),
 ) -> Result<RespondResult, Error> {
+    // this chooses what loading does rather than making sure of it, so without questions, the
+    // save replaces the soundscape like it would when nothing was loaded yet
     let add_to_soundscape = !state.players.is_empty()
+        && config::confirms()
         && get_confirmation("Do you want to add this to you current soundscape?")?;
     let perform_action = add_to_soundscape
         || has_been_saved
-        || get_confirmation("Are you sure you want to overwrite this soundscape without saving?")?;
+        || confirm_destructive(
+            "Are you sure you want to overwrite this soundscape without saving?",
+        )?;
     if perform_action {
-        let json: SerializableAppState = serde_json::from_reader(File::open(path)?)?;
-
+        load_file(state, path, add_to_soundscape, resume_playback, progress)?;
         if !add_to_soundscape {
-            state.players.clear();
-            state.top_group.clear();
-            state.groups.clear();
+            state.config.remember_save(path);
+        }
+    }
+    Ok(RespondResult {
+        mutated: add_to_soundscape && perform_action,
+        saved: !add_to_soundscape && perform_action,
+        quit: false,
+    })
+}
+
+// opens the save that was loaded or saved last, at the start of a session
+pub fn reopen_last(
+    state: &mut AppState,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    let path = state.config.last_save.clone().ok_or_else(|| {
+        Error::msg("error: there is no save to open again. The one that is loaded or saved last is remembered.")
+    })?;
+    load(state, &path, true, false, progress)?;
+    progress::suspend();
+    say!("Opened {} again.", path.display());
+    Ok(RespondResult {
+        mutated: false,
+        saved: true,
+        quit: false,
+    })
+}
+
+// offers to play what was playing when troubadour was closed, after reopen_last. It asks after the
+// loading is done, so the answer isn't taken as typing ahead
+pub fn replay_last(state: &mut AppState) -> Result<RespondResult, Error> {
+    let Some(path) = state.config.last_save.clone() else {
+        return Ok(RespondResult {
+            mutated: false,
+            saved: false,
+            quit: false,
+        });
+    };
+    let playing: Vec<String> = config::playing_in(&path)
+        .into_iter()
+        .filter(|id| state.players.contains_key(id))
+        .collect();
+    // a script on stdin can't be asked, and its first line isn't the answer
+    if !playing.is_empty()
+        && io::stdin().is_terminal()
+        && get_confirmation(&format!(
+            "{} {} playing when troubadour was closed. Play {} again?",
+            playing.join(", "),
+            if playing.len() == 1 { "was" } else { "were" },
+            if playing.len() == 1 { "it" } else { "them" }
+        ))
+        .unwrap_or(false)
+    {
+        return play(state, playing, Vec::new(), None);
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: false,
+        quit: false,
+    })
+}
+
+// loads another save in one go, for sessions that move between prepared soundscapes. The sounds
+// that are playing fade out while the default scene of the new one fades in
+pub fn switch(
+    state: &mut AppState,
+    path: &Path,
+    fade: Option<Duration>,
+    has_been_saved: bool,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<RespondResult, Error> {
+    if !has_been_saved
+        && !confirm_destructive(
+            "Are you sure you want to switch away from this soundscape without saving?",
+        )?
+    {
+        return Ok(RespondResult {
+            mutated: false,
+            saved: false,
+            quit: false,
+        });
+    }
+    // a soundscape that keeps playing beats a session that goes quiet, so a failure leaves it be
+    let (default_scene, previous) = load_file(state, path, false, false, progress)?;
+    state.config.remember_save(path);
+    let old = previous
+        .map(|previous| previous.players)
+        .unwrap_or_default();
+    for (_, mut player) in old {
+        if let (true, Some(fade)) = (player.get_is_playing(), fade) {
+            player.fade_out(fade);
+            state.removed.push(player);
+        } else {
+            fade_out_removed(state, player);
+        }
+    }
+    match default_scene {
+        Some(name) => {
+            scene_switch(state, name, fade)?;
+        }
+        None => say!("{} has no scenes, so nothing plays yet.", path.display()),
+    }
+    Ok(RespondResult {
+        mutated: false,
+        saved: true,
+        quit: false,
+    })
+}
+
+// the parts of the state that make up a soundscape
+struct Soundscape {
+    players: HashMap<String, Player>,
+    top_group: IndexSet<String>,
+    groups: IndexMap<String, IndexSet<String>>,
+    scenes: IndexMap<String, Scene>,
+    active_scene: Option<String>,
+    default_group: Option<String>,
+    remaps: IndexMap<String, String>,
+    project: Option<PathBuf>,
+    suspended: IndexSet<String>,
+    group_loops: IndexMap<String, GroupLoop>,
+    group_defaults: IndexMap<String, Settings>,
+    volume_links: IndexMap<String, IndexSet<String>>,
+    playlists: IndexMap<String, Playlist>,
+    ducks: Vec<Duck>,
+    archived: IndexMap<String, Serializable>,
+    schedule: Schedule,
+    cues: CueList,
+    bindings: Vec<Binding>,
+    save_version: Option<u32>,
+}
+
+impl Soundscape {
+    // leaves the state without a soundscape
+    fn take(state: &mut AppState) -> Self {
+        Self {
+            players: std::mem::take(&mut state.players),
+            top_group: std::mem::take(&mut state.top_group),
+            groups: std::mem::take(&mut state.groups),
+            scenes: std::mem::take(&mut state.scenes),
+            active_scene: state.active_scene.take(),
+            default_group: state.default_group.take(),
+            remaps: std::mem::take(&mut state.remaps),
+            project: state.project.take(),
+            suspended: std::mem::take(&mut state.suspended),
+            group_loops: std::mem::take(&mut state.group_loops),
+            group_defaults: std::mem::take(&mut state.group_defaults),
+            volume_links: std::mem::take(&mut state.volume_links),
+            playlists: std::mem::take(&mut state.playlists),
+            ducks: std::mem::take(&mut state.ducks),
+            archived: std::mem::take(&mut state.archived),
+            schedule: std::mem::take(&mut state.schedule),
+            cues: std::mem::take(&mut state.cues),
+            bindings: std::mem::take(&mut state.midi.bindings),
+            save_version: state.save_version.take(),
+        }
+    }
+
+    // puts it back in place of what the state has now, which is dropped
+    fn restore(self, state: &mut AppState) {
+        state.players = self.players;
+        state.top_group = self.top_group;
+        state.groups = self.groups;
+        state.scenes = self.scenes;
+        state.active_scene = self.active_scene;
+        state.default_group = self.default_group;
+        state.remaps = self.remaps;
+        state.project = self.project;
+        state.suspended = self.suspended;
+        state.group_loops = self.group_loops;
+        state.group_defaults = self.group_defaults;
+        state.volume_links = self.volume_links;
+        state.playlists = self.playlists;
+        state.ducks = self.ducks;
+        state.archived = self.archived;
+        state.schedule = self.schedule;
+        state.cues = self.cues;
+        state.midi.bindings = self.bindings;
+        state.save_version = self.save_version;
+    }
+}
+
+// reads a save into the soundscape, or instead of it. Returns the scene that was active when the
+// file was saved, or else its first one, under the name it was loaded with, and the soundscape it
+// replaced. Nothing changes when the save can't be loaded
+fn load_file(
+    state: &mut AppState,
+    path: &Path,
+    add_to_soundscape: bool,
+    resume_playback: bool,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<(Option<String>, Option<Soundscape>), Error> {
+    let project_dir = path.is_dir().then_some(path);
+    let file = match project_dir {
+        Some(dir) => project::save_file(dir),
+        None => path.to_path_buf(),
+    };
+    let json =
+        SaveFormat::of(&file).read(&fs::read_to_string(&file).map_err(|err| {
+            Error::msg(format!("error: could not read {}. {err}", file.display()))
+        })?)?;
+    let version = check_save_version(&json)?;
+    let mut json: SerializableAppState = serde_json::from_value(json)?;
+
+    for warning in reconcile(&mut json) {
+        say!("{warning}");
+    }
+
+    // the soundscape that is replaced is put aside, and only dropped once the new one has loaded
+    let previous = (!add_to_soundscape).then(|| Soundscape::take(state));
+    state.save_version = Some(version);
+    if !add_to_soundscape {
+        state.default_group = json.default_group.take();
+        state.project = project_dir.map(Path::to_path_buf);
+    }
+    match read_save(state, json, project_dir, resume_playback, progress) {
+        Ok(default_scene) => Ok((default_scene, previous)),
+        Err(err) => {
+            if let Some(previous) = previous {
+                previous.restore(state);
+            }
+            Err(err)
+        }
+    }
+}
+
+// adds what is in a save to the soundscape. Returns the scene that was active when the file was
+// saved, or else its first one, under the name it was loaded with
+fn read_save(
+    state: &mut AppState,
+    mut json: SerializableAppState,
+    project_dir: Option<&Path>,
+    resume_playback: bool,
+    progress: &mut dyn FnMut(usize, usize, &str),
+) -> Result<Option<String>, Error> {
+    // sounds in a project are saved relative to its folder
+    if let Some(dir) = project_dir {
+        for player in json.players.values_mut().chain(json.archived.values_mut()) {
+            let media = project::resolve(dir, player.media());
+            player.set_media(media);
         }
+    }
+
+    // remaps that are made in the session win over the ones in the file, and those over the
+    // ones in the config. The ones in the file are kept, so they are saved again
+    for (from, to) in json.remaps.drain(..) {
+        state.remaps.entry(from).or_insert(to);
+    }
+    let mut remaps = state.config.remaps.clone();
+    remaps.extend(state.remaps.clone());
+    let remapped = json
+        .players
+        .values_mut()
+        .chain(json.archived.values_mut())
+        .map(|player| player.remap(&remaps))
+        .filter(|&changed| changed)
+        .count();
+    if remapped > 0 {
+        say!("Remapped the paths of {remapped} sounds.");
+    }
 
-        let get_new_name = |thing: String, name: String, existing_group: &IndexSet<&String>| {
-            let mut new_name = name.clone();
-            let mut skip = false;
+    // the answer that was given for all remaining conflicts, or the one from the config
+    let for_all = Cell::new(match state.config.load_conflicts {
+        LoadConflicts::Ask => None,
+        choice => Some(choice),
+    });
+    let get_new_name = |thing: String, name: String, existing_group: &IndexSet<&String>| {
+        let mut new_name = name.clone();
+        let mut skip = false;
 
-            while existing_group.contains(&&new_name) {
-                let option = get_option(
+        while existing_group.contains(&&new_name) {
+            let option = match for_all.get() {
+                Some(LoadConflicts::Overwrite) => "o".to_string(),
+                Some(LoadConflicts::Skip) => "s".to_string(),
+                Some(LoadConflicts::Rename) => "n".to_string(),
+                _ => get_option(
                     format!(
-                        "A {thing} with the name {new_name} already exists. Overwrite(O)/Skip(S)/Rename(R)"
+                        "A {thing} with the name {new_name} already exists. Overwrite(O)/Skip(S)/Rename(R), or the same for all remaining conflicts(OA/SA/RA)"
                     )
                     .as_str(),
-                    vec!["o", "s", "r"],
-                )?;
-                match option.as_str() {
-                    "o" => {
-                        break;
-                    }
-                    "s" => {
-                        skip = true;
-                    }
-                    "r" => {
-                        new_name = readline("enter new name: ")?;
-                    }
-                    _ => {
-                        return Err(Error::msg("error: non-allowed option got through validation. This is a bug. Contact the developer"));
-                    }
+                    vec!["o", "s", "r", "oa", "sa", "ra"],
+                )?,
+            };
+            match option.as_str() {
+                "o" => {
+                    break;
+                }
+                "oa" => {
+                    for_all.set(Some(LoadConflicts::Overwrite));
+                    break;
+                }
+                "s" => {
+                    skip = true;
+                    break;
+                }
+                "sa" => {
+                    for_all.set(Some(LoadConflicts::Skip));
+                    skip = true;
+                    break;
+                }
+                "r" => {
+                    new_name = readline("enter new name: ")?;
+                }
+                // renaming dozens of players by hand is what answering for all should save
+                "ra" | "n" => {
+                    for_all.set(Some(LoadConflicts::Rename));
+                    new_name = unique_name(&name, existing_group);
+                }
+                _ => {
+                    return Err(Error::msg("error: non-allowed option got through validation. This is a bug. Contact the developer"));
                 }
             }
+        }
 
-            if skip {
-                return Ok(None);
-            }
-            Ok(Some(new_name))
-        };
+        if skip {
+            return Ok(None);
+        }
+        Ok(Some(new_name))
+    };
 
-        let mut handle_new_player =
-            |name: String, group: &mut IndexSet<String>| -> Result<(), Error> {
-                let new_name = get_new_name(
-                    "player".to_string(),
-                    name.clone(),
-                    &state.players.keys().into_iter().collect(),
-                )?;
+    // scenes refer to players by the name they had in the file
+    let mut loaded_names = HashMap::new();
+    let mut loaded_groups = HashMap::new();
+    // the sounds that were playing when the file was saved, and where. They start together once
+    // everything is loaded
+    let mut playing_at = Vec::new();
+    let total = json.players.len();
+    let mut done = 0;
 
-                if let None = new_name {
-                    return Ok(());
-                }
+    let mut handle_new_player = |name: String,
+                                 group: &mut IndexSet<String>,
+                                 group_name: Option<&String>|
+     -> Result<(), Error> {
+        let new_name = get_new_name(
+            "player".to_string(),
+            name.clone(),
+            &state.players.keys().collect(),
+        )?;
 
-                let player = json.players.get(&name).unwrap();
+        if new_name.is_none() {
+            return Ok(());
+        }
 
-                state.players.insert(
-                    new_name.clone().unwrap(),
-                    Player::from_serializable(player)?,
-                );
+        let player = json.players.get(&name).unwrap();
 
-                group.insert(new_name.unwrap());
+        done += 1;
+        progress(done, total, &name);
 
-                Ok(())
-            };
+        // membership comes from the reconciled group lists, not from what the player itself remembers
+        let mut new_player =
+            Player::from_serializable(player, &state.mixer, state.project.as_deref())?;
+        new_player.name = new_name.clone().unwrap();
+        new_player.group = group_name.cloned();
+        if let Some(at) = player.playing_at().filter(|_| resume_playback) {
+            playing_at.push((new_name.clone().unwrap(), at));
+        }
+
+        state.players.insert(new_name.clone().unwrap(), new_player);
+        loaded_names.insert(name, new_name.clone().unwrap());
+
+        group.insert(new_name.unwrap());
+
+        Ok(())
+    };
+
+    for name in json.top_group {
+        handle_new_player(name, &mut state.top_group, None)?;
+    }
 
-        for name in json.top_group {
-            handle_new_player(name, &mut state.top_group)?;
+    for (group_name, group) in json.groups {
+        let new_name = get_new_name(
+            "group".to_string(),
+            group_name.clone(),
+            &state.groups.keys().collect(),
+        )?;
+
+        if new_name.is_none() {
+            continue;
         }
 
-        for (group_name, group) in json.groups {
-            let new_name = get_new_name(
-                "group".to_string(),
-                group_name,
-                &state.groups.keys().into_iter().collect(),
-            )?;
+        let new_name = new_name.unwrap();
+        let mut new_group = IndexSet::new();
 
-            if let None = new_name {
-                continue;
-            }
+        for name in group {
+            handle_new_player(name, &mut new_group, Some(&new_name))?;
+        }
+
+        state.group_loops.shift_remove(&new_name);
+        state.playlists.shift_remove(&new_name);
+        state.group_defaults.shift_remove(&new_name);
+        loaded_groups.insert(group_name, new_name.clone());
+        state.groups.insert(new_name, new_group);
+    }
+
+    for (group_name, group_loop) in json.group_loops {
+        let Some(new_name) = loaded_groups.get(&group_name) else {
+            continue;
+        };
+        let leader = group_loop
+            .leader
+            .and_then(|leader| loaded_names.get(&leader).cloned())
+            .filter(|leader| state.groups[new_name].contains(leader));
+        state
+            .group_loops
+            .insert(new_name.clone(), GroupLoop::new(leader, group_loop.length));
+    }
 
-            let mut new_group = IndexSet::new();
+    for (group_name, defaults) in json.group_defaults {
+        if let Some(new_name) = loaded_groups.get(&group_name) {
+            state.group_defaults.insert(new_name.clone(), defaults);
+        }
+    }
+    for id in loaded_names.values() {
+        apply_bus(state, id)?;
+    }
 
-            for name in group {
-                handle_new_player(name, &mut new_group)?;
+    for (group_name, playlist) in json.playlists {
+        let Some(new_name) = loaded_groups.get(&group_name) else {
+            continue;
+        };
+        let mut new_playlist = Playlist::new(playlist.cooldown);
+        for (id, weight) in playlist.weights {
+            if let Some(new_id) = loaded_names.get(&id) {
+                new_playlist.weights.insert(new_id.clone(), weight);
             }
+        }
+        // a group can't loop and be a playlist at the same time
+        if !state.group_loops.contains_key(new_name) {
+            state.playlists.insert(new_name.clone(), new_playlist);
+        }
+    }
+
+    for duck in json.ducks {
+        let (Some(when), Some(target)) = (
+            loaded_groups.get(&duck.when),
+            loaded_groups.get(&duck.target),
+        ) else {
+            continue;
+        };
+        state
+            .ducks
+            .retain(|existing| &existing.when != when || &existing.target != target);
+        state.ducks.push(Duck::new(
+            when.clone(),
+            target.clone(),
+            duck.amount,
+            duck.release,
+        ));
+    }
+
+    // the commands are kept as they are, even when players had to be renamed
+    for binding in json.midi {
+        state
+            .midi
+            .bindings
+            .retain(|existing| !existing.is_for(binding.kind, binding.number, binding.channel));
+        state.midi.bindings.push(binding);
+    }
+
+    for (link_name, link) in json.volume_links {
+        let members: IndexSet<String> = link
+            .iter()
+            .filter_map(|name| loaded_names.get(name).cloned())
+            .collect();
+        if members.len() < 2 {
+            continue;
+        }
+        let Some(new_name) = get_new_name(
+            "volume link".to_string(),
+            link_name,
+            &state.volume_links.keys().collect(),
+        )?
+        else {
+            continue;
+        };
+        // merging into an existing link of the same name takes its members out of their old links
+        unlink_players(state, &members.iter().cloned().collect::<Vec<_>>());
+        let mut merged = state
+            .volume_links
+            .get(&new_name)
+            .cloned()
+            .unwrap_or_default();
+        merged.extend(members);
+        for id in &merged {
+            state.players.get_mut(id).unwrap().link = Some(new_name.clone());
+        }
+        state.volume_links.insert(new_name, merged);
+    }
 
-            state.groups.insert(new_name.unwrap(), new_group);
+    for (id, player) in json.archived {
+        let new_name = get_new_name(
+            "player".to_string(),
+            id,
+            &state.players.keys().chain(state.archived.keys()).collect(),
+        )?;
+        if let Some(new_name) = new_name {
+            state.archived.insert(new_name, player);
         }
+    }
 
-        show_selection(
-            state,
-            &state.top_group.clone().into_iter().collect(),
-            &state.groups.keys().cloned().collect(),
+    let active_scene = json
+        .active_scene
+        .filter(|name| json.scenes.contains_key(name))
+        .or_else(|| json.scenes.keys().next().cloned());
+    let mut default_scene = None;
+    for (scene_name, scene) in json.scenes {
+        let is_active = active_scene.as_ref() == Some(&scene_name);
+        let new_name = get_new_name(
+            "scene".to_string(),
+            scene_name,
+            &state.scenes.keys().collect(),
         )?;
+
+        if new_name.is_none() {
+            continue;
+        }
+
+        let new_name = new_name.unwrap();
+        if is_active {
+            default_scene = Some(new_name.clone());
+        }
+        let players = scene
+            .players
+            .into_iter()
+            .filter_map(|(id, entry)| loaded_names.get(&id).map(|id| (id.clone(), entry)))
+            .collect();
+
+        state.scenes.insert(
+            new_name.clone(),
+            Scene {
+                name: new_name,
+                players,
+            },
+        );
+    }
+
+    // the commands are kept as they are, like the MIDI bindings. Ones that should have run
+    // while troubadour was closed would come out of the blue now
+    let now = Local::now();
+    let (missed, upcoming): (Vec<_>, Vec<_>) = json
+        .schedule
+        .into_iter()
+        .partition(|scheduled| scheduled.at <= now);
+    for scheduled in upcoming {
+        state.schedule.add(scheduled);
+    }
+    if !missed.is_empty() {
+        say!(
+            "warning: skipped {} scheduled commands, whose time has passed.",
+            missed.len()
+        );
+    }
+
+    // the cues of an added soundscape come after the ones that are already there
+    for cue in json.cues {
+        state.cues.add(cue);
+    }
+
+    if json.device.is_some() && json.device != state.mixer.device_name() {
+        state.mixer.arm_safe_start();
+    }
+
+    for (id, at) in playing_at {
+        state.players.get_mut(&id).unwrap().play_at(at)?;
+    }
+
+    progress::suspend();
+    show_selection(
+        state,
+        &state.top_group.clone().into_iter().collect(),
+        &state.groups.keys().cloned().collect(),
+    )?;
+    Ok(default_scene)
+}
+
+pub fn version(state: &AppState) -> Result<RespondResult, Error> {
+    say!("troubadour {}", crate::VERSION);
+    let supported = supported_save_versions();
+    say!(
+        "reads save versions {} to {}, and writes version {SAVE_VERSION}",
+        supported.start(),
+        supported.end()
+    );
+    match state.save_version {
+        Some(version) => {
+            say!("the current soundscape was last loaded or saved as version {version}")
+        }
+        None => say!("the current soundscape hasn't been loaded or saved yet"),
     }
     Ok(RespondResult {
-        mutated: add_to_soundscape && perform_action,
-        saved: !add_to_soundscape && perform_action,
+        mutated: false,
+        saved: false,
         quit: false,
     })
 }
@@ -585,3 +4721,483 @@ pub fn exit() -> Result<RespondResult, Error> {
         quit: true,
     })
 }
+
+#[test]
+fn reconcile_orphaned_references() {
+    let player = |name: &str| {
+        format!(
+            r#""{name}": {{"media": "{name}.ogg", "name": "{name}", "group": null, "volume": 100, "looping": false, "loop_length": null, "delay_length": {{"secs": 0, "nanos": 0}}, "take_length": null, "skip_length": {{"secs": 0, "nanos": 0}}}}"#
+        )
+    };
+    let json = format!(
+        r#"{{
+            "players": {{{}, {}, {}}},
+            "top_group": ["rain", "ghost"],
+            "groups": {{"forest": ["birds", "rain"], "crypt": ["ghost"]}}
+        }}"#,
+        player("rain"),
+        player("birds"),
+        player("wind")
+    );
+    let mut json: SerializableAppState = serde_json::from_str(&json).unwrap();
+    let warnings = reconcile(&mut json);
+    assert_eq!(
+        warnings,
+        vec![
+            LoadWarning::MissingPlayer {
+                id: "ghost".to_string(),
+                group: None
+            },
+            LoadWarning::DuplicateMembership {
+                id: "rain".to_string(),
+                group: Some("forest".to_string())
+            },
+            LoadWarning::MissingPlayer {
+                id: "ghost".to_string(),
+                group: Some("crypt".to_string())
+            },
+            LoadWarning::EmptyGroup {
+                group: "crypt".to_string()
+            },
+            LoadWarning::Unreferenced {
+                id: "wind".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        json.top_group.into_iter().collect::<Vec<_>>(),
+        vec!["rain", "wind"]
+    );
+    assert_eq!(json.groups.keys().collect::<Vec<_>>(), vec!["forest"]);
+    assert_eq!(
+        json.groups["forest"].iter().collect::<Vec<_>>(),
+        vec!["birds"]
+    );
+}
+
+#[test]
+fn save_versions_are_checked() {
+    assert_eq!(
+        check_save_version(&serde_json::json!({"players": {}})).unwrap(),
+        0
+    );
+    assert_eq!(
+        check_save_version(&serde_json::json!({ "version": SAVE_VERSION })).unwrap(),
+        SAVE_VERSION
+    );
+    assert!(check_save_version(&serde_json::json!({ "version": SAVE_VERSION + 1 })).is_err());
+}
+
+#[test]
+fn linked_volumes_follow_in_proportion() {
+    assert_eq!(linked_volume(40, 80, 40), 20);
+    assert_eq!(linked_volume(30, 100, 150), 45);
+    assert_eq!(linked_volume(30, 0, 20), 50);
+    assert_eq!(linked_volume(0, 50, 100), 0);
+}
+
+#[test]
+fn saves_survive_toml() {
+    let json = serde_json::json!({
+        "version": SAVE_VERSION,
+        "players": {"rain": Serializable::new("rain.ogg".into(), "rain".to_string(), Some("forest".to_string()), 40, true)},
+        "top_group": [],
+        "groups": {"forest": ["rain"]},
+        "scenes": {"storm": {"name": "storm", "players": {"rain": {"volume": 80, "loop_settings": null}}}},
+        "group_loops": {"forest": {"leader": null}},
+        "midi": [{"kind": "note", "number": 36, "channel": null, "command": "play rain"}],
+        "remaps": {"D:\\Sounds": "/home/me/sounds"},
+    });
+    let save: SerializableAppState = serde_json::from_value(json).unwrap();
+    let toml = SaveFormat::Toml.write(&save).unwrap();
+    let read: SerializableAppState =
+        serde_json::from_value(SaveFormat::Toml.read(&toml).unwrap()).unwrap();
+    assert_eq!(
+        serde_json::to_value(&read).unwrap(),
+        serde_json::to_value(&save).unwrap()
+    );
+}
+
+#[test]
+fn failed_switches_keep_the_soundscape() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("switch");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(30));
+    let mut state = crate::testing::app_state();
+    let mut rain = Player::new(tone, "rain".to_string(), &state.mixer).unwrap();
+    rain.play().unwrap();
+    state.players.insert("rain".to_string(), rain);
+    state.top_group.insert("rain".to_string());
+    let calm = Scene {
+        name: "calm".to_string(),
+        players: IndexMap::new(),
+    };
+    state.scenes.insert("calm".to_string(), calm);
+    state.active_scene = Some("calm".to_string());
+    // a sound that can't be found, and can't be asked for either, fails the load halfway through
+    let save = dir.join("dungeon.json");
+    let missing = Serializable::new(dir.join("bats.ogg"), "bats".to_string(), None, 100, false);
+    let json = serde_json::json!({
+        "version": SAVE_VERSION,
+        "players": {"bats": missing},
+        "top_group": ["bats"],
+        "groups": {},
+        "scenes": {"cave": {"name": "cave", "players": {}}},
+    });
+    fs::write(&save, json.to_string()).unwrap();
+    let result = switch(&mut state, &save, None, true, &mut |_, _, _| {});
+    assert!(result.is_err());
+    assert!(state.players["rain"].get_is_playing());
+    assert!(!state.players.contains_key("bats"));
+    assert_eq!(state.top_group, IndexSet::from(["rain".to_string()]));
+    assert_eq!(state.scenes.keys().collect::<Vec<_>>(), ["calm"]);
+    assert_eq!(state.active_scene.as_deref(), Some("calm"));
+    assert_eq!(state.save_version, None);
+}
+
+#[test]
+fn switches_fade_out_what_they_drop() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("switch fades");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(30));
+    let mut state = crate::testing::app_state();
+    let mut rain = Player::new(tone.clone(), "rain".to_string(), &state.mixer).unwrap();
+    rain.play().unwrap();
+    state.players.insert("rain".to_string(), rain);
+    state.top_group.insert("rain".to_string());
+    let calm = Scene {
+        name: "calm".to_string(),
+        players: IndexMap::new(),
+    };
+    state.scenes.insert("calm".to_string(), calm);
+    scene_switch(&mut state, "calm".to_string(), None).unwrap();
+    assert!(state.players["rain"].get_is_playing());
+    let save = dir.join("dungeon.json");
+    let bats = Serializable::new(tone, "bats".to_string(), None, 100, false);
+    let json = serde_json::json!({
+        "version": SAVE_VERSION,
+        "players": {"bats": bats},
+        "top_group": ["bats"],
+        "groups": {},
+        "scenes": {},
+    });
+    fs::write(&save, json.to_string()).unwrap();
+    switch(&mut state, &save, None, true, &mut |_, _, _| {}).unwrap();
+    assert!(!state.players.contains_key("rain"));
+    assert_eq!(state.removed.len(), 1);
+    assert!(state.removed[0].get_is_playing());
+}
+
+#[test]
+fn playback_is_resumed_where_it_was_saved() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("resume playback");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(30));
+    let mut state = crate::testing::app_state();
+    for name in ["rain", "owl"] {
+        let player = Player::new(tone.clone(), name.to_string(), &state.mixer).unwrap();
+        state.players.insert(name.to_string(), player);
+        state.top_group.insert(name.to_string());
+    }
+    let rain = state.players.get_mut("rain").unwrap();
+    rain.play_at(Duration::from_secs(12)).unwrap();
+    let save_path = dir.join("tavern.json");
+    save(&mut state, &save_path, false).unwrap();
+    let near = |player: &Player, at: u64| {
+        let play_time = player.get_play_time();
+        Duration::from_secs(at) <= play_time && play_time < Duration::from_secs(at + 1)
+    };
+    for resume_playback in [true, false] {
+        let mut loaded = crate::testing::app_state();
+        load(
+            &mut loaded,
+            &save_path,
+            true,
+            resume_playback,
+            &mut |_, _, _| {},
+        )
+        .unwrap();
+        let rain = &loaded.players["rain"];
+        assert_eq!(rain.get_is_playing(), resume_playback);
+        assert!(!resume_playback || near(rain, 12));
+        assert!(!loaded.players["owl"].get_is_playing());
+    }
+}
+
+#[test]
+fn loop_lengths_dont_override_loop_regions() {
+    let dir = crate::testing::TempDir::new("region length");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(10));
+    let mut state = crate::testing::app_state();
+    let theme = Player::new(tone, "theme".to_string(), &state.mixer).unwrap();
+    state.players.insert("theme".to_string(), theme);
+    state.top_group.insert("theme".to_string());
+    let ids = vec!["theme".to_string()];
+    let secs = Duration::from_secs;
+    set_end(&mut state, ids.clone(), Vec::new(), Some(secs(5))).unwrap();
+    assert!(set_loop_region(
+        &mut state,
+        ids.clone(),
+        Vec::new(),
+        Some((secs(2), secs(6)))
+    )
+    .is_err());
+    set_loop_region(
+        &mut state,
+        ids.clone(),
+        Vec::new(),
+        Some((secs(2), secs(4))),
+    )
+    .unwrap();
+    let length = Some(secs(3));
+    let result = toggle_loop(
+        &mut state,
+        ids.clone(),
+        Vec::new(),
+        length,
+        false,
+        None,
+        &mut |_, _, _| {},
+    );
+    assert!(result.is_err());
+    assert_eq!(state.players["theme"].get_loop_length(), None);
+    set_loop_region(&mut state, ids.clone(), Vec::new(), None).unwrap();
+    toggle_loop(
+        &mut state,
+        ids,
+        Vec::new(),
+        length,
+        false,
+        None,
+        &mut |_, _, _| {},
+    )
+    .unwrap();
+    assert_eq!(state.players["theme"].get_loop_length(), length);
+}
+
+#[test]
+fn unlooping_a_locked_group_unloops_its_sounds() {
+    let dir = crate::testing::TempDir::new("group loop");
+    let mut state = crate::testing::app_state();
+    for (name, secs) in [("drums", 4), ("bass", 6)] {
+        let path = dir.join(format!("{name}.wav"));
+        crate::testing::write_tone(&path, Duration::from_secs(secs));
+        let player = Player::new(path, name.to_string(), &state.mixer).unwrap();
+        state.players.insert(name.to_string(), player);
+        state.top_group.insert(name.to_string());
+    }
+    let ids = vec!["drums".to_string(), "bass".to_string()];
+    group(&mut state, "band".to_string(), ids).unwrap();
+    loop_group(&mut state, "band".to_string(), None, None, true).unwrap();
+    for player in state.players.values() {
+        assert!(player.get_looping());
+        assert_eq!(player.get_loop_length(), Some(Duration::from_secs(6)));
+    }
+    unloop_group(&mut state, "band".to_string()).unwrap();
+    for player in state.players.values() {
+        assert!(!player.get_looping());
+        assert_eq!(player.get_loop_length(), None);
+    }
+    assert!(unloop_group(&mut state, "band".to_string()).is_err());
+}
+
+#[test]
+fn checks_know_the_names_that_adds_give() {
+    let dir = crate::testing::TempDir::new("check names");
+    let rain = dir.join("rain.wav");
+    crate::testing::write_tone(&rain, Duration::from_secs(1));
+    let state = crate::testing::app_state();
+    let mut added = HashSet::new();
+    let add = format!("add -p \"{}\"", rain.display());
+    assert_eq!(check_command(&state, &add, &mut added), None);
+    assert_eq!(check_command(&state, "play rain", &mut added), None);
+    assert!(check_command(&state, "play thunder", &mut added).is_some());
+    assert_eq!(check_command(&state, "play rai*", &mut added), None);
+}
+
+#[test]
+fn checks_know_tags_and_patterns() {
+    let dir = crate::testing::TempDir::new("check selectors");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(1));
+    let mut state = crate::testing::app_state();
+    let mut drums = Player::new(tone, "war drums".to_string(), &state.mixer).unwrap();
+    drums.tags.insert("battle".to_string());
+    state.players.insert("war drums".to_string(), drums);
+    state.top_group.insert("war drums".to_string());
+    let mut added = HashSet::new();
+    assert_eq!(check_command(&state, "play @battle", &mut added), None);
+    assert_eq!(check_command(&state, "stop war*", &mut added), None);
+    assert!(check_command(&state, "play @tavern", &mut added).is_some());
+    assert!(check_command(&state, "play owl*", &mut added).is_some());
+}
+
+#[test]
+fn foreach_puts_the_name_in_the_command() {
+    let words = |line: &str| shlex::split(line).unwrap();
+    assert_eq!(
+        command_for(&words("volume -v 40"), "rain").unwrap(),
+        "volume -v 40 rain"
+    );
+    assert_eq!(
+        command_for(&words("fades {} -o 5s"), "big rain").unwrap(),
+        "fades 'big rain' -o 5s"
+    );
+}
+
+#[test]
+fn sounds_are_moved_to_their_markers() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("markers");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(120));
+    let mut state = crate::testing::app_state();
+    let horn = Player::new(tone, "horn".to_string(), &state.mixer).unwrap();
+    state.players.insert("horn".to_string(), horn);
+    state.top_group.insert("horn".to_string());
+    let run = |state: &mut AppState, line: &str| crate::respond(state, line, false);
+    assert!(
+        run(&mut state, "mark add horn climax 1m20s")
+            .unwrap()
+            .mutated
+    );
+    assert!(run(&mut state, "mark add horn end 3m").is_err());
+    assert!(run(&mut state, "seek horn --marker climax").is_err());
+    let near = |state: &AppState, at: u64| {
+        let play_time = state.players["horn"].get_play_time();
+        Duration::from_secs(at) <= play_time && play_time < Duration::from_secs(at + 1)
+    };
+    run(&mut state, "play horn").unwrap();
+    run(&mut state, "seek horn --marker climax").unwrap();
+    assert!(near(&state, 80));
+    // a position is never taken for a marker, or the other way around
+    run(&mut state, "seek horn -t 1:00").unwrap();
+    assert!(near(&state, 60));
+    assert!(run(&mut state, "seek horn -t climax").is_err());
+    assert!(run(&mut state, "seek horn --marker 1:00").is_err());
+    assert!(run(&mut state, "seek horn").is_err());
+    run(&mut state, "stop horn").unwrap();
+    run(&mut state, "play horn --from climax").unwrap();
+    assert!(near(&state, 80));
+    run(&mut state, "play horn -f 1:00").unwrap();
+    assert!(near(&state, 60));
+    assert!(run(&mut state, "mark add horn 90s 1:30").is_err());
+    // bookmark is the old name of mark
+    run(&mut state, "bookmark add horn chorus 0:30").unwrap();
+    run(&mut state, "play horn -f chorus").unwrap();
+    assert!(near(&state, 30));
+    run(&mut state, "mark remove horn climax").unwrap();
+    assert!(run(&mut state, "seek horn -m climax").is_err());
+}
+
+#[test]
+fn preflight_decodes_without_the_lock() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("preflight");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(1));
+    let mut state = crate::testing::app_state();
+    let rain = Player::new(tone, "rain".to_string(), &state.mixer).unwrap();
+    state.players.insert("rain".to_string(), rain);
+    state.top_group.insert("rain".to_string());
+    let state = std::sync::Mutex::new(state);
+    let mut decoded = 0;
+    // the device may or may not be there, so only the decoding is looked at
+    let _ = preflight(&mut Shared::Locked(&state), &mut |_, _, message| {
+        if message.starts_with("decoding") {
+            assert!(state.try_lock().is_ok());
+            decoded += 1;
+        }
+    });
+    assert_eq!(decoded, 1);
+}
+
+#[test]
+fn sounds_keep_playing_in_edit_mode() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("edit mode");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(30));
+    let mut state = crate::testing::app_state();
+    for name in ["rain", "owl"] {
+        let player = Player::new(tone.clone(), name.to_string(), &state.mixer).unwrap();
+        state.players.insert(name.to_string(), player);
+        state.top_group.insert(name.to_string());
+    }
+    let rain = state.players.get_mut("rain").unwrap();
+    rain.play_at(Duration::from_secs(12)).unwrap();
+    let near = |player: &Player, at: u64| {
+        let play_time = player.get_play_time();
+        Duration::from_secs(at) <= play_time && play_time < Duration::from_secs(at + 1)
+    };
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+    edit_mode(&mut state, ids(&["rain"]), Vec::new()).unwrap();
+    let rain = &state.players["rain"];
+    assert!(rain.is_editing() && rain.get_is_playing() && near(rain, 12));
+    // when only some are in edit mode, all of them go into it
+    edit_mode(&mut state, ids(&["rain", "owl"]), Vec::new()).unwrap();
+    assert!(state.players.values().all(Player::is_editing));
+    edit_mode(&mut state, ids(&["rain", "owl"]), Vec::new()).unwrap();
+    let rain = &state.players["rain"];
+    assert!(!rain.is_editing() && rain.get_is_playing() && near(rain, 12));
+    assert!(!state.players["owl"].get_is_playing());
+}
+
+#[test]
+fn loading_without_questions_replaces_the_soundscape() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("load without questions");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(5));
+    let mut state = crate::testing::app_state();
+    let horn = Player::new(tone.clone(), "horn".to_string(), &state.mixer).unwrap();
+    state.players.insert("horn".to_string(), horn);
+    state.top_group.insert("horn".to_string());
+    let save_path = dir.join("tavern.json");
+    save(&mut state, &save_path, false).unwrap();
+
+    let mut state = crate::testing::app_state();
+    let rain = Player::new(tone, "rain".to_string(), &state.mixer).unwrap();
+    state.players.insert("rain".to_string(), rain);
+    state.top_group.insert("rain".to_string());
+    config::set_confirms(false);
+    // unsaved, with something loaded, so it would ask both whether to add and whether to overwrite
+    let result = load(&mut state, &save_path, false, false, &mut |_, _, _| {});
+    config::set_confirms(true);
+    result.unwrap();
+    assert_eq!(state.players.keys().collect::<Vec<_>>(), ["horn"]);
+    assert_eq!(state.top_group, IndexSet::from(["horn".to_string()]));
+}
+
+#[test]
+fn scene_volumes_take_db_like_volume() {
+    crate::INTERACTIVE.set(false);
+    let dir = crate::testing::TempDir::new("scene volume");
+    let tone = dir.join("tone.wav");
+    crate::testing::write_tone(&tone, Duration::from_secs(5));
+    let mut state = crate::testing::app_state();
+    let rain = Player::new(tone, "rain".to_string(), &state.mixer).unwrap();
+    state.players.insert("rain".to_string(), rain);
+    state.top_group.insert("rain".to_string());
+    let calm = Scene {
+        name: "calm".to_string(),
+        players: IndexMap::from([(
+            "rain".to_string(),
+            SceneEntry {
+                volume: None,
+                loop_settings: None,
+            },
+        )]),
+    };
+    state.scenes.insert("calm".to_string(), calm);
+    let volume = |state: &AppState| state.scenes["calm"].players["rain"].volume;
+    crate::respond(&mut state, "scene volume calm rain -v -6dB", false).unwrap();
+    assert_eq!(volume(&state), Some(crate::player::volume_for_db(-6.0)));
+    // like volume, it isn't held to 200
+    crate::respond(&mut state, "scene volume calm rain -v 250", false).unwrap();
+    assert_eq!(volume(&state), Some(250));
+}
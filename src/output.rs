@@ -0,0 +1,40 @@
+use std::cell::RefCell;
+
+// what a command prints goes to the terminal, unless whoever gave the command wants it back, like
+// a client of the server
+thread_local! {static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) }}
+
+pub fn say(text: &str) {
+    CAPTURED.with_borrow_mut(|captured| match captured {
+        Some(captured) => {
+            captured.push_str(text);
+            captured.push('\n');
+        }
+        None => println!("{text}"),
+    });
+}
+
+pub fn is_captured() -> bool {
+    CAPTURED.with_borrow(|captured| captured.is_some())
+}
+
+// runs something, and returns what it said instead of printing it
+pub fn capture<T>(run: impl FnOnce() -> T) -> (T, String) {
+    let outer = CAPTURED.replace(Some(String::new()));
+    let result = run();
+    let captured = CAPTURED.replace(outer).unwrap_or_default();
+    (result, captured)
+}
+
+#[test]
+fn output_is_captured() {
+    assert!(!is_captured());
+    let ((), outer) = capture(|| {
+        say("outer");
+        let ((), inner) = capture(|| say("inner"));
+        assert_eq!(inner, "inner\n");
+        assert!(is_captured());
+    });
+    assert_eq!(outer, "outer\n");
+    assert!(!is_captured());
+}
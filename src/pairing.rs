@@ -0,0 +1,324 @@
+use anyhow::Error;
+use chrono::Local;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::IpAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::config::config_dir;
+use crate::progress;
+
+// long enough to walk over to the laptop, short enough that an old code isn't lying around
+const CODE_LIFETIME: Duration = Duration::from_secs(120);
+// a six digit code can be guessed, given enough tries
+const CODE_ATTEMPTS: u32 = 5;
+// how long pairing is refused to an address after that many wrong tries in a row from it,
+// whichever code they were for, so asking for new codes doesn't give a guesser new tries
+const LOCKOUT: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Client {
+    pub name: String,
+    pub paired: String,
+}
+
+// the code that is shown in the terminal, for the client that asked for it
+struct Code {
+    code: String,
+    name: String,
+    expires: Instant,
+}
+
+// how the tries from one address went
+#[derive(Default)]
+struct Attempts {
+    // wrong codes since the last pairing that worked
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+// remotes on other machines have to pair before they can control anything. A client asks with a
+// name, the terminal shows a short code, and once the client sends that code back, it gets a
+// token to connect with from then on. Remotes on this machine don't need one. Codes and wrong
+// tries are kept by the address they came from, so one client can't keep the others from pairing
+#[derive(Default)]
+pub struct Pairing {
+    // by token
+    clients: IndexMap<String, Client>,
+    path: Option<PathBuf>,
+    codes: HashMap<IpAddr, Code>,
+    attempts: HashMap<IpAddr, Attempts>,
+}
+
+// codes and tokens have to be unguessable, so they come from the random numbers of the system
+fn random_u64() -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|err| Error::msg(format!("error: could not get random numbers. {err}")))?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// whether a Host header names this machine, with or without a port. A web page that got its
+// name pointed at 127.0.0.1 still sends its own name here
+pub fn is_local_host(host: &str) -> bool {
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map_or("", |(host, _)| host),
+        None => host.rsplit_once(':').map_or(host, |(host, _)| host),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+// whether a page was served from this machine. A page that has no origin to give, like a file or
+// a sandboxed frame, sends null, which isn't
+pub fn is_local_origin(origin: &str) -> bool {
+    origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+        .is_some_and(is_local_host)
+}
+
+fn trusted_clients_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("trusted_clients.json"))
+}
+
+impl Pairing {
+    // a missing file means nothing is paired yet
+    pub fn load() -> Result<Self, Error> {
+        let path = trusted_clients_path();
+        let clients = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(text)) => serde_json::from_str(&text).map_err(|err| {
+                Error::msg(format!(
+                    "error: could not read the trusted remotes at {}. {err}",
+                    path.as_ref().unwrap().display()
+                ))
+            })?,
+            Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(Error::msg(format!(
+                    "error: could not open the trusted remotes at {}. {err}",
+                    path.as_ref().unwrap().display()
+                )))
+            }
+            _ => IndexMap::new(),
+        };
+        Ok(Self {
+            clients,
+            path,
+            ..Default::default()
+        })
+    }
+
+    fn persist(&self) -> Result<(), Error> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(path, serde_json::to_string_pretty(&self.clients)?))
+            .map_err(|err| {
+                Error::msg(format!(
+                    "error: could not save the trusted remotes to {}. {err}",
+                    path.display()
+                ))
+            })
+    }
+
+    pub fn clients(&self) -> impl Iterator<Item = &Client> {
+        self.clients.values()
+    }
+
+    // returns who is connecting. Whether a client is on this machine takes more than its address,
+    // since every page in a browser here connects from here too, so the servers decide that
+    pub fn authorize(&self, local: bool, token: Option<&str>) -> Result<String, Error> {
+        if local {
+            return Ok("this computer".to_string());
+        }
+        token
+            .and_then(|token| self.clients.get(token))
+            .map(|client| client.name.clone())
+            .ok_or(Error::msg(
+                "error: this remote is not paired. Ask for a code with name=<NAME>, and send back the code shown in the terminal with code=<CODE>.",
+            ))
+    }
+
+    // also throws away the codes and lockouts that ran out, so they don't pile up
+    fn check_lockout(&mut self, peer: IpAddr, now: Instant) -> Result<(), Error> {
+        self.codes.retain(|_, pending| pending.expires > now);
+        self.attempts.retain(|_, attempts| {
+            attempts.failures > 0 || attempts.locked_until.is_some_and(|until| until > now)
+        });
+        match self.attempts.get_mut(&peer) {
+            Some(Attempts {
+                locked_until: Some(until),
+                ..
+            }) if *until > now => Err(Error::msg(format!(
+                "error: there were too many wrong codes. Try again in {} seconds.",
+                (*until - now).as_secs() + 1
+            ))),
+            Some(attempts) => {
+                attempts.locked_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    // shows a new code in the terminal. While a code is waiting, the same address gets no new one,
+    // so it can't keep the terminal busy with codes
+    pub fn start(&mut self, peer: IpAddr, name: &str, now: Instant) -> Result<String, Error> {
+        self.check_lockout(peer, now)?;
+        if let Some(pending) = self.codes.get(&peer) {
+            return Err(Error::msg(format!(
+                "error: {} is pairing already. Try again in {} seconds.",
+                pending.name,
+                (pending.expires - now).as_secs() + 1
+            )));
+        }
+        let code = format!("{:06}", random_u64()? % 1_000_000);
+        self.codes.insert(
+            peer,
+            Code {
+                code: code.clone(),
+                name: name.to_string(),
+                expires: now + CODE_LIFETIME,
+            },
+        );
+        progress::suspend();
+        println!(
+            "\n{name} ({peer}) wants to pair as a remote. Enter this code on it to allow it: {code}. The code is valid for {} seconds.",
+            CODE_LIFETIME.as_secs()
+        );
+        Ok(code)
+    }
+
+    // trades the code for a token. A client that pairs again under the same name replaces the
+    // old one, so a phone can be paired again after its browser forgot the token
+    pub fn complete(
+        &mut self,
+        peer: IpAddr,
+        code: &str,
+        now: Instant,
+    ) -> Result<(String, String), Error> {
+        self.check_lockout(peer, now)?;
+        let Some(pending) = self.codes.get(&peer) else {
+            return Err(Error::msg(
+                "error: there is no code waiting. Ask for a new one with name=<NAME>.",
+            ));
+        };
+        if pending.code != code.trim() {
+            let attempts = self.attempts.entry(peer).or_default();
+            attempts.failures += 1;
+            if attempts.failures.is_multiple_of(CODE_ATTEMPTS) {
+                attempts.locked_until = Some(now + LOCKOUT);
+                self.codes.remove(&peer);
+                return Err(Error::msg(format!(
+                    "error: that is not the code. There were too many wrong tries, so pairing is refused for {} seconds.",
+                    LOCKOUT.as_secs()
+                )));
+            }
+            return Err(Error::msg("error: that is not the code."));
+        }
+        let token = format!("{:016x}{:016x}", random_u64()?, random_u64()?);
+        let name = self.codes.remove(&peer).unwrap().name;
+        self.attempts.remove(&peer);
+        self.clients.retain(|_, client| client.name != name);
+        self.clients.insert(
+            token.clone(),
+            Client {
+                name: name.clone(),
+                paired: Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            },
+        );
+        self.persist()?;
+        progress::suspend();
+        println!("\n{name} is paired.");
+        Ok((name, token))
+    }
+
+    pub fn forget(&mut self, name: &str) -> Result<(), Error> {
+        let before = self.clients.len();
+        self.clients.retain(|_, client| client.name != name);
+        if self.clients.len() == before {
+            return Err(Error::msg(format!(
+                "error: no paired remote found with name {name}"
+            )));
+        }
+        self.persist()
+    }
+}
+
+#[test]
+fn clients_pair_with_the_code() {
+    let mut pairing = Pairing::default();
+    let now = Instant::now();
+    let phone: IpAddr = "192.168.1.20".parse().unwrap();
+    let tablet: IpAddr = "192.168.1.21".parse().unwrap();
+    assert!(pairing.authorize(true, None).is_ok());
+    assert!(pairing.authorize(false, None).is_err());
+
+    let code = pairing.start(phone, "phone", now).unwrap();
+    assert_eq!(code.len(), 6);
+    // a code that is waiting can't be replaced
+    assert!(pairing.start(phone, "intruder", now).is_err());
+    assert!(pairing.complete(phone, "not it", now).is_err());
+    // nor sent back from somewhere else
+    assert!(pairing.complete(tablet, &code, now).is_err());
+    let (name, token) = pairing.complete(phone, &code, now).unwrap();
+    assert_eq!(name, "phone");
+    assert_eq!(pairing.authorize(false, Some(&token)).unwrap(), "phone");
+    assert!(pairing.authorize(false, Some("guessed")).is_err());
+    // the code only works once
+    assert!(pairing.complete(phone, &code, now).is_err());
+
+    // waiting too long throws the code away
+    let code = pairing.start(tablet, "tablet", now).unwrap();
+    assert!(pairing
+        .complete(tablet, &code, now + CODE_LIFETIME)
+        .is_err());
+    // wrong tries count across codes, and too many of them stop pairing for a while
+    let later = now + CODE_LIFETIME;
+    pairing.start(tablet, "tablet", later).unwrap();
+    assert!(pairing.complete(tablet, "wrong", later).is_err());
+    let code = pairing
+        .start(tablet, "tablet", later + CODE_LIFETIME)
+        .unwrap();
+    for _ in 1..CODE_ATTEMPTS {
+        assert!(pairing
+            .complete(tablet, "wrong", later + CODE_LIFETIME)
+            .is_err());
+    }
+    assert!(pairing
+        .complete(tablet, &code, later + CODE_LIFETIME)
+        .is_err());
+    let later = later + CODE_LIFETIME;
+    assert!(pairing
+        .start(tablet, "tablet", later + LOCKOUT / 2)
+        .is_err());
+    // but only for the address they came from
+    let code = pairing.start(phone, "phone", later + LOCKOUT / 2).unwrap();
+    assert!(pairing.complete(phone, &code, later + LOCKOUT / 2).is_ok());
+    let code = pairing.start(tablet, "tablet", later + LOCKOUT).unwrap();
+    assert!(pairing.complete(tablet, &code, later + LOCKOUT).is_ok());
+
+    pairing.forget("phone").unwrap();
+    assert!(pairing.authorize(false, Some(&token)).is_err());
+    assert!(pairing.forget("phone").is_err());
+}
+
+#[test]
+fn only_this_machine_is_local() {
+    assert!(is_local_host("localhost:7880"));
+    assert!(is_local_host("127.0.0.1"));
+    assert!(is_local_host("[::1]:7880"));
+    assert!(!is_local_host("evil.example:7880"));
+    assert!(!is_local_host("192.168.1.10:7880"));
+    assert!(is_local_origin("http://localhost:8000"));
+    assert!(is_local_origin("https://127.0.0.1"));
+    assert!(!is_local_origin("https://evil.example"));
+    assert!(!is_local_origin("http://127.0.0.1.evil.example"));
+    assert!(!is_local_origin("null"));
+}
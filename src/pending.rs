@@ -0,0 +1,142 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::player::{duration_to_string, Player};
+
+// the things a player is going to do by itself, without another command
+pub enum Action {
+    // playing, but still waiting out its delay
+    DelayedStart,
+    // a fade that is running. A fade out stops the sound once it is done
+    Fade { stops: bool },
+    // a fade out that pauses the sound once it is done
+    PausingFade,
+    // a play that came during the cooldown of the sound
+    QueuedTrigger,
+}
+
+pub struct Pending {
+    pub player: String,
+    pub action: Action,
+    pub left: Duration,
+    // a paused sound doesn't get any closer to its start
+    pub paused: bool,
+}
+
+impl Pending {
+    pub fn id(&self) -> String {
+        match self.action {
+            Action::DelayedStart => format!("delay:{}", self.player),
+            Action::Fade { .. } | Action::PausingFade => format!("fade:{}", self.player),
+            Action::QueuedTrigger => format!("queued:{}", self.player),
+        }
+    }
+
+    // the name of a player cancels everything that player has pending
+    pub fn matches(&self, id: &str) -> bool {
+        self.id() == id || self.player == id
+    }
+
+    pub fn describe(&self) -> String {
+        let what = match self.action {
+            Action::DelayedStart => "starts",
+            Action::Fade { stops: true } => "fades out and stops",
+            Action::Fade { stops: false } => "finishes fading",
+            Action::PausingFade => "fades out and pauses",
+            Action::QueuedTrigger => "plays again",
+        };
+        let paused = if self.paused { ", when resumed" } else { "" };
+        format!(
+            "{}\n\t{} {what} in {}{paused}",
+            self.id(),
+            self.player,
+            duration_to_string(self.left, false)
+        )
+    }
+}
+
+// everything that is about to happen, the soonest first
+pub fn collect(players: &HashMap<String, Player>) -> Vec<Pending> {
+    let mut pending = Vec::new();
+    for (name, player) in players {
+        if let Some(left) = player.get_delay_left() {
+            pending.push(Pending {
+                player: name.clone(),
+                action: Action::DelayedStart,
+                left,
+                paused: player.get_is_paused(),
+            });
+        }
+        if let Some((left, stops)) = player.get_fade_left() {
+            pending.push(Pending {
+                player: name.clone(),
+                action: if player.is_pausing() {
+                    Action::PausingFade
+                } else {
+                    Action::Fade { stops }
+                },
+                left,
+                paused: false,
+            });
+        }
+        if let Some(left) = player.get_queued_left() {
+            pending.push(Pending {
+                player: name.clone(),
+                action: Action::QueuedTrigger,
+                left,
+                paused: false,
+            });
+        }
+    }
+    sort(&mut pending);
+    pending
+}
+
+fn sort(pending: &mut [Pending]) {
+    pending.sort_by(|a, b| a.left.cmp(&b.left).then_with(|| a.id().cmp(&b.id())));
+}
+
+#[test]
+fn pending_actions_are_matched_and_sorted() {
+    let mut pending = vec![
+        Pending {
+            player: "rain".to_string(),
+            action: Action::Fade { stops: true },
+            left: Duration::from_secs(3),
+            paused: false,
+        },
+        Pending {
+            player: "rain".to_string(),
+            action: Action::DelayedStart,
+            left: Duration::from_secs(3),
+            paused: false,
+        },
+        Pending {
+            player: "bells".to_string(),
+            action: Action::DelayedStart,
+            left: Duration::from_secs(1),
+            paused: true,
+        },
+    ];
+    sort(&mut pending);
+    let ids: Vec<String> = pending.iter().map(Pending::id).collect();
+    assert_eq!(ids, ["delay:bells", "delay:rain", "fade:rain"]);
+
+    assert!(pending[2].matches("fade:rain"));
+    assert!(pending[2].matches("rain"));
+    assert!(!pending[2].matches("delay:rain"));
+    assert_eq!(
+        pending[0].describe(),
+        "delay:bells\n\tbells starts in 1s, when resumed"
+    );
+    let pausing = Pending {
+        player: "owl".to_string(),
+        action: Action::PausingFade,
+        left: Duration::from_secs(3),
+        paused: false,
+    };
+    assert!(pausing.matches("fade:owl"));
+    assert_eq!(
+        pausing.describe(),
+        "fade:owl\n\towl fades out and pauses in 3s"
+    );
+}
@@ -3,21 +3,81 @@
 use anyhow::Error;
 use clap::Parser;
 use duration_human::DurationHuman;
-use fomat_macros::fomat;
+use fomat_macros::wite;
+use indexmap::{IndexMap, IndexSet};
 use paste::item;
-use rodio::{source::Zero, Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::{
+    source::{self, Zero},
+    Decoder, Sink, Source,
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::RefCell,
+    fmt::{self, Display, Formatter},
     fs::File,
-    io::{self, BufReader},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    ops::Range,
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime},
+};
+use symphonia::core::{
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
 };
 
-use crate::readline;
+use crate::analysis::{self, MonoCompatibility};
+use crate::cooldown::{Cooldown, Trigger};
+use crate::download;
+use crate::effects::{EffectControl, Effects};
+use crate::fade::{FadeControl, Faded};
+use crate::handles::{self, Handle};
+use crate::limiter::{from_db, to_db};
+use crate::meter::{Level, Meter, MeterControl};
+use crate::mixer::Mixer;
+use crate::playhead::PlayHead;
+use crate::project;
+use crate::remap;
+use crate::repeat::Repeated;
+use crate::settings::Settings;
+use crate::stretch::Stretched;
+use crate::waveform;
+use crate::{progress, readline};
+
+// analysing a whole album-length track would take too long, and the start says enough
+const MAX_ANALYSIS_LENGTH: Duration = Duration::from_secs(10 * 60);
+
+// cancelling a fade doesn't jump to the full volume, which would click
+const CANCEL_RAMP: Duration = Duration::from_millis(200);
+
+// the formats that rodio can decode with its default features
+pub const SUPPORTED_EXTENSIONS: [&str; 4] = ["mp3", "wav", "ogg", "flac"];
+
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
 
-#[derive(Serialize, Deserialize)]
+// why a sound couldn't be decoded to do something with it, like play it. A file of a format that
+// can't be decoded is told apart from one that is broken
+pub fn decode_error(media: &Path, action: &str) -> Error {
+    if is_supported(media) {
+        return Error::msg(format!(
+            "error: cannot {action} {}. The data is corrupt, or it isn't the format its extension says.",
+            media.display()
+        ));
+    }
+    let format = match media.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{ext} files are"),
+        None => "files without an extension are".to_string(),
+    };
+    Error::msg(format!(
+        "error: cannot {action} {}. {format} not supported. troubadour can decode {} and {} files.",
+        media.display(),
+        SUPPORTED_EXTENSIONS[..SUPPORTED_EXTENSIONS.len() - 1].join(", "),
+        SUPPORTED_EXTENSIONS[SUPPORTED_EXTENSIONS.len() - 1]
+    ))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Serializable {
     media: PathBuf,
     name: String,
@@ -25,29 +85,210 @@ pub struct Serializable {
     volume: u32,
     looping: bool,
     loop_length: Option<Duration>,
+    // how many times a looping sound plays before it stops. None loops it forever
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loop_count: Option<u32>,
+    // the part of the sound that loops after the intro, from the start of the sound up to its end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loop_start: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    loop_end: Option<Duration>,
     delay_length: Duration,
     take_length: Option<Duration>,
     skip_length: Duration,
+    #[serde(default = "default_width")]
+    width: u32,
+    #[serde(default = "default_speed")]
+    speed: f32,
+    #[serde(default)]
+    pan: f32,
+    // measured once, so loading a save doesn't decode every sound again
+    #[serde(default)]
+    loudness: Option<f32>,
+    // counted once for formats that don't tell their length, for the same reason
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media_length: Option<Duration>,
+    // the size of the file and the time it last changed when it was counted, so it is counted again
+    // when it changed. Older saves don't have it, and keep the length they counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    media_stamp: Option<(u64, SystemTime)>,
+    #[serde(default)]
+    normalize: bool,
+    #[serde(default)]
+    tags: IndexSet<String>,
+    #[serde(default)]
+    cooldown: Option<Cooldown>,
+    #[serde(default)]
+    stretch: Option<Duration>,
+    // what the player sets itself, instead of taking it from its group
+    #[serde(default, skip_serializing_if = "Settings::is_empty")]
+    settings: Settings,
+    // saves from before markers were renamed call them bookmarks
+    #[serde(
+        default,
+        alias = "bookmarks",
+        skip_serializing_if = "IndexMap::is_empty"
+    )]
+    markers: IndexMap<String, Duration>,
+    // the web address the sound was downloaded from, so it can be downloaded again where the file
+    // is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_url: Option<String>,
+    // the same for the sound of a video, which is downloaded differently
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    video_url: Option<String>,
+    // where the play head was, when the sound was playing as it was saved. See load
+    // --resume-playback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    playing_at: Option<Duration>,
+}
+
+impl Serializable {
+    // a player that hasn't been made yet, with everything but these at the defaults
+    pub fn new(
+        media: PathBuf,
+        name: String,
+        group: Option<String>,
+        volume: u32,
+        looping: bool,
+    ) -> Self {
+        Self {
+            media,
+            name,
+            group,
+            volume,
+            looping,
+            loop_length: None,
+            loop_count: None,
+            loop_start: None,
+            loop_end: None,
+            delay_length: Duration::ZERO,
+            take_length: None,
+            skip_length: Duration::ZERO,
+            width: default_width(),
+            speed: default_speed(),
+            pan: 0.0,
+            loudness: None,
+            media_length: None,
+            media_stamp: None,
+            normalize: false,
+            tags: IndexSet::new(),
+            cooldown: None,
+            stretch: None,
+            settings: Settings::default(),
+            markers: IndexMap::new(),
+            source_url: None,
+            video_url: None,
+            playing_at: None,
+        }
+    }
+
+    pub fn playing_at(&self) -> Option<Duration> {
+        self.playing_at
+    }
+
+    // the length that was counted before, unless the file changed since
+    fn counted_length(&self, media: &Path) -> Option<Duration> {
+        match (self.media_stamp, stamp_of(media)) {
+            (Some(counted), now) if Some(counted) != now => None,
+            _ => self.media_length,
+        }
+    }
+
+    pub fn media(&self) -> &Path {
+        &self.media
+    }
+
+    pub fn set_media(&mut self, media: PathBuf) {
+        self.media = media;
+    }
+
+    // returns whether the path changed
+    pub fn remap(&mut self, remaps: &IndexMap<String, String>) -> bool {
+        match remap::apply(&self.media, remaps) {
+            Some(media) => {
+                self.media = media;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn default_width() -> u32 {
+    100
+}
+
+fn default_speed() -> f32 {
+    1.0
 }
 
 pub struct Player {
-    stream: OutputStream,
-    handle: OutputStreamHandle,
     sink: Sink,
     media: PathBuf,
-    file_handle: RefCell<File>,
-    last_time_poll: Option<Instant>,
-    time_at_last_poll: Duration,
+    // not every format knows its length up front
+    media_length: Option<Duration>,
+    // what the file was like when its length was found, see Serializable
+    media_stamp: Option<(u64, SystemTime)>,
+    play_head: PlayHead,
     pub name: String,
     pub group: Option<String>,
+    // the volume link this player is in, if any
+    pub link: Option<String>,
+    pub tags: IndexSet<String>,
+    // named points in the sound that it can be played from, like the chorus of a song
+    pub markers: IndexMap<String, Duration>,
+    // the web address the sound was downloaded from, if it was
+    pub source_url: Option<String>,
+    // the address of the video the sound was taken from, if it was
+    pub video_url: Option<String>,
     playing: bool,
     paused: bool,
     volume: u32,
+    // the volume the active scene plays the sound at instead, if it has one for it
+    scene_volume: Option<u32>,
     looping: bool,
     loop_length: Option<Duration>,
+    // how many times a looping sound plays before it stops. None loops it forever
+    loop_count: Option<u32>,
+    // the part of the sound that loops after the intro, from the start of the sound up to its end
+    loop_start: Option<Duration>,
+    loop_end: Option<Duration>,
     delay_length: Duration,
     take_length: Option<Duration>,
     skip_length: Duration,
+    width: u32,
+    // the sink speeds up everything it plays, so the play head keeps counting in the time of the sound
+    speed: f32,
+    pan: f32,
+    // the average level of the sound in dB. None when it hasn't been measured, or for silence
+    loudness: Option<f32>,
+    // whether the volume is corrected for the loudness, so all sounds play at a comparable level
+    normalize: bool,
+    fade: FadeControl,
+    // turns the sound down while another group is heard, on top of any fade
+    ducking: FadeControl,
+    duck_gain: f32,
+    effects: EffectControl,
+    meter: MeterControl,
+    // plays into the edit bus instead of the master output, so changes can be tried out unheard
+    editing: bool,
+    cooldown: Option<Cooldown>,
+    // one pass through the sound is stretched to this length, without changing the pitch
+    stretch: Option<Duration>,
+    // the settings it has of its own. The rest come from its group, see settings
+    pub settings: Settings,
+    // when the sound was last triggered with play, for the cooldown
+    last_trigger: Option<Instant>,
+    // a trigger that came during the cooldown, and plays once it is over
+    queued: bool,
+    // how long the held back trigger fades in
+    queued_fade: Duration,
+    // fading out to be paused, which happens once the fade is done
+    pausing: bool,
+    // a fade to silence that is going, with the volume the sink keeps until it is done. A fade is
+    // a gain on top of the volume of the sink, so it can't ramp down from a sink that is silent
+    silencing: Option<f32>,
 }
 
 macro_rules! optional {
@@ -85,16 +326,6 @@ macro_rules! as_builder {
     };
 }
 
-fn get_device_stuff() -> Result<(OutputStream, OutputStreamHandle, Sink), Error> {
-    let (stream, handle) = OutputStream::try_default().or(Err(Error::msg(
-        "error: failed to set up up your audio device.",
-    )))?;
-    let sink = Sink::try_new(&handle).or(Err(Error::msg(
-        "error: failed to set up up your audio device.",
-    )))?;
-    Ok((stream, handle, sink))
-}
-
 fn convert_file_error(path: &Path, err: &io::Error) -> Error {
     let path_dis = path.display();
     match err.kind() {
@@ -116,25 +347,29 @@ struct FileLocation {
     path: Option<PathBuf>,
 }
 
-fn file_user_fallback(mut path: PathBuf, name: &String) -> Result<(File, PathBuf), Error> {
+// only checks that the file can be opened. It is opened again whenever the player needs it, so
+// idle players don't hold on to a file handle
+fn file_user_fallback(mut path: PathBuf, name: &String) -> Result<PathBuf, Error> {
     loop {
-        let file = File::open(&path).map_err(|err| convert_file_error(&path, &err));
+        let file =
+            File::open(download::readable(&path)).map_err(|err| convert_file_error(&path, &err));
         if let Err(err) = file {
+            progress::suspend();
             println!("{err}");
             path = loop {
-                let new_path = readline(&format!(
+                // without anyone to answer, like at the end of the input, there is no use in asking again
+                let line = readline(&format!(
                     "Type in new path for {name} (leave empty to skip): "
-                ))
-                .and_then(|line| {
-                    shlex::split(&line).ok_or_else(|| {
+                ))?;
+                let new_path = shlex::split(&line)
+                    .ok_or_else(|| {
                         Error::msg(
                             "error: cannot parse input. Perhaps you have erronous quotation(\"\")?",
                         )
                     })
-                })
-                .and_then(|line| {
-                    FileLocation::try_parse_from(line).map_err(|e| Error::msg(e.to_string()))
-                });
+                    .and_then(|line| {
+                        FileLocation::try_parse_from(line).map_err(|e| Error::msg(e.to_string()))
+                    });
                 if let Err(err) = new_path {
                     println!("{err}");
                 } else if matches!(new_path, Ok(FileLocation { path: None })) {
@@ -144,33 +379,67 @@ fn file_user_fallback(mut path: PathBuf, name: &String) -> Result<(File, PathBuf
                 }
             };
         } else {
-            break Ok((file.unwrap(), path));
+            break Ok(path);
         }
     }
 }
 
 impl Player {
-    pub fn new(media: PathBuf, name: String) -> Result<Self, Error> {
-        let (stream, handle, sink) = get_device_stuff()?;
-        let (file, media) = file_user_fallback(media, &name)?;
+    pub fn new(media: PathBuf, name: String, mixer: &Mixer) -> Result<Self, Error> {
+        Self::with_sink(media, name, mixer.new_sink()?)
+    }
+
+    // for a player that is made without holding on to the mixer, with a sink it gave out before
+    pub fn with_sink(media: PathBuf, name: String, sink: Sink) -> Result<Self, Error> {
+        let media = file_user_fallback(media, &name)?;
+        // a file that can't be decoded is refused right away, instead of when it is played
+        let file = handles::open(&media).map_err(|err| convert_file_error(&media, &err))?;
+        let decoder = Decoder::new(file).map_err(|_| decode_error(&media, "add"))?;
+        let media_length = length_of(decoder, &media, None);
         Ok(Self {
             name,
             group: None,
+            link: None,
+            tags: IndexSet::new(),
+            markers: IndexMap::new(),
+            source_url: None,
+            video_url: None,
+            media_length,
+            media_stamp: stamp_of(&media),
             media,
-            file_handle: RefCell::new(file),
             playing: false,
             paused: false,
             volume: 100,
+            scene_volume: None,
             looping: false,
             loop_length: None,
+            loop_count: None,
+            loop_start: None,
+            loop_end: None,
             delay_length: Duration::from_secs(0),
             take_length: None,
             skip_length: Duration::from_secs(0),
-            stream,
-            handle,
             sink,
-            last_time_poll: None,
-            time_at_last_poll: Duration::from_secs(0),
+            play_head: PlayHead::new(),
+            width: 100,
+            speed: 1.0,
+            pan: 0.0,
+            loudness: None,
+            normalize: false,
+            fade: FadeControl::new(),
+            ducking: FadeControl::new(),
+            duck_gain: 1.0,
+            effects: EffectControl::new(),
+            meter: MeterControl::new(),
+            editing: false,
+            stretch: None,
+            cooldown: None,
+            settings: Settings::default(),
+            last_trigger: None,
+            queued: false,
+            queued_fade: Duration::ZERO,
+            pausing: false,
+            silencing: None,
         })
     }
 
@@ -182,35 +451,98 @@ impl Player {
             volume: self.volume,
             looping: self.looping,
             loop_length: self.loop_length,
+            loop_count: self.loop_count,
+            loop_start: self.loop_start,
+            loop_end: self.loop_end,
             delay_length: self.delay_length,
             take_length: self.take_length,
             skip_length: self.skip_length,
+            width: self.width,
+            speed: self.speed,
+            pan: self.pan,
+            loudness: self.loudness,
+            media_length: self.media_length,
+            media_stamp: self.media_stamp,
+            normalize: self.normalize,
+            tags: self.tags.clone(),
+            cooldown: self.cooldown,
+            stretch: self.stretch,
+            settings: self.settings,
+            markers: self.markers.clone(),
+            source_url: self.source_url.clone(),
+            video_url: self.video_url.clone(),
+            playing_at: self.get_is_playing().then(|| self.get_play_time()),
         }
     }
 
-    pub fn from_serializable(player: &Serializable) -> Result<Self, Error> {
-        let (stream, handle, sink) = get_device_stuff()?;
-        let (file, media) = file_user_fallback(player.media.clone(), &player.name)?;
+    // a sound that was downloaded is downloaded again when it is missing, a video into the media
+    // folder of the project like when it was added
+    pub fn from_serializable(
+        player: &Serializable,
+        mixer: &Mixer,
+        project_dir: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let sink = mixer.new_sink()?;
+        let media = match (&player.source_url, &player.video_url) {
+            _ if player.media.is_file() => player.media.clone(),
+            (Some(url), _) => download::fetch(url)?,
+            (_, Some(url)) => {
+                let dir = project_dir.map(|dir| dir.join(project::MEDIA_DIR));
+                download::fetch_video(url, dir.as_deref(), &mut |_, _, _| {})?
+            }
+            _ => player.media.clone(),
+        };
+        let media = file_user_fallback(media, &player.name)?;
+        let counted = player.counted_length(&media);
         let mut new_player = Self {
             name: player.name.clone(),
             group: player.group.clone(),
+            link: None,
+            tags: player.tags.clone(),
+            markers: player.markers.clone(),
+            source_url: player.source_url.clone(),
+            video_url: player.video_url.clone(),
+            media_length: probe_length(&media, counted),
+            media_stamp: stamp_of(&media),
             media,
-            file_handle: RefCell::new(file),
             playing: false,
             paused: false,
             volume: player.volume,
+            scene_volume: None,
             looping: player.looping,
             loop_length: player.loop_length,
+            loop_count: player.loop_count,
+            loop_start: player.loop_start,
+            loop_end: player.loop_end,
             delay_length: player.delay_length,
             take_length: player.take_length,
             skip_length: player.skip_length,
-            stream,
-            handle,
             sink,
-            last_time_poll: None,
-            time_at_last_poll: Duration::from_secs(0),
+            play_head: PlayHead::new(),
+            width: player.width,
+            speed: player.speed,
+            pan: player.pan,
+            loudness: player.loudness,
+            normalize: player.normalize,
+            fade: FadeControl::new(),
+            ducking: FadeControl::new(),
+            duck_gain: 1.0,
+            effects: EffectControl::new(),
+            meter: MeterControl::new(),
+            editing: false,
+            stretch: player.stretch,
+            cooldown: player.cooldown,
+            settings: player.settings,
+            last_trigger: None,
+            queued: false,
+            queued_fade: Duration::ZERO,
+            pausing: false,
+            silencing: None,
         };
         new_player.volume(player.volume);
+        new_player.width(player.width);
+        new_player.set_speed(player.speed);
+        new_player.set_pan(player.pan);
         Ok(new_player)
     }
 
@@ -234,28 +566,56 @@ impl Player {
         pub fn loop_length(&mut self, length: Option<Duration>){
             self.loop_length = length;
         }
+
+        pub fn loop_count(&mut self, count: Option<u32>) {
+            self.loop_count = count;
+        }
+
+        pub fn loop_region(&mut self, region: Option<(Duration, Duration)>) {
+            (self.loop_start, self.loop_end) = region.unzip();
+        }
     }
 
-    fn apply_settings_internal(
+    // the sound with the cuts, loop and delay applied, starting at the given point
+    fn open_decoder(&self) -> Result<Decoder<BufReader<Handle>>, Error> {
+        let media =
+            handles::open(&self.media).map_err(|err| convert_file_error(&self.media, &err))?;
+        Decoder::new(media).map_err(|_| decode_error(&self.media, "play"))
+    }
+
+    fn build_source(
         &self,
-        start_immediately: bool,
         start_at: Duration,
-    ) -> Result<(), Error> {
-        // possible edge case: prev buffer reads from file at same time as this operation, causing a race condition?
-        let is_empty = self.sink.empty();
-        let file = File::open(&self.media).map_err(|err| convert_file_error(&self.media, &err))?;
-        self.file_handle.replace(file);
-        let media = BufReader::new(
-            self.file_handle
-                .borrow()
-                .try_clone()
-                .map_err(|err| convert_file_error(&self.media, &err))?,
-        );
-        let decoder = Decoder::new(media).map_err(|_| {
-            Error::msg(
-                "error: cannot play file. The format might not be supported, or the data is corrupt.",
-            )
-        })?;
+    ) -> Result<Box<dyn Source<Item = f32> + Send>, Error> {
+        let decoder = self.open_decoder()?;
+
+        // picks up where the play head is. The delay only happens once, before the first loop
+        let delay_left = self.delay_length.saturating_sub(start_at);
+        let elapsed = start_at.saturating_sub(self.delay_length);
+        let (passes_done, position) = self
+            .pass_at(elapsed)
+            .map_or((0, elapsed), |(passes_done, position, _)| {
+                (passes_done, position)
+            });
+        // a sound that loops a number of times has fewer passes left the further it got
+        let passes_left = self
+            .loop_count
+            .unwrap_or(1)
+            .saturating_sub(passes_done)
+            .max(1);
+        if let Some((loop_start, loop_end)) = self.get_loop_region() {
+            let source = self.build_region_source(
+                decoder,
+                loop_start..loop_end,
+                passes_done,
+                passes_left,
+                position,
+            )?;
+            if delay_left > Duration::ZERO {
+                return Ok(Box::new(source.delay(delay_left)));
+            }
+            return Ok(source);
+        }
 
         optional!(
             self.take_length.is_some() && self.take_length.unwrap() > Duration::from_secs(0) && (
@@ -276,15 +636,102 @@ impl Player {
                 decoder_padded.take_duration(self.loop_length.unwrap())
             },
         optional!(
-            self.looping,
+            self.looping && self.loop_count.is_none(),
             let decoder = {decoder.repeat_infinite()},
-        optional!(start_at > self.skip_length,
-            let decoder = decoder.skip_duration(start_at - self.skip_length),
         optional!(
-            self.delay_length > Duration::from_secs(0),
-            let decoder = decoder.delay(self.delay_length),
-        self.sink.append(decoder)
-        ))))));
+            self.looping && self.loop_count.is_some(),
+            let decoder = Repeated::new(decoder, passes_left),
+        optional!(position > Duration::from_secs(0),
+            let decoder = decoder.skip_duration(position),
+        optional!(
+            delay_left > Duration::from_secs(0),
+            let decoder = decoder.delay(delay_left),
+        Ok(Box::new(decoder.convert_samples()))
+        )))))))
+    }
+
+    // the intro plays once, from the start of the sound up to the end of the region, and then the
+    // region loops. Takes where the play head is in the pass it is in
+    fn build_region_source(
+        &self,
+        decoder: Decoder<BufReader<Handle>>,
+        region: Range<Duration>,
+        passes_done: u32,
+        passes_left: u32,
+        position: Duration,
+    ) -> Result<Box<dyn Source<Item = f32> + Send>, Error> {
+        let mut parts: Vec<Box<dyn Source<Item = f32> + Send>> = Vec::new();
+        let mut loops = passes_left;
+        if passes_done == 0 {
+            let intro = decoder
+                .skip_duration(self.skip_length)
+                .take_duration(region.end.saturating_sub(self.skip_length))
+                .skip_duration(position);
+            parts.push(Box::new(intro.convert_samples()));
+            loops -= 1;
+        }
+        if self.loop_count.is_none() || loops > 0 {
+            let looped = self
+                .open_decoder()?
+                .skip_duration(region.start)
+                .take_duration(region.end - region.start);
+            let looped: Box<dyn Source<Item = f32> + Send> = match self.loop_count {
+                Some(_) => Box::new(Repeated::new(looped, loops).convert_samples()),
+                None => Box::new(looped.repeat_infinite().convert_samples()),
+            };
+            if passes_done > 0 {
+                parts.push(Box::new(looped.skip_duration(position)));
+            } else {
+                parts.push(looped);
+            }
+        }
+        Ok(Box::new(source::from_iter(parts)))
+    }
+
+    // the sound as it would be heard when played from the start, for rendering it offline
+    pub fn render_source(&self) -> Result<Box<dyn Source<Item = f32> + Send>, Error> {
+        let source = Effects::new(self.build_source(Duration::ZERO)?, self.effects.clone());
+        let source: Box<dyn Source<Item = f32> + Send> = match self.get_stretch_factor() {
+            Some(factor) => Box::new(Stretched::new(source, factor)),
+            None => Box::new(source),
+        };
+        Ok(Box::new(source.speed(self.speed).amplify(
+            real_volume(self.volume) * self.normalization_gain(),
+        )))
+    }
+
+    fn apply_settings_internal(
+        &self,
+        start_immediately: bool,
+        start_at: Duration,
+    ) -> Result<(), Error> {
+        // a sound that isn't playing or paused has nothing to rebuild, and doesn't hold on to its
+        // file. It is built again when it is played
+        if !start_immediately && !self.paused {
+            self.sink.clear();
+            return Ok(());
+        }
+        // possible edge case: prev buffer reads from file at same time as this operation, causing a race condition?
+        let is_empty = self.sink.empty();
+        let source = self.build_source(start_at)?;
+        let tracked = self.play_head.track(
+            Meter::new(
+                Faded::new(
+                    Faded::new(
+                        Effects::new(source, self.effects.clone()),
+                        self.fade.clone(),
+                    ),
+                    self.ducking.clone(),
+                ),
+                self.meter.clone(),
+            ),
+            start_at,
+        );
+        // stretching comes after the play head, so that it keeps counting in the time of the sound
+        match self.get_stretch_factor() {
+            Some(factor) => self.sink.append(Stretched::new(tracked, factor)),
+            None => self.sink.append(tracked),
+        }
 
         if !is_empty {
             self.sink.skip_one();
@@ -307,12 +754,10 @@ impl Player {
         self.apply_settings_internal(self.get_is_playing() || play_if_not_playing, play_time)
     }
 
-    //TODO: an implementation of get_play_time() which relies on the play data, instead of the time crate
+    // counted in the samples that have been played, including the delay
     pub fn get_play_time(&self) -> Duration {
-        if self.get_is_playing() && self.last_time_poll.is_some() {
-            self.time_at_last_poll + self.last_time_poll.unwrap().elapsed()
-        } else if !self.get_is_playing() && self.get_is_paused() {
-            self.time_at_last_poll
+        if self.get_is_playing() || self.get_is_paused() {
+            self.play_head.position()
         } else {
             Duration::from_secs(0)
         }
@@ -326,6 +771,251 @@ impl Player {
         self.playing && !self.sink.empty() && !self.paused && !self.sink.is_paused()
     }
 
+    pub fn get_media(&self) -> &Path {
+        &self.media
+    }
+
+    pub fn get_volume(&self) -> u32 {
+        self.volume
+    }
+
+    pub fn get_scene_volume(&self) -> Option<u32> {
+        self.scene_volume
+    }
+
+    // the volume the sound is heard at, which the scene can override
+    pub fn get_heard_volume(&self) -> u32 {
+        self.scene_volume.unwrap_or(self.volume)
+    }
+
+    pub fn get_looping(&self) -> bool {
+        self.looping
+    }
+
+    pub fn get_loop_length(&self) -> Option<Duration> {
+        self.loop_length
+    }
+
+    pub fn get_loop_count(&self) -> Option<u32> {
+        self.loop_count
+    }
+
+    // the part that loops after the intro. None when the sound doesn't loop, or loops as a whole.
+    // An end set with set-end cuts the region off, so neither the intro nor the loop go past it
+    pub fn get_loop_region(&self) -> Option<(Duration, Duration)> {
+        if !self.looping {
+            return None;
+        }
+        let (start, end) = (self.loop_start?, self.loop_end?);
+        let end = match self.take_length.filter(|take| !take.is_zero()) {
+            Some(take) => end.min(take),
+            None => end,
+        };
+        (start < end).then_some((start, end))
+    }
+
+    // whether a loop region is set, even while the sound doesn't loop
+    pub fn has_loop_region(&self) -> bool {
+        self.loop_start.is_some()
+    }
+
+    pub fn get_start(&self) -> Duration {
+        self.skip_length
+    }
+
+    pub fn get_end(&self) -> Option<Duration> {
+        self.take_length
+    }
+
+    pub fn get_delay(&self) -> Duration {
+        self.delay_length
+    }
+
+    // the time between two loop points, as far as it is known without decoding the whole sound
+    pub fn get_loop_period(&self) -> Option<Duration> {
+        if !self.looping {
+            return None;
+        }
+        self.get_play_length()
+    }
+
+    // the length of one pass through the sound, with the cuts and the loop length applied. With a
+    // loop region, that of one pass through the region
+    pub fn get_play_length(&self) -> Option<Duration> {
+        if let Some((start, end)) = self.get_loop_region() {
+            return Some(end - start);
+        }
+        if let (true, Some(length)) = (self.looping, self.loop_length) {
+            return Some(length);
+        }
+        self.get_effective_length()
+    }
+
+    // the length of the part of the file that plays, between the start and the end it is cut to
+    pub fn get_effective_length(&self) -> Option<Duration> {
+        Some(self.get_end_point()?.saturating_sub(self.skip_length))
+    }
+
+    // where the sound ends in the file, the end it is cut to or else the end of the file
+    fn get_end_point(&self) -> Option<Duration> {
+        self.take_length
+            .filter(|take| !take.is_zero())
+            .or(self.media_length)
+    }
+
+    // how many passes a looping sound has done this long after its delay, how far it is into the
+    // pass it is in, and how long that pass is. With a loop region, the intro is the first pass
+    pub fn pass_at(&self, elapsed: Duration) -> Option<(u32, Duration, Duration)> {
+        let within = |elapsed: Duration, period: Duration| {
+            (
+                (elapsed.as_nanos() / period.as_nanos()) as u32,
+                Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64),
+            )
+        };
+        let period = self.get_loop_period().filter(|period| !period.is_zero())?;
+        let Some((_, end)) = self.get_loop_region() else {
+            let (passes_done, position) = within(elapsed, period);
+            return Some((passes_done, position, period));
+        };
+        let intro = end.saturating_sub(self.skip_length);
+        if elapsed < intro {
+            return Some((0, elapsed, intro));
+        }
+        let (passes_done, position) = within(elapsed - intro, period);
+        Some((passes_done + 1, position, period))
+    }
+
+    // how far the current pass has gotten, and how long it is. None during the delay
+    fn get_pass_position(&self) -> Option<(Duration, Duration)> {
+        if !self.get_is_playing() && !self.get_is_paused() {
+            return None;
+        }
+        let elapsed = self.get_play_time().checked_sub(self.delay_length)?;
+        if !self.looping {
+            let length = self.get_play_length().filter(|length| !length.is_zero())?;
+            return Some((elapsed.min(length), length));
+        }
+        let (passes_done, position, length) = self.pass_at(elapsed)?;
+        match self.loop_count {
+            Some(count) if passes_done >= count => Some((length, length)),
+            _ => Some((position, length)),
+        }
+    }
+
+    // which pass a sound that loops a number of times is in, and how many it has
+    pub fn get_pass(&self) -> Option<(u32, u32)> {
+        let count = self.loop_count.filter(|_| self.looping)?;
+        let elapsed = self.get_play_time().checked_sub(self.delay_length)?;
+        let (passes_done, _, _) = self.pass_at(elapsed)?;
+        Some(((passes_done + 1).min(count), count))
+    }
+
+    // how long until the sound is heard, while it waits out its delay
+    pub fn get_delay_left(&self) -> Option<Duration> {
+        if !self.get_is_playing() && !self.get_is_paused() {
+            return None;
+        }
+        let waited = self.get_play_time();
+        (waited < self.delay_length).then(|| self.delay_length - waited)
+    }
+
+    // how loud the sound is right now, as it goes into the mixer
+    pub fn current_level(&self) -> Level {
+        if !self.get_is_playing() {
+            return Level::default();
+        }
+        self.meter.level().scaled(self.sink.volume())
+    }
+
+    pub fn get_fade_left(&self) -> Option<(Duration, bool)> {
+        if !self.get_is_playing() {
+            return None;
+        }
+        self.fade.remaining()
+    }
+
+    // the time until a looping sound reaches its next loop point, or until any other sound ends
+    pub fn get_remaining_time(&self) -> Option<Duration> {
+        if !self.get_is_playing() && !self.get_is_paused() {
+            return None;
+        }
+        let waited = self.get_play_time();
+        if waited < self.delay_length {
+            let first_pass = match self.pass_at(Duration::ZERO) {
+                Some((_, _, length)) => length,
+                None => self.get_play_length()?,
+            };
+            return Some(self.delay_length - waited + first_pass);
+        }
+        let (position, length) = self.get_pass_position()?;
+        Some(length - position)
+    }
+
+    // from 0 at the start of the current pass, to 1 at its end. 0 when the length is unknown
+    pub fn get_loop_progress(&self) -> f32 {
+        self.get_pass_position()
+            .map(|(position, length)| position.as_secs_f32() / length.as_secs_f32())
+            .unwrap_or(0.0)
+    }
+
+    // loop points near the current start and end, where the loop doesn't click. None when the part
+    // that plays is too short to loop
+    pub fn find_loop_points(&self) -> Result<Option<(Duration, Duration)>, Error> {
+        let end = self.get_end_point().ok_or_else(|| {
+            Error::msg(format!(
+                "error: the length of {} is unknown. Use set-end first.",
+                self.name
+            ))
+        })?;
+        let media =
+            handles::open(&self.media).map_err(|err| convert_file_error(&self.media, &err))?;
+        let decoder = Decoder::new(media).map_err(|_| decode_error(&self.media, "analyse"))?;
+        let (channels, sample_rate) = (decoder.channels() as usize, decoder.sample_rate());
+        let mut samples = decoder.convert_samples::<f32>();
+        let frames = std::iter::from_fn(move || {
+            let mut sum = 0.0;
+            for _ in 0..channels {
+                sum += samples.next()?;
+            }
+            Some(sum / channels as f32)
+        });
+        Ok(analysis::find_loop_points(
+            frames,
+            sample_rate,
+            self.skip_length,
+            end,
+        ))
+    }
+
+    // only looks at the part of the sound that actually plays. Returns None for sounds that aren't stereo
+    pub fn analyze_mono_compatibility(&self) -> Result<Option<MonoCompatibility>, Error> {
+        let media =
+            handles::open(&self.media).map_err(|err| convert_file_error(&self.media, &err))?;
+        let decoder = Decoder::new(media).map_err(|_| decode_error(&self.media, "analyse"))?;
+        if decoder.channels() != 2 {
+            return Ok(None);
+        }
+        let take = self
+            .take_length
+            .filter(|take| !take.is_zero())
+            .unwrap_or(Duration::MAX);
+        let samples = decoder
+            .convert_samples::<f32>()
+            .take_duration(take)
+            .skip_duration(self.skip_length)
+            .take_duration(MAX_ANALYSIS_LENGTH);
+        Ok(MonoCompatibility::measure(samples))
+    }
+
+    // the loudest peaks of the whole sound, ignoring the cuts, in as many buckets as asked for
+    pub fn compute_peaks(&self, buckets: usize) -> Result<Vec<f32>, Error> {
+        Ok(waveform::envelope(&self.media)?.downsample(buckets))
+    }
+
+    pub fn get_width(&self) -> u32 {
+        self.width
+    }
+
     pub fn play(&mut self) -> Result<(), Error> {
         if self.get_is_playing() {
             return Ok(());
@@ -333,44 +1023,608 @@ impl Player {
         if self.get_is_paused() {
             self.sink.play();
         } else {
-            self.time_at_last_poll = Duration::from_secs(0);
+            self.play_head.reset();
+            if !self.fade.is_fading() {
+                self.fade.reset();
+            }
             self.apply_settings_in_place(true)?;
         }
-        self.last_time_poll = Some(Instant::now());
         self.playing = true;
         self.paused = false;
         Ok(())
     }
 
+    // plays from a point in the sound, like a marker, even when it is already playing. The
+    // delay is skipped, since the sound is picked up in the middle
+    pub fn play_from(&mut self, position: Duration, fade_in: Duration) -> Result<(), Error> {
+        self.pausing = false;
+        if fade_in.is_zero() {
+            self.fade.reset();
+        } else {
+            self.fade.start(0.0, 1.0, fade_in, false);
+        }
+        let start_at = self.delay_length + position.saturating_sub(self.skip_length);
+        self.apply_settings_internal(true, start_at)?;
+        self.playing = true;
+        self.paused = false;
+        Ok(())
+    }
+
+    // moves the play head to a point in the sound, counted from the start of the file. A paused
+    // sound stays paused, and a fade that is going keeps going
+    pub fn seek(&mut self, position: Duration) -> Result<(), Error> {
+        let start_at = self.delay_length + position.saturating_sub(self.skip_length);
+        self.apply_settings_internal(self.get_is_playing(), start_at)
+    }
+
+    // picks the sound up where the play head was, counted like get_play_time, so a sound that was
+    // saved during its delay waits out the rest of it
+    pub fn play_at(&mut self, play_time: Duration) -> Result<(), Error> {
+        self.pausing = false;
+        self.fade.reset();
+        self.apply_settings_internal(true, play_time)?;
+        self.playing = true;
+        self.paused = false;
+        Ok(())
+    }
+
+    // the length of the whole file, when the format tells
+    pub fn get_media_length(&self) -> Option<Duration> {
+        self.media_length
+    }
+
+    // play, as given by whoever plays the sound, which the cooldown applies to. The sound fades in
+    // over the given length, if it isn't zero
+    pub fn trigger(&mut self, fade_in: Duration) -> Result<Trigger, Error> {
+        let since_last = self.last_trigger.map(|last| last.elapsed());
+        let trigger = self
+            .cooldown
+            .map_or(Trigger::Play, |cooldown| cooldown.trigger(since_last));
+        match trigger {
+            Trigger::Play => {
+                self.last_trigger = Some(Instant::now());
+                self.queued = false;
+                if fade_in.is_zero() {
+                    self.play()?;
+                } else {
+                    self.fade_in(fade_in)?;
+                }
+            }
+            Trigger::Queue => {
+                self.queued = true;
+                self.queued_fade = fade_in;
+            }
+            Trigger::Ignore => {}
+        }
+        Ok(trigger)
+    }
+
+    // plays a trigger that was held back by the cooldown, once it is over
+    pub fn poll_queued(&mut self) -> Result<(), Error> {
+        if self.queued && self.get_cooldown_left().is_none() {
+            self.trigger(self.queued_fade)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_cooldown_left(&self) -> Option<Duration> {
+        self.cooldown?
+            .left(self.last_trigger.map(|last| last.elapsed()))
+    }
+
+    // how long until a held back trigger plays the sound
+    pub fn get_queued_left(&self) -> Option<Duration> {
+        self.queued
+            .then(|| self.get_cooldown_left().unwrap_or_default())
+    }
+
+    pub fn cancel_queued(&mut self) {
+        self.queued = false;
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: Option<Cooldown>) {
+        self.cooldown = cooldown;
+        if cooldown.is_none() {
+            self.queued = false;
+        }
+    }
+
+    pub fn get_cooldown(&self) -> Option<Cooldown> {
+        self.cooldown
+    }
+
+    // returns true once, when a sound that was playing has reached its end by itself
+    pub fn poll_finished(&mut self) -> bool {
+        if !self.playing || !self.sink.empty() {
+            return false;
+        }
+        let faded_out = self.fade.has_stopped();
+        self.stop();
+        !faded_out
+    }
+
     pub fn pause(&mut self) {
+        self.end_silencing();
         if self.get_is_playing() {
-            self.time_at_last_poll = self.get_play_time();
-            self.last_time_poll = Some(Instant::now());
             self.sink.pause();
             self.paused = true;
             self.playing = false;
         }
+        // it is resumed at its volume, also when it is paused halfway through a fade out to pause
+        if self.pausing {
+            self.pausing = false;
+            self.fade.reset();
+        }
+    }
+
+    // fades the sound out, and pauses it once that is done, so that it is resumed at its volume
+    pub fn fade_pause(&mut self, duration: Duration) {
+        if !self.get_is_playing() || duration.is_zero() {
+            self.pause();
+        } else {
+            self.fade.start_from_current(0.0, duration, false);
+            self.pausing = true;
+        }
+    }
+
+    // pauses a sound that has faded out to be paused
+    pub fn poll_pausing(&mut self) {
+        if self.pausing && !self.fade.is_fading() {
+            self.pause();
+        }
+        // a fade out to stop that was started on top of it stops the sound instead
+        if self.silencing.is_some() && !self.fade.is_fading() && !self.fade.has_stopped() {
+            self.end_silencing();
+        }
+    }
+
+    // the sink gets the volume that was faded to, and the fade is no longer needed
+    fn end_silencing(&mut self) {
+        if self.silencing.take().is_some() {
+            self.apply_volume();
+            self.fade.reset();
+        }
+    }
+
+    pub fn is_pausing(&self) -> bool {
+        self.pausing
     }
 
     pub fn stop(&mut self) {
+        self.end_silencing();
+        self.pausing = false;
         self.playing = false;
         self.paused = false;
-        self.last_time_poll = None;
-        self.time_at_last_poll = Duration::from_secs(0);
+        self.queued = false;
+        self.play_head.reset();
+        self.fade.reset();
         self.sink.clear();
     }
 
     pub fn volume(&mut self, volume: u32) {
+        self.end_silencing();
         self.volume = volume;
-        let real_volume = f32::powf(
-            2.0,
-            f32::sqrt(f32::sqrt(f32::sqrt(volume as f32 / 100.0))).mul_add(192.0, -192.0) / 6.0,
-        );
-        self.sink.set_volume(real_volume);
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        self.sink
+            .set_volume(real_volume(self.get_heard_volume()) * self.normalization_gain());
+    }
+
+    fn normalization_gain(&self) -> f32 {
+        match (self.normalize, self.loudness) {
+            (true, Some(loudness)) => analysis::normalization_gain(loudness),
+            _ => 1.0,
+        }
+    }
+
+    // decodes the sound to find out how loud it is on average
+    pub fn measure_loudness(&mut self) -> Result<(), Error> {
+        let media =
+            handles::open(&self.media).map_err(|err| convert_file_error(&self.media, &err))?;
+        let decoder = Decoder::new(media).map_err(|_| decode_error(&self.media, "analyse"))?;
+        let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+        let samples = decoder
+            .convert_samples::<f32>()
+            .take_duration(MAX_ANALYSIS_LENGTH);
+        self.loudness = analysis::measure_loudness(samples, channels, sample_rate);
+        Ok(())
+    }
+
+    pub fn get_loudness(&self) -> Option<f32> {
+        self.loudness
+    }
+
+    // how loud the sound is heard, in dB, after normalization and its volume
+    pub fn heard_loudness(&self) -> Option<f32> {
+        let gain = real_volume(self.volume) * self.normalization_gain();
+        self.loudness.map(|loudness| loudness + 20.0 * gain.log10())
+    }
+
+    pub fn set_normalize(&mut self, normalize: bool) {
+        self.normalize = normalize;
+        self.volume(self.volume);
+    }
+
+    pub fn get_normalize(&self) -> bool {
+        self.normalize
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+        self.sink.set_speed(speed);
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_stretch(&mut self, length: Option<Duration>) {
+        self.stretch = length;
+    }
+
+    pub fn get_stretch(&self) -> Option<Duration> {
+        self.stretch
+    }
+
+    // how much longer the sound is made. None when it isn't stretched
+    pub fn get_stretch_factor(&self) -> Option<f64> {
+        let natural = self.get_play_length().filter(|length| !length.is_zero())?;
+        let factor = self.stretch?.as_secs_f64() / natural.as_secs_f64();
+        ((factor - 1.0).abs() > 1e-4).then_some(factor)
+    }
+
+    // the time of the sound, as it is heard, after the speed and the stretch
+    fn heard(&self, time: Duration) -> Duration {
+        time.mul_f64(self.get_stretch_factor().unwrap_or(1.0))
+            .div_f32(self.speed)
+    }
+
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan = pan;
+        self.effects.set_pan(pan);
+    }
+
+    pub fn get_pan(&self) -> f32 {
+        self.pan
+    }
+
+    // 0% folds the sound to mono, 100% leaves it as it is, and above that it gets wider
+    pub fn width(&mut self, width: u32) {
+        self.width = width;
+        self.effects.set_width(width as f32 / 100.0);
+    }
+
+    pub fn fade_in(&mut self, duration: Duration) -> Result<(), Error> {
+        if self.get_is_playing() {
+            return Ok(());
+        }
+        self.fade.start(0.0, 1.0, duration, false);
+        self.play()
+    }
+
+    pub fn fade_out(&mut self, duration: Duration) {
+        if !self.get_is_playing() || duration.is_zero() {
+            self.stop();
+        } else {
+            self.fade.start_from_current(0.0, duration, true);
+        }
+    }
+
+    // going down takes the attack, coming back up the release
+    pub fn duck(&mut self, gain: f32, attack: Duration, release: Duration) {
+        if gain == self.duck_gain {
+            return;
+        }
+        let ramp = if gain < self.duck_gain {
+            attack
+        } else {
+            release
+        };
+        self.duck_gain = gain;
+        self.ducking.start_from_current(gain, ramp, false);
+    }
+
+    pub fn is_ducked(&self) -> bool {
+        self.duck_gain < 1.0
+    }
+
+    // moves the sound to the edit bus, where it isn't heard, or back to the master output. It keeps
+    // playing where it was
+    pub fn set_editing(&mut self, editing: bool, mixer: &Mixer) -> Result<(), Error> {
+        if editing == self.editing {
+            return Ok(());
+        }
+        let sink = if editing {
+            mixer.new_edit_sink()
+        } else {
+            mixer.new_sink()?
+        };
+        let (playing, paused) = (self.get_is_playing(), self.get_is_paused());
+        let play_time = self.get_play_time();
+        sink.set_volume(self.sink.volume());
+        sink.set_speed(self.speed);
+        std::mem::replace(&mut self.sink, sink).stop();
+        self.editing = editing;
+        if playing || paused {
+            self.apply_settings_internal(playing, play_time)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    // a fade that is called off brings the sound back to its volume setting, and keeps it playing
+    pub fn cancel_fade(&mut self) {
+        // a fade to silence is already where it would be brought back to
+        if self.silencing.is_some() {
+            self.end_silencing();
+            return;
+        }
+        self.pausing = false;
+        self.fade.start_from_current(1.0, CANCEL_RAMP, false);
+    }
+
+    // changes the volume setting right away, but ramps the gain so that what you hear gets there gradually
+    pub fn fade_volume(&mut self, volume: u32, duration: Duration) {
+        self.fade_to(duration, |player| player.volume = volume);
+    }
+
+    // a scene can play the sound at another volume than its own, without changing its own. None
+    // goes back to its own volume
+    pub fn fade_scene_volume(&mut self, volume: Option<u32>, duration: Duration) {
+        self.fade_to(duration, |player| player.scene_volume = volume);
+    }
+
+    // the volume the sink is at, before normalisation
+    fn held_volume(&self) -> f32 {
+        self.silencing
+            .unwrap_or_else(|| real_volume(self.get_heard_volume()))
+    }
+
+    // the volume that is heard right now, with the fade that is going
+    fn heard_now(&self) -> f32 {
+        self.held_volume() * self.fade.gain()
+    }
+
+    fn fade_to(&mut self, duration: Duration, change: impl FnOnce(&mut Self)) {
+        let held = self.held_volume();
+        let heard = held * self.fade.gain();
+        change(self);
+        let target = real_volume(self.get_heard_volume());
+        // a sound that is fading out to stop or pause keeps doing so
+        let stopping = self.pausing || self.fade.remaining().is_some_and(|(_, stops)| stops);
+        if !self.get_is_playing() || duration.is_zero() {
+            self.end_silencing();
+            self.apply_volume();
+        } else if stopping {
+            // the volume is applied once the sound has stopped
+            if self.silencing.is_none() {
+                self.apply_volume();
+            }
+        } else if self.get_heard_volume() > 0 {
+            // starting over from what is heard calls off a fade that was still going
+            self.silencing = None;
+            self.apply_volume();
+            self.fade.start(heard / target, 1.0, duration, false);
+        } else {
+            // the sink keeps what it has, and the fade takes the sound down to silence instead
+            self.silencing = Some(held);
+            self.fade.start(heard / held, 0.0, duration, false);
+        }
+    }
+
+    // fades out a sound that isn't in the scene that is switched to. It is at its own volume again
+    // the next time it plays
+    pub fn leave_scene(&mut self, duration: Duration) {
+        let heard = self.heard_now();
+        self.end_silencing();
+        self.scene_volume = None;
+        self.apply_volume();
+        let own = real_volume(self.volume);
+        if self.get_is_playing() && !duration.is_zero() && self.volume > 0 {
+            self.fade.start(heard / own, 0.0, duration, true);
+        } else {
+            self.stop();
+        }
     }
 }
 
-fn duration_to_string(dur: Duration, no_smaller_than_secs: bool) -> String {
+// decodes the whole sound, so a file that is missing, or that breaks off halfway, turns up before
+// it is needed
+pub fn check_media(media: &Path) -> Result<(), Error> {
+    let file = handles::open(media).map_err(|err| convert_file_error(media, &err))?;
+    let decoder = Decoder::new(file).map_err(|_| decode_error(media, "decode"))?;
+    decoder.for_each(drop);
+    Ok(())
+}
+
+// the length of the file. Formats that don't tell, like mp3 files without a header that says, are
+// decoded to count their samples, unless that was done before
+pub fn probe_length(media: &Path, counted: Option<Duration>) -> Option<Duration> {
+    length_of(
+        Decoder::new(handles::open(media).ok()?).ok()?,
+        media,
+        counted,
+    )
+}
+
+// the length the decoder knows, or else the one the file says in its headers, or else the one that
+// was counted before, or else it is counted
+fn length_of(
+    decoder: Decoder<BufReader<Handle>>,
+    media: &Path,
+    counted: Option<Duration>,
+) -> Option<Duration> {
+    if let Some(length) = decoder.total_duration() {
+        return Some(length);
+    }
+    if let Some(length) = header_length(media, decoder.sample_rate()) {
+        return Some(length);
+    }
+    if counted.is_some() {
+        return counted;
+    }
+    let samples_per_second = decoder.channels() as u64 * decoder.sample_rate() as u64;
+    if samples_per_second == 0 {
+        return None;
+    }
+    // counting takes a while for a long sound, so it shows how far it got
+    let file_name = media.file_name().unwrap_or_default().to_string_lossy();
+    let mut samples = 0;
+    for _ in decoder {
+        samples += 1;
+        if samples % (samples_per_second * 10) == 0 {
+            progress::note(format!(
+                "counting the length of {file_name} ({} so far)",
+                duration_to_string(Duration::from_secs(samples / samples_per_second), false)
+            ));
+        }
+    }
+    Some(Duration::from_secs_f64(
+        samples as f64 / samples_per_second as f64,
+    ))
+}
+
+// the size of a file and when it last changed, to tell whether it is still the same file
+fn stamp_of(media: &Path) -> Option<(u64, SystemTime)> {
+    let metadata = std::fs::metadata(media).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+// the decoders of mp3 and ogg files don't know how long they are, but it can be read from the file
+// without decoding it: an mp3 has the number of frames in its Xing header, or else it is estimated
+// from its bit rate, and the last page of an ogg has the number of samples before its end. The end of
+// a sound that is still being downloaded isn't in yet
+fn header_length(media: &Path, sample_rate: u32) -> Option<Duration> {
+    if download::in_progress(media).is_some() {
+        return None;
+    }
+    let extension = media.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "mp3" => mp3_length(media),
+        "ogg" if sample_rate > 0 => {
+            let samples = last_granule(&read_tail(media, OGG_TAIL)?)?;
+            Some(Duration::from_secs_f64(samples as f64 / sample_rate as f64))
+        }
+        _ => None,
+    }
+}
+
+fn mp3_length(media: &Path) -> Option<Duration> {
+    let stream = MediaSourceStream::new(Box::new(File::open(media).ok()?), Default::default());
+    let mut hint = Hint::new();
+    hint.with_extension("mp3");
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let params = &probed.format.default_track()?.codec_params;
+    let (frames, sample_rate) = (params.n_frames?, params.sample_rate?);
+    Some(Duration::from_secs_f64(frames as f64 / sample_rate as f64))
+}
+
+// the last page of an ogg is at most 64 KiB, and usually much less
+const OGG_TAIL: u64 = 64 * 1024;
+
+fn read_tail(media: &Path, length: u64) -> Option<Vec<u8>> {
+    let mut file = File::open(media).ok()?;
+    let size = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(size.saturating_sub(length)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    Some(tail)
+}
+
+// the granule position of the last ogg page that has one, which is the number of samples a channel
+// has up to the end of that page
+fn last_granule(tail: &[u8]) -> Option<u64> {
+    (0..tail.len().saturating_sub(13))
+        .rev()
+        .filter(|&at| &tail[at..at + 4] == b"OggS" && tail[at + 4] == 0)
+        .map(|at| u64::from_le_bytes(tail[at + 6..at + 14].try_into().unwrap()))
+        // pages in the middle of a packet have no position
+        .find(|&granule| granule != u64::MAX)
+}
+
+pub fn real_volume(volume: u32) -> f32 {
+    f32::powf(
+        2.0,
+        f32::sqrt(f32::sqrt(f32::sqrt(volume as f32 / 100.0))).mul_add(192.0, -192.0) / 6.0,
+    )
+}
+
+// the volume that real_volume turns into this gain
+pub fn volume_for_gain(gain: f32) -> u32 {
+    let root = (gain.log2() * 6.0 + 192.0) / 192.0;
+    if root <= 0.0 {
+        return 0;
+    }
+    (root.powi(8) * 100.0).round() as u32
+}
+
+// the volume that changes the gain by this many dB, like -6 for half as loud in amplitude
+pub fn volume_for_db(db: f32) -> u32 {
+    volume_for_gain(from_db(db))
+}
+
+pub fn volume_to_db(volume: u32) -> f32 {
+    to_db(real_volume(volume))
+}
+
+// a volume as it is written on the command line: a percentage, like 80 or 80%, or a change in dB,
+// like -6dB, for those who are used to a mixing desk
+pub fn parse_volume(text: &str) -> Result<u32, Error> {
+    let text = text.trim();
+    let lowercase = text.to_lowercase();
+    if let Some(db) = lowercase.strip_suffix("db") {
+        let db: f32 = db
+            .trim()
+            .parse()
+            .ok()
+            .filter(|db: &f32| db.is_finite())
+            .ok_or_else(|| Error::msg(format!("{text} is not a volume in dB, like -6dB")))?;
+        return Ok(volume_for_db(db));
+    }
+    text.strip_suffix('%')
+        .unwrap_or(text)
+        .trim()
+        .parse()
+        .map_err(|_| Error::msg(format!("{text} is not a volume, like 80 or -6dB")))
+}
+
+// a volume as it is shown, with what it is in dB
+pub fn volume_to_string(volume: u32) -> String {
+    if volume == 0 {
+        return "0% (silent)".to_string();
+    }
+    format!("{volume}% ({:+.1} dB)", volume_to_db(volume))
+}
+
+// a point in a sound as it is written on a timeline, like 1:32 or 1:02:05.5
+pub fn parse_timestamp(text: &str) -> Option<Duration> {
+    let mut parts = text.rsplit(':');
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let hours: u64 = match parts.next() {
+        Some(hours) => hours.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || !(0.0..60.0).contains(&seconds) {
+        return None;
+    }
+    Some(Duration::from_secs((hours * 60 + minutes) * 60) + Duration::from_secs_f64(seconds))
+}
+
+pub fn duration_to_string(dur: Duration, no_smaller_than_secs: bool) -> String {
     let nanos = if no_smaller_than_secs {
         dur.as_secs() * 1_000_000_000
     } else {
@@ -383,9 +1637,9 @@ fn duration_to_string(dur: Duration, no_smaller_than_secs: bool) -> String {
     }
 }
 
-impl ToString for Player {
-    fn to_string(&self) -> String {
-        fomat!(
+impl Display for Player {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        wite!(f,
             (self.name) ":"
             if self.get_is_playing() {
                 "\n\tplaying"
@@ -396,14 +1650,76 @@ impl ToString for Player {
                     "\n\tnot playing"
                 }
             }
+            if self.is_ducked() {
+                " (ducked)"
+            }
+            if self.editing {
+                " (edit mode, not heard)"
+            }
             if self.get_is_playing() || self.get_is_paused() {
-                "\n\thas been playing for: " (duration_to_string(self.get_play_time(), true))
+                // the play head counts in the time of the sound, which runs faster or slower than the clock
+                "\n\thas been playing for: " (duration_to_string(self.heard(self.get_play_time()), true))
+                if let Some((pass, count)) = self.get_pass() {
+                    "\n\tpass: " (pass) " of " (count)
+                }
+                if let Some(remaining) = self.get_remaining_time() {
+                    // the last pass of a sound that loops a number of times ends it
+                    if self.looping && self.get_pass().is_none_or(|(pass, count)| pass < count) {
+                        "\n\tnext loop point in: " (duration_to_string(self.heard(remaining), true))
+                    } else {
+                        "\n\ttime left: " (duration_to_string(self.heard(remaining), true))
+                    }
+                }
+            }
+        )?;
+        wite!(f,
+            "\n\tvolume: " (volume_to_string(self.volume))
+            if let Some(volume) = self.scene_volume {
+                "\n\tvolume in this scene: " (volume_to_string(volume))
+            }
+            if self.width != 100 {
+                "\n\tstereo width: " (self.width) "%"
+            }
+            if let Some(link) = &self.link {
+                "\n\tvolume linked in: " (link)
+            }
+            if self.pan < 0.0 {
+                "\n\tpanned: " ((-self.pan * 100.0).round()) "% left"
+            }
+            if self.pan > 0.0 {
+                "\n\tpanned: " ((self.pan * 100.0).round()) "% right"
+            }
+            if self.normalize {
+                "\n\tnormalized"
+                if self.loudness.is_some() {
+                    ": " (format!("{:+.1}", 20.0 * self.normalization_gain().log10())) " dB"
+                }
+            }
+            if self.speed != 1.0 {
+                "\n\tspeed: " (self.speed) "x"
+            }
+        )?;
+        wite!(f,
+            if let Some(length) = self.media_length {
+                "\n\tlength: " (duration_to_string(length, false))
+                if let Some(effective) = self.get_effective_length().filter(|effective| *effective != length) {
+                    ", " (duration_to_string(effective, false)) " after the cuts"
+                }
+            }
+            if let Some(length) = self.stretch {
+                "\n\tstretched to: " (duration_to_string(length, false))
             }
-            "\n\tvolume: " (self.volume) "%"
             if self.looping {
                 "\n\tloops"
-                if let Some(length) = self.loop_length {
-                    ": every " (duration_to_string(length, false))
+                if let Some(count) = self.loop_count {
+                    " " (count) " times"
+                }
+                if let Some((start, end)) = self.get_loop_region() {
+                    " between " (duration_to_string(start, false)) " and "
+                    (duration_to_string(end, false)) ", after the intro"
+                }
+                if let (None, Some(period)) = (self.get_loop_region(), self.get_loop_period()) {
+                    ": every " (duration_to_string(period, false))
                 }
             }
             if self.skip_length > Duration::new(0, 0) {
@@ -417,15 +1733,34 @@ impl ToString for Player {
             if self.delay_length > Duration::new(0, 0) {
                 "\n\tdelay: "  (duration_to_string(self.delay_length, false))
             }
+            if let Some(cooldown) = self.cooldown {
+                "\n\tcooldown: " (duration_to_string(cooldown.length, false))
+                if cooldown.queue { ", queues triggers" }
+            }
+            if !self.tags.is_empty() {
+                "\n\ttags: " for tag in &self.tags { (tag) } sep { ", " }
+            }
+            if let Some(url) = self.source_url.as_ref().or(self.video_url.as_ref()) {
+                "\n\tdownloaded from: " (url)
+            }
+            if !self.markers.is_empty() {
+                "\n\tmarkers: "
+                for (name, position) in &self.markers {
+                    (name) " at " (duration_to_string(*position, false))
+                } sep { ", " }
+            }
+            (self.settings)
         )
     }
 }
 
 #[test]
 fn player_functionality() {
+    let mixer = Mixer::new();
     let mut player = Player::new(
         PathBuf::from(r"C:\Users\dexte\Music\ambience\combat\War Horn.ogg"),
         "giant".to_string(),
+        &mixer,
     )
     .unwrap();
     println!("delay");
@@ -461,3 +1796,179 @@ fn player_functionality() {
     player.play().unwrap();
     std::thread::sleep(Duration::from_secs(3));
 }
+
+#[test]
+fn volumes_fade_down_to_silence() {
+    let dir = crate::testing::TempDir::new("silence");
+    let path = dir.join("tone.wav");
+    crate::testing::write_tone(&path, Duration::from_secs(30));
+    let mixer = Mixer::new();
+    let mut player = Player::new(path, "tone".to_string(), &mixer).unwrap();
+    player.play().unwrap();
+    let full = player.heard_now();
+    player.fade_volume(0, Duration::from_secs(60));
+    // the setting changes right away, but the sound is still heard while it goes down
+    assert_eq!(player.get_volume(), 0);
+    let heard = player.heard_now();
+    assert!(heard > 0.9 * full && heard <= full, "{heard}");
+    assert!(player.sink.volume() > 0.0);
+    assert!(player.fade.is_fading());
+    // called off, it is at its setting right away
+    player.cancel_fade();
+    assert!(player.heard_now() < 1e-6);
+    assert!(player.sink.volume() < 1e-6);
+    // once a fade is done, the sink has the volume that was faded to
+    player.volume(100);
+    player.fade_volume(0, Duration::from_millis(20));
+    assert!(player.heard_now() > 0.0);
+    std::thread::sleep(Duration::from_millis(100));
+    player.poll_pausing();
+    assert!(player.heard_now() < 1e-6);
+    assert!(player.sink.volume() < 1e-6);
+    assert_eq!(player.fade.gain(), 1.0);
+    // and fades up from there again
+    player.fade_volume(100, Duration::from_secs(60));
+    assert!(player.heard_now() < 0.1 * full);
+}
+
+#[test]
+fn idle_players_dont_hold_their_file() {
+    let dir = crate::testing::TempDir::new("idle");
+    let path = dir.join("tone.wav");
+    crate::testing::write_tone(&path, Duration::from_secs(30));
+    let mixer = Mixer::new();
+    let mut player = Player::new(path, "tone".to_string(), &mixer).unwrap();
+    player.set_delay(Duration::from_secs(1));
+    player.apply_settings_in_place(false).unwrap();
+    assert!(player.sink.empty());
+    // a paused one is rebuilt, so it resumes with the new settings
+    player.play().unwrap();
+    player.pause();
+    player.set_delay(Duration::ZERO);
+    player.apply_settings_in_place(false).unwrap();
+    assert!(!player.sink.empty());
+    assert!(player.get_is_paused());
+    player.stop();
+    assert!(player.sink.empty());
+}
+
+#[test]
+fn loop_regions_play_an_intro_and_then_loop() {
+    let dir = crate::testing::TempDir::new("region");
+    let path = dir.join("tone.wav");
+    crate::testing::write_tone(&path, Duration::from_secs(10));
+    let mixer = Mixer::new();
+    let mut player = Player::new(path, "tone".to_string(), &mixer).unwrap();
+    let secs = Duration::from_secs;
+    player.loop_region(Some((secs(2), secs(6))));
+    player.toggle_loop(true);
+    player.loop_count(Some(3));
+    // the intro is the first pass, and runs from the start up to the end of the region
+    assert_eq!(player.pass_at(secs(0)), Some((0, secs(0), secs(6))));
+    assert_eq!(player.pass_at(secs(7)), Some((1, secs(1), secs(4))));
+    assert_eq!(player.pass_at(secs(14)), Some((3, secs(0), secs(4))));
+    player.skip_duration(secs(1));
+    assert_eq!(player.pass_at(secs(4)), Some((0, secs(4), secs(5))));
+    assert_eq!(player.pass_at(secs(5)), Some((1, secs(0), secs(4))));
+    player.skip_duration(secs(0));
+    // the intro and two passes through the region. Cutting rounds to whole samples, at 8000 a
+    // second
+    let length =
+        |player: &Player, start_at| player.build_source(start_at).unwrap().count() as f64 / 8000.0;
+    let near = |length: f64, expected: f64| (length - expected).abs() < 0.01;
+    assert!(near(length(&player, secs(0)), 14.0));
+    assert!(near(length(&player, secs(7)), 7.0));
+    // an end set before the end of the region cuts off the intro and the loop alike
+    player.take_duration(Some(secs(4)));
+    assert_eq!(player.get_loop_region(), Some((secs(2), secs(4))));
+    assert_eq!(player.pass_at(secs(5)), Some((1, secs(1), secs(2))));
+    assert!(near(length(&player, secs(0)), 8.0));
+    player.take_duration(Some(secs(2)));
+    assert_eq!(player.get_loop_region(), None);
+}
+
+#[test]
+fn ogg_lengths_are_read_from_the_last_page() {
+    let page = |granule: u64| {
+        let mut page = b"OggS\0\x04".to_vec();
+        page.extend(granule.to_le_bytes());
+        page.extend([0; 13]);
+        page
+    };
+    let tail = [
+        b"the rest of a page before".to_vec(),
+        page(44_100 * 3),
+        page(44_100 * 5),
+        page(u64::MAX),
+    ]
+    .concat();
+    assert_eq!(last_granule(&tail), Some(44_100 * 5));
+    assert_eq!(last_granule(b"no pages here"), None);
+    // formats that the decoder knows the length of don't need it
+    let dir = crate::testing::TempDir::new("lengths");
+    let path = dir.join("tone.wav");
+    crate::testing::write_tone(&path, Duration::from_secs(2));
+    assert_eq!(probe_length(&path, None), Some(Duration::from_secs(2)));
+    assert_eq!(header_length(&path, 8000), None);
+}
+
+#[test]
+fn lengths_are_counted_again_when_the_file_changes() {
+    let dir = crate::testing::TempDir::new("stamps");
+    let path = dir.join("rain.ogg");
+    std::fs::write(&path, b"a sound").unwrap();
+    let mut player = Serializable::new(path.clone(), "rain".to_string(), None, 100, false);
+    player.media_length = Some(Duration::from_secs(90));
+    // older saves have no stamp, and keep their length
+    assert_eq!(player.counted_length(&path), Some(Duration::from_secs(90)));
+    player.media_stamp = stamp_of(&path);
+    assert_eq!(player.counted_length(&path), Some(Duration::from_secs(90)));
+    std::fs::write(&path, b"another sound").unwrap();
+    assert_eq!(player.counted_length(&path), None);
+}
+
+#[test]
+fn timestamps_are_read_like_a_timeline() {
+    assert_eq!(parse_timestamp("1:32"), Some(Duration::from_secs(92)));
+    assert_eq!(parse_timestamp("0:05.5"), Some(Duration::from_millis(5500)));
+    assert_eq!(parse_timestamp("1:02:05"), Some(Duration::from_secs(3725)));
+    assert_eq!(parse_timestamp("92s"), None);
+    assert_eq!(parse_timestamp("1:75"), None);
+    assert_eq!(parse_timestamp("1:2:3:4"), None);
+}
+
+#[test]
+fn volume_for_gain_undoes_real_volume() {
+    for volume in [1, 20, 50, 100, 150, 300] {
+        assert_eq!(volume_for_gain(real_volume(volume)), volume);
+    }
+    assert_eq!(volume_for_gain(0.0), 0);
+}
+
+#[test]
+fn unsupported_formats_are_named() {
+    let message = |path: &str| decode_error(Path::new(path), "play").to_string();
+    assert_eq!(
+        message("theme.m4a"),
+        "error: cannot play theme.m4a. m4a files are not supported. troubadour can decode mp3, wav, ogg and flac files."
+    );
+    assert!(message("theme").contains("files without an extension are not supported"));
+    assert!(message("theme.MP3").contains("The data is corrupt"));
+}
+
+#[test]
+fn volumes_can_be_written_in_db() {
+    assert_eq!(parse_volume("80").unwrap(), 80);
+    assert_eq!(parse_volume("80%").unwrap(), 80);
+    assert_eq!(parse_volume("0dB").unwrap(), 100);
+    assert_eq!(parse_volume("-6dB").unwrap(), volume_for_db(-6.0));
+    assert_eq!(parse_volume("+3 db").unwrap(), volume_for_db(3.0));
+    assert!(parse_volume("-6").is_err());
+    assert!(parse_volume("loud").is_err());
+    assert!(parse_volume("infdB").is_err());
+    for db in [-20.0, -6.0, 0.0, 6.0] {
+        assert!((volume_to_db(volume_for_db(db)) - db).abs() < 0.5);
+    }
+    assert_eq!(volume_to_string(100), "100% (+0.0 dB)");
+    assert_eq!(volume_to_string(0), "0% (silent)");
+}
@@ -0,0 +1,152 @@
+use rodio::Source;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::control::SAMPLES_PER_UPDATE;
+
+struct PlayHeadState {
+    // sources from an older generation have been replaced, and don't get to move the play head anymore
+    generation: u64,
+    position: Duration,
+}
+
+// how far a player has gotten, counted in the samples that were actually played. Unlike a clock,
+// this stands still when the device stalls or the sink is paused
+#[derive(Clone)]
+pub struct PlayHead(Arc<Mutex<PlayHeadState>>);
+
+impl PlayHead {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(PlayHeadState {
+            generation: 0,
+            position: Duration::ZERO,
+        })))
+    }
+
+    pub fn position(&self) -> Duration {
+        self.0.lock().unwrap().position
+    }
+
+    pub fn reset(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.generation += 1;
+        state.position = Duration::ZERO;
+    }
+
+    // the returned source moves the play head, starting at `from`. Sources that were tracked
+    // before are ignored from now on
+    pub fn track<S: Source>(&self, input: S, from: Duration) -> Tracked<S>
+    where
+        S::Item: rodio::Sample,
+    {
+        let mut state = self.0.lock().unwrap();
+        state.generation += 1;
+        state.position = from;
+        Tracked {
+            samples_per_second: input.sample_rate() as f64 * input.channels() as f64,
+            input,
+            head: self.clone(),
+            generation: state.generation,
+            from,
+            samples: 0,
+            until_update: SAMPLES_PER_UPDATE,
+        }
+    }
+
+    fn publish(&self, generation: u64, position: Duration) {
+        let mut state = self.0.lock().unwrap();
+        if state.generation == generation {
+            state.position = position;
+        }
+    }
+}
+
+impl Default for PlayHead {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Tracked<S> {
+    input: S,
+    head: PlayHead,
+    generation: u64,
+    from: Duration,
+    samples: u64,
+    samples_per_second: f64,
+    until_update: usize,
+}
+
+impl<S> Tracked<S> {
+    fn publish(&self) {
+        let played = Duration::from_secs_f64(self.samples as f64 / self.samples_per_second);
+        self.head.publish(self.generation, self.from + played);
+    }
+}
+
+impl<S> Iterator for Tracked<S>
+where
+    S: Source,
+    S::Item: rodio::Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Some(sample) = self.input.next() else {
+            self.publish();
+            return None;
+        };
+        self.samples += 1;
+        self.until_update -= 1;
+        if self.until_update == 0 {
+            self.publish();
+            self.until_update = SAMPLES_PER_UPDATE;
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Tracked<S>
+where
+    S: Source,
+    S::Item: rodio::Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[test]
+fn play_head_counts_samples() {
+    let head = PlayHead::new();
+    let second = vec![0.0f32; 2 * 44_100];
+    let input = rodio::buffer::SamplesBuffer::new(2, 44_100, second.clone());
+    let mut tracked = head.track(input, Duration::from_secs(2));
+    assert_eq!(head.position(), Duration::from_secs(2));
+    tracked.by_ref().take(44_100).for_each(drop);
+    assert!(head.position().abs_diff(Duration::from_millis(2500)) < Duration::from_millis(10));
+
+    // a source that has been replaced doesn't move the play head anymore
+    let replacement = rodio::buffer::SamplesBuffer::new(2, 44_100, second);
+    let _replacement = head.track(replacement, Duration::from_secs(10));
+    tracked.for_each(drop);
+    assert_eq!(head.position(), Duration::from_secs(10));
+}
@@ -0,0 +1,122 @@
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// the members of a playlist group play one at a time, in a random order. Tracks with a higher
+// weight come up more often, and a track doesn't come back until a few others have played
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Playlist {
+    // tracks that aren't in here have a weight of 1. A weight of 0 takes a track out of the rotation
+    pub weights: IndexMap<String, u32>,
+    // a track doesn't come up again within this many picks
+    pub cooldown: usize,
+    // the latest picks, the newest last
+    #[serde(skip)]
+    recent: VecDeque<String>,
+}
+
+// a number from 0 up to 1
+pub fn random() -> f64 {
+    rand::random()
+}
+
+impl Playlist {
+    pub fn new(cooldown: usize) -> Self {
+        Self {
+            weights: IndexMap::new(),
+            cooldown,
+            recent: VecDeque::new(),
+        }
+    }
+
+    pub fn weight(&self, id: &String) -> u32 {
+        self.weights.get(id).copied().unwrap_or(1)
+    }
+
+    // a member left the group
+    pub fn forget(&mut self, id: &String) {
+        self.weights.shift_remove(id);
+        self.recent.retain(|recent| recent != id);
+    }
+
+    // picks the next track. When the cooldown rules out every track, the ones that played longest
+    // ago are allowed back in, but never the track that just played, unless it is the only one
+    pub fn pick(&mut self, members: &IndexSet<String>, random: f64) -> Option<String> {
+        let in_rotation: Vec<&String> = members.iter().filter(|id| self.weight(id) > 0).collect();
+        let mut candidates: Vec<&String> = Vec::new();
+        for cooling in (0..=self.cooldown.min(self.recent.len())).rev() {
+            let cooling_down: Vec<&String> = self.recent.iter().rev().take(cooling).collect();
+            candidates = in_rotation
+                .iter()
+                .filter(|id| !cooling_down.contains(id))
+                .copied()
+                .collect();
+            if !candidates.is_empty() {
+                break;
+            }
+        }
+        if candidates.is_empty() {
+            candidates = in_rotation;
+        }
+
+        let total: u32 = candidates.iter().map(|id| self.weight(id)).sum();
+        let mut target = random * total as f64;
+        let picked = candidates
+            .iter()
+            .find(|id| {
+                target -= self.weight(id) as f64;
+                target < 0.0
+            })
+            .or(candidates.last())?
+            .to_string();
+
+        self.recent.push_back(picked.clone());
+        while self.recent.len() > self.cooldown.max(1) {
+            self.recent.pop_front();
+        }
+        Some(picked)
+    }
+
+    // returns the next track, once the one that was playing has ended. Stopping the playlist by
+    // hand means nothing of it is running, without anything having finished, so it stays stopped
+    pub fn poll(
+        &mut self,
+        members: &IndexSet<String>,
+        finished: &[String],
+        is_running: impl Fn(&String) -> bool,
+        random: f64,
+    ) -> Option<String> {
+        if !finished.iter().any(|id| members.contains(id)) || members.iter().any(is_running) {
+            return None;
+        }
+        self.pick(members, random)
+    }
+}
+
+#[test]
+fn playlist_picks_by_weight_and_cooldown() {
+    let members: IndexSet<String> = ["tavern", "market", "harbour"].map(String::from).into();
+    let mut playlist = Playlist::new(1);
+    playlist.weights.insert("tavern".to_string(), 2);
+    playlist.weights.insert("harbour".to_string(), 0);
+
+    // tavern takes up the first two thirds of the range, market the last third
+    assert_eq!(playlist.pick(&members, 0.5).as_deref(), Some("tavern"));
+    // tavern just played, so it sits out one pick
+    assert_eq!(playlist.pick(&members, 0.1).as_deref(), Some("market"));
+    assert_eq!(playlist.pick(&members, 0.9).as_deref(), Some("tavern"));
+
+    // with every track cooling down, the one that played longest ago goes first
+    let mut strict = Playlist::new(5);
+    let two: IndexSet<String> = ["tavern", "market"].map(String::from).into();
+    assert_eq!(strict.pick(&two, 0.0).as_deref(), Some("tavern"));
+    assert_eq!(strict.pick(&two, 0.0).as_deref(), Some("market"));
+    assert_eq!(strict.pick(&two, 0.0).as_deref(), Some("tavern"));
+
+    let finished = vec!["market".to_string()];
+    assert_eq!(playlist.poll(&members, &finished, |_| true, 0.0), None);
+    assert!(playlist.poll(&members, &finished, |_| false, 0.0).is_some());
+    assert_eq!(playlist.poll(&members, &[], |_| false, 0.0), None);
+
+    assert!((0.0..1.0).contains(&random()));
+}
@@ -0,0 +1,127 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::{Mutex, Once},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{typeahead, INTERACTIVE};
+
+const FRAMES: [char; 4] = ['|', '/', '-', '\\'];
+const TICK: Duration = Duration::from_millis(100);
+// quick operations finish before the spinner would even show up
+const SHOW_AFTER: Duration = Duration::from_millis(500);
+
+struct SpinnerState {
+    message: String,
+    started: Instant,
+    suspended: bool,
+    shown: bool,
+}
+
+// there is only one terminal, so there is only ever one spinner
+static SPINNER: Mutex<Option<SpinnerState>> = Mutex::new(None);
+static TICKER: Once = Once::new();
+
+fn clear_line(state: &mut SpinnerState) {
+    if state.shown {
+        print!("\r\x1b[2K");
+        io::stdout().flush().unwrap_or_default();
+        state.shown = false;
+    }
+}
+
+fn tick() {
+    let mut frame = 0;
+    loop {
+        thread::sleep(TICK);
+        if let Some(state) = SPINNER.lock().unwrap().as_mut() {
+            if !state.suspended && state.started.elapsed() > SHOW_AFTER {
+                let queued = match typeahead::queued() {
+                    0 => String::new(),
+                    1 => " (1 command queued)".to_string(),
+                    count => format!(" ({count} commands queued)"),
+                };
+                print!(
+                    "\r\x1b[2K{} {}{queued}",
+                    FRAMES[frame % FRAMES.len()],
+                    state.message
+                );
+                io::stdout().flush().unwrap_or_default();
+                state.shown = true;
+                frame += 1;
+            }
+        }
+    }
+}
+
+// shows a spinning progress line until it is dropped. Does nothing if stdout is not a terminal.
+// Commands that are typed in the meantime are queued
+pub struct Spinner;
+
+impl Spinner {
+    pub fn start(message: impl Into<String>) -> Self {
+        if io::stdout().is_terminal() {
+            TICKER.call_once(|| {
+                thread::spawn(tick);
+            });
+            *SPINNER.lock().unwrap() = Some(SpinnerState {
+                message: message.into(),
+                started: Instant::now(),
+                suspended: false,
+                shown: false,
+            });
+            if INTERACTIVE.get() {
+                typeahead::listen();
+            }
+        }
+        Self
+    }
+
+    pub fn update(&self, message: impl Into<String>) {
+        if let Some(state) = SPINNER.lock().unwrap().as_mut() {
+            state.message = message.into();
+        }
+    }
+
+    pub fn progress(&self, done: usize, total: usize, message: &str) {
+        self.update(format!("{message} ({done}/{total})"));
+    }
+}
+
+impl Drop for Spinner {
+    fn drop(&mut self) {
+        typeahead::stop();
+        let mut spinner = SPINNER.lock().unwrap();
+        if let Some(state) = spinner.as_mut() {
+            clear_line(state);
+        }
+        *spinner = None;
+    }
+}
+
+// hides the spinner while something else is written to the terminal, like a prompt
+pub fn suspend() {
+    if let Some(state) = SPINNER.lock().unwrap().as_mut() {
+        clear_line(state);
+        state.suspended = true;
+    }
+}
+
+pub fn resume() {
+    if let Some(state) = SPINNER.lock().unwrap().as_mut() {
+        state.suspended = false;
+    }
+}
+
+// what the spinner says, for a step deep down that takes a while, like counting the length of a
+// sound
+pub fn note(message: impl Into<String>) {
+    if let Some(state) = SPINNER.lock().unwrap().as_mut() {
+        state.message = message.into();
+    }
+}
+
+pub fn is_spinning() -> bool {
+    SPINNER.lock().unwrap().is_some()
+}
@@ -0,0 +1,143 @@
+use anyhow::Error;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+// a project is a folder with the save and the sounds it uses in it, so the whole thing can be
+// backed up or synced with Dropbox or git. Sounds inside the folder are saved relative to it, so
+// the folder can be moved. Sounds outside of it are linked to where they are
+pub const SAVE_FILE: &str = "soundscape.json";
+pub const MEDIA_DIR: &str = "media";
+
+pub fn save_file(dir: &Path) -> PathBuf {
+    dir.join(SAVE_FILE)
+}
+
+// sounds that can't be found are made absolute too, so they aren't looked for in the project
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+pub fn relative(dir: &Path, media: &Path) -> PathBuf {
+    let (dir, media) = (canonical(dir), canonical(media));
+    media
+        .strip_prefix(&dir)
+        .map(Path::to_path_buf)
+        .unwrap_or(media)
+}
+
+pub fn resolve(dir: &Path, media: &Path) -> PathBuf {
+    if media.is_relative() {
+        dir.join(media)
+    } else {
+        media.to_path_buf()
+    }
+}
+
+// copies a sound into the media folder of the project, and returns where it ended up. A sound
+// that is already in the project is left where it is. Another file with the same name gets a
+// number, but the same file that was copied before is used again
+pub fn copy_in(dir: &Path, media: &Path) -> Result<PathBuf, Error> {
+    let (dir, media) = (canonical(dir), canonical(media));
+    if media.starts_with(&dir) {
+        return Ok(media);
+    }
+    let media_dir = dir.join(MEDIA_DIR);
+    fs::create_dir_all(&media_dir).map_err(|err| {
+        Error::msg(format!(
+            "error: could not create the media folder at {}. {err}",
+            media_dir.display()
+        ))
+    })?;
+    let stem = media
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    let extension = media
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    for number in 1.. {
+        let name = match number {
+            1 => format!("{stem}{extension}"),
+            number => format!("{stem} {number}{extension}"),
+        };
+        let target = media_dir.join(name);
+        if target.exists() {
+            if same_content(&media, &target) {
+                return Ok(target);
+            }
+            continue;
+        }
+        fs::copy(&media, &target).map_err(|err| {
+            Error::msg(format!(
+                "error: could not copy {} into the project. {err}",
+                media.display()
+            ))
+        })?;
+        return Ok(target);
+    }
+    unreachable!()
+}
+
+// files of the same size are read side by side, until they differ
+fn same_content(first: &Path, second: &Path) -> bool {
+    let size = |path| fs::metadata(path).map(|meta| meta.len()).ok();
+    if size(first).is_none() || size(first) != size(second) {
+        return false;
+    }
+    let (Ok(first), Ok(second)) = (fs::File::open(first), fs::File::open(second)) else {
+        return false;
+    };
+    let (mut first, mut second) = (BufReader::new(first), BufReader::new(second));
+    loop {
+        let (Ok(left), Ok(right)) = (first.fill_buf(), second.fill_buf()) else {
+            return false;
+        };
+        let length = left.len().min(right.len());
+        if length == 0 {
+            return left.len() == right.len();
+        }
+        if left[..length] != right[..length] {
+            return false;
+        }
+        first.consume(length);
+        second.consume(length);
+    }
+}
+
+#[test]
+fn sounds_are_copied_into_the_project() {
+    let temp = crate::testing::TempDir::new("project");
+    let root = temp.path();
+    let (dir, outside) = (root.join("scape"), root.join("sounds"));
+    fs::create_dir_all(&dir).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+    fs::write(outside.join("rain.ogg"), "rain").unwrap();
+    fs::write(root.join("rain.ogg"), "other rain").unwrap();
+
+    let copied = copy_in(&dir, &outside.join("rain.ogg")).unwrap();
+    assert_eq!(fs::read_to_string(&copied).unwrap(), "rain");
+    // the same file again isn't copied twice
+    assert_eq!(copy_in(&dir, &outside.join("rain.ogg")).unwrap(), copied);
+    let other = copy_in(&dir, &root.join("rain.ogg")).unwrap();
+    assert!(other.ends_with("media/rain 2.ogg"));
+    // a file of the same size is only the same file when it has the same in it
+    fs::write(root.join("rain.ogg"), "RAIN").unwrap();
+    let third = copy_in(&dir, &root.join("rain.ogg")).unwrap();
+    assert!(third.ends_with("media/rain 3.ogg"));
+    assert_eq!(fs::read_to_string(&third).unwrap(), "RAIN");
+    // sounds in the project are kept where they are
+    assert_eq!(copy_in(&dir, &copied).unwrap(), copied);
+
+    let saved = relative(&dir, &copied);
+    assert_eq!(saved, Path::new("media/rain.ogg"));
+    assert_eq!(resolve(&dir, &saved), copied);
+    assert_eq!(
+        relative(&dir, &root.join("rain.ogg")),
+        root.join("rain.ogg")
+    );
+}
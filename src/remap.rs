@@ -0,0 +1,46 @@
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+
+// a save made on another machine points at files where they are on that machine. A remap swaps
+// the start of those paths for where the files are here, like D:\Sounds for /home/me/sounds.
+// Slashes and backslashes are the same to a remap, so paths from Windows work everywhere
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+// the longest prefix that matches wins, so a remap for one folder can override the one for the
+// drive it is on
+pub fn apply(path: &Path, remaps: &IndexMap<String, String>) -> Option<PathBuf> {
+    let text = path.to_string_lossy();
+    let parts = components(&text);
+    let (from, to) = remaps
+        .iter()
+        .map(|(from, to)| (components(from), to))
+        .filter(|(from, _)| !from.is_empty() && parts.starts_with(from))
+        .max_by_key(|(from, _)| from.len())?;
+    let mut remapped = PathBuf::from(to);
+    remapped.extend(&parts[from.len()..]);
+    Some(remapped)
+}
+
+#[test]
+fn paths_are_remapped_by_prefix() {
+    let remaps: IndexMap<String, String> = [
+        (r"D:\Sounds".to_string(), "/home/me/sounds".to_string()),
+        (r"D:\Sounds\Music".to_string(), "/mnt/music".to_string()),
+    ]
+    .into();
+    assert_eq!(
+        apply(Path::new(r"D:\Sounds\forest\rain.ogg"), &remaps),
+        Some(PathBuf::from("/home/me/sounds/forest/rain.ogg"))
+    );
+    assert_eq!(
+        apply(Path::new("D:/Sounds/Music/theme.mp3"), &remaps),
+        Some(PathBuf::from("/mnt/music/theme.mp3"))
+    );
+    // only whole folder names match
+    assert_eq!(apply(Path::new(r"D:\SoundsOld\rain.ogg"), &remaps), None);
+    assert_eq!(apply(Path::new("/home/me/rain.ogg"), &remaps), None);
+}
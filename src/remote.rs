@@ -0,0 +1,260 @@
+use anyhow::Error;
+use serde_json::json;
+use std::{
+    io,
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    Message, WebSocket,
+};
+
+use crate::events::AppEvent;
+use crate::http::query_value;
+use crate::pairing::is_local_origin;
+use crate::server::Session;
+use crate::{AppState, INTERACTIVE};
+
+// how often the connection is checked for commands, in between pushing what happened
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+fn snapshot_message(state: &Mutex<AppState>) -> Message {
+    let snapshot = state.lock().unwrap().snapshot();
+    Message::text(json!({"type": "state", "state": snapshot}).to_string())
+}
+
+fn reject((status, err): (StatusCode, String)) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(err));
+    *response.status_mut() = status;
+    response
+}
+
+// browsers can't set headers on a WebSocket, so the token can also go in the query, like
+// ws://host:7879/?token=<TOKEN>. Pairing goes the same way as over HTTP, with name=<NAME> and
+// then code=<CODE>. Returns who connected, and the token when it was just paired
+fn authorize(
+    request: &Request,
+    state: &Mutex<AppState>,
+    peer: &SocketAddr,
+) -> Result<(String, Option<String>), (StatusCode, String)> {
+    // any page that is open in a browser on this machine can connect from here, so a page from
+    // somewhere else is turned away, even when it connects from here
+    let origin = request.headers().get("Origin");
+    if peer.ip().is_loopback()
+        && !origin.is_none_or(|origin| origin.to_str().is_ok_and(is_local_origin))
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "error: pages from other sites can't connect as a remote.".to_string(),
+        ));
+    }
+    let query = request.uri().query().unwrap_or("");
+    let mut state = state.lock().unwrap();
+    if let Some(code) = query_value(query, "code") {
+        let (name, token) = state
+            .pairing
+            .complete(peer.ip(), &code, Instant::now())
+            .map_err(|err| (StatusCode::FORBIDDEN, err.to_string()))?;
+        return Ok((name, Some(token)));
+    }
+    if let Some(name) = query_value(query, "name") {
+        if !peer.ip().is_loopback() {
+            return Err(
+                match state.pairing.start(peer.ip(), &name, Instant::now()) {
+                    Ok(_) => (
+                        StatusCode::UNAUTHORIZED,
+                        "Enter the code that is shown in the terminal, with code=<CODE>."
+                            .to_string(),
+                    ),
+                    Err(err) => (StatusCode::TOO_MANY_REQUESTS, err.to_string()),
+                },
+            );
+        }
+    }
+    let header = request
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let token = header.or_else(|| query_value(query, "token"));
+    state
+        .pairing
+        .authorize(peer.ip().is_loopback(), token.as_deref())
+        .map(|name| (name, None))
+        .map_err(|err| (StatusCode::UNAUTHORIZED, err.to_string()))
+}
+
+fn is_timeout(err: &tungstenite::Error) -> bool {
+    matches!(err, tungstenite::Error::Io(err)
+        if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut))
+}
+
+// sends the state when the client connects, then every event as it happens, followed by the state
+// it left behind. Commands come in as text messages, in the same forms the server takes
+fn handle(
+    mut socket: WebSocket<TcpStream>,
+    state: &Arc<Mutex<AppState>>,
+    session: &Session,
+    events: Receiver<AppEvent>,
+    token: Option<String>,
+) -> Result<(), Error> {
+    if let Some(token) = token {
+        socket.send(Message::text(
+            json!({"type": "paired", "token": token}).to_string(),
+        ))?;
+    }
+    socket.send(snapshot_message(state))?;
+    loop {
+        let mut changed = false;
+        loop {
+            match events.try_recv() {
+                Ok(event) => {
+                    socket.send(Message::text(
+                        json!({"type": "event", "event": event}).to_string(),
+                    ))?;
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+
+        match socket.read() {
+            Ok(Message::Text(line)) => {
+                let (reply, quit) = session.reply(&line);
+                socket.send(Message::text(
+                    json!({"type": "reply", "reply": reply}).to_string(),
+                ))?;
+                if quit {
+                    socket.close(None)?;
+                    return Ok(());
+                }
+                changed = true;
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            // pings are answered by tungstenite itself
+            Ok(_) => {}
+            Err(err) if is_timeout(&err) => {}
+            Err(err) => return Err(err.into()),
+        }
+        if changed {
+            socket.send(snapshot_message(state))?;
+        }
+    }
+}
+
+// tungstenite decides what a rejected handshake looks like, however big it is
+#[allow(clippy::result_large_err)]
+fn accept(
+    stream: TcpStream,
+    state: Arc<Mutex<AppState>>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) {
+    // there is nobody at this end to answer prompts
+    INTERACTIVE.set(false);
+    let Ok(address) = stream.peer_addr() else {
+        return;
+    };
+    let mut client = None;
+    let socket = tungstenite::accept_hdr(stream, |request: &Request, response: Response| {
+        client = Some(authorize(request, &state, &address).map_err(reject)?);
+        Ok(response)
+    });
+    let socket = match socket {
+        Ok(socket) => socket,
+        Err(err) => {
+            println!("warning: {address} could not connect as a remote. {err}");
+            return;
+        }
+    };
+    // the handshake only goes through once the client is let in
+    let Some((name, token)) = client else {
+        return;
+    };
+    let peer = format!("{name} ({address})");
+    // reading has to give up now and then, so that events can be pushed in between
+    if let Err(err) = socket.get_ref().set_read_timeout(Some(POLL_INTERVAL)) {
+        println!("warning: {peer} could not connect as a remote. {err}");
+        return;
+    }
+    println!("Remote {peer} connected.");
+    let events = state.lock().unwrap().bus.subscribe();
+    let session = Session::new(state.clone(), saved, background_mutated);
+    if let Err(err) = handle(socket, &state, &session, events, token) {
+        println!("warning: the connection with remote {peer} broke. {err}");
+    }
+    println!("Remote {peer} disconnected.");
+}
+
+// lets browsers control the soundscape over WebSocket, next to the prompt or the server
+pub fn spawn(
+    state: Arc<Mutex<AppState>>,
+    address: SocketAddr,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let listener = TcpListener::bind(address).map_err(|err| {
+        Error::msg(format!(
+            "error: could not listen for remotes on {address}. {err}"
+        ))
+    })?;
+    println!("Listening for remotes on ws://{}.", listener.local_addr()?);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let (state, saved, background_mutated) =
+                        (state.clone(), saved.clone(), background_mutated.clone());
+                    thread::spawn(move || accept(stream, state, saved, background_mutated));
+                }
+                Err(err) => println!("warning: a remote could not connect. {err}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[test]
+fn events_are_tagged() {
+    let started = json!({"type": "event", "event": AppEvent::Started("rain".to_string())});
+    assert_eq!(
+        started.to_string(),
+        r#"{"event":{"data":"rain","kind":"started"},"type":"event"}"#
+    );
+    let volume = json!(AppEvent::VolumeChanged {
+        id: "rain".to_string(),
+        volume: 40
+    });
+    assert_eq!(volume["data"]["volume"], 40);
+}
+
+#[test]
+fn pages_from_other_sites_are_turned_away() {
+    let state = Mutex::new(crate::testing::app_state());
+    let here: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+    let request = |origin: Option<&str>| {
+        let mut request = Request::builder().uri("/");
+        if let Some(origin) = origin {
+            request = request.header("Origin", origin);
+        }
+        request.body(()).unwrap()
+    };
+    assert!(authorize(&request(None), &state, &here).is_ok());
+    assert!(authorize(&request(Some("http://localhost:8000")), &state, &here).is_ok());
+    assert_eq!(
+        authorize(&request(Some("https://evil.example")), &state, &here)
+            .unwrap_err()
+            .0,
+        StatusCode::FORBIDDEN
+    );
+}
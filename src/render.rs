@@ -0,0 +1,67 @@
+use anyhow::Error;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::{dynamic_mixer, source::Zero, Source};
+use std::{path::Path, time::Duration};
+
+use crate::limiter::{Limiter, LimiterControl};
+use crate::mixer::{CHANNELS, SAMPLE_RATE};
+
+// mixes the sounds the way the master output would, as fast as they can be decoded, and writes
+// the first part of the mix to a wav file. Sounds that end early leave silence behind
+pub fn render(
+    sounds: Vec<Box<dyn Source<Item = f32> + Send>>,
+    limiter: LimiterControl,
+    duration: Duration,
+    path: &Path,
+) -> Result<(), Error> {
+    let (controller, mixer) = dynamic_mixer::mixer(CHANNELS, SAMPLE_RATE);
+    // keeps the mix going for as long as it is asked for, even when all sounds have ended
+    controller.add(Zero::<f32>::new(CHANNELS, SAMPLE_RATE));
+    for sound in sounds {
+        controller.add(sound);
+    }
+    let frames = (duration.as_secs_f64() * SAMPLE_RATE as f64).round() as usize;
+    let spec = WavSpec {
+        channels: CHANNELS,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let error = |err: hound::Error| {
+        Error::msg(format!(
+            "error: could not write the render to {}. {err}",
+            path.display()
+        ))
+    };
+    let mut writer = WavWriter::create(path, spec).map_err(error)?;
+    for sample in Limiter::new(mixer, limiter).take(frames * CHANNELS as usize) {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_sample(sample).map_err(error)?;
+    }
+    writer.finalize().map_err(error)
+}
+
+#[test]
+fn renders_the_asked_for_length() {
+    use rodio::source::SineWave;
+
+    let dir = crate::testing::TempDir::new("render");
+    let path = dir.join("render.wav");
+    // a sound that ends halfway through, and one that never ends
+    let sounds: Vec<Box<dyn Source<Item = f32> + Send>> = vec![
+        Box::new(SineWave::new(440.0).take_duration(Duration::from_millis(250))),
+        Box::new(SineWave::new(220.0).amplify(0.1)),
+    ];
+    let limiter = LimiterControl::new(0.5, true);
+    render(sounds, limiter, Duration::from_millis(500), &path).unwrap();
+    let mut reader = hound::WavReader::open(&path).unwrap();
+    assert_eq!(reader.spec().channels, CHANNELS);
+    assert_eq!(reader.duration(), SAMPLE_RATE / 2);
+    let peak = reader
+        .samples::<i16>()
+        .map(|sample| sample.unwrap().unsigned_abs())
+        .max()
+        .unwrap();
+    // the limiter kept the sine waves from going over half of full scale
+    assert!(peak <= i16::MAX as u16 / 2 + 100, "{peak}");
+}
@@ -0,0 +1,83 @@
+use rodio::{source::Buffered, Sample, Source};
+use std::time::Duration;
+
+// plays a sound a number of times and then ends, like repeat_infinite does without ever ending. The
+// sound is decoded once, and each pass plays the buffered copy again
+pub struct Repeated<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    input: Buffered<S>,
+    current: Buffered<S>,
+    // the passes after the current one
+    left: u32,
+}
+
+impl<S> Repeated<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    pub fn new(input: S, passes: u32) -> Self {
+        let input = input.buffered();
+        Self {
+            current: input.clone(),
+            input,
+            left: passes.saturating_sub(1),
+        }
+    }
+}
+
+impl<S> Iterator for Repeated<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(sample) = self.current.next() {
+            return Some(sample);
+        }
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        self.current = self.input.clone();
+        self.current.next()
+    }
+}
+
+impl<S> Source for Repeated<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        match self.current.current_frame_len() {
+            Some(0) if self.left > 0 => self.input.current_frame_len(),
+            len => len,
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.current.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.current.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[test]
+fn a_sound_is_repeated_as_many_times_as_asked() {
+    let sound = || rodio::buffer::SamplesBuffer::new(1, 44100, vec![1i16, 2, 3]);
+    let samples: Vec<i16> = Repeated::new(sound(), 3).collect();
+    assert_eq!(samples, [1, 2, 3, 1, 2, 3, 1, 2, 3]);
+    assert_eq!(Repeated::new(sound(), 1).count(), 3);
+}
@@ -0,0 +1,50 @@
+use anyhow::Error;
+use serde::Serialize;
+use std::path::Path;
+
+// saves are json, unless they are given a .toml extension. TOML is easier to edit by hand and to
+// keep in version control
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SaveFormat {
+    Json,
+    Toml,
+}
+
+impl SaveFormat {
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn write(self, save: &impl Serialize) -> Result<String, Error> {
+        match self {
+            Self::Json => Ok(serde_json::to_string(save)?),
+            Self::Toml => toml::to_string_pretty(save).map_err(|err| {
+                Error::msg(format!("error: could not write the save as TOML. {err}"))
+            }),
+        }
+    }
+
+    // both formats are read into the same value, so the version can be checked before the rest
+    pub fn read(self, text: &str) -> Result<serde_json::Value, Error> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(text)?),
+            Self::Toml => {
+                let save: toml::Value = toml::from_str(text).map_err(|err| {
+                    Error::msg(format!("error: could not read the save as TOML. {err}"))
+                })?;
+                Ok(serde_json::to_value(save)?)
+            }
+        }
+    }
+}
+
+#[test]
+fn formats_follow_the_extension() {
+    assert_eq!(SaveFormat::of(Path::new("scape.toml")), SaveFormat::Toml);
+    assert_eq!(SaveFormat::of(Path::new("scape.TOML")), SaveFormat::Toml);
+    assert_eq!(SaveFormat::of(Path::new("scape.json")), SaveFormat::Json);
+    assert_eq!(SaveFormat::of(Path::new("scape")), SaveFormat::Json);
+}
@@ -0,0 +1,147 @@
+use fomat_macros::wite;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+use crate::player::duration_to_string;
+use crate::AppState;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct LoopSettings {
+    pub looping: bool,
+    pub length: Option<Duration>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SceneEntry {
+    // the volume the sound plays at in this scene, instead of its own. The same sound can be quiet
+    // in one scene and loud in another
+    pub volume: Option<u32>,
+    // only recorded by scene save. Captured scenes leave the loop settings of players alone
+    pub loop_settings: Option<LoopSettings>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Scene {
+    pub name: String,
+    pub players: IndexMap<String, SceneEntry>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SceneDiff {
+    // in the scene, but not playing yet
+    pub start: Vec<String>,
+    // in the scene and already playing
+    pub keep: Vec<String>,
+    // playing, but not in the scene
+    pub fade_out: Vec<String>,
+    // neither playing nor in the scene
+    pub rest: Vec<String>,
+}
+
+impl Scene {
+    pub fn capture(state: &AppState, name: String, with_loop_settings: bool) -> Self {
+        let mut players = IndexMap::new();
+        for id in state.ordered_ids() {
+            if let Some(player) = state.players.get(id) {
+                if player.get_is_playing() {
+                    let loop_settings = with_loop_settings.then(|| LoopSettings {
+                        looping: player.get_looping(),
+                        length: player.get_loop_length(),
+                    });
+                    players.insert(
+                        id.clone(),
+                        SceneEntry {
+                            volume: Some(player.get_heard_volume()),
+                            loop_settings,
+                        },
+                    );
+                }
+            }
+        }
+        Self { name, players }
+    }
+
+    pub fn diff<'a>(&self, players: impl Iterator<Item = (&'a String, bool)>) -> SceneDiff {
+        let mut diff = SceneDiff::default();
+        for (id, is_playing) in players {
+            let list = match (self.players.contains_key(id), is_playing) {
+                (true, false) => &mut diff.start,
+                (true, true) => &mut diff.keep,
+                (false, true) => &mut diff.fade_out,
+                (false, false) => &mut diff.rest,
+            };
+            list.push(id.clone());
+        }
+        diff
+    }
+}
+
+impl Display for Scene {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        wite!(f,
+            (self.name) ":"
+            for (id, entry) in &self.players {
+                "\n\t" (id) ": "
+                if let Some(volume) = entry.volume {
+                    (volume) "%"
+                } else {
+                    "own volume"
+                }
+                if let Some(loop_settings) = &entry.loop_settings {
+                    if loop_settings.looping {
+                        ", loops"
+                        if let Some(length) = loop_settings.length {
+                            " every " (duration_to_string(length, false))
+                        }
+                    } else {
+                        ", doesn't loop"
+                    }
+                }
+            }
+        )
+    }
+}
+
+#[test]
+fn scene_diff() {
+    let entry = || SceneEntry {
+        volume: None,
+        loop_settings: None,
+    };
+    let scene = Scene {
+        name: "combat".to_string(),
+        players: IndexMap::from([
+            ("drums".to_string(), entry()),
+            ("horn".to_string(), entry()),
+            ("ghost".to_string(), entry()),
+        ]),
+    };
+    let players = [
+        ("drums".to_string(), true),
+        ("horn".to_string(), false),
+        ("rain".to_string(), true),
+        ("birds".to_string(), false),
+    ];
+    let diff = scene.diff(players.iter().map(|(id, playing)| (id, *playing)));
+    assert_eq!(
+        diff,
+        SceneDiff {
+            start: vec!["horn".to_string()],
+            keep: vec!["drums".to_string()],
+            fade_out: vec!["rain".to_string()],
+            rest: vec!["birds".to_string()],
+        }
+    );
+}
+
+#[test]
+fn scene_volumes_from_older_saves_are_kept() {
+    let entry: SceneEntry =
+        serde_json::from_str(r#"{"volume": 40, "loop_settings": null}"#).unwrap();
+    assert_eq!(entry.volume, Some(40));
+    let entry: SceneEntry =
+        serde_json::from_str(r#"{"volume": null, "loop_settings": null}"#).unwrap();
+    assert_eq!(entry.volume, None);
+}
@@ -0,0 +1,177 @@
+use anyhow::Error;
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeDelta};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{respond, AppState, RespondResult};
+
+// a command that runs by itself at some time, like after 10m play owl, or at 21:30 scene switch night
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Scheduled {
+    #[serde(with = "moment")]
+    pub at: DateTime<Local>,
+    pub command: String,
+}
+
+// moments are written like "2024-05-01T21:30:00+02:00", so a save stays readable
+mod moment {
+    use chrono::{DateTime, Local};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        moment: &DateTime<Local>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&moment.to_rfc3339())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Local>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&text)
+            .map(|moment| moment.with_timezone(&Local))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+// the scheduled commands by their ID. IDs are numbers, which aren't reused within a session
+#[derive(Default)]
+pub struct Schedule {
+    entries: IndexMap<u32, Scheduled>,
+    next_id: u32,
+}
+
+impl Schedule {
+    pub fn add(&mut self, scheduled: Scheduled) -> u32 {
+        self.next_id += 1;
+        self.entries.insert(self.next_id, scheduled);
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<Scheduled> {
+        self.entries.shift_remove(&id)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // the soonest first
+    pub fn list(&self) -> Vec<(u32, &Scheduled)> {
+        let mut list: Vec<(u32, &Scheduled)> = self
+            .entries
+            .iter()
+            .map(|(id, scheduled)| (*id, scheduled))
+            .collect();
+        list.sort_by_key(|(id, scheduled)| (scheduled.at, *id));
+        list
+    }
+
+    // takes out the commands whose time has come, the soonest first
+    pub fn take_due(&mut self, now: DateTime<Local>) -> Vec<Scheduled> {
+        let due: Vec<u32> = self
+            .list()
+            .into_iter()
+            .filter(|(_, scheduled)| scheduled.at <= now)
+            .map(|(id, _)| id)
+            .collect();
+        due.into_iter()
+            .filter_map(|id| self.entries.shift_remove(&id))
+            .collect()
+    }
+}
+
+// times of day are written like 21:30, or 21:30:15
+pub fn parse_time_of_day(time: &str) -> Result<NaiveTime, Error> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M:%S"))
+        .map_err(|_| {
+            Error::msg(format!(
+                "error: {time} is not a time of day. Write it like 21:30."
+            ))
+        })
+}
+
+// the first time the clock shows TIME from now on, which is tomorrow if it already did today
+pub fn next_time_of_day(time: NaiveTime, now: NaiveDateTime) -> NaiveDateTime {
+    let today = now.date().and_time(time);
+    if today > now {
+        today
+    } else {
+        today + TimeDelta::days(1)
+    }
+}
+
+// runs the commands whose time has come. Taking them off the schedule always mutates the state,
+// and the result quits when one of them does, like at 23:00 exit
+pub fn run_due(state: &mut AppState) -> RespondResult {
+    let due = state.schedule.take_due(Local::now());
+    let mut result = RespondResult {
+        mutated: !due.is_empty(),
+        saved: false,
+        quit: false,
+    };
+    for scheduled in &due {
+        say!("Running {}, as scheduled.", scheduled.command);
+        match respond(state, &scheduled.command, false) {
+            Ok(ran) => result.quit |= ran.quit,
+            Err(err) => say!("{err}"),
+        }
+    }
+    result
+}
+
+#[test]
+fn scheduled_commands_come_due_in_order() {
+    let at = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+    let evening = NaiveDateTime::parse_from_str("2024-05-01 20:00", "%Y-%m-%d %H:%M").unwrap();
+    assert_eq!(
+        next_time_of_day(at(21, 30), evening).to_string(),
+        "2024-05-01 21:30:00"
+    );
+    assert_eq!(
+        next_time_of_day(at(8, 0), evening).to_string(),
+        "2024-05-02 08:00:00"
+    );
+    assert_eq!(
+        parse_time_of_day("21:30:15").unwrap(),
+        at(21, 30) + TimeDelta::seconds(15)
+    );
+    assert!(parse_time_of_day("half past nine").is_err());
+
+    let now = Local::now();
+    let mut schedule = Schedule::default();
+    let scheduled = |minutes, command: &str| Scheduled {
+        at: now + TimeDelta::minutes(minutes),
+        command: command.to_string(),
+    };
+    let owl = schedule.add(scheduled(-1, "play owl"));
+    schedule.add(scheduled(10, "scene switch night"));
+    let rain = schedule.add(scheduled(-5, "stop rain"));
+    let commands = |due: Vec<Scheduled>| due.into_iter().map(|s| s.command).collect::<Vec<_>>();
+    assert_eq!(commands(schedule.take_due(now)), ["stop rain", "play owl"]);
+    assert!(schedule.remove(owl).is_none() && schedule.remove(rain).is_none());
+    assert_eq!(schedule.list().len(), 1);
+    assert!(schedule.take_due(now).is_empty());
+}
+
+#[test]
+fn scheduled_exits_quit() {
+    let mut state = crate::testing::app_state();
+    let now = Local::now();
+    for command in ["volume -v 50 owl", "exit"] {
+        state.schedule.add(Scheduled {
+            at: now - TimeDelta::minutes(1),
+            command: command.to_string(),
+        });
+    }
+    let result = run_due(&mut state);
+    assert!(result.mutated && result.quit);
+    assert!(state.schedule.is_empty());
+    assert!(!run_due(&mut state).mutated);
+}
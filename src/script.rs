@@ -0,0 +1,216 @@
+use std::{borrow::Cow, fmt::Write, time::Duration};
+
+use crate::{settings::Settings, AppState};
+
+fn quote(text: &str) -> Cow<'_, str> {
+    shlex::try_quote(text).unwrap_or(Cow::Borrowed(text))
+}
+
+// milliseconds are precise enough, and parse back without any rounding surprises
+fn duration_arg(duration: Duration) -> String {
+    format!("{}ms", duration.as_millis())
+}
+
+// the options that set the fades, if any are set
+fn fade_args(settings: &Settings) -> Option<String> {
+    let mut args = String::new();
+    if let Some(length) = settings.fade_in {
+        write!(args, " -i {}", duration_arg(length)).unwrap();
+    }
+    if let Some(length) = settings.fade_out {
+        write!(args, " -o {}", duration_arg(length)).unwrap();
+    }
+    (!args.is_empty()).then_some(args)
+}
+
+// the commands that build the soundscape from scratch, one per line
+pub fn export(state: &AppState) -> String {
+    let mut script = String::from("# rebuilds the soundscape from scratch\n");
+    let ids: Vec<&String> = state.ordered_ids().collect();
+
+    for id in &ids {
+        let player = &state.players[*id];
+        let media = player.get_media().to_string_lossy();
+        writeln!(script, "add -p {} -n {}", quote(&media), quote(id)).unwrap();
+    }
+
+    for id in &ids {
+        let player = &state.players[*id];
+        let id = quote(id);
+        if player.get_volume() != 100 {
+            writeln!(script, "volume {id} -v {}", player.get_volume()).unwrap();
+        }
+        if player.get_width() != 100 {
+            writeln!(script, "width {id} -w {}", player.get_width()).unwrap();
+        }
+        if player.get_normalize() {
+            writeln!(script, "normalize {id}").unwrap();
+        }
+        if player.get_pan() != 0.0 {
+            writeln!(script, "pan {id} -p {}", player.get_pan()).unwrap();
+        }
+        if player.get_speed() != 1.0 {
+            writeln!(script, "speed {id} -s {}", player.get_speed()).unwrap();
+        }
+        if let Some(length) = player.get_stretch() {
+            writeln!(script, "stretch {id} -l {}", duration_arg(length)).unwrap();
+        }
+        if !player.get_start().is_zero() {
+            writeln!(
+                script,
+                "set-start {id} -p {}",
+                duration_arg(player.get_start())
+            )
+            .unwrap();
+        }
+        if let Some(end) = player.get_end().filter(|end| !end.is_zero()) {
+            writeln!(script, "set-end {id} -p {}", duration_arg(end)).unwrap();
+        }
+        if !player.get_delay().is_zero() {
+            writeln!(script, "delay {id} -d {}", duration_arg(player.get_delay())).unwrap();
+        }
+        if let Some(cooldown) = player.get_cooldown() {
+            let queue = if cooldown.queue { " -q" } else { "" };
+            writeln!(
+                script,
+                "cooldown {id} -d {}{queue}",
+                duration_arg(cooldown.length)
+            )
+            .unwrap();
+        }
+        for (name, position) in &player.markers {
+            writeln!(
+                script,
+                "mark add {id} {} {}",
+                quote(name),
+                duration_arg(*position)
+            )
+            .unwrap();
+        }
+        if let Some(fades) = fade_args(&player.settings) {
+            writeln!(script, "fades {id}{fades}").unwrap();
+        }
+        if player.get_looping() {
+            match player.get_loop_length() {
+                Some(length) => writeln!(script, "loop {id} -d {}", duration_arg(length)),
+                None => writeln!(script, "loop {id}"),
+            }
+            .unwrap();
+        }
+    }
+
+    for (name, group) in &state.groups {
+        let members: Vec<Cow<'_, str>> = group.iter().map(|id| quote(id)).collect();
+        writeln!(script, "group {} -g {}", members.join(" "), quote(name)).unwrap();
+        if let Some(fades) = state.group_defaults.get(name).and_then(fade_args) {
+            writeln!(script, "group-defaults -g {}{fades}", quote(name)).unwrap();
+        }
+        if let Some(group_loop) = state.group_loops.get(name) {
+            match (&group_loop.leader, group_loop.length) {
+                (_, Some(length)) => writeln!(
+                    script,
+                    "loop-group -g {} -d {}",
+                    quote(name),
+                    duration_arg(length)
+                ),
+                (Some(leader), None) => {
+                    writeln!(script, "loop-group -g {} -l {}", quote(name), quote(leader))
+                }
+                (None, None) => writeln!(script, "loop-group -g {}", quote(name)),
+            }
+            .unwrap();
+        }
+    }
+
+    // playlists start playing right away, so they come last
+    for (name, playlist) in &state.playlists {
+        writeln!(
+            script,
+            "playlist -g {} -c {}",
+            quote(name),
+            playlist.cooldown
+        )
+        .unwrap();
+        for id in state.groups.get(name).into_iter().flatten() {
+            if playlist.weight(id) != 1 {
+                writeln!(script, "weight {} -w {}", quote(id), playlist.weight(id)).unwrap();
+            }
+        }
+    }
+
+    for duck in &state.ducks {
+        writeln!(
+            script,
+            "duck -w {} -t {} -a {} -r {}",
+            quote(&duck.when),
+            quote(&duck.target),
+            duck.amount,
+            duration_arg(duck.release)
+        )
+        .unwrap();
+    }
+
+    for binding in &state.midi.bindings {
+        let channel = binding
+            .channel
+            .map(|channel| format!(" -c {channel}"))
+            .unwrap_or_default();
+        writeln!(
+            script,
+            "midi map {} {}{channel}",
+            binding.control(),
+            quote(&binding.command)
+        )
+        .unwrap();
+    }
+
+    for cue in &state.cues.cues {
+        let commands: Vec<Cow<'_, str>> =
+            cue.commands.iter().map(|command| quote(command)).collect();
+        writeln!(script, "cue add {}", commands.join(" ")).unwrap();
+    }
+
+    for (name, link) in &state.volume_links {
+        let members: Vec<Cow<'_, str>> = link.iter().map(|id| quote(id)).collect();
+        writeln!(script, "link {} -l {}", members.join(" "), quote(name)).unwrap();
+    }
+
+    for (from, to) in &state.remaps {
+        writeln!(script, "remap add {} {}", quote(from), quote(to)).unwrap();
+    }
+
+    // last, so that the sounds above stay where the script puts them
+    if let Some(group) = &state.default_group {
+        writeln!(script, "set default-group {}", quote(group)).unwrap();
+    }
+
+    // scenes are captured from what is playing, so there is no command that makes one directly
+    if !state.scenes.is_empty() {
+        let names: Vec<&str> = state.scenes.keys().map(|name| name.as_str()).collect();
+        writeln!(
+            script,
+            "# these scenes can't be rebuilt with commands: {}",
+            names.join(", ")
+        )
+        .unwrap();
+    }
+    script
+}
+
+#[test]
+fn script_arguments_parse_back() {
+    let duration = Duration::from_millis(90_500);
+    assert_eq!(
+        duration_str::parse(duration_arg(duration)).unwrap(),
+        duration
+    );
+    let line = format!(
+        "add -p {} -n {}",
+        quote("sounds/night owl.ogg"),
+        quote("owl")
+    );
+    assert_eq!(
+        shlex::split(&line).unwrap(),
+        vec!["add", "-p", "sounds/night owl.ogg", "-n", "owl"]
+    );
+}
@@ -0,0 +1,363 @@
+use anyhow::Error;
+use serde_json::{json, Value};
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{output, progress, run_line, AppState, INTERACTIVE};
+
+// the error codes from the JSON-RPC 2.0 spec, and one for commands that failed
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const COMMAND_FAILED: i64 = -32000;
+
+// a line is either a command, written the same way as at the prompt, or a JSON-RPC request. The
+// method is the name of a command, with its arguments as the params, or "run" with the whole
+// command line as its only param
+#[derive(Debug, PartialEq)]
+enum Request {
+    Line(String),
+    Rpc { id: Value, command: String },
+}
+
+// goes over the whole token however early it differs, so how long the answer takes doesn't give
+// away how much of a guess was right
+fn same_token(token: &str, given: &str) -> bool {
+    token.len() == given.len()
+        && token
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |differs, (a, b)| differs | (a ^ b))
+            == 0
+}
+
+fn is_http_request(line: &str) -> bool {
+    line.trim_end()
+        .rsplit_once(' ')
+        .is_some_and(|(_, version)| version.starts_with("HTTP/"))
+}
+
+fn parse_request(line: &str) -> Result<Request, (Value, i64, String)> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return Ok(Request::Line(line.to_string()));
+    }
+    let request: Value = serde_json::from_str(line)
+        .map_err(|err| (Value::Null, PARSE_ERROR, format!("invalid JSON. {err}")))?;
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let invalid = |message: &str| (id.clone(), INVALID_REQUEST, message.to_string());
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("the request has no method."))?;
+    let params: Vec<String> = match request.get("params") {
+        None => Vec::new(),
+        Some(Value::Array(params)) => params
+            .iter()
+            .map(|param| match param {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        Some(_) => return Err(invalid("the params have to be a list.")),
+    };
+    let command = if method == "run" {
+        match params.as_slice() {
+            [command] => command.clone(),
+            _ => return Err(invalid("run takes the command line as its only param.")),
+        }
+    } else {
+        let args: Vec<Cow<'_, str>> = params
+            .iter()
+            .map(|param| shlex::try_quote(param).unwrap_or(Cow::Borrowed(param)))
+            .collect();
+        format!("{method} {}", args.join(" "))
+    };
+    Ok(Request::Rpc { id, command })
+}
+
+// a client, taking turns with the prompt and the other clients
+pub struct Session {
+    state: Arc<Mutex<AppState>>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+}
+
+impl Session {
+    pub fn new(
+        state: Arc<Mutex<AppState>>,
+        saved: Arc<AtomicBool>,
+        background_mutated: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            state,
+            saved,
+            background_mutated,
+        }
+    }
+
+    // returns what the command said, and whether the client wants to disconnect
+    pub fn run(&self, line: &str) -> (Result<String, (String, String)>, bool) {
+        // the prompt can be running next to this, so changes are reported the same way the
+        // watchers in the background do
+        let has_been_saved =
+            self.saved.load(Ordering::SeqCst) && !self.background_mutated.load(Ordering::SeqCst);
+        let (result, said) = output::capture(|| run_line(&self.state, line, has_been_saved));
+        match result {
+            Ok(result) => {
+                if result.mutated {
+                    self.background_mutated.store(true, Ordering::SeqCst);
+                } else if result.saved {
+                    self.saved.store(true, Ordering::SeqCst);
+                    self.background_mutated.store(false, Ordering::SeqCst);
+                }
+                // exit only ends the connection. The server keeps running for the next one
+                (Ok(said), result.quit)
+            }
+            Err(err) => (Err((said, err.to_string())), false),
+        }
+    }
+
+    // for commands that come from something that can't show an answer, like a MIDI pad. Only
+    // what went wrong is shown, at the prompt
+    pub fn run_quietly(&self, line: &str) {
+        if let Err((_, err)) = self.run(line).0 {
+            progress::suspend();
+            println!("\n{err}");
+        }
+    }
+
+    // answers a line in the same form it came in. The reply ends with a newline
+    pub fn reply(&self, line: &str) -> (String, bool) {
+        match parse_request(line) {
+            Ok(Request::Line(line)) => {
+                let (result, quit) = self.run(&line);
+                let reply = match result {
+                    Ok(said) => said,
+                    Err((said, err)) => format!("{said}{err}\n"),
+                };
+                (reply, quit)
+            }
+            Ok(Request::Rpc { id, command }) => {
+                let (result, quit) = self.run(&command);
+                let reply = match result {
+                    Ok(said) => json!({"jsonrpc": "2.0", "id": id, "result": {"output": said}}),
+                    Err((said, err)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": COMMAND_FAILED, "message": err, "data": {"output": said}}
+                    }),
+                };
+                (format!("{reply}\n"), quit)
+            }
+            Err((id, code, message)) => {
+                let reply = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": code, "message": message}
+                });
+                (format!("{reply}\n"), false)
+            }
+        }
+    }
+
+    // a client on another machine has to give a token first, see authenticate
+    fn handle(&self, stream: TcpStream, token: Option<&str>) -> io::Result<()> {
+        let peer = stream.peer_addr()?.ip();
+        let mut trusted = peer.is_loopback();
+        let mut writer = stream.try_clone()?;
+        for (i, line) in BufReader::new(stream).lines().enumerate() {
+            let line = line?;
+            // a web page in a browser on this machine can send a request here too, whose body
+            // would be taken as commands. Those start with the line of an HTTP request
+            if i == 0 && is_http_request(&line) {
+                break;
+            }
+            if !trusted {
+                let (reply, who) = self.authenticate(&line, token);
+                writer.write_all(reply.as_bytes())?;
+                match who {
+                    Some(who) => println!("{peer} is {who}."),
+                    None => break,
+                }
+                trusted = true;
+                continue;
+            }
+            let (reply, quit) = self.reply(&line);
+            writer.write_all(reply.as_bytes())?;
+            if quit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // the first line of a client on another machine is auth <TOKEN>, with the token serve was
+    // started with, or the one a remote got when it was paired. Returns the reply, and who the
+    // client is. Anything else ends the connection
+    fn authenticate(&self, line: &str, token: Option<&str>) -> (String, Option<String>) {
+        let (id, command) = match parse_request(line) {
+            Ok(Request::Line(command)) => (None, command),
+            Ok(Request::Rpc { id, command }) => (Some(id), command),
+            Err(_) => (None, String::new()),
+        };
+        let given = match shlex::split(&command).as_deref() {
+            Some([auth, given]) if auth == "auth" => Some(given.clone()),
+            _ => None,
+        };
+        let who = given.as_deref().and_then(|given| {
+            if token.is_some_and(|token| same_token(token, given)) {
+                return Some("trusted by its token".to_string());
+            }
+            self.state
+                .lock()
+                .unwrap()
+                .pairing
+                .authorize(false, Some(given))
+                .ok()
+        });
+        let reply = match (&who, id) {
+            (Some(who), None) => format!("Welcome, {who}.\n"),
+            (None, None) => format!("{NOT_AUTHORIZED}\n"),
+            (Some(who), Some(id)) => {
+                format!(
+                    "{}\n",
+                    json!({"jsonrpc": "2.0", "id": id, "result": {"output": format!("Welcome, {who}.\n")}})
+                )
+            }
+            (None, Some(id)) => format!(
+                "{}\n",
+                json!({"jsonrpc": "2.0", "id": id, "error": {"code": COMMAND_FAILED, "message": NOT_AUTHORIZED}})
+            ),
+        };
+        (reply, who)
+    }
+}
+
+const NOT_AUTHORIZED: &str =
+    "error: commands from other machines need auth <TOKEN> first, with the token serve was started with, or the one of a paired remote.";
+
+// runs the soundscape without a prompt, taking commands from the network instead. Every
+// connection gets its own thread, and the commands take turns on the state. Other machines can
+// only be listened to when they can be told apart from anyone else who can reach the port: with a
+// token, or with remotes that have been paired
+pub fn serve(
+    state: Arc<Mutex<AppState>>,
+    address: (&str, u16),
+    token: Option<String>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) -> Result<(), Error> {
+    let listener = TcpListener::bind(address).map_err(|err| {
+        Error::msg(format!(
+            "error: could not listen on {}:{}. {err}",
+            address.0, address.1
+        ))
+    })?;
+    let local = listener.local_addr()?;
+    let paired = state.lock().unwrap().pairing.clients().next().is_some();
+    if !local.ip().is_loopback() && token.is_none() && !paired {
+        return Err(Error::msg(format!(
+            "error: {} can be reached from other machines, and anyone who can reach it could control the soundscape. Start serve with --token, or pair a remote first.",
+            address.0
+        )));
+    }
+    println!("Listening for commands on {local}.");
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                println!("warning: a connection failed. {err}");
+                continue;
+            }
+        };
+        let session = Session::new(state.clone(), saved.clone(), background_mutated.clone());
+        let token = token.clone();
+        thread::spawn(move || {
+            // there is nobody at this end to answer prompts
+            INTERACTIVE.set(false);
+            let peer = stream
+                .peer_addr()
+                .map(|peer| peer.to_string())
+                .unwrap_or_else(|_| "a client".to_string());
+            println!("{peer} connected.");
+            if let Err(err) = session.handle(stream, token.as_deref()) {
+                println!("warning: the connection with {peer} broke. {err}");
+            }
+            println!("{peer} disconnected.");
+        });
+    }
+    Ok(())
+}
+
+#[test]
+fn requests_map_onto_commands() {
+    assert_eq!(
+        parse_request("play rain\n"),
+        Ok(Request::Line("play rain".to_string()))
+    );
+    assert_eq!(
+        parse_request(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "volume", "params": ["night rain", "-v", 40]}"#
+        ),
+        Ok(Request::Rpc {
+            id: json!(1),
+            command: "volume 'night rain' -v 40".to_string()
+        })
+    );
+    assert_eq!(
+        parse_request(r#"{"jsonrpc": "2.0", "id": "a", "method": "run", "params": ["stop all"]}"#),
+        Ok(Request::Rpc {
+            id: json!("a"),
+            command: "stop all".to_string()
+        })
+    );
+    assert_eq!(parse_request("{nope").unwrap_err().1, PARSE_ERROR);
+    assert_eq!(
+        parse_request(r#"{"id": 2, "method": "run", "params": []}"#).unwrap_err(),
+        (
+            json!(2),
+            INVALID_REQUEST,
+            "run takes the command line as its only param.".to_string()
+        )
+    );
+}
+
+#[test]
+fn other_machines_need_a_token() {
+    let state = Arc::new(Mutex::new(crate::testing::app_state()));
+    let session = Session::new(state, Arc::default(), Arc::default());
+    let token = Some("secret");
+    let (reply, who) = session.authenticate("auth secret", token);
+    assert_eq!(reply, "Welcome, trusted by its token.\n");
+    assert!(who.is_some());
+    let (reply, who) = session.authenticate(
+        r#"{"jsonrpc": "2.0", "id": 1, "method": "auth", "params": ["secret"]}"#,
+        token,
+    );
+    assert!(reply.contains(r#""result""#));
+    assert!(who.is_some());
+    for line in [
+        "auth guessed",
+        "auth secreT",
+        "auth secrets",
+        "play rain",
+        "{nope",
+    ] {
+        let (reply, who) = session.authenticate(line, token);
+        assert_eq!(reply, format!("{NOT_AUTHORIZED}\n"));
+        assert!(who.is_none());
+    }
+    // without a token, only paired remotes get in
+    assert!(session.authenticate("auth secret", None).1.is_none());
+    // a page in a browser can't get its request taken as commands
+    assert!(is_http_request("POST / HTTP/1.1"));
+    assert!(!is_http_request("play rain"));
+}
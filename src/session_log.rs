@@ -0,0 +1,76 @@
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+
+use crate::events::AppEvent;
+
+pub enum Entry {
+    Note(String),
+    Event(AppEvent),
+}
+
+// what happened during this session, for writing up a recap afterwards. Notes are written with
+// log, and sounds starting and stopping and scenes switching are recorded along with them
+pub struct SessionLog {
+    started: DateTime<Local>,
+    entries: Vec<(DateTime<Local>, Entry)>,
+}
+
+impl SessionLog {
+    pub fn new() -> Self {
+        Self {
+            started: Local::now(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn note(&mut self, text: String) {
+        self.entries.push((Local::now(), Entry::Note(text)));
+    }
+
+    // loops and volume changes would drown out everything else
+    pub fn record(&mut self, event: &AppEvent) {
+        if let AppEvent::Started(_)
+        | AppEvent::Paused(_)
+        | AppEvent::Stopped(_)
+        | AppEvent::SceneActivated(_) = event
+        {
+            self.entries
+                .push((Local::now(), Entry::Event(event.clone())));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "Session of {}\n\n",
+            self.started.format("%Y-%m-%d, started at %H:%M")
+        );
+        for (time, entry) in &self.entries {
+            let time = time.format("%H:%M");
+            match entry {
+                Entry::Note(note) => writeln!(text, "{time} note: {note}"),
+                Entry::Event(event) => writeln!(text, "{time} {event}"),
+            }
+            .unwrap();
+        }
+        text
+    }
+}
+
+#[test]
+fn notes_and_events_are_logged() {
+    let mut log = SessionLog::new();
+    assert!(log.is_empty());
+    log.record(&AppEvent::Started("battle_03".to_string()));
+    log.record(&AppEvent::LoopWrapped("battle_03".to_string()));
+    log.note("dragon appeared".to_string());
+    let text = log.render();
+    let lines: Vec<&str> = text.lines().skip(2).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" battle_03 started playing"));
+    assert!(lines[1].ends_with(" note: dragon appeared"));
+    assert!(text.starts_with("Session of "));
+}
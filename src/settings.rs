@@ -0,0 +1,229 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use fomat_macros::wite;
+use serde::{Deserialize, Serialize};
+
+use crate::player::duration_to_string;
+
+// how long a sound that starts softly takes to come up, when it doesn't fade in for longer anyway.
+// Short enough to not be heard as a fade, long enough to not click
+pub const SOFT_START: Duration = Duration::from_millis(50);
+
+// where a sound plays into. The edit bus isn't heard, see edit
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug, ValueEnum)]
+pub enum Bus {
+    #[default]
+    Master,
+    Edit,
+}
+
+// settings that a player takes from its group, unless it has its own. None is not set
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Debug)]
+pub struct Settings {
+    // how long the sound fades in when it is played, so it starts softly
+    #[serde(default)]
+    pub fade_in: Option<Duration>,
+    // how long the sound fades out when it is stopped
+    #[serde(default)]
+    pub fade_out: Option<Duration>,
+    // whether the sound comes up over SOFT_START when it is played without a fade in, so it
+    // doesn't click
+    #[serde(default)]
+    pub soft_start: Option<bool>,
+    #[serde(default)]
+    pub bus: Option<Bus>,
+}
+
+// where a setting that is in effect comes from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Source<'a> {
+    Player,
+    Group(&'a str),
+    Default,
+}
+
+// the settings that are in effect for a player, and where each of them comes from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Effective<'a> {
+    pub fade_in: (Duration, Source<'a>),
+    pub fade_out: (Duration, Source<'a>),
+    pub soft_start: (bool, Source<'a>),
+    pub bus: (Bus, Source<'a>),
+}
+
+impl Settings {
+    pub fn is_empty(&self) -> bool {
+        *self == Settings::default()
+    }
+
+    // takes what is set in the other, and keeps the rest
+    pub fn update(&mut self, other: Settings) {
+        self.fade_in = other.fade_in.or(self.fade_in);
+        self.fade_out = other.fade_out.or(self.fade_out);
+        self.soft_start = other.soft_start.or(self.soft_start);
+        self.bus = other.bus.or(self.bus);
+    }
+
+    // the settings of a player, with what it doesn't set taken from the defaults of its group, and
+    // what that doesn't set either from the defaults of the config
+    pub fn resolve<'a>(
+        &self,
+        group: Option<(&'a str, &Settings)>,
+        config: &Settings,
+    ) -> Effective<'a> {
+        let (name, defaults) = group.map_or(("", Settings::default()), |(name, defaults)| {
+            (name, *defaults)
+        });
+        fn pick<T: Default>(
+            name: &str,
+            own: Option<T>,
+            inherited: Option<T>,
+            default: Option<T>,
+        ) -> (T, Source<'_>) {
+            match (own, inherited) {
+                (Some(value), _) => (value, Source::Player),
+                (None, Some(value)) => (value, Source::Group(name)),
+                (None, None) => (default.unwrap_or_default(), Source::Default),
+            }
+        }
+        Effective {
+            fade_in: pick(name, self.fade_in, defaults.fade_in, config.fade_in),
+            fade_out: pick(name, self.fade_out, defaults.fade_out, config.fade_out),
+            soft_start: pick(
+                name,
+                self.soft_start,
+                defaults.soft_start,
+                config.soft_start,
+            ),
+            bus: pick(name, self.bus, defaults.bus, config.bus),
+        }
+    }
+}
+
+impl Effective<'_> {
+    // how long the sound takes to come up when it is played
+    pub fn start_fade(&self) -> Duration {
+        if self.soft_start.0 {
+            self.fade_in.0.max(SOFT_START)
+        } else {
+            self.fade_in.0
+        }
+    }
+}
+
+impl Display for Bus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Bus::Master => write!(f, "master"),
+            Bus::Edit => write!(f, "edit"),
+        }
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+impl Display for Settings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        wite!(f,
+            if let Some(length) = self.fade_in {
+                "\n\tfades in: " (duration_to_string(length, false))
+            }
+            if let Some(length) = self.fade_out {
+                "\n\tfades out: " (duration_to_string(length, false))
+            }
+            if let Some(soft_start) = self.soft_start {
+                "\n\tsoft start: " (on_off(soft_start))
+            }
+            if let Some(bus) = self.bus {
+                "\n\tbus: " (bus)
+            }
+        )
+    }
+}
+
+impl Display for Source<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Player => write!(f, "its own"),
+            Source::Group(name) => write!(f, "from group {name}"),
+            Source::Default => write!(f, "default of the config"),
+        }
+    }
+}
+
+impl Display for Effective<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (fade_in, fade_in_source) = self.fade_in;
+        let (fade_out, fade_out_source) = self.fade_out;
+        let (soft_start, soft_start_source) = self.soft_start;
+        let (bus, bus_source) = self.bus;
+        wite!(f,
+            "\n\t\tfades in: " (duration_to_string(fade_in, false)) " (" (fade_in_source) ")"
+            "\n\t\tfades out: " (duration_to_string(fade_out, false)) " (" (fade_out_source) ")"
+            "\n\t\tsoft start: " (on_off(soft_start)) " (" (soft_start_source) ")"
+            "\n\t\tbus: " (bus) " (" (bus_source) ")"
+        )
+    }
+}
+
+#[test]
+fn players_inherit_what_they_dont_set() {
+    let second = Duration::from_secs(1);
+    let defaults = Settings {
+        fade_in: Some(2 * second),
+        fade_out: Some(4 * second),
+        soft_start: Some(true),
+        bus: Some(Bus::Edit),
+    };
+    let own = Settings {
+        fade_in: None,
+        fade_out: Some(second),
+        soft_start: None,
+        bus: Some(Bus::Master),
+    };
+    let none = Settings::default();
+    let effective = own.resolve(Some(("forest", &defaults)), &none);
+    assert_eq!(effective.fade_in, (2 * second, Source::Group("forest")));
+    assert_eq!(effective.fade_out, (second, Source::Player));
+    assert_eq!(effective.soft_start, (true, Source::Group("forest")));
+    assert_eq!(effective.bus, (Bus::Master, Source::Player));
+    // a sound that starts softly comes up over at least SOFT_START
+    assert_eq!(effective.start_fade(), 2 * second);
+    let quick = Settings {
+        fade_in: Some(Duration::ZERO),
+        ..own
+    };
+    let effective = quick.resolve(Some(("forest", &defaults)), &none);
+    assert_eq!(effective.start_fade(), SOFT_START);
+    // a player that isn't in a group, or whose group has no defaults, doesn't fade
+    let effective = own.resolve(None, &none);
+    assert_eq!(effective.fade_in, (Duration::ZERO, Source::Default));
+    assert_eq!(effective.soft_start, (false, Source::Default));
+    assert_eq!(effective.start_fade(), Duration::ZERO);
+    let effective = own.resolve(Some(("forest", &Settings::default())), &none);
+    assert_eq!(effective.fade_in, (Duration::ZERO, Source::Default));
+    // unless the config sets a fade for every sound
+    let config = Settings {
+        fade_in: Some(3 * second),
+        fade_out: None,
+        ..Settings::default()
+    };
+    let effective = own.resolve(None, &config);
+    assert_eq!(effective.fade_in, (3 * second, Source::Default));
+    assert_eq!(effective.fade_out, (second, Source::Player));
+    // an update only changes what it sets
+    let mut updated = defaults;
+    updated.update(own);
+    assert_eq!(updated.fade_in, Some(2 * second));
+    assert_eq!(updated.fade_out, Some(second));
+}
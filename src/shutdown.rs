@@ -0,0 +1,113 @@
+use anyhow::Error;
+use std::{
+    fs,
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, TryLockError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::config::config_dir;
+use crate::operations::to_serializable;
+use crate::save_format::SaveFormat;
+use crate::{progress, AppState};
+
+const FADE_OUT: Duration = Duration::from_millis(500);
+// a command that is waiting for an answer holds on to the state. Shutting down shouldn't wait
+// for that forever
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn autosave_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("autosave.json"))
+}
+
+// writes the soundscape to autosave.json. Unlike save, it doesn't change where the soundscape is
+// saved to, or whether it counts as saved
+fn autosave(state: &AppState) -> Result<PathBuf, Error> {
+    let path = autosave_path()
+        .ok_or_else(|| Error::msg("error: there is no folder to save the unsaved changes to."))?;
+    let text = SaveFormat::Json.write(&to_serializable(state))?;
+    fs::create_dir_all(path.parent().unwrap())
+        .and_then(|_| fs::write(&path, text))
+        .map_err(|err| Error::msg(format!("error: could not save the unsaved changes. {err}")))?;
+    Ok(path)
+}
+
+fn lock_with_timeout(state: &Mutex<AppState>) -> Option<MutexGuard<'_, AppState>> {
+    let started = Instant::now();
+    loop {
+        match state.try_lock() {
+            Ok(state) => return Some(state),
+            // whatever went wrong, the unsaved changes are still worth keeping
+            Err(TryLockError::Poisoned(poisoned)) => return Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => {
+                thread::sleep(Duration::from_millis(20));
+            }
+            Err(TryLockError::WouldBlock) => return None,
+        }
+    }
+}
+
+fn shut_down(state: &Mutex<AppState>, unsaved: bool) {
+    progress::suspend();
+    println!("\nShutting down.");
+    let Some(state) = lock_with_timeout(state) else {
+        println!("Troubadour is busy, so it stops without saving.");
+        return;
+    };
+    if unsaved && !state.players.is_empty() {
+        match autosave(&state) {
+            Ok(path) => println!(
+                "The unsaved changes were saved to {}. Load them with: load -p {}",
+                path.display(),
+                shlex::try_quote(&path.to_string_lossy()).unwrap_or_default()
+            ),
+            Err(err) => println!("{err}"),
+        }
+    }
+    state.remember_playing();
+    state.mixer.shutdown(FADE_OUT);
+}
+
+// shuts down cleanly when the terminal closes or the system asks troubadour to stop, instead of
+// cutting off the sound and losing the unsaved changes
+pub fn install(
+    state: Arc<Mutex<AppState>>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) {
+    let installed = ctrlc::set_handler(move || {
+        let unsaved = !saved.load(Ordering::SeqCst) || background_mutated.load(Ordering::SeqCst);
+        shut_down(&state, unsaved);
+        process::exit(0);
+    });
+    if let Err(err) = installed {
+        println!("warning: troubadour can't save when it is shut down from outside. {err}");
+    }
+}
+
+// saves the unsaved changes to autosave.json every so often, so that a crash or a power cut loses
+// at most that much work
+pub fn autosave_every(
+    interval: Duration,
+    state: Arc<Mutex<AppState>>,
+    saved: Arc<AtomicBool>,
+    background_mutated: Arc<AtomicBool>,
+) {
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let unsaved = !saved.load(Ordering::SeqCst) || background_mutated.load(Ordering::SeqCst);
+        let state = state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if unsaved && !state.players.is_empty() {
+            if let Err(err) = autosave(&state) {
+                println!("\n{err}");
+            }
+        }
+    });
+}
@@ -0,0 +1,108 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::limiter::to_db;
+use crate::operations::{to_serializable, SerializableAppState};
+use crate::pending::{self, Action};
+use crate::AppState;
+
+// everything a save has, and what is happening right now on top of that. Remotes and other
+// programs read this instead of the output of show
+#[derive(Serialize)]
+pub struct StateSnapshot {
+    pub soundscape: SerializableAppState,
+    pub status: HashMap<String, PlayerStatus>,
+    pub active_scene: Option<String>,
+    pub buses: Buses,
+    // the soonest first
+    pub pending: Vec<PendingAction>,
+}
+
+#[derive(Serialize)]
+pub struct PlayerStatus {
+    pub playing: bool,
+    pub paused: bool,
+    // in seconds, None for sounds that loop forever
+    pub remaining: Option<f64>,
+    pub editing: bool,
+}
+
+#[derive(Serialize)]
+pub struct Buses {
+    pub master: MasterBus,
+    // the players in edit mode, which play into a bus that isn't heard
+    pub edit: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct MasterBus {
+    pub device: Option<String>,
+    // the threshold in dBFS, when the limiter is on
+    pub limit: Option<f32>,
+    pub headphone_protect: bool,
+}
+
+#[derive(Serialize)]
+pub struct PendingAction {
+    pub id: String,
+    pub player: String,
+    // "start", "fade", "fade out" or "queued"
+    pub action: &'static str,
+    // in seconds
+    pub left: f64,
+    pub paused: bool,
+}
+
+impl StateSnapshot {
+    pub fn new(state: &AppState) -> Self {
+        let status = state
+            .players
+            .iter()
+            .map(|(id, player)| {
+                let status = PlayerStatus {
+                    playing: player.get_is_playing(),
+                    paused: player.get_is_paused(),
+                    remaining: player.get_remaining_time().map(|left| left.as_secs_f64()),
+                    editing: player.is_editing(),
+                };
+                (id.clone(), status)
+            })
+            .collect();
+        let limit = &state.mixer.master_limit;
+        let buses = Buses {
+            master: MasterBus {
+                device: state.mixer.device_name(),
+                limit: limit.is_enabled().then(|| to_db(limit.threshold())),
+                headphone_protect: state.mixer.headphone_protect.is_enabled(),
+            },
+            edit: state
+                .ordered_ids()
+                .filter(|id| state.players[*id].is_editing())
+                .cloned()
+                .collect(),
+        };
+        let pending = pending::collect(&state.players)
+            .into_iter()
+            .map(|pending| PendingAction {
+                id: pending.id(),
+                action: match pending.action {
+                    Action::DelayedStart => "start",
+                    Action::Fade { stops: false } => "fade",
+                    Action::Fade { stops: true } => "fade out",
+                    Action::PausingFade => "fade out and pause",
+                    Action::QueuedTrigger => "queued",
+                },
+                player: pending.player,
+                left: pending.left.as_secs_f64(),
+                paused: pending.paused,
+            })
+            .collect();
+        Self {
+            soundscape: to_serializable(state),
+            status,
+            active_scene: state.active_scene.clone(),
+            buses,
+            pending,
+        }
+    }
+}
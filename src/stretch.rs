@@ -0,0 +1,237 @@
+use rodio::Source;
+use std::{f32::consts::TAU, time::Duration};
+
+// the sound is cut into overlapping windows of this length, which are laid down at another
+// spacing than they were taken at. Long enough to hold a few periods of low sounds
+const WINDOW: Duration = Duration::from_millis(40);
+// how far a window may be moved from where it should be taken, to line up with the one before
+const TOLERANCE: Duration = Duration::from_millis(10);
+// the search for the best fit only looks at every this many frames, which is plenty
+const SEARCH_STEP: usize = 2;
+// further than this, the sound gets smeared or choppy
+pub const STRETCH_RANGE: std::ops::RangeInclusive<f64> = 0.5..=2.0;
+
+// makes a sound longer or shorter without changing its pitch, with WSOLA: windows are taken from
+// the input at one spacing and overlapped at another. Each window is moved a little, to where it
+// lines up best with the one before it, so the waveforms don't cancel each other out
+pub struct Stretched<S> {
+    input: S,
+    channels: usize,
+    sample_rate: u32,
+    factor: f64,
+    // in frames
+    window: usize,
+    hop: usize,
+    tolerance: usize,
+    hann: Vec<f32>,
+    // the frames of the input from frame `start` on, interleaved
+    buffer: Vec<f32>,
+    start: usize,
+    input_done: bool,
+    // where the next window should be taken, in frames of the input
+    position: f64,
+    // where the last window was taken
+    previous: Option<usize>,
+    // the second half of the last window, which the next one is laid over
+    tail: Vec<f32>,
+    output: Vec<f32>,
+    output_at: usize,
+    finished: bool,
+}
+
+impl<S> Stretched<S>
+where
+    S: Source<Item = f32>,
+{
+    // a factor of 2 makes the sound twice as long
+    pub fn new(input: S, factor: f64) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate();
+        let frames = |duration: Duration| (duration.as_secs_f64() * sample_rate as f64) as usize;
+        let window = frames(WINDOW).max(4) & !1;
+        // a periodic Hann window, so that two halves that overlap add up to exactly one
+        let hann = (0..window)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / window as f32).cos())
+            .collect();
+        Self {
+            input,
+            channels,
+            sample_rate,
+            factor,
+            window,
+            hop: window / 2,
+            tolerance: frames(TOLERANCE),
+            hann,
+            buffer: Vec::new(),
+            start: 0,
+            input_done: false,
+            position: 0.0,
+            previous: None,
+            tail: vec![0.0; window / 2 * channels],
+            output: Vec::new(),
+            output_at: 0,
+            finished: false,
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.buffer.len() / self.channels
+    }
+
+    fn fill(&mut self, until: usize) {
+        while !self.input_done && self.end() < until {
+            let frame: Option<Vec<f32>> = (0..self.channels).map(|_| self.input.next()).collect();
+            match frame {
+                Some(frame) => self.buffer.extend(frame),
+                None => self.input_done = true,
+            }
+        }
+    }
+
+    // the channels mixed down, and silence outside of the buffer
+    fn mono(&self, frame: usize) -> f32 {
+        if frame < self.start || frame >= self.end() {
+            return 0.0;
+        }
+        let at = (frame - self.start) * self.channels;
+        self.buffer[at..at + self.channels].iter().sum()
+    }
+
+    // the start near the ideal one where the window continues the last one most smoothly
+    fn best_start(&self, ideal: usize) -> usize {
+        let Some(previous) = self.previous else {
+            return ideal;
+        };
+        let natural = previous + self.hop;
+        let mut best = (ideal, f32::MIN);
+        let from = ideal.saturating_sub(self.tolerance).max(self.start);
+        for candidate in (from..=ideal + self.tolerance).step_by(SEARCH_STEP) {
+            let (mut correlation, mut energy) = (0.0, 0.0);
+            for i in (0..self.hop).step_by(SEARCH_STEP) {
+                let sample = self.mono(candidate + i);
+                correlation += sample * self.mono(natural + i);
+                energy += sample * sample;
+            }
+            let fit = correlation / energy.sqrt().max(f32::EPSILON);
+            if fit > best.1 {
+                best = (candidate, fit);
+            }
+        }
+        best.0
+    }
+
+    // lays down the next window, which finishes another half window of output
+    fn step(&mut self) {
+        let ideal = self.position.round() as usize;
+        self.fill(ideal + self.tolerance + self.window);
+        if self.input_done && ideal >= self.end() {
+            self.output = std::mem::take(&mut self.tail);
+            self.output_at = 0;
+            self.finished = true;
+            return;
+        }
+        let start = self.best_start(ideal);
+        let sample = |frame: usize, channel: usize| {
+            if frame < self.start || frame >= self.end() {
+                return 0.0;
+            }
+            self.buffer[(frame - self.start) * self.channels + channel]
+        };
+        let mut output = Vec::with_capacity(self.hop * self.channels);
+        let mut tail = Vec::with_capacity(self.hop * self.channels);
+        for i in 0..self.hop {
+            // the very first window has nothing to be laid over, so it doesn't fade in
+            let gain = if self.previous.is_some() {
+                self.hann[i]
+            } else {
+                1.0
+            };
+            for channel in 0..self.channels {
+                let overlap = self.tail[i * self.channels + channel];
+                output.push(overlap + sample(start + i, channel) * gain);
+                tail.push(sample(start + self.hop + i, channel) * self.hann[self.hop + i]);
+            }
+        }
+        self.output = output;
+        self.output_at = 0;
+        self.tail = tail;
+        self.previous = Some(start);
+        self.position += self.hop as f64 / self.factor;
+
+        // what comes before the next search range and the continuation of this window isn't needed anymore
+        let keep_from = (self.position as usize)
+            .saturating_sub(self.tolerance)
+            .min(start + self.hop)
+            .max(self.start);
+        let drop = (keep_from - self.start).min(self.buffer.len() / self.channels);
+        self.buffer.drain(..drop * self.channels);
+        self.start += drop;
+    }
+}
+
+impl<S> Iterator for Stretched<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.output_at >= self.output.len() {
+            if self.finished {
+                return None;
+            }
+            self.step();
+        }
+        self.output_at += 1;
+        Some(self.output[self.output_at - 1])
+    }
+}
+
+impl<S> Source for Stretched<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input
+            .total_duration()
+            .map(|duration| duration.mul_f64(self.factor))
+    }
+}
+
+#[test]
+fn stretching_keeps_the_pitch() {
+    let sample_rate = 44_100;
+    // a 441 Hz sine, which crosses zero 882 times a second
+    let sine: Vec<f32> = (0..sample_rate)
+        .map(|i| (TAU * 441.0 * i as f32 / sample_rate as f32).sin())
+        .collect();
+    for factor in [0.75, 1.5] {
+        let input = rodio::buffer::SamplesBuffer::new(1, sample_rate, sine.clone());
+        let stretched: Vec<f32> = Stretched::new(input, factor).collect();
+        let expected = sample_rate as f64 * factor;
+        assert!((stretched.len() as f64 - expected).abs() < expected * 0.05);
+        let crossings = stretched
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count() as f64;
+        let per_second = crossings / (stretched.len() as f64 / sample_rate as f64);
+        assert!((per_second - 882.0).abs() < 882.0 * 0.03, "{per_second}");
+        // the windows line up, so the level stays where it was
+        let peak = stretched[4410..stretched.len() - 4410]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!(peak > 0.9 && peak < 1.1);
+    }
+}
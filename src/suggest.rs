@@ -0,0 +1,159 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::player::is_supported;
+use crate::AppState;
+
+pub struct Suggestion {
+    pub path: PathBuf,
+    pub shared_tags: Vec<String>,
+    score: usize,
+}
+
+// the words in names and file names serve as tags too, so sounds that were never tagged still match
+pub fn tags_of(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2 && !word.chars().all(|c| c.is_numeric()))
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+// tags that more of the playing sounds share weigh heavier. Candidates come with the tags they have
+// besides the words of their file name, like the folders they are in in the library
+pub fn rank(
+    wanted: &HashMap<String, usize>,
+    candidates: HashMap<PathBuf, HashSet<String>>,
+) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = candidates
+        .into_iter()
+        .filter_map(|(path, tags)| {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            let mut shared_tags: Vec<String> = tags_of(&stem)
+                .union(&tags)
+                .filter(|tag| wanted.contains_key(*tag))
+                .cloned()
+                .collect();
+            shared_tags.sort();
+            let score = shared_tags.iter().map(|tag| wanted[tag]).sum();
+            (score > 0).then_some(Suggestion {
+                path,
+                shared_tags,
+                score,
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    suggestions
+}
+
+// looks for sounds that are not in the soundscape yet, in the folders of the sounds that are playing
+// and in the library
+pub fn find_suggestions(state: &AppState) -> (Vec<String>, Vec<Suggestion>) {
+    let mut wanted: HashMap<String, usize> = HashMap::new();
+    let mut folders = HashSet::new();
+    let used: HashSet<PathBuf> = state
+        .players
+        .values()
+        .map(|player| canonical(player.get_media()))
+        .collect();
+
+    for (name, player) in &state.players {
+        if !player.get_is_playing() {
+            continue;
+        }
+        let stem = player
+            .get_media()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut tags = tags_of(name);
+        tags.extend(tags_of(&stem));
+        tags.extend(player.tags.iter().map(|tag| tag.to_lowercase()));
+        for tag in tags {
+            *wanted.entry(tag).or_default() += 1;
+        }
+        if let Some(folder) = player.get_media().parent() {
+            folders.insert(folder.to_path_buf());
+        }
+    }
+
+    let mut candidates: HashMap<PathBuf, HashSet<String>> = folders
+        .iter()
+        .filter_map(|folder| fs::read_dir(folder).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| canonical(&entry.path()))
+        .filter(|path| is_supported(path))
+        .map(|path| (path, HashSet::new()))
+        .collect();
+    // the paths of the library are canonical already
+    for sound in state.library.sounds() {
+        candidates
+            .entry(sound.path.clone())
+            .or_default()
+            .extend(sound.tags.iter().map(|tag| tag.to_lowercase()));
+    }
+    candidates.retain(|path, _| !used.contains(path));
+
+    let mut tags: Vec<String> = wanted.keys().cloned().collect();
+    tags.sort_by(|a, b| wanted[b].cmp(&wanted[a]).then_with(|| a.cmp(b)));
+    (tags, rank(&wanted, candidates))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[test]
+fn rank_by_shared_tags() {
+    let wanted = HashMap::from([("forest".to_string(), 2), ("night".to_string(), 1)]);
+    let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+    let candidates = HashMap::from([
+        (PathBuf::from("sounds/night_owl.ogg"), tags(&[])),
+        (PathBuf::from("sounds/Forest Night 02.mp3"), tags(&[])),
+        (PathBuf::from("sounds/tavern.ogg"), tags(&[])),
+        (PathBuf::from("sounds/forest_creek.ogg"), tags(&["night"])),
+        (PathBuf::from("sounds/wolves.ogg"), tags(&["forest"])),
+    ]);
+    let suggestions = rank(&wanted, candidates);
+    let paths: Vec<&Path> = suggestions.iter().map(|s| s.path.as_path()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            Path::new("sounds/Forest Night 02.mp3"),
+            Path::new("sounds/forest_creek.ogg"),
+            Path::new("sounds/wolves.ogg"),
+            Path::new("sounds/night_owl.ogg"),
+        ]
+    );
+    assert_eq!(suggestions[0].shared_tags, vec!["forest", "night"]);
+    assert_eq!(suggestions[1].shared_tags, vec!["forest", "night"]);
+}
+
+#[test]
+fn library_sounds_are_suggested_by_tag() {
+    use crate::player::Player;
+    use std::time::Duration;
+
+    let dir = crate::testing::TempDir::new("suggest");
+    let forest = dir.join("library/forest");
+    fs::create_dir_all(&forest).unwrap();
+    crate::testing::write_tone(&forest.join("creek.wav"), Duration::from_secs(1));
+    crate::testing::write_tone(&dir.join("owl.wav"), Duration::from_secs(1));
+    let mut state = crate::testing::app_state();
+    state.library.add_root(&dir.join("library")).unwrap();
+    let (scanned, _) = state.library.start_scan().run(&mut |_, _, _| {});
+    state.library.finish_scan(scanned).unwrap();
+    let mut owl = Player::new(dir.join("owl.wav"), "owl".to_string(), &state.mixer).unwrap();
+    owl.tags.insert("Forest".to_string());
+    owl.play_at(Duration::ZERO).unwrap();
+    state.players.insert("owl".to_string(), owl);
+    let (tags, suggestions) = find_suggestions(&state);
+    assert_eq!(tags, ["forest", "owl"]);
+    assert_eq!(suggestions.len(), 1);
+    assert!(suggestions[0].path.ends_with("forest/creek.wav"));
+    assert_eq!(suggestions[0].shared_tags, ["forest"]);
+}
@@ -0,0 +1,106 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use indexmap::{IndexMap, IndexSet};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    aliases::Aliases, config::Config, cue::CueList, discord::Discord, events::EventBus,
+    hotkeys::Hotkeys, library::Library, midi::Midi, mixer::Mixer, pairing::Pairing,
+    schedule::Schedule, session_log::SessionLog, AppState,
+};
+
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+// a folder of its own for a test to put files in, which is removed again when the test is done,
+// even when it fails
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let number = CREATED.fetch_add(1, Ordering::SeqCst);
+        let path =
+            std::env::temp_dir().join(format!("troubadour-{name}-{}-{number}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+// a mono wav file of the given length, with a steady tone, for tests that need a sound to play
+pub fn write_tone(path: &Path, length: Duration) {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+    let mut writer = WavWriter::create(path, spec).unwrap();
+    let samples = (length.as_secs_f64() * spec.sample_rate as f64) as usize;
+    for index in 0..samples {
+        let sample = if index % 20 < 10 { 8000 } else { -8000 };
+        writer.write_sample(sample as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+// a state without any sounds, like the one troubadour starts with, but with the default config and
+// nothing loaded from the config folder
+pub fn app_state() -> AppState {
+    AppState {
+        players: HashMap::new(),
+        top_group: IndexSet::new(),
+        groups: IndexMap::new(),
+        scenes: IndexMap::new(),
+        active_scene: None,
+        default_group: None,
+        remaps: IndexMap::new(),
+        project: None,
+        suspended: IndexSet::new(),
+        removed: Vec::new(),
+        group_loops: IndexMap::new(),
+        group_defaults: IndexMap::new(),
+        volume_links: IndexMap::new(),
+        playlists: IndexMap::new(),
+        ducks: Vec::new(),
+        schedule: Schedule::default(),
+        cues: CueList::default(),
+        quiet_override: None,
+        archived: IndexMap::new(),
+        mixer: Mixer::new(),
+        config: Config::default(),
+        events: Vec::new(),
+        bus: EventBus::default(),
+        observer: Default::default(),
+        monitor: None,
+        save_version: None,
+        pairing: Pairing::default(),
+        midi: Midi::new(mpsc::channel().0),
+        discord: Discord::new(),
+        hotkeys: Hotkeys::new(mpsc::channel().0),
+        aliases: Aliases::new(),
+        library: Library::new(),
+        variables: IndexMap::new(),
+        session_log: SessionLog::new(),
+    }
+}
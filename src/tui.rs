@@ -0,0 +1,426 @@
+use anyhow::Error;
+use std::{
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::hooks;
+use crate::player::duration_to_string;
+use crate::{output, run_command, AppState, RespondResult, INTERACTIVE};
+
+// how often the dashboard is redrawn, and so how long a key may wait
+const REFRESH: Duration = Duration::from_millis(100);
+const VOLUME_STEP: u32 = 5;
+const MAX_VOLUME: u32 = 200;
+const BAR_WIDTH: usize = 20;
+const SLIDER_WIDTH: usize = 10;
+// what the last command said is shown at the bottom, in at most this many lines
+const MESSAGE_LINES: usize = 4;
+const HELP: &str = "↑↓ pick  ←→ volume  space play/stop  p pause  : command  q back to the prompt";
+
+// set by the tui command. The dashboard runs between commands, instead of inside one, so that it
+// only holds on to the soundscape while it draws it, and hooks and schedules keep running
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn take_request() -> bool {
+    REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Escape,
+    Interrupt,
+    Char(char),
+}
+
+// the keys in what the terminal sent. Arrow keys come as escape sequences
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        keys.push(match char {
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                match chars.next() {
+                    Some('A') => Key::Up,
+                    Some('B') => Key::Down,
+                    Some('C') => Key::Right,
+                    Some('D') => Key::Left,
+                    _ => continue,
+                }
+            }
+            '\x1b' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\x03' => Key::Interrupt,
+            char if char.is_control() => continue,
+            char => Key::Char(char),
+        });
+    }
+    keys
+}
+
+struct Dashboard {
+    selected: usize,
+    // what is typed after :, while a command is typed
+    command: Option<String>,
+    messages: Vec<String>,
+    has_been_saved: bool,
+    mutated: bool,
+}
+
+impl Dashboard {
+    fn run(&mut self, state: &mut AppState, command: &str) -> bool {
+        let (result, said) = output::capture(|| run_command(state, command, self.has_been_saved));
+        let mut messages: Vec<String> = said.lines().map(str::to_string).collect();
+        let quit = match result {
+            Ok(result) => {
+                self.has_been_saved = (self.has_been_saved || result.saved) && !result.mutated;
+                self.mutated |= result.mutated;
+                result.quit
+            }
+            Err(err) => {
+                messages.push(err.to_string());
+                false
+            }
+        };
+        self.messages = messages;
+        quit
+    }
+
+    // handles a key, and returns whether the dashboard should be left, and whether troubadour
+    // should quit
+    fn press(&mut self, state: &mut AppState, key: Key) -> Option<bool> {
+        if let Some(command) = &mut self.command {
+            match key {
+                Key::Enter => {
+                    let command = self.command.take().unwrap();
+                    if self.run(state, command.trim()) {
+                        return Some(true);
+                    }
+                }
+                Key::Escape | Key::Interrupt => self.command = None,
+                Key::Backspace => {
+                    command.pop();
+                }
+                Key::Char(char) => command.push(char),
+                _ => {}
+            }
+            return None;
+        }
+
+        let ids: Vec<String> = state.ordered_ids().cloned().collect();
+        let quoted = ids.get(self.selected).map(|id| {
+            shlex::try_quote(id)
+                .map(|quoted| quoted.into_owned())
+                .unwrap_or_else(|_| id.clone())
+        });
+        let player = ids.get(self.selected).map(|id| &state.players[id]);
+        let command = match (key, player, quoted) {
+            (Key::Char('q') | Key::Interrupt, _, _) => return Some(false),
+            (Key::Char(':'), _, _) => {
+                self.command = Some(String::new());
+                None
+            }
+            (Key::Up | Key::Char('k'), _, _) => {
+                self.selected = self.selected.saturating_sub(1);
+                None
+            }
+            (Key::Down | Key::Char('j'), _, _) => {
+                self.selected = (self.selected + 1).min(ids.len().saturating_sub(1));
+                None
+            }
+            (Key::Left, Some(player), Some(id)) => {
+                let volume = player.get_heard_volume().saturating_sub(VOLUME_STEP);
+                Some(format!("volume {id} -v {volume}"))
+            }
+            (Key::Right, Some(player), Some(id)) => {
+                let volume = (player.get_heard_volume() + VOLUME_STEP).min(MAX_VOLUME);
+                Some(format!("volume {id} -v {volume}"))
+            }
+            (Key::Char(' ') | Key::Enter, Some(player), Some(id)) => {
+                if player.get_is_playing() || player.get_is_paused() {
+                    Some(format!("stop {id}"))
+                } else {
+                    Some(format!("play {id}"))
+                }
+            }
+            (Key::Char('p'), Some(player), Some(id)) => {
+                if player.get_is_playing() {
+                    Some(format!("pause {id}"))
+                } else {
+                    Some(format!("play {id}"))
+                }
+            }
+            _ => None,
+        };
+        match command {
+            Some(command) if self.run(state, &command) => Some(true),
+            _ => None,
+        }
+    }
+
+    fn draw(&mut self, state: &AppState, rows: usize) -> String {
+        let ids: Vec<&String> = state.ordered_ids().collect();
+        self.selected = self.selected.min(ids.len().saturating_sub(1));
+        let playing = ids
+            .iter()
+            .filter(|id| state.players[**id].get_is_playing())
+            .count();
+        let scene = match &state.active_scene {
+            Some(scene) => format!(", scene {scene}"),
+            None => String::new(),
+        };
+        let mut lines = vec![
+            format!(
+                "\x1b[1mtroubadour\x1b[0m  {} sounds, {playing} playing{scene}",
+                ids.len()
+            ),
+            String::new(),
+        ];
+
+        // the title above, the messages and the footer below
+        let room = rows.saturating_sub(MESSAGE_LINES + 4).max(1);
+        let first = (self.selected + 1).saturating_sub(room);
+        let name_width = ids.iter().map(|id| id.chars().count()).max().unwrap_or(0);
+        for (index, id) in ids.iter().enumerate().skip(first).take(room) {
+            let player = &state.players[*id];
+            let status = if player.get_is_playing() {
+                "▶"
+            } else if player.get_is_paused() {
+                "‖"
+            } else {
+                "·"
+            };
+            let filled = (player.get_loop_progress() * BAR_WIDTH as f32).round() as usize;
+            let bar = format!(
+                "{}{}",
+                "█".repeat(filled.min(BAR_WIDTH)),
+                "░".repeat(BAR_WIDTH - filled.min(BAR_WIDTH))
+            );
+            let left = player
+                .get_remaining_time()
+                .map(|left| format!("{} left", duration_to_string(left, true)))
+                .unwrap_or_default();
+            let volume = player.get_heard_volume();
+            let slider = (volume as usize * SLIDER_WIDTH / MAX_VOLUME as usize).min(SLIDER_WIDTH);
+            let line = format!(
+                "{status} {id:name_width$}  {bar} {left:12} [{}{}] {volume:>3}%",
+                "█".repeat(slider),
+                "·".repeat(SLIDER_WIDTH - slider)
+            );
+            lines.push(if index == self.selected {
+                format!("\x1b[7m> {line}\x1b[0m")
+            } else {
+                format!("  {line}")
+            });
+        }
+        if ids.is_empty() {
+            lines.push(
+                "There are no sounds yet. Type :add -p <PATH> -n <NAME> to add one.".to_string(),
+            );
+        }
+
+        while lines.len() < rows.saturating_sub(MESSAGE_LINES + 2) {
+            lines.push(String::new());
+        }
+        lines.push(String::new());
+        let skipped = self.messages.len().saturating_sub(MESSAGE_LINES);
+        lines.extend(self.messages.iter().skip(skipped).cloned());
+        while lines.len() < rows.saturating_sub(1) {
+            lines.push(String::new());
+        }
+        lines.push(match &self.command {
+            Some(command) => format!(":{command}"),
+            None => format!("\x1b[2m{HELP}\x1b[0m"),
+        });
+
+        let mut screen = String::from("\x1b[H");
+        for line in lines {
+            screen.push_str(&format!("\x1b[2K{line}\r\n"));
+        }
+        // the last line has no line break, so the screen doesn't scroll
+        screen.truncate(screen.len() - 2);
+        screen.push_str("\x1b[J");
+        screen.push_str(if self.command.is_some() {
+            "\x1b[?25h"
+        } else {
+            "\x1b[?25l"
+        });
+        screen
+    }
+}
+
+// the dashboard: every player with how far along it is and its volume. The arrow keys pick a
+// player and turn it up or down, space plays or stops it, and : takes a command as it is written
+// at the prompt. Returns when it is left, with what the commands did to the soundscape
+#[cfg(unix)]
+pub fn run(state: &Arc<Mutex<AppState>>, has_been_saved: bool) -> Result<RespondResult, Error> {
+    let _screen = terminal::Screen::enter()?;
+    // there is no prompt to answer questions with in here
+    INTERACTIVE.set(false);
+    let mut dashboard = Dashboard {
+        selected: 0,
+        command: None,
+        messages: Vec::new(),
+        has_been_saved,
+        mutated: false,
+    };
+    let quit = 'dashboard: loop {
+        if hooks::quit_is_requested() {
+            break true;
+        }
+        let screen = dashboard.draw(&state.lock().unwrap(), terminal::rows());
+        print!("{screen}");
+        io::stdout().flush().unwrap_or_default();
+        for key in terminal::read_keys(REFRESH) {
+            if let Some(quit) = dashboard.press(&mut state.lock().unwrap(), key) {
+                break 'dashboard quit;
+            }
+        }
+    };
+    INTERACTIVE.set(true);
+    Ok(RespondResult {
+        mutated: dashboard.mutated && !dashboard.has_been_saved,
+        saved: dashboard.has_been_saved,
+        quit,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn run(_state: &Arc<Mutex<AppState>>, _has_been_saved: bool) -> Result<RespondResult, Error> {
+    Err(Error::msg(UNSUPPORTED))
+}
+
+// the terminal is taken over through libc, which there is no such thing as on windows
+pub const UNSUPPORTED: &str =
+    "error: the dashboard only works in a unix terminal, like on linux or macOS. Use the prompt instead.";
+
+// the raw terminal, which is only reached through libc on unix. Everything unsafe in the dashboard
+// is in here
+#[cfg(unix)]
+mod terminal {
+    use anyhow::Error;
+    use std::{
+        io::{self, Write},
+        time::Duration,
+    };
+
+    use super::{parse_keys, Key};
+
+    // the terminal as the dashboard needs it: a screen of its own, and every key as soon as it is
+    // pressed, without it being echoed. The terminal goes back to how it was when this is dropped
+    pub struct Screen(libc::termios);
+
+    impl Screen {
+        pub fn enter() -> Result<Self, Error> {
+            // SAFETY: termios is a plain C struct, for which all zeroes is a valid value. It is
+            // filled in by tcgetattr before it is used
+            let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+            // SAFETY: the pointer is to a termios that lives on the stack for the whole call
+            if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+                return Err(Error::msg("error: cannot take over the terminal."));
+            }
+            let mut raw = original;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            // SAFETY: the pointer is to a termios that lives on the stack for the whole call, and
+            // was filled in by tcgetattr
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+                return Err(Error::msg("error: cannot take over the terminal."));
+            }
+            // the alternate screen, without the cursor, and without wrapping long lines
+            print!("\x1b[?1049h\x1b[?25l\x1b[?7l");
+            io::stdout().flush().unwrap_or_default();
+            Ok(Self(original))
+        }
+    }
+
+    impl Drop for Screen {
+        fn drop(&mut self) {
+            print!("\x1b[?7h\x1b[?25h\x1b[?1049l");
+            io::stdout().flush().unwrap_or_default();
+            // SAFETY: the pointer is to the termios that tcgetattr filled in, which self owns.
+            // There is nothing left to do when it fails
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+        }
+    }
+
+    pub fn rows() -> usize {
+        // SAFETY: winsize is a plain C struct, for which all zeroes is a valid value
+        let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+        // SAFETY: TIOCGWINSZ writes a winsize through the pointer, which is to one on the stack
+        match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+            0 if size.ws_row > 0 => size.ws_row as usize,
+            _ => 24,
+        }
+    }
+
+    // waits at most TIMEOUT for keys
+    pub fn read_keys(timeout: Duration) -> Vec<Key> {
+        let mut stdin = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: the pointer is to one pollfd on the stack, and the count says one
+        if unsafe { libc::poll(&mut stdin, 1, timeout.as_millis() as libc::c_int) } <= 0 {
+            return Vec::new();
+        }
+        // read straight from the terminal, so nothing is left behind in a buffer that poll can't
+        // see
+        let mut bytes = [0u8; 64];
+        // SAFETY: read writes at most bytes.len() bytes through the pointer, which is to the
+        // buffer on the stack
+        let read = unsafe {
+            libc::read(
+                libc::STDIN_FILENO,
+                bytes.as_mut_ptr() as *mut libc::c_void,
+                bytes.len(),
+            )
+        };
+        match usize::try_from(read) {
+            Ok(read) => parse_keys(&bytes[..read]),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[test]
+fn keys_are_parsed() {
+    assert_eq!(
+        parse_keys(b"\x1b[A\x1b[Dj :\x7f\r"),
+        [
+            Key::Up,
+            Key::Left,
+            Key::Char('j'),
+            Key::Char(' '),
+            Key::Char(':'),
+            Key::Backspace,
+            Key::Enter
+        ]
+    );
+    assert_eq!(parse_keys(b"\x1b"), [Key::Escape]);
+    assert_eq!(
+        parse_keys("é\x03".as_bytes()),
+        [Key::Char('é'), Key::Interrupt]
+    );
+    // keys that the dashboard doesn't use, like home, are left out
+    assert_eq!(parse_keys(b"\x1b[Hq"), [Key::Char('q')]);
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once,
+    },
+    thread,
+    time::Duration,
+};
+
+// how long the listener waits for a line at a time, and so how long a prompt may have to wait
+// for it to let go of stdin
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+// lines that were typed while a long command ran, like a load. They run once it is done, so a
+// stop that is typed in a hurry isn't lost
+static QUEUE: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static LISTENING: AtomicBool = AtomicBool::new(false);
+// held while the listener reads, so that it never takes the answer to a prompt
+static STDIN: Mutex<()> = Mutex::new(());
+static LISTENER: Once = Once::new();
+
+// starts taking the lines that are typed. Only on unix, where stdin can be waited on without
+// blocking the prompt that comes after
+pub fn listen() {
+    if !cfg!(unix) || !io::stdin().is_terminal() {
+        return;
+    }
+    LISTENER.call_once(|| {
+        thread::spawn(run);
+    });
+    LISTENING.store(true, Ordering::SeqCst);
+}
+
+// stops taking lines, and waits until the listener has let go of stdin
+pub fn stop() {
+    LISTENING.store(false, Ordering::SeqCst);
+    drop(STDIN.lock().unwrap());
+}
+
+pub fn queued() -> usize {
+    QUEUE.lock().unwrap().len()
+}
+
+pub fn next() -> Option<String> {
+    QUEUE.lock().unwrap().pop_front()
+}
+
+fn run() {
+    loop {
+        if !LISTENING.load(Ordering::SeqCst) {
+            thread::sleep(POLL_TIMEOUT);
+            continue;
+        }
+        let _reading = STDIN.lock().unwrap();
+        // stop may have come in between
+        if !LISTENING.load(Ordering::SeqCst) || !line_waiting(POLL_TIMEOUT) {
+            continue;
+        }
+        queue_line(&mut io::stdin().lock());
+    }
+}
+
+// queues the next line that was typed. Blank lines are left out, like at the prompt. Returns
+// whether there was a line
+fn queue_line(input: &mut impl BufRead) -> bool {
+    let mut line = String::new();
+    if !input.read_line(&mut line).is_ok_and(|read| read > 0) {
+        return false;
+    }
+    let line = line.trim();
+    if !line.is_empty() {
+        QUEUE.lock().unwrap().push_back(line.to_string());
+    }
+    true
+}
+
+// waits at the prompt for the first key, which the prompt can't be woken up from, so that the wait
+// can end without one when STOP says so, like for a scheduled exit. Returns whether a key was
+// pressed. The key is left for the prompt to read
+#[cfg(unix)]
+pub fn wait_for_key(prompt: &str, stop: impl Fn() -> bool) -> bool {
+    if !io::stdin().is_terminal() {
+        return true;
+    }
+    let Some(_keys) = KeyMode::enter() else {
+        return true;
+    };
+    print!("{prompt}");
+    io::stdout().flush().unwrap_or_default();
+    loop {
+        if stop() {
+            println!();
+            return false;
+        }
+        // without line mode, stdin is readable as soon as a key is pressed
+        if line_waiting(POLL_TIMEOUT) {
+            return true;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn wait_for_key(_prompt: &str, _stop: impl Fn() -> bool) -> bool {
+    true
+}
+
+// the terminal without line mode and without echo, while this lives, so a key isn't shown twice
+// when the prompt takes it
+#[cfg(unix)]
+struct KeyMode(libc::termios);
+
+#[cfg(unix)]
+impl KeyMode {
+    fn enter() -> Option<Self> {
+        // SAFETY: termios is a plain C struct, for which all zeroes is a valid value. It is filled
+        // in by tcgetattr before it is used
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        // SAFETY: the pointer is to a termios that lives on the stack for the whole call
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return None;
+        }
+        let mut keys = original;
+        // ctrl-c is a key to the prompt as well
+        keys.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        // SAFETY: the pointer is to a termios that lives on the stack for the whole call, and was
+        // filled in by tcgetattr
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &keys) } != 0 {
+            return None;
+        }
+        Some(Self(original))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for KeyMode {
+    fn drop(&mut self) {
+        // SAFETY: the pointer is to the termios that tcgetattr filled in, which self owns. There
+        // is nothing left to do when it fails
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+    }
+}
+
+// outside of the prompt, the terminal is in line mode, so stdin only becomes readable once a
+// whole line was typed, and a read doesn't take more than that line
+#[cfg(unix)]
+fn line_waiting(timeout: Duration) -> bool {
+    let mut stdin = libc::pollfd {
+        fd: libc::STDIN_FILENO,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: the pointer is to one pollfd that lives on the stack for the whole call, and the
+    // count says there is one
+    unsafe { libc::poll(&mut stdin, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
+
+#[cfg(not(unix))]
+fn line_waiting(timeout: Duration) -> bool {
+    thread::sleep(timeout);
+    false
+}
+
+#[test]
+fn typed_lines_are_queued_in_order() {
+    let mut typed = io::Cursor::new("stop rain\n\n  play owl \nvolume owl -v 20");
+    while queue_line(&mut typed) {}
+    assert_eq!(queued(), 3);
+    assert_eq!(next().as_deref(), Some("stop rain"));
+    assert_eq!(next().as_deref(), Some("play owl"));
+    assert_eq!(next().as_deref(), Some("volume owl -v 20"));
+    assert_eq!(next(), None);
+}
@@ -0,0 +1,66 @@
+use anyhow::Error;
+use indexmap::IndexMap;
+
+use crate::fuzzy;
+
+// a word like $battle, which stands for the words it was set to with set, so that a long selection
+// doesn't have to be typed again during play. Variables last until troubadour exits
+pub fn is_variable(word: &str) -> bool {
+    word.strip_prefix('$').is_some_and(|name| {
+        name.starts_with(|char: char| char.is_alphabetic())
+            && name
+                .chars()
+                .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+    })
+}
+
+// puts the words of the variables in the place of their names. The name that set and unset are
+// about is left alone, so a variable can be set again
+pub fn expand(
+    variables: &IndexMap<String, String>,
+    words: Vec<String>,
+) -> Result<Vec<String>, Error> {
+    let names_a_variable = matches!(words.first().map(String::as_str), Some("set" | "unset"));
+    let mut expanded = Vec::with_capacity(words.len());
+    for (index, word) in words.into_iter().enumerate() {
+        if !is_variable(&word) || (names_a_variable && index == 1) {
+            expanded.push(word);
+            continue;
+        }
+        let name = &word[1..];
+        let value = variables.get(name).ok_or_else(|| {
+            Error::msg(format!(
+                "error: there is no variable {word}{}",
+                fuzzy::did_you_mean(name, variables.keys())
+            ))
+        })?;
+        // values are kept the way they were written, so a name with a space in it stays one word
+        expanded.extend(shlex::split(value).unwrap_or_default());
+    }
+    Ok(expanded)
+}
+
+#[test]
+fn variables_stand_for_their_words() {
+    let words = |line: &str| shlex::split(line).unwrap();
+    let variables = IndexMap::from([
+        ("battle".to_string(), "drums horns 'war cry'".to_string()),
+        ("calm".to_string(), "rain".to_string()),
+    ]);
+    assert_eq!(
+        expand(&variables, words("play $battle $calm -f intro")).unwrap(),
+        ["play", "drums", "horns", "war cry", "rain", "-f", "intro"]
+    );
+    assert_eq!(
+        expand(&variables, words("set $battle $calm owl")).unwrap(),
+        ["set", "$battle", "rain", "owl"]
+    );
+    assert!(expand(&variables, words("play $battel")).is_err());
+    // words that only look a bit like variables are left alone
+    assert_eq!(
+        expand(&variables, words("alias loud 'volume $1' ^$ $")).unwrap(),
+        ["alias", "loud", "volume $1", "^$", "$"]
+    );
+    assert!(is_variable("$big_rain2"));
+    assert!(!is_variable("$1"));
+}
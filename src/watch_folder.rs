@@ -0,0 +1,160 @@
+use indexmap::IndexSet;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::download;
+use crate::events::AppEvent;
+use crate::metadata;
+use crate::operations::{add, group, unique_name};
+use crate::player::is_supported;
+use crate::{progress, AppState, INTERACTIVE};
+
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+// keeps track of the files in the folder. Files that were already there when watching started are
+// left alone, and new files are only picked up once they stop growing, so half-finished downloads
+// don't get added
+pub struct WatchFolder {
+    path: PathBuf,
+    known: HashSet<PathBuf>,
+    // new files, with their size during the last scan
+    growing: HashMap<PathBuf, u64>,
+}
+
+fn supported_files(folder: &Path) -> Vec<(PathBuf, u64)> {
+    fs::read_dir(folder)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+        .filter(|(path, metadata)| metadata.is_file() && is_supported(path))
+        .map(|(path, metadata)| (path, metadata.len()))
+        .collect()
+}
+
+impl WatchFolder {
+    pub fn new(path: PathBuf) -> Self {
+        let known = supported_files(&path)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        Self {
+            path,
+            known,
+            growing: HashMap::new(),
+        }
+    }
+
+    // returns the files that are ready to be added
+    pub fn scan(&mut self) -> Vec<PathBuf> {
+        let mut ready = Vec::new();
+        for (path, size) in supported_files(&self.path) {
+            if self.known.contains(&path) {
+                continue;
+            }
+            if self.growing.insert(path.clone(), size) == Some(size) && size > 0 {
+                self.growing.remove(&path);
+                self.known.insert(path.clone());
+                ready.push(path);
+            }
+        }
+        ready.sort();
+        ready
+    }
+}
+
+// the title in the tags of the sound, or else the file name, but made unique among the players
+pub fn player_name(path: &Path, existing: &IndexSet<&String>) -> String {
+    let stem = metadata::read(&download::readable(path))
+        .title
+        .and_then(|title| typeable(&title))
+        .or_else(|| typeable(&path.file_stem()?.to_string_lossy()))
+        .unwrap_or_else(|| "sound".to_string());
+    unique_name(&stem, existing)
+}
+
+// a title or a file name can have anything in it, but a name has to be typed at the prompt. What
+// means something there is left out: quotes, @ for tags, $ for variables, * and ? for patterns, # for
+// comments, and a dash at the start, which would be read as an option
+fn typeable(title: &str) -> Option<String> {
+    let name: String = title
+        .chars()
+        .filter(|c| !c.is_control() && !"\"'\\@$*?#".contains(*c))
+        .collect();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let name = name.trim_start_matches('-').trim_start();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn add_file(state: &mut AppState, path: PathBuf, group_name: &str) {
+    let name = player_name(&path, &state.players.keys().collect());
+    progress::suspend();
+    println!("\nAdding {} from the watch folder.", path.display());
+    let added = add(state, path, Some(name.clone()), &mut |_, _, _| {})
+        .and_then(|_| group(state, group_name.to_string(), vec![name.clone()]));
+    match added {
+        Ok(_) => state.emit(AppEvent::AutoAdded(name)),
+        Err(err) => println!("{err}"),
+    }
+}
+
+pub fn spawn_watcher(
+    state: Arc<Mutex<AppState>>,
+    folder: PathBuf,
+    group_name: String,
+    background_mutated: Arc<AtomicBool>,
+) {
+    thread::spawn(move || {
+        // there is nobody to answer prompts on this thread
+        INTERACTIVE.set(false);
+        let mut watch_folder = WatchFolder::new(folder);
+        loop {
+            thread::sleep(SCAN_INTERVAL);
+            let ready = watch_folder.scan();
+            if ready.is_empty() {
+                continue;
+            }
+            let mut state = state.lock().unwrap();
+            for path in ready {
+                add_file(&mut state, path, &group_name);
+            }
+            background_mutated.store(true, Ordering::SeqCst);
+        }
+    });
+}
+
+#[test]
+fn watch_folder_waits_for_new_files() {
+    let temp = crate::testing::TempDir::new("watch");
+    let folder = temp.path().to_path_buf();
+    fs::write(folder.join("old.ogg"), b"old").unwrap();
+
+    let mut watch_folder = WatchFolder::new(folder.clone());
+    fs::write(folder.join("new.ogg"), b"partial").unwrap();
+    fs::write(folder.join("notes.txt"), b"not a sound").unwrap();
+    assert!(watch_folder.scan().is_empty());
+    fs::write(folder.join("new.ogg"), b"partial and then some").unwrap();
+    assert!(watch_folder.scan().is_empty());
+    assert_eq!(watch_folder.scan(), vec![folder.join("new.ogg")]);
+    assert!(watch_folder.scan().is_empty());
+
+    let rain = "rain".to_string();
+    let existing: IndexSet<&String> = [&rain].into();
+    assert_eq!(player_name(Path::new("a/rain.ogg"), &existing), "rain-2");
+    assert_eq!(player_name(Path::new("a/All.mp3"), &existing), "All-2");
+    assert_eq!(
+        typeable("  -- \"Rain\" @ the   *Inn*?\n").as_deref(),
+        Some("Rain the Inn")
+    );
+    assert_eq!(typeable("#$@").as_deref(), None);
+    assert_eq!(player_name(Path::new("a/-@.ogg"), &existing), "sound");
+}
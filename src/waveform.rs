@@ -0,0 +1,118 @@
+use anyhow::Error;
+use rodio::{Decoder, Source};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::handles;
+use crate::player::decode_error;
+
+// the peaks are kept at this resolution, and downsampled from there to whatever is asked for
+const WINDOW: Duration = Duration::from_millis(10);
+
+// the loudest sample of every window of the sound, over all channels
+pub struct Envelope {
+    pub peaks: Vec<f32>,
+}
+
+impl Envelope {
+    pub fn measure(source: impl Source<Item = f32>) -> Self {
+        let samples_per_window = ((source.sample_rate() as f64 * source.channels() as f64)
+            * WINDOW.as_secs_f64())
+        .max(1.0) as usize;
+        let mut peaks = Vec::new();
+        let mut peak = 0.0f32;
+        let mut in_window = 0;
+        for sample in source {
+            peak = peak.max(sample.abs());
+            in_window += 1;
+            if in_window == samples_per_window {
+                peaks.push(peak);
+                peak = 0.0;
+                in_window = 0;
+            }
+        }
+        if in_window > 0 {
+            peaks.push(peak);
+        }
+        Self { peaks }
+    }
+
+    pub fn duration(&self) -> Duration {
+        WINDOW * self.peaks.len() as u32
+    }
+
+    // every bucket gets the loudest peak in its part of the sound
+    pub fn downsample(&self, buckets: usize) -> Vec<f32> {
+        if self.peaks.is_empty() {
+            return vec![0.0; buckets];
+        }
+        (0..buckets)
+            .map(|bucket| {
+                let start = bucket * self.peaks.len() / buckets;
+                let end = ((bucket + 1) * self.peaks.len() / buckets).max(start + 1);
+                self.peaks[start..end.min(self.peaks.len())]
+                    .iter()
+                    .copied()
+                    .fold(0.0, f32::max)
+            })
+            .collect()
+    }
+}
+
+// the envelopes, with the modification time of the file they were measured from
+type Cache = HashMap<PathBuf, (Option<SystemTime>, Arc<Envelope>)>;
+
+// decoding a whole file takes a while, so the envelopes are kept until the file changes
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+pub fn envelope(media: &Path) -> Result<Arc<Envelope>, Error> {
+    let modified = fs::metadata(media).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, envelope)) = CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .get(media)
+    {
+        if *cached_modified == modified {
+            return Ok(envelope.clone());
+        }
+    }
+
+    let media_file = handles::open(media)
+        .map_err(|err| Error::msg(format!("error: could not open {}. {err}", media.display())))?;
+    let decoder = Decoder::new(media_file).map_err(|_| decode_error(media, "read"))?;
+    let envelope = Arc::new(Envelope::measure(decoder.convert_samples()));
+    CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(media.to_path_buf(), (modified, envelope.clone()));
+    Ok(envelope)
+}
+
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn render(peaks: &[f32]) -> String {
+    peaks
+        .iter()
+        .map(|peak| BARS[((peak.clamp(0.0, 1.0) * (BARS.len() - 1) as f32).round()) as usize])
+        .collect()
+}
+
+#[test]
+fn peaks_are_downsampled() {
+    // 50ms of mono sound, so five windows, with a single loud sample in the fourth one
+    let mut samples = vec![0.1f32; 2205];
+    samples[1500] = -0.9;
+    let envelope = Envelope::measure(rodio::buffer::SamplesBuffer::new(1, 44_100, samples));
+    assert_eq!(envelope.peaks, vec![0.1, 0.1, 0.1, 0.9, 0.1]);
+    assert_eq!(envelope.duration(), Duration::from_millis(50));
+    assert_eq!(envelope.downsample(2), vec![0.1, 0.9]);
+    assert_eq!(envelope.downsample(10).len(), 10);
+    assert_eq!(render(&[0.0, 1.0]), "▁█");
+}